@@ -21,6 +21,8 @@ pub const ALTREF_FRAME: usize = 7;
 pub struct Sequence {
     pub sh: Option<obu::SequenceHeader>,
     pub rfman: RefFrameManager,
+    pub hdr_cll: Option<obu::HdrCllMetadata>, // last HDR CLL metadata
+    pub hdr_mdcv: Option<obu::HdrMdcvMetadata>, // last HDR MDCV metadata
 }
 
 impl Sequence {
@@ -28,8 +30,26 @@ impl Sequence {
         Sequence {
             sh: None,
             rfman: RefFrameManager::new(),
+            hdr_cll: None,
+            hdr_mdcv: None,
         }
     }
+
+    /// Decode frame wrapup process
+    pub fn decode_frame_wrapup(&mut self, fh: &obu::FrameHeader) {
+        if fh.show_frame || fh.show_existing_frame {
+            self.rfman.output_process(fh);
+        }
+        if !fh.show_existing_frame {
+            self.rfman.update_process(fh);
+        }
+    }
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 ///
@@ -42,12 +62,19 @@ pub struct RefFrameManager {
     pub ref_frame_type: [u8; NUM_REF_FRAMES], // RefFrameType[i]
     pub ref_order_hint: [u8; NUM_REF_FRAMES], // RefOrderHint[i]
     pub saved_gm_params: [[[i32; 6]; NUM_REF_FRAMES]; NUM_REF_FRAMES], // SavedGmParams[i][ref][j]
+    pub saved_film_grain_params: [obu::FilmGrainParams; NUM_REF_FRAMES], // film_grain_params() saved by save_grain_params(i)
     // user data
     pub decode_order: i64,  // frame decoding oreder
     pub present_order: i64, // frame presentation order
     pub frame_buf: [i64; NUM_REF_FRAMES],
 }
 
+impl Default for RefFrameManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RefFrameManager {
     pub fn new() -> Self {
         RefFrameManager {
@@ -56,9 +83,10 @@ impl RefFrameManager {
             ref_frame_type: [0; NUM_REF_FRAMES],
             ref_order_hint: [0; NUM_REF_FRAMES],
             saved_gm_params: [[[0; 6]; NUM_REF_FRAMES]; NUM_REF_FRAMES],
+            saved_film_grain_params: Default::default(),
             decode_order: 0,
             present_order: 0,
-            frame_buf: [i64::min_value(); NUM_REF_FRAMES],
+            frame_buf: [i64::MIN; NUM_REF_FRAMES],
         }
     }
 
@@ -93,6 +121,22 @@ impl RefFrameManager {
         self.present_order += 1;
     }
 
+    /// get decoding order of frames referenced by frame header
+    pub fn reference_frames(&self, fh: &obu::FrameHeader) -> Vec<i64> {
+        let mut refs = Vec::new();
+        if fh.show_existing_frame {
+            refs.push(self.frame_buf[fh.frame_to_show_map_idx as usize]);
+        } else if !fh.frame_is_intra {
+            for ref_ in LAST_FRAME..=ALTREF_FRAME {
+                let frame = self.frame_buf[fh.ref_frame_idx[ref_ - LAST_FRAME] as usize];
+                if !refs.contains(&frame) {
+                    refs.push(frame);
+                }
+            }
+        }
+        refs
+    }
+
     /// Reference frame update process
     pub fn update_process(&mut self, fh: &obu::FrameHeader) {
         for i in 0..NUM_REF_FRAMES {
@@ -107,6 +151,7 @@ impl RefFrameManager {
                             fh.global_motion_params.gm_params[ref_][j];
                     }
                 }
+                self.saved_film_grain_params[i] = fh.film_grain_params.clone();
                 // user data
                 self.frame_buf[i] = self.decode_order;
             }
@@ -123,13 +168,395 @@ pub fn get_relative_dist(a: i32, b: i32, sh: &obu::SequenceHeader) -> i32 {
     let mut diff = a - b;
     let m = 1 << (sh.order_hint_bits - 1);
     diff = (diff & (m - 1)) - (diff & m);
-    return diff;
+    diff
+}
+
+// default values of optional fields in codec parameter string (M.CCC.cp.tc.mc.F)
+const CODEC_STRING_DEFAULTS: [&str; 6] = ["0", "110", "01", "01", "01", "0"];
+
+///
+/// generate codec parameter string "av01.P.LLT.DD[.M.CCC.cp.tc.mc.F]" (AV1 Codec ISO Media File Format Binding)
+///
+pub fn codec_string(sh: &obu::SequenceHeader) -> String {
+    let cc = &sh.color_config;
+    let mut codecs = format!(
+        "av01.{}.{:02}{}.{:02}",
+        sh.seq_profile,
+        sh.op[0].seq_level_idx,
+        if sh.op[0].seq_tier == 0 { 'M' } else { 'H' },
+        cc.bit_depth
+    );
+    let optional = [
+        format!("{}", cc.mono_chrome as u8),
+        format!(
+            "{}{}{}",
+            cc.subsampling_x,
+            cc.subsampling_y,
+            if cc.subsampling_x == 1 && cc.subsampling_y == 1 {
+                cc.chroma_sample_position
+            } else {
+                0
+            }
+        ),
+        format!("{:02}", cc.color_primaries),
+        format!("{:02}", cc.transfer_characteristics),
+        format!("{:02}", cc.matrix_coefficients),
+        format!("{}", cc.color_range as u8),
+    ];
+    // optional fields are all omitted or all present
+    if optional != CODEC_STRING_DEFAULTS {
+        for field in optional.iter() {
+            codecs.push('.');
+            codecs.push_str(field);
+        }
+    }
+    codecs
+}
+
+///
+/// check declared codec parameter string matches with generated one,
+/// fields omitted in declared string are not compared
+///
+pub fn match_codec_string(declared: &str, generated: &str) -> bool {
+    let declared: Vec<&str> = declared.trim().split('.').collect();
+    let mut generated: Vec<&str> = generated.split('.').collect();
+    if generated.len() == 4 {
+        generated.extend_from_slice(&CODEC_STRING_DEFAULTS);
+    }
+    (4..=generated.len()).contains(&declared.len())
+        && declared.iter().zip(generated.iter()).all(|(a, b)| a == b)
+}
+
+///
+/// limits of defined levels (Annex A.3)
+///
+#[derive(Debug)]
+pub struct LevelLimits {
+    pub seq_level_idx: u8,
+    pub max_pic_size: u64,      // MaxPicSize
+    pub max_h_size: u32,        // MaxHSize
+    pub max_v_size: u32,        // MaxVSize
+    pub max_display_rate: u64,  // MaxDisplayRate
+    pub max_decode_rate: u64,   // MaxDecodeRate
+    pub max_header_rate: u32,   // MaxHeaderRate
+    pub main_mbps: f64,         // MainMbps
+    pub high_mbps: Option<f64>, // HighMbps (no High tier below level 4.0)
+    pub max_tiles: u32,         // MaxTiles
+    pub max_tile_cols: u32,     // MaxTileCols
+}
+
+pub const LEVEL_LIMITS: [LevelLimits; 14] = [
+    LevelLimits {
+        seq_level_idx: 0,
+        max_pic_size: 147456,
+        max_h_size: 2048,
+        max_v_size: 1152,
+        max_display_rate: 4423680,
+        max_decode_rate: 5529600,
+        max_header_rate: 150,
+        main_mbps: 1.5,
+        high_mbps: None,
+        max_tiles: 8,
+        max_tile_cols: 4,
+    },
+    LevelLimits {
+        seq_level_idx: 1,
+        max_pic_size: 278784,
+        max_h_size: 2816,
+        max_v_size: 1584,
+        max_display_rate: 8363520,
+        max_decode_rate: 10454400,
+        max_header_rate: 150,
+        main_mbps: 3.0,
+        high_mbps: None,
+        max_tiles: 8,
+        max_tile_cols: 4,
+    },
+    LevelLimits {
+        seq_level_idx: 4,
+        max_pic_size: 665856,
+        max_h_size: 4352,
+        max_v_size: 2448,
+        max_display_rate: 19975680,
+        max_decode_rate: 24969600,
+        max_header_rate: 150,
+        main_mbps: 6.0,
+        high_mbps: None,
+        max_tiles: 16,
+        max_tile_cols: 6,
+    },
+    LevelLimits {
+        seq_level_idx: 5,
+        max_pic_size: 1065024,
+        max_h_size: 5504,
+        max_v_size: 3096,
+        max_display_rate: 31950720,
+        max_decode_rate: 39938400,
+        max_header_rate: 150,
+        main_mbps: 10.0,
+        high_mbps: None,
+        max_tiles: 16,
+        max_tile_cols: 6,
+    },
+    LevelLimits {
+        seq_level_idx: 8,
+        max_pic_size: 2359296,
+        max_h_size: 6144,
+        max_v_size: 3456,
+        max_display_rate: 70778880,
+        max_decode_rate: 77856768,
+        max_header_rate: 300,
+        main_mbps: 12.0,
+        high_mbps: Some(30.0),
+        max_tiles: 32,
+        max_tile_cols: 8,
+    },
+    LevelLimits {
+        seq_level_idx: 9,
+        max_pic_size: 2359296,
+        max_h_size: 6144,
+        max_v_size: 3456,
+        max_display_rate: 141557760,
+        max_decode_rate: 155713536,
+        max_header_rate: 300,
+        main_mbps: 20.0,
+        high_mbps: Some(50.0),
+        max_tiles: 32,
+        max_tile_cols: 8,
+    },
+    LevelLimits {
+        seq_level_idx: 12,
+        max_pic_size: 8912896,
+        max_h_size: 8192,
+        max_v_size: 4352,
+        max_display_rate: 267386880,
+        max_decode_rate: 273715200,
+        max_header_rate: 300,
+        main_mbps: 30.0,
+        high_mbps: Some(100.0),
+        max_tiles: 64,
+        max_tile_cols: 8,
+    },
+    LevelLimits {
+        seq_level_idx: 13,
+        max_pic_size: 8912896,
+        max_h_size: 8192,
+        max_v_size: 4352,
+        max_display_rate: 534773760,
+        max_decode_rate: 547430400,
+        max_header_rate: 300,
+        main_mbps: 40.0,
+        high_mbps: Some(160.0),
+        max_tiles: 64,
+        max_tile_cols: 8,
+    },
+    LevelLimits {
+        seq_level_idx: 14,
+        max_pic_size: 8912896,
+        max_h_size: 8192,
+        max_v_size: 4352,
+        max_display_rate: 1069547520,
+        max_decode_rate: 1094860800,
+        max_header_rate: 300,
+        main_mbps: 60.0,
+        high_mbps: Some(240.0),
+        max_tiles: 64,
+        max_tile_cols: 8,
+    },
+    LevelLimits {
+        seq_level_idx: 15,
+        max_pic_size: 8912896,
+        max_h_size: 8192,
+        max_v_size: 4352,
+        max_display_rate: 1069547520,
+        max_decode_rate: 1176502272,
+        max_header_rate: 300,
+        main_mbps: 60.0,
+        high_mbps: Some(240.0),
+        max_tiles: 64,
+        max_tile_cols: 8,
+    },
+    LevelLimits {
+        seq_level_idx: 16,
+        max_pic_size: 35651584,
+        max_h_size: 16384,
+        max_v_size: 8704,
+        max_display_rate: 1069547520,
+        max_decode_rate: 1176502272,
+        max_header_rate: 300,
+        main_mbps: 60.0,
+        high_mbps: Some(240.0),
+        max_tiles: 128,
+        max_tile_cols: 16,
+    },
+    LevelLimits {
+        seq_level_idx: 17,
+        max_pic_size: 35651584,
+        max_h_size: 16384,
+        max_v_size: 8704,
+        max_display_rate: 2139095040,
+        max_decode_rate: 2189721600,
+        max_header_rate: 300,
+        main_mbps: 100.0,
+        high_mbps: Some(480.0),
+        max_tiles: 128,
+        max_tile_cols: 16,
+    },
+    LevelLimits {
+        seq_level_idx: 18,
+        max_pic_size: 35651584,
+        max_h_size: 16384,
+        max_v_size: 8704,
+        max_display_rate: 4278190080,
+        max_decode_rate: 4379443200,
+        max_header_rate: 300,
+        main_mbps: 160.0,
+        high_mbps: Some(800.0),
+        max_tiles: 128,
+        max_tile_cols: 16,
+    },
+    LevelLimits {
+        seq_level_idx: 19,
+        max_pic_size: 35651584,
+        max_h_size: 16384,
+        max_v_size: 8704,
+        max_display_rate: 4278190080,
+        max_decode_rate: 4706009088,
+        max_header_rate: 300,
+        main_mbps: 160.0,
+        high_mbps: Some(800.0),
+        max_tiles: 128,
+        max_tile_cols: 16,
+    },
+];
+
+///
+/// stream properties constrained by level, rates are None if frame rate is unknown
+///
+#[derive(Debug, Default)]
+pub struct LevelMeasure {
+    pub max_width: u32,            // max UpscaledWidth
+    pub max_height: u32,           // max FrameHeight
+    pub max_pic_size: u64,         // max UpscaledWidth * FrameHeight
+    pub max_tiles: u32,            // max TileCols * TileRows
+    pub max_tile_cols: u32,        // max TileCols
+    pub display_rate: Option<f64>, // luma samples of shown frames per second
+    pub decode_rate: Option<f64>,  // luma samples of coded frames per second
+    pub header_rate: Option<f64>,  // frame headers per second
+    pub bitrate: Option<f64>,      // bits per second
+}
+
+impl LevelLimits {
+    ///
+    /// list limits exceeded by stream at seq_tier
+    ///
+    pub fn exceeded(&self, m: &LevelMeasure, seq_profile: u8, seq_tier: u8) -> Vec<&'static str> {
+        // BitrateProfileFactor
+        let profile_factor = match seq_profile {
+            0 => 1.0,
+            1 => 2.0,
+            _ => 3.0,
+        };
+        let mbps = match (seq_tier, self.high_mbps) {
+            (0, _) => self.main_mbps,
+            (_, Some(high_mbps)) => high_mbps,
+            (_, None) => return vec!["seq_tier"],
+        };
+        let over = |rate: Option<f64>, limit: f64| rate.is_some_and(|rate| rate > limit);
+        let mut exceeded = Vec::new();
+        if m.max_pic_size > self.max_pic_size {
+            exceeded.push("MaxPicSize");
+        }
+        if m.max_width > self.max_h_size {
+            exceeded.push("MaxHSize");
+        }
+        if m.max_height > self.max_v_size {
+            exceeded.push("MaxVSize");
+        }
+        if over(m.display_rate, self.max_display_rate as f64) {
+            exceeded.push("MaxDisplayRate");
+        }
+        if over(m.decode_rate, self.max_decode_rate as f64) {
+            exceeded.push("MaxDecodeRate");
+        }
+        if over(m.header_rate, self.max_header_rate as f64) {
+            exceeded.push("MaxHeaderRate");
+        }
+        if over(m.bitrate, mbps * profile_factor * 1_000_000.0) {
+            exceeded.push("MaxBitrate");
+        }
+        if m.max_tiles > self.max_tiles {
+            exceeded.push("MaxTiles");
+        }
+        if m.max_tile_cols > self.max_tile_cols {
+            exceeded.push("MaxTileCols");
+        }
+        exceeded
+    }
+}
+
+///
+/// find limits of seq_level_idx, None for undefined levels and level 31 (no restriction)
+///
+pub fn level_limits(seq_level_idx: u8) -> Option<&'static LevelLimits> {
+    LEVEL_LIMITS
+        .iter()
+        .find(|limits| limits.seq_level_idx == seq_level_idx)
+}
+
+///
+/// lowest (seq_level_idx, seq_tier) which stream conforms to, Main tier is preferred within level
+///
+pub fn minimum_level(m: &LevelMeasure, seq_profile: u8) -> Option<(u8, u8)> {
+    for limits in LEVEL_LIMITS.iter() {
+        for seq_tier in 0..=1 {
+            if limits.exceeded(m, seq_profile, seq_tier).is_empty() {
+                return Some((limits.seq_level_idx, seq_tier));
+            }
+        }
+    }
+    None
 }
 
 pub mod stringify {
     use super::obu;
     use std::borrow::Cow;
 
+    pub fn obu_type(v: u8) -> Cow<'static, str> {
+        match v {
+            obu::OBU_SEQUENCE_HEADER => Cow::Borrowed("SEQUENCE_HEADER"),
+            obu::OBU_TEMPORAL_DELIMITER => Cow::Borrowed("TEMPORAL_DELIMITER"),
+            obu::OBU_FRAME_HEADER => Cow::Borrowed("FRAME_HEADER"),
+            obu::OBU_TILE_GROUP => Cow::Borrowed("TILE_GROUP"),
+            obu::OBU_FRAME => Cow::Borrowed("FRAME"),
+            obu::OBU_METADATA => Cow::Borrowed("METADATA"),
+            obu::OBU_REDUNDANT_FRAME_HEADER => Cow::Borrowed("REDUNDANT_FRAME_HEADER"),
+            obu::OBU_TILE_LIST => Cow::Borrowed("TILE_LIST"),
+            obu::OBU_PADDING => Cow::Borrowed("PADDING"),
+            _ => Cow::Owned(format!("Reserved({})", v)), // Reserved
+        }
+    }
+
+    pub fn profile(v: u8) -> Cow<'static, str> {
+        match v {
+            0 => Cow::Borrowed("Main"),
+            1 => Cow::Borrowed("High"),
+            2 => Cow::Borrowed("Professional"),
+            _ => Cow::Owned(format!("Reserved({})", v)),
+        }
+    }
+
+    pub fn level(seq_level_idx: u8) -> Cow<'static, str> {
+        match seq_level_idx {
+            31 => Cow::Borrowed("max"), // no level restriction
+            _ => Cow::Owned(format!(
+                "{}.{}",
+                2 + (seq_level_idx >> 2),
+                seq_level_idx & 3
+            )),
+        }
+    }
+
     pub fn frame_type(v: u8) -> &'static str {
         match v {
             obu::KEY_FRAME => "KeyFrame",
@@ -140,6 +567,60 @@ pub mod stringify {
         }
     }
 
+    pub fn ref_frame_name(ref_frame: usize) -> &'static str {
+        match ref_frame {
+            super::INTRA_FRAME => "INTRA",
+            super::LAST_FRAME => "LAST",
+            super::LAST2_FRAME => "LAST2",
+            super::LAST3_FRAME => "LAST3",
+            super::GOLDEN_FRAME => "GOLDEN",
+            super::BWDREF_FRAME => "BWDREF",
+            super::ALTREF2_FRAME => "ALTREF2",
+            super::ALTREF_FRAME => "ALTREF",
+            _ => "(undefined)",
+        }
+    }
+
+    pub fn warp_model(v: u8) -> &'static str {
+        match v {
+            obu::IDENTITY => "IDENTITY",
+            obu::TRANSLATION => "TRANSLATION",
+            obu::ROTZOOM => "ROTZOOM",
+            obu::AFFINE => "AFFINE",
+            _ => "(undefined)",
+        }
+    }
+
+    pub fn interpolation_filter(v: u8) -> &'static str {
+        match v {
+            obu::EIGHTTAP => "EIGHTTAP",
+            obu::EIGHTTAP_SMOOTH => "EIGHTTAP_SMOOTH",
+            obu::EIGHTTAP_SHARP => "EIGHTTAP_SHARP",
+            obu::BILINEAR => "BILINEAR",
+            obu::SWITCHABLE => "SWITCHABLE",
+            _ => "(undefined)",
+        }
+    }
+
+    pub fn tx_mode(v: u8) -> &'static str {
+        match v {
+            obu::ONLY_4X4 => "ONLY_4X4",
+            obu::TX_MODE_LARGEST => "TX_MODE_LARGEST",
+            obu::TX_MODE_SELECT => "TX_MODE_SELECT",
+            _ => "(undefined)",
+        }
+    }
+
+    pub fn restoration_type(v: u8) -> &'static str {
+        match v {
+            obu::RESTORE_NONE => "NONE",
+            obu::RESTORE_WIENER => "WIENER",
+            obu::RESTORE_SGRPROJ => "SGRPROJ",
+            obu::RESTORE_SWITCHABLE => "SWITCHABLE",
+            _ => "(undefined)",
+        }
+    }
+
     pub fn ref_frame(bitmask: u8) -> Cow<'static, str> {
         const INTRA_FRAME: u8 = 1 << super::INTRA_FRAME;
         const LAST_FRAME: u8 = 1 << super::LAST_FRAME;
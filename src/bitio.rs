@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::io;
 
 /// numeric cast helper (u32 as T)
@@ -27,6 +28,39 @@ macro_rules! impl_from_u32 {
 
 impl_from_u32!(u8 u16 u32 u64 usize);
 
+///
+/// traced syntax element
+///
+#[derive(Debug, Clone)]
+pub struct TraceElement {
+    pub name: &'static str,       // syntax element name
+    pub descriptor: &'static str, // f/su/ns/uvlc
+    pub bit_pos: u64,             // bit position from beginning of reader
+    pub width: usize,             // number of bits
+    pub bits: u32,                // raw bits
+    pub value: i64,               // decoded value
+}
+
+thread_local! {
+    static TRACE: RefCell<Option<Vec<TraceElement>>> = const { RefCell::new(None) };
+}
+
+/// enable or disable syntax element trace on current thread
+pub fn set_trace(enable: bool) {
+    TRACE.with(|trace| *trace.borrow_mut() = if enable { Some(Vec::new()) } else { None });
+}
+
+/// take syntax elements traced since last call
+pub fn take_trace() -> Vec<TraceElement> {
+    TRACE.with(|trace| {
+        trace
+            .borrow_mut()
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    })
+}
+
 ///
 /// Bitwise reader
 ///
@@ -34,6 +68,8 @@ pub struct BitReader<R> {
     inner: R,
     bbuf: u8,
     bpos: u8,
+    pos: u64,                   // consumed bits
+    name: Option<&'static str>, // name of next syntax element
 }
 
 impl<R: io::Read> BitReader<R> {
@@ -42,9 +78,43 @@ impl<R: io::Read> BitReader<R> {
             inner,
             bbuf: 0,
             bpos: 0,
+            pos: 0,
+            name: None,
         }
     }
 
+    /// number of consumed bits
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// set syntax element name of next read for trace
+    pub fn name(&mut self, name: &'static str) -> &mut Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// record syntax element when trace is enabled
+    fn trace(&mut self, descriptor: &'static str, bit_pos: u64, bits: u32, value: i64) {
+        let name = match self.name.take() {
+            Some(name) => name,
+            None => return,
+        };
+        let width = (self.pos - bit_pos) as usize;
+        TRACE.with(|trace| {
+            if let Some(ref mut trace) = *trace.borrow_mut() {
+                trace.push(TraceElement {
+                    name,
+                    descriptor,
+                    bit_pos,
+                    width,
+                    bits,
+                    value,
+                });
+            }
+        });
+    }
+
     /// read_bit: read 1 bit
     pub fn read_bit(&mut self) -> Option<u8> {
         if self.bpos == 0 {
@@ -57,39 +127,249 @@ impl<R: io::Read> BitReader<R> {
             self.bpos = 8;
         }
         self.bpos -= 1;
+        self.pos += 1;
         Some((self.bbuf >> self.bpos) & 1)
     }
 
-    /// f(n): read n-bits
-    pub fn f<T: FromU32>(&mut self, nbit: usize) -> Option<T> {
+    // read n-bits without trace
+    fn read_bits(&mut self, nbit: usize) -> Option<u32> {
         assert!(nbit <= 32);
         let mut x: u32 = 0;
         for _ in 0..nbit {
             x = (x << 1) | self.read_bit()? as u32;
         }
+        Some(x)
+    }
+
+    /// f(n): read n-bits
+    pub fn f<T: FromU32>(&mut self, nbit: usize) -> Option<T> {
+        let bit_pos = self.pos;
+        let x = self.read_bits(nbit)?;
+        self.trace("f", bit_pos, x, x as i64);
         Some(FromU32::from_u32(x))
     }
 
     /// su(n)
     pub fn su(&mut self, n: usize) -> Option<i32> {
-        let mut value = self.f::<u32>(n)? as i32;
+        let bit_pos = self.pos;
+        let x = self.read_bits(n)?;
+        let mut value = x as i32;
         let sign_mask = 1 << (n - 1);
         if value & sign_mask != 0 {
             value -= 2 * sign_mask
         }
+        self.trace("su", bit_pos, x, value as i64);
         Some(value)
     }
 
     /// ns(n)
     pub fn ns(&mut self, n: u32) -> Option<u32> {
+        let bit_pos = self.pos;
         let w = Self::floor_log2(n) + 1;
         let m = (1 << w) - n;
-        let v = self.f::<u32>(w as usize - 1)?; // f(w - 1)
+        let v = self.read_bits(w as usize - 1)?; // f(w - 1)
         if v < m {
+            self.trace("ns", bit_pos, v, v as i64);
             return Some(v);
         }
-        let extra_bit = self.f::<u32>(1)?; // f(1)
-        Some((v << 1) - m + extra_bit)
+        let extra_bit = self.read_bits(1)?; // f(1)
+        let value = (v << 1) - m + extra_bit;
+        self.trace("ns", bit_pos, (v << 1) | extra_bit, value as i64);
+        Some(value)
+    }
+
+    /// uvlc()
+    pub fn uvlc(&mut self) -> Option<u32> {
+        let bit_pos = self.pos;
+        let mut leading_zeros = 0;
+        while self.read_bit()? == 0 {
+            leading_zeros += 1;
+        }
+        if leading_zeros >= 32 {
+            let value = u32::MAX; // (1 << 32) - 1
+            self.trace("uvlc", bit_pos, 1, value as i64);
+            return Some(value);
+        }
+        let x = self.read_bits(leading_zeros)?; // f(leadingZeros)
+        let value = (x as u64 + (1u64 << leading_zeros) - 1) as u32;
+        self.trace("uvlc", bit_pos, x, value as i64);
+        Some(value)
+    }
+
+    // FloorLog2(x)
+    fn floor_log2(mut x: u32) -> u32 {
+        let mut s = 0;
+        while x != 0 {
+            x >>= 1;
+            s += 1;
+        }
+        s - 1
+    }
+}
+
+/// error for value which cannot be written as syntax element descriptor
+fn exceeds(descriptor: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("value exceeds {}", descriptor),
+    )
+}
+
+///
+/// Bitwise writer
+///
+pub struct BitWriter<W> {
+    inner: W,
+    bbuf: u8,
+    bpos: u8, // number of pending bits in bbuf
+    pos: u64, // written bits
+}
+
+impl<W: io::Write> BitWriter<W> {
+    pub fn new(inner: W) -> BitWriter<W> {
+        BitWriter {
+            inner,
+            bbuf: 0,
+            bpos: 0,
+            pos: 0,
+        }
+    }
+
+    /// number of written bits
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// check writer is on byte boundary
+    pub fn is_aligned(&self) -> bool {
+        self.bpos == 0
+    }
+
+    /// unwrap inner writer, all bits must be flushed by byte_alignment() or trailing_bits()
+    pub fn into_inner(self) -> io::Result<W> {
+        if !self.is_aligned() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "BitWriter is not byte aligned",
+            ));
+        }
+        Ok(self.inner)
+    }
+
+    /// write_bit: write 1 bit
+    pub fn write_bit(&mut self, bit: u8) -> io::Result<()> {
+        self.bbuf = (self.bbuf << 1) | (bit & 1);
+        self.bpos += 1;
+        self.pos += 1;
+        if self.bpos == 8 {
+            self.inner.write_all(&[self.bbuf])?;
+            self.bbuf = 0;
+            self.bpos = 0;
+        }
+        Ok(())
+    }
+
+    // write n-bits of x from MSB
+    fn write_bits(&mut self, x: u32, nbit: usize) -> io::Result<()> {
+        if nbit > 32 {
+            return Err(exceeds(format!("f({})", nbit)));
+        }
+        for i in (0..nbit).rev() {
+            self.write_bit((x >> i) as u8 & 1)?;
+        }
+        Ok(())
+    }
+
+    /// f(n): write n-bits
+    pub fn f<T: Into<u32>>(&mut self, nbit: usize, value: T) -> io::Result<()> {
+        let x = value.into();
+        if nbit < 32 && x >> nbit != 0 {
+            return Err(exceeds(format!("f({})", nbit)));
+        }
+        self.write_bits(x, nbit)
+    }
+
+    /// su(n)
+    pub fn su(&mut self, n: usize, value: i32) -> io::Result<()> {
+        if n == 0 || n > 32 {
+            return Err(exceeds(format!("su({})", n)));
+        }
+        let sign_mask = 1i64 << (n - 1);
+        if (value as i64) < -sign_mask || sign_mask <= value as i64 {
+            return Err(exceeds(format!("su({})", n)));
+        }
+        let x = (value as i64 & ((sign_mask << 1) - 1)) as u32;
+        self.write_bits(x, n)
+    }
+
+    /// ns(n)
+    pub fn ns(&mut self, n: u32, value: u32) -> io::Result<()> {
+        if value >= n {
+            return Err(exceeds(format!("ns({})", n)));
+        }
+        let w = Self::floor_log2(n) + 1;
+        let m = (1 << w) - n;
+        if value < m {
+            return self.write_bits(value, w as usize - 1); // f(w - 1)
+        }
+        let v = value + m;
+        self.write_bits(v >> 1, w as usize - 1)?; // f(w - 1)
+        self.write_bits(v & 1, 1) // extra_bit f(1)
+    }
+
+    /// uvlc()
+    pub fn uvlc(&mut self, value: u32) -> io::Result<()> {
+        let x = value as u64 + 1;
+        let leading_zeros = 63 - x.leading_zeros() as usize;
+        for _ in 0..leading_zeros {
+            self.write_bit(0)?; // leadingZero
+        }
+        self.write_bit(1)?; // done
+        if leading_zeros >= 32 {
+            return Ok(()); // (1 << 32) - 1
+        }
+        let rest = x - (1 << leading_zeros);
+        for i in (0..leading_zeros).rev() {
+            self.write_bit((rest >> i) as u8 & 1)?; // f(leadingZeros)
+        }
+        Ok(())
+    }
+
+    /// le(n): write n-bytes in little-endian
+    pub fn le(&mut self, n: usize, value: u64) -> io::Result<()> {
+        if n > 8 || (n < 8 && value >> (n * 8) != 0) {
+            return Err(exceeds(format!("le({})", n)));
+        }
+        for i in 0..n {
+            self.write_bits((value >> (i * 8)) as u32 & 0xff, 8)?; // f(8)
+        }
+        Ok(())
+    }
+
+    /// leb128(): write value in minimum number of bytes
+    pub fn leb128(&mut self, mut value: u32) -> io::Result<()> {
+        loop {
+            let byte = value & 0x7f;
+            value >>= 7;
+            if value == 0 {
+                return self.write_bits(byte, 8); // f(8)
+            }
+            self.write_bits(byte | 0x80, 8)?; // f(8)
+        }
+    }
+
+    /// byte_alignment(): write zero bits up to byte boundary
+    pub fn byte_alignment(&mut self) -> io::Result<()> {
+        while !self.is_aligned() {
+            self.write_bit(0)?; // zero_bit f(1)
+        }
+        Ok(())
+    }
+
+    /// trailing_bits(): write trailing_one_bit and zero bits up to byte boundary
+    pub fn trailing_bits(&mut self) -> io::Result<()> {
+        self.write_bit(1)?; // trailing_one_bit f(1)
+        self.byte_alignment() // trailing_zero_bit f(1)
     }
 
     // FloorLog2(x)
@@ -102,3 +382,35 @@ impl<R: io::Read> BitReader<R> {
         s - 1
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_out_of_range() {
+        let mut bw = BitWriter::new(Vec::new());
+        let invalid =
+            |result: io::Result<()>| result.unwrap_err().kind() == io::ErrorKind::InvalidInput;
+        assert!(invalid(bw.f(3, 8u32)));
+        assert!(invalid(bw.f(33, 0u32)));
+        assert!(invalid(bw.su(0, 0)));
+        assert!(invalid(bw.su(4, 8)));
+        assert!(invalid(bw.su(4, -9)));
+        assert!(invalid(bw.ns(0, 0)));
+        assert!(invalid(bw.ns(5, 5)));
+        assert!(invalid(bw.le(2, 0x10000)));
+        assert!(invalid(bw.le(9, 0)));
+        // nothing is written by rejected values
+        assert_eq!(bw.position(), 0);
+        bw.su(4, -8).unwrap();
+        bw.su(4, 7).unwrap();
+        bw.ns(5, 1).unwrap();
+        bw.f(32, u32::MAX).unwrap();
+        bw.trailing_bits().unwrap();
+        assert_eq!(
+            bw.into_inner().unwrap(),
+            [0x87, 0x7f, 0xff, 0xff, 0xff, 0xe0]
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//
+// Compressed input (gzip/zstd)
+//
+use super::FileFormat;
+use std::io;
+
+pub const GZIP_SIGNATURE: [u8; 2] = [0x1f, 0x8b]; // RFC 1952
+pub const ZSTD_SIGNATURE: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd]; // RFC 8878
+
+///
+/// decompress whole gzip/zstd-compressed input into memory
+///
+pub fn decompress<R: io::Read>(reader: R, format: FileFormat) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    match format {
+        FileFormat::Gzip => decompress_gzip(reader, &mut buf)?,
+        FileFormat::Zstd => decompress_zstd(reader, &mut buf)?,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not compressed input",
+            ))
+        }
+    };
+    Ok(buf)
+}
+
+#[cfg(feature = "gzip")]
+fn decompress_gzip<R: io::Read>(reader: R, buf: &mut Vec<u8>) -> io::Result<usize> {
+    // concatenated gzip members are decoded as single stream
+    io::Read::read_to_end(&mut flate2::read::MultiGzDecoder::new(reader), buf)
+}
+
+#[cfg(not(feature = "gzip"))]
+fn decompress_gzip<R: io::Read>(_reader: R, _buf: &mut Vec<u8>) -> io::Result<usize> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "gzip-compressed input requires \"gzip\" feature",
+    ))
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd<R: io::Read>(reader: R, buf: &mut Vec<u8>) -> io::Result<usize> {
+    io::Read::read_to_end(&mut zstd::stream::read::Decoder::new(reader)?, buf)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd<R: io::Read>(_reader: R, _buf: &mut Vec<u8>) -> io::Result<usize> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "zstd-compressed input requires \"zstd\" feature",
+    ))
+}
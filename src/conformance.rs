@@ -0,0 +1,97 @@
+//
+// bitstream conformance requirements
+//
+use std::cell::RefCell;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,   // violation of bitstream conformance requirement
+    Warning, // value which shall be ignored by decoder, or container inconsistency
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+///
+/// conformance rule
+///
+#[derive(Debug)]
+pub struct Rule {
+    pub id: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+}
+
+macro_rules! rules {
+    ($($name:ident = ($id:expr, $severity:ident, $description:expr);)*) => {
+        $(
+            pub const $name: Rule = Rule {
+                id: $id,
+                severity: Severity::$severity,
+                description: $description,
+            };
+        )*
+    }
+}
+
+rules! {
+    // OBU syntax
+    OBU_RESERVED_BITS = ("OBU01", Warning, "obu_reserved_1bit and extension_header_reserved_3bits are equal to 0");
+    LEB128_RANGE = ("OBU02", Error, "leb128() value is less than or equal to (1 << 32) - 1");
+    OBU_PAYLOAD = ("OBU03", Error, "OBU payload is well-formed");
+    // sequence header
+    SEQ_PROFILE = ("SH01", Error, "seq_profile is less than or equal to 2");
+    REDUCED_STILL_PICTURE = ("SH02", Error, "reduced_still_picture_header is 0 when still_picture is 0");
+    FRAME_ID_LENGTH = ("SH03", Error, "additional_frame_id_length_minus_1 + delta_frame_id_length_minus_2 + 3 is less than or equal to 16");
+    // frame header
+    NO_SEQUENCE_HEADER = ("FH01", Error, "sequence header precedes frame header");
+    REF_VALID = ("FH02", Error, "RefValid[ref_frame_idx[i]] is equal to 1");
+    EXPECTED_FRAME_ID = ("FH03", Error, "expectedFrameId[i] matches RefFrameId[ref_frame_idx[i]]");
+    // film grain params
+    FILM_GRAIN_REF_IDX = ("FG01", Error, "film_grain_params_ref_idx is equal to ref_frame_idx[j] for some j");
+    FILM_GRAIN_NUM_POINTS = ("FG02", Error, "num_y_points is at most 14, num_cb_points and num_cr_points are at most 10");
+    FILM_GRAIN_POINT_VALUE = ("FG03", Error, "point_y_value/point_cb_value/point_cr_value are increasing");
+    // metadata
+    HDR10PLUS_MISSING = ("MD01", Error, "each shown frame carries ST 2094-40 metadata when HDR10+ is used");
+    HDR10PLUS_DUPLICATE = ("MD02", Error, "each shown frame carries at most one ST 2094-40 metadata");
+    // timestamps
+    PTS_MONOTONIC = ("TS01", Warning, "container timestamps of shown frames are strictly increasing");
+    FRAME_RATE = ("TS02", Warning, "container frame interval matches timing_info");
+    PTS_GAP = ("TS03", Warning, "container timestamps have no gap or drift from frame interval");
+    SHOW_EXISTING_PTS = ("TS04", Warning, "show_existing_frame is presented at container timestamp of its order_hint");
+    // stream and container
+    NO_FRAMES = ("ST01", Error, "stream contains frames");
+    NO_KEY_FRAME = ("ST02", Error, "stream contains key frame");
+    CODEC_STRING = ("ST03", Error, "declared codec parameter string matches sequence header");
+    LEVEL_LIMITS = ("ST04", Error, "stream conforms to limits of signaled seq_level_idx and seq_tier");
+}
+
+///
+/// detected violation of rule
+///
+#[derive(Debug)]
+pub struct Finding {
+    pub rule: &'static Rule,
+    pub message: String,
+}
+
+thread_local! {
+    static FINDINGS: RefCell<Vec<Finding>> = const { RefCell::new(Vec::new()) };
+}
+
+/// record finding on current thread
+pub fn report(rule: &'static Rule, message: String) {
+    FINDINGS.with(|findings| findings.borrow_mut().push(Finding { rule, message }));
+}
+
+/// take findings recorded since last call
+pub fn take_findings() -> Vec<Finding> {
+    FINDINGS.with(|findings| std::mem::take(&mut *findings.borrow_mut()))
+}
@@ -3,9 +3,11 @@
 //
 use byteorder::{ByteOrder, LittleEndian};
 use hex;
+use std::io;
 use std::io::Read;
 
 pub const IVF_HEADER_SIZE: usize = 32;
+pub const IVF_FRAME_HEADER_SIZE: usize = 4 + 8; // frame size + pts
 pub const IVF_SIGNATURE: [u8; 4] = *b"DKIF";
 pub const IVF_VERSION: u16 = 0;
 
@@ -19,7 +21,8 @@ pub struct IvfHeader {
     pub height: u16,    // [pel]
     pub timescale_num: u32,
     pub timescale_den: u32,
-    pub length: u32, // nframes in libvpx, duration in ffmpeg
+    pub length: u32,     // nframes in libvpx, duration in ffmpeg
+    pub header_len: u16, // [byte], may exceed IVF_HEADER_SIZE in extended header
 }
 
 ///
@@ -56,7 +59,7 @@ pub fn parse_ivf_header(mut ivf: &[u8]) -> Result<IvfHeader, String> {
     let mut hdrlen = [0; 2];
     ivf.read_exact(&mut hdrlen).unwrap();
     let hdrlen = LittleEndian::read_u16(&hdrlen);
-    if hdrlen != IVF_HEADER_SIZE as u16 {
+    if hdrlen < IVF_HEADER_SIZE as u16 {
         return Err(format!("Invalid IVF header length({})", hdrlen));
     }
     // codec (4b)
@@ -88,6 +91,7 @@ pub fn parse_ivf_header(mut ivf: &[u8]) -> Result<IvfHeader, String> {
         timescale_num,
         timescale_den,
         length,
+        header_len: hdrlen,
     })
 }
 
@@ -95,7 +99,7 @@ pub fn parse_ivf_header(mut ivf: &[u8]) -> Result<IvfHeader, String> {
 /// parse IVF frame header
 ///
 pub fn parse_ivf_frame<R: Read>(bs: &mut R) -> Result<IvfFrame, String> {
-    let mut hdr = [0; 4 + 8];
+    let mut hdr = [0; IVF_FRAME_HEADER_SIZE];
     match bs.read_exact(&mut hdr) {
         Ok(_) => (),
         Err(_) => return Err("IO error".to_owned()),
@@ -106,3 +110,30 @@ pub fn parse_ivf_frame<R: Read>(bs: &mut R) -> Result<IvfFrame, String> {
         pts: LittleEndian::read_u64(&hdr[4..]),   // presentation timestamp (8b)
     })
 }
+
+///
+/// write IVF file header (always IVF_HEADER_SIZE bytes)
+///
+pub fn write_ivf_header<W: io::Write>(mut writer: W, hdr: &IvfHeader) -> io::Result<()> {
+    let mut buf = [0; IVF_HEADER_SIZE];
+    buf[0..4].copy_from_slice(&IVF_SIGNATURE); // signature (4b)
+    LittleEndian::write_u16(&mut buf[4..6], IVF_VERSION); // version (2b)
+    LittleEndian::write_u16(&mut buf[6..8], IVF_HEADER_SIZE as u16); // header length (2b)
+    buf[8..12].copy_from_slice(&hdr.codec); // codec (4b)
+    LittleEndian::write_u16(&mut buf[12..14], hdr.width); // width (2b)
+    LittleEndian::write_u16(&mut buf[14..16], hdr.height); // height (2b)
+    LittleEndian::write_u32(&mut buf[16..20], hdr.timescale_num); // timescale_num (4b)
+    LittleEndian::write_u32(&mut buf[20..24], hdr.timescale_den); // timescale_den (4b)
+    LittleEndian::write_u32(&mut buf[24..28], hdr.length); // length (4b)
+    writer.write_all(&buf)
+}
+
+///
+/// write IVF frame header
+///
+pub fn write_ivf_frame<W: io::Write>(mut writer: W, frame: &IvfFrame) -> io::Result<()> {
+    let mut hdr = [0; IVF_FRAME_HEADER_SIZE];
+    LittleEndian::write_u32(&mut hdr[0..4], frame.size); // frame size (4b)
+    LittleEndian::write_u64(&mut hdr[4..], frame.pts); // presentation timestamp (8b)
+    writer.write_all(&hdr)
+}
@@ -1,39 +1,288 @@
+// syntax parsing code mirrors the pseudocode in AV1 specification
+#![allow(
+    clippy::collapsible_else_if,
+    clippy::collapsible_if,
+    clippy::field_reassign_with_default,
+    clippy::needless_late_init
+)]
+
 extern crate byteorder;
+#[cfg(feature = "gzip")]
+extern crate flate2;
 extern crate hex;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "zstd")]
+extern crate zstd;
 
 pub mod av1;
-mod bitio;
+pub mod bitio;
+pub mod compress;
+pub mod conformance;
 pub mod ivf;
 pub mod mkv;
 pub mod mp4;
 pub mod obu;
+pub mod playlist;
+pub mod rtp;
+pub mod stream;
+pub mod ts;
 
 use std::io;
+use std::io::Read;
 
 pub const FCC_AV01: [u8; 4] = *b"AV01"; // AV1 codec
 const WEBM_SIGNATURE: [u8; 4] = [0x1A, 0x45, 0xDF, 0xA3]; // EBML(Matroska/WebM)
+pub const PROBE_SIZE: usize = 4096; // peeked bytes for format probing
+const PROBE_TS_PACKETS: usize = 4;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FileFormat {
     IVF,       // IVF format
     WebM,      // Matroska/WebM format
     MP4,       // ISOBMFF/MP4 format
+    AVIF,      // AVIF image format
     Bitstream, // Raw bitstream
+    AnnexB,    // Length delimited bitstream (AV1 Annex B)
+    MPEGTS,    // MPEG-2 Transport Stream
+    RtpDump,   // RTP packets in rtpdump format
+    Gzip,      // gzip-compressed input
+    Zstd,      // zstd-compressed input
 }
 
-/// probe file format
-pub fn probe_fileformat<R: io::Read>(reader: &mut R) -> io::Result<FileFormat> {
-    let mut b4 = [0; 4];
-    reader.read_exact(&mut b4)?;
-    let fmt = match b4 {
-        ivf::IVF_SIGNATURE => FileFormat::IVF,
-        WEBM_SIGNATURE => FileFormat::WebM,
-        _ => {
-            reader.read_exact(&mut b4)?;
-            match b4 {
-                mp4::BOX_FILETYPE => FileFormat::MP4,
-                _ => FileFormat::Bitstream,
-            }
+impl std::str::FromStr for FileFormat {
+    type Err = String;
+
+    /// parse format name (ivf, webm, mp4, avif, obu, annexb, ts, rtpdump)
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "ivf" => Ok(FileFormat::IVF),
+            "webm" | "mkv" => Ok(FileFormat::WebM),
+            "mp4" => Ok(FileFormat::MP4),
+            "avif" => Ok(FileFormat::AVIF),
+            "obu" => Ok(FileFormat::Bitstream),
+            "annexb" => Ok(FileFormat::AnnexB),
+            "ts" => Ok(FileFormat::MPEGTS),
+            "rtpdump" => Ok(FileFormat::RtpDump),
+            _ => Err(format!("unknown format name '{}'", name)),
         }
+    }
+}
+
+/// check first temporal_unit() starts with TemporalDelimiter OBU in Annex B format
+fn probe_annexb(buf: &[u8]) -> Option<bool> {
+    // Section 5 low overhead bitstream starts with TemporalDelimiter OBU (obu_size=0)
+    if buf.get(..2)? == stream::TEMPORAL_DELIMITER_OBU {
+        return Some(false);
+    }
+    // leb128() at pos, return (value, next position)
+    let read_leb128 = |pos: usize| {
+        let (len, value) = obu::read_leb128(&mut buf.get(pos..)?).ok()?;
+        Some((value, pos + len as usize))
     };
-    Ok(fmt)
+    let (temporal_unit_size, tu_start) = read_leb128(0)?;
+    let (frame_unit_size, pos) = read_leb128(tu_start)?;
+    let (obu_length, pos) = read_leb128(pos)?;
+    let obu_type = (buf.get(pos)? >> 3) & 0b1111;
+    if !(0 < obu_length
+        && obu_length <= frame_unit_size
+        && frame_unit_size < temporal_unit_size
+        && obu_type == obu::OBU_TEMPORAL_DELIMITER)
+    {
+        return Some(false);
+    }
+    // frame_unit()s fill temporal_unit() exactly, if whole temporal_unit() is peeked
+    let tu_end = tu_start + temporal_unit_size as usize;
+    if tu_end <= buf.len() {
+        let mut pos = tu_start;
+        while pos < tu_end {
+            let (frame_unit_size, next) = read_leb128(pos)?;
+            pos = next + frame_unit_size as usize;
+        }
+        return Some(pos == tu_end);
+    }
+    Some(true)
+}
+
+/// check top-level Box structure of ISOBMFF, return MP4 or AVIF
+fn probe_isobmff(buf: &[u8]) -> Option<FileFormat> {
+    let mut fmt = None;
+    let mut pos = 0;
+    // walk top-level Boxes within peeked bytes
+    while pos + 8 <= buf.len() {
+        let size =
+            u32::from_be_bytes([buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]]) as usize;
+        let boxtype = [buf[pos + 4], buf[pos + 5], buf[pos + 6], buf[pos + 7]];
+        if !mp4::TOPLEVEL_BOXES.contains(&boxtype) {
+            // unknown Box type following valid Box is acceptable
+            return fmt;
+        }
+        if boxtype == mp4::BOX_FILETYPE && buf.get(pos + 8..pos + 12) == Some(&mp4::BRAND_AVIF) {
+            fmt = Some(FileFormat::AVIF);
+        } else if fmt.is_none() {
+            fmt = Some(FileFormat::MP4);
+        }
+        match size {
+            0 | 1 => break, // extends to end of file, or largesize
+            _ if size < 8 => return None,
+            _ => pos += size,
+        }
+    }
+    fmt
+}
+
+/// check sync_byte of consecutive TS packets
+fn probe_mpegts(buf: &[u8]) -> bool {
+    if buf.len() < 4 || buf[1] & 0x80 != 0 || buf[3] & 0x30 == 0 {
+        // transport_error_indicator=1 or adaptation_field_control=0b00
+        return false;
+    }
+    buf.iter()
+        .step_by(ts::TS_PACKET_SIZE)
+        .take(PROBE_TS_PACKETS)
+        .all(|&b| b == ts::TS_SYNC_BYTE)
+}
+
+/// probe file format from leading bytes of input
+pub fn probe_buffer(buf: &[u8]) -> FileFormat {
+    // stage 1: fixed signatures
+    if buf.starts_with(&ivf::IVF_SIGNATURE) {
+        FileFormat::IVF
+    } else if buf.starts_with(&WEBM_SIGNATURE) {
+        FileFormat::WebM
+    } else if buf.starts_with(&rtp::RTPDUMP_SIGNATURE) {
+        FileFormat::RtpDump
+    } else if buf.starts_with(&compress::ZSTD_SIGNATURE) {
+        FileFormat::Zstd
+    } else if buf.starts_with(&compress::GZIP_SIGNATURE) {
+        FileFormat::Gzip
+    } else if let Some(fmt) = probe_isobmff(buf) {
+        // stage 2: Box structure
+        fmt
+    } else if probe_mpegts(buf) {
+        // stage 3: periodic sync_byte
+        FileFormat::MPEGTS
+    } else if probe_annexb(buf) == Some(true) {
+        // stage 4: size fields of temporal_unit()/frame_unit()
+        FileFormat::AnnexB
+    } else {
+        FileFormat::Bitstream
+    }
+}
+
+/// guess file format from file extension, ".obu" is ambiguous (raw bitstream or Annex B)
+pub fn format_from_extension(path: &str) -> Option<FileFormat> {
+    let ext = std::path::Path::new(path)
+        .extension()?
+        .to_str()?
+        .to_lowercase();
+    match ext.as_str() {
+        "ivf" => Some(FileFormat::IVF),
+        "webm" | "mkv" => Some(FileFormat::WebM),
+        "mp4" | "m4s" | "mov" => Some(FileFormat::MP4),
+        "avif" => Some(FileFormat::AVIF),
+        "av1" => Some(FileFormat::Bitstream),
+        "annexb" => Some(FileFormat::AnnexB),
+        "ts" | "m2ts" => Some(FileFormat::MPEGTS),
+        "rtpdump" => Some(FileFormat::RtpDump),
+        _ => None,
+    }
+}
+
+///
+/// probe file format with file extension as fallback hint,
+/// heuristic results (Annex B, MPEG-2 TS, raw bitstream) yield to the hint
+///
+pub fn probe_fileformat_with_hint<R: io::Read + io::Seek>(
+    reader: &mut R,
+    path: &str,
+) -> io::Result<FileFormat> {
+    let fmt = peek_fileformat(reader)?;
+    match fmt {
+        FileFormat::AnnexB | FileFormat::MPEGTS | FileFormat::Bitstream => {
+            Ok(format_from_extension(path).unwrap_or(fmt))
+        }
+        _ => Ok(fmt),
+    }
+}
+
+/// probe file format, consume up to PROBE_SIZE bytes from reader
+pub fn probe_fileformat<R: io::Read>(reader: &mut R) -> io::Result<FileFormat> {
+    let mut buf = Vec::with_capacity(PROBE_SIZE);
+    reader.take(PROBE_SIZE as u64).read_to_end(&mut buf)?;
+    if buf.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "empty input"));
+    }
+    Ok(probe_buffer(&buf))
+}
+
+/// probe file format, reader position is left untouched
+pub fn peek_fileformat<R: io::Read + io::Seek>(reader: &mut R) -> io::Result<FileFormat> {
+    let pos = reader.stream_position()?;
+    let fmt = probe_fileformat(reader);
+    reader.seek(io::SeekFrom::Start(pos))?;
+    fmt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_signatures() {
+        assert_eq!(probe_buffer(b"DKIF\x00\x00\x20\x00AV01"), FileFormat::IVF);
+        assert_eq!(
+            probe_buffer(&[0x1A, 0x45, 0xDF, 0xA3, 0x9F]),
+            FileFormat::WebM
+        );
+        assert_eq!(probe_buffer(b"#!rtpplay1.0 "), FileFormat::RtpDump);
+        let ftyp = |brand: &[u8; 4]| [&[0, 0, 0, 16][..], b"ftyp", brand, &[0; 4]].concat();
+        assert_eq!(probe_buffer(&ftyp(b"isom")), FileFormat::MP4);
+        assert_eq!(probe_buffer(&ftyp(b"avif")), FileFormat::AVIF);
+    }
+
+    #[test]
+    fn probe_heuristics() {
+        // sync_byte in consecutive TS packets
+        let mut ts = vec![0; ts::TS_PACKET_SIZE * PROBE_TS_PACKETS];
+        for packet in ts.chunks_mut(ts::TS_PACKET_SIZE) {
+            packet[..4].copy_from_slice(&[ts::TS_SYNC_BYTE, 0x40, 0x00, 0x10]);
+        }
+        assert_eq!(probe_buffer(&ts), FileFormat::MPEGTS);
+        ts[ts::TS_PACKET_SIZE] = 0;
+        assert_ne!(probe_buffer(&ts), FileFormat::MPEGTS);
+        // temporal_unit(6) = frame_unit(5) = { obu(1): TemporalDelimiter, obu(2) }
+        let annexb = [0x06, 0x05, 0x01, 0x10, 0x02, 0x18, 0x00];
+        assert_eq!(probe_buffer(&annexb), FileFormat::AnnexB);
+        // frame_unit()s do not fill temporal_unit()
+        assert_eq!(
+            probe_buffer(&[0x07, 0x05, 0x01, 0x10, 0x02, 0x18, 0x00, 0x03]),
+            FileFormat::Bitstream
+        );
+        assert_eq!(
+            probe_buffer(&stream::TEMPORAL_DELIMITER_OBU),
+            FileFormat::Bitstream
+        );
+    }
+
+    #[test]
+    fn probe_reader_position() {
+        let data = b"DKIF\x00\x00\x20\x00AV01";
+        let mut reader = io::Cursor::new(&data[..]);
+        reader.set_position(4);
+        assert_eq!(
+            peek_fileformat(&mut io::Cursor::new(&data[..])).unwrap(),
+            FileFormat::IVF
+        );
+        // peek_fileformat() probes from current position and leaves it untouched
+        assert_eq!(peek_fileformat(&mut reader).unwrap(), FileFormat::Bitstream);
+        assert_eq!(reader.position(), 4);
+        // probe_fileformat() consumes probed bytes
+        let mut reader = &data[..];
+        assert_eq!(probe_fileformat(&mut reader).unwrap(), FileFormat::IVF);
+        assert!(reader.is_empty());
+        let err = probe_fileformat(&mut reader).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
 }
@@ -2,366 +2,4390 @@ extern crate av1parser;
 extern crate byteorder;
 #[macro_use]
 extern crate clap;
+extern crate glob;
 extern crate hex;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate serde_yaml;
+
+use av1parser::*;
+use clap::{App, AppSettings, Arg, SubCommand};
+use serde::Serialize;
+use std::cell;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::{Read, SeekFrom, Write};
+use std::mem;
+use std::path;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time;
+use tools::diff::DiffFrame;
+use tools::{
+    av1c, convert, diff, grain, hdr10plus, level, mux, repair, rewrite, scan, split, tracks,
+};
+
+// exit status of analysis (0 = no problem)
+const EXIT_PARSE_ERROR: i32 = 1; // malformed input or I/O error
+const EXIT_VIOLATION: i32 = 2; // conformance problems found
+const EXIT_UNSUPPORTED: i32 = 3; // unsupported format or codec
+
+// polling interval for growing file/segments
+const FOLLOW_INTERVAL: time::Duration = time::Duration::from_millis(500);
+
+// text log is written into stderr while stdout carries NDJSON/CSV/YAML records
+static TEXT_LOG_STDERR: AtomicBool = AtomicBool::new(false);
+
+// suppress all report and log output (--quiet)
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// report file of current input (--output), otherwise report is written into stdout
+static REPORT_FILE: Mutex<Option<io::LineWriter<fs::File>>> = Mutex::new(None);
+
+/// print report line into stdout (or report file)
+macro_rules! outln {
+    ($($arg:tt)*) => {
+        if !$crate::QUIET.load(::std::sync::atomic::Ordering::Relaxed) {
+            match $crate::REPORT_FILE.lock().unwrap().as_mut() {
+                Some(file) => {
+                    use std::io::Write;
+                    writeln!(file, $($arg)*).expect("failed writing report file")
+                }
+                None => println!($($arg)*),
+            }
+        }
+    };
+}
+
+/// print text log line into report (or stderr in NDJSON/CSV/YAML output)
+macro_rules! logln {
+    ($($arg:tt)*) => {
+        if $crate::TEXT_LOG_STDERR.load(::std::sync::atomic::Ordering::Relaxed) {
+            if !$crate::QUIET.load(::std::sync::atomic::Ordering::Relaxed) {
+                eprintln!($($arg)*);
+            }
+        } else {
+            outln!($($arg)*);
+        }
+    };
+}
+
+mod tools;
+
+/// per-frame output format
+#[derive(Clone, Copy, PartialEq)]
+enum FrameOutput {
+    Text,
+    Ndjson, // JSON Lines
+    Csv,
+    Yaml,        // YAML sequence
+    AomAnalyzer, // JSON array for AOM analyzer
+    Ffprobe,     // ffprobe-style sections
+}
+
+/// ffprobe-style output options
+#[derive(Clone, Copy)]
+struct FfprobeOptions {
+    show_streams: bool,
+    show_frames: bool,
+    compact: bool, // compact format, otherwise default format
+}
+
+/// seek target of --seek option
+#[derive(Clone, Copy)]
+enum SeekTarget {
+    Time(u64),  // timestamp [msec]
+    Frame(u64), // frame index in container
+}
+
+impl std::str::FromStr for SeekTarget {
+    type Err = String;
+
+    /// parse "#N" frame index, "[HH:]MM:SS[.sss]" timestamp or milliseconds
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(index) = value.strip_prefix('#') {
+            return index
+                .parse()
+                .map(SeekTarget::Frame)
+                .map_err(|_| format!("invalid frame index '{}'", value));
+        }
+        if !value.contains(':') {
+            return value
+                .parse()
+                .map(SeekTarget::Time)
+                .map_err(|_| format!("invalid timestamp '{}'", value));
+        }
+        let parts: Vec<&str> = value.split(':').collect();
+        let mut sec = 0.0;
+        for part in parts.iter() {
+            match part.parse::<f64>() {
+                Ok(v) if parts.len() <= 3 && v >= 0.0 => sec = sec * 60.0 + v,
+                _ => return Err(format!("invalid timestamp '{}'", value)),
+            }
+        }
+        Ok(SeekTarget::Time((sec * 1000.0).round() as u64))
+    }
+}
+
+/// header sections printed in verbose log (--show)
+#[derive(Clone, Copy, Default)]
+struct ShowSections {
+    sh: bool,       // SequenceHeader
+    fh: bool,       // FrameHeader
+    gm: bool,       // global_motion_params()
+    fg: bool,       // film_grain_params()
+    metadata: bool, // MetadataObu
+    refs: bool,     // reference frame manager
+    tiles: bool,    // tile configuration
+    superres: bool, // coded and upscaled frame size
+}
+
+impl ShowSections {
+    /// sections enabled by verbose level (-v, -vv, -vvv)
+    fn from_verbose(verbose: u64) -> Self {
+        ShowSections {
+            sh: verbose > 1,
+            fh: verbose > 1,
+            gm: false,
+            fg: false,
+            metadata: verbose > 1,
+            refs: verbose > 2,
+            tiles: false,
+            superres: verbose > 0,
+        }
+    }
+}
+
+/// per-frame output filter
+#[derive(Clone, Copy, Default)]
+struct FrameFilter {
+    only_keyframes: bool,
+    only_shown: bool,
+    frame_types: Option<u8>, // bitmask of frame_type
+}
+
+impl FrameFilter {
+    /// check frame is written in per-frame output
+    fn matches(&self, fh: &obu::FrameHeader) -> bool {
+        (!self.only_keyframes || (fh.frame_type == obu::KEY_FRAME && !fh.show_existing_frame))
+            && (!self.only_shown || fh.show_frame || fh.show_existing_frame)
+            && self
+                .frame_types
+                .is_none_or(|mask| mask & (1 << (fh.frame_type & 0x3)) != 0)
+    }
+}
+
+/// table export format
+#[derive(Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Csv,
+    Json,
+    Yaml,
+}
+
+/// analysis subcommand
+#[derive(Clone, Copy, PartialEq)]
+enum Command {
+    Info,
+    Frames,
+    Obus,
+    Stats,
+    Check,
+    Index,
+    Summary,
+    Bitrate,
+    Diff, // frames are collected for comparison
+}
+
+/// size of coded frame (Frame or FrameHeader OBU with following TileGroup OBUs)
+struct FrameSize {
+    frame_type: u8,
+    temporal_id: u8,
+    show_frame: bool,
+    bytes: u64,
+}
+
+/// container timestamp of shown frame for --validate-timestamps
+struct ShownTimestamp {
+    pts: Option<i64>,
+    order_hint: u8, // OrderHint of shown frame
+    key_frame: bool,
+    show_existing_frame: bool,
+}
+
+/// histograms of CDEF and loop restoration params
+#[derive(Default)]
+struct FilterStats {
+    cdef_damping: BTreeMap<u8, u64>,
+    cdef_bits: BTreeMap<u8, u64>,
+    cdef_strength: [BTreeMap<u8, u64>; 4], // y_pri, y_sec, uv_pri, uv_sec
+    lr_type: [BTreeMap<u8, u64>; 3],       // FrameRestorationType per plane
+    lr_unit_size: [BTreeMap<u16, u64>; 3], // LoopRestorationSize per plane
+}
+
+/// histograms of coding tools selected in frame header
+#[derive(Default)]
+struct ToolStats {
+    frames: u64,
+    inter_frames: u64,
+    interpolation_filter: BTreeMap<u8, u64>, // inter frames per interpolation_filter
+    tx_mode: BTreeMap<u8, u64>,              // frames per TxMode
+    reduced_tx_set: u64,                     // frames with reduced_tx_set
+    reference_select: u64,                   // inter frames with reference_select
+}
+
+/// HDR static and dynamic metadata collected across stream
+#[derive(Default)]
+struct HdrStats {
+    cll: Option<obu::HdrCllMetadata>,   // first HDR CLL metadata
+    cll_count: u64,                     // number of HDR CLL metadata OBUs
+    cll_varies: bool,                   // HDR CLL metadata with different values
+    mdcv: Option<obu::HdrMdcvMetadata>, // first HDR MDCV metadata
+    mdcv_count: u64,                    // number of HDR MDCV metadata OBUs
+    mdcv_varies: bool,                  // HDR MDCV metadata with different values
+    hdr10plus_count: u64,               // number of ST 2094-40 metadata OBUs
+    hdr10plus_target_max: u32,          // max targeted_system_display_maximum_luminance [cd/m2]
+    hdr10plus_maxscl: u32,              // max maxscl of all windows [0.1 cd/m2]
+    t35_other: u64,                     // number of other ITU-T T.35 metadata OBUs
+}
+
+/// bytes spent on OBU framing instead of payload
+#[derive(Default)]
+struct FramingBytes {
+    obu_headers: u64,
+    extension_headers: u64,
+    size_fields: u64,
+    temporal_delimiters: u64,
+    redundant_frame_headers: u64,
+}
+
+///
+/// per-file statistics for info/stats/check subcommands
+///
+#[derive(Default)]
+struct FrameStats {
+    obu_count: [u64; 16],  // number of OBUs per obu_type
+    obu_bytes: [u64; 16],  // total OBU size per obu_type
+    frame_count: [u64; 4], // number of frames per frame_type
+    shown_frames: u64,
+    frame_headers: u64, // number of parsed frame headers
+    sequence_headers: u64,
+    problems: u64,
+    warnings: u64,
+    unsupported: bool, // no supported AV1 stream in input
+    sequence_header: Option<obu::SequenceHeader>, // first Sequence Header
+    frame_size: Option<(u32, u32)>, // first frame size
+    pts_range: Option<(i64, i64)>, // (min, max) timestamp of shown frames
+    hdr: HdrStats,
+    hdr10plus: bool,                    // HDR10+ (ST 2094-40) metadata
+    hdr10plus_pending: u32,             // ST 2094-40 metadata OBUs since last shown frame
+    hdr10plus_frames: Vec<(u64, u32)>,  // (frame #, ST 2094-40 metadata OBUs) per shown frame
+    bitrate: BTreeMap<i64, (u64, u64)>, // (shown frames, bytes) per time window
+    untimed_bytes: u64,                 // bytes without presentation time
+    frame_sizes: Vec<FrameSize>,
+    framing: FramingBytes,
+    input_bytes: Option<u64>,            // size of uncompressed input file
+    base_q_idx: BTreeMap<u8, u64>,       // number of coded frames per base_q_idx
+    tile_configs: BTreeMap<String, u64>, // number of coded frames per tile configuration
+    gm_types: [[u64; 4]; obu::NUM_REF_FRAMES], // number of inter frames per reference and warp model
+    filters: FilterStats,
+    tools: ToolStats,
+    level: av1::LevelMeasure, // picture size and tiles, rates are filled at end of stream
+    timestamps: Vec<ShownTimestamp>, // shown frames for --validate-timestamps
+    delta_q_frames: u64,      // number of frames with delta_q_present
+    film_grain: Vec<(Option<f64>, obu::FilmGrainParams)>, // (presentation time, params) per shown frame
+    superres_denoms: BTreeMap<u8, u64>, // number of coded frames per SuperresDenom of superres frames
+}
+
+/// application global config
+struct AppConfig {
+    command: Command,
+    output: FrameOutput,
+    stats: cell::RefCell<FrameStats>,
+    pts: cell::Cell<Option<i64>>, // timestamp of current temporal unit
+    records: cell::Cell<u64>,     // number of written records in JSON array
+    timebase: cell::Cell<Option<(u64, u64)>>, // timestamp unit [sec] of container
+    ffprobe: Option<FfprobeOptions>,
+    obu_filter: Option<u16>, // bitmask of obu_type shown in output
+    frame_filter: FrameFilter,
+    skip_frames: u64,        // number of frames omitted from output
+    max_frames: Option<u64>, // number of frames parsed after skipped frames
+    verbose: u64,
+    show: ShowSections,
+    hexdump: Option<u64>, // number of dumped bytes in OBU payload
+    trace: bool,          // print syntax elements with bit position
+    quiet: bool,
+    strict: bool, // warnings are counted as problems
+    window: f64,  // time window [sec] of bitrate subcommand
+    export: Option<ExportFormat>,
+    follow: bool,
+    idle_timeout: Option<time::Duration>,
+    mp4: mp4::Mp4Options,
+    seek: Option<SeekTarget>,
+    init: Option<String>, // initialization segment (or av1C) for headerless media segments
+    format: Option<FileFormat>, // forced input format
+    codecs: Option<String>, // declared codec parameter string
+    validate_timestamps: bool,
+    frames: cell::RefCell<Vec<DiffFrame>>, // collected frames for diff subcommand
+}
+
+impl AppConfig {
+    /// check per-frame log is printed
+    fn print_frames(&self) -> bool {
+        self.export.is_none() && matches!(self.command, Command::Frames | Command::Obus)
+    }
+
+    /// check OBU type is shown in output
+    fn show_obu(&self, obu_type: u8) -> bool {
+        self.obu_filter
+            .is_none_or(|mask| mask & (1 << (obu_type & 0xf)) != 0)
+    }
+
+    /// check frame limit is reached, parsing loop stops
+    fn frame_limit_reached(&self) -> bool {
+        self.max_frames
+            .is_some_and(|n| self.stats.borrow().frame_headers >= self.skip_frames + n)
+    }
+
+    /// report conformance finding, errors (and warnings in strict mode) count as problems
+    fn report(&self, rule: &'static conformance::Rule, location: &str, message: &str) {
+        let error = rule.severity == conformance::Severity::Error || self.strict;
+        {
+            let mut stats = self.stats.borrow_mut();
+            if error {
+                stats.problems += 1;
+            } else {
+                stats.warnings += 1;
+            }
+        }
+        if self.export.is_none() && (error || self.command == Command::Check || self.verbose > 0) {
+            let severity = if error {
+                conformance::Severity::Error
+            } else {
+                rule.severity
+            };
+            logln!("  {}[{}] {}: {}", severity, rule.id, location, message);
+        }
+    }
+
+    /// mark input as unsupported format or codec
+    fn set_unsupported(&self) {
+        self.stats.borrow_mut().unsupported = true;
+    }
+}
+
+///
+/// MP4 sample record for table export
+///
+#[derive(Serialize)]
+struct SampleRecord {
+    index: usize,
+    offset: u64,
+    size: u64,
+    dts: i64,
+    pts: i64,
+    sync: bool,
+    frame_types: Vec<&'static str>,
+}
+
+///
+/// OBU record for NDJSON output
+///
+#[derive(Serialize)]
+struct ObuRecord {
+    obu_type: String,
+    temporal_id: u8,
+    spatial_id: u8,
+    header_len: u32,
+    obu_size: u32,
+}
+
+// column names of frame record in CSV output
+const FRAME_CSV_HEADER: &str = "decode_idx,display_idx,frame_type,show_frame,show_existing_frame,size_bytes,base_q_idx,delta_q_present,refresh_mask,refs,pts,temporal_id,spatial_id";
+
+///
+/// frame record for NDJSON/CSV output
+///
+#[derive(Serialize)]
+struct FrameRecord {
+    decode_idx: i64,
+    display_idx: Option<i64>,
+    frame_type: &'static str,
+    show_frame: bool,
+    show_existing_frame: bool,
+    size_bytes: u32,
+    base_q_idx: Option<u8>,
+    delta_q_present: Option<bool>,
+    refresh_mask: u8,
+    refs: Vec<i64>,
+    pts: Option<i64>,
+    temporal_id: u8,
+    spatial_id: u8,
+}
+
+// MI_SIZE (4x4 luma samples)
+const MI_SIZE: u32 = 4;
+
+///
+/// frame record in AOM analyzer JSON layout (libaom examples/inspect.c),
+/// mode info maps are not available without decoding
+///
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnalyzerFrameRecord {
+    frame: i64,
+    show_frame: u8,
+    frame_type: u8,
+    base_q_index: u8,
+    tile_cols: u32, // tile width in MI units
+    tile_rows: u32, // tile height in MI units
+    delta_q_present_flag: u8,
+    delta_q_res: u8,
+    config: AnalyzerConfig,
+    config_string: String,
+}
+
+#[derive(Serialize)]
+struct AnalyzerConfig {
+    #[serde(rename = "MI_SIZE")]
+    mi_size: u32,
+}
+
+///
+/// per-OBU/frame record in NDJSON output
+///
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum StreamRecord {
+    Obu(ObuRecord),
+    Frame(FrameRecord),
+}
+
+///
+/// WebM block record for table export
+///
+#[derive(Serialize)]
+struct BlockRecord {
+    index: usize,
+    track: u64,
+    timecode: i64,
+    timestamp_ns: i64,
+    flags: u8,
+    keyframe: bool,
+    offset: u64,
+    size: u64,
+    lace_count: u32,
+    frame_types: Vec<&'static str>,
+}
+
+/// print frame summary line
+fn print_frame(rfman: &av1::RefFrameManager, fh: &obu::FrameHeader) {
+    if !fh.show_existing_frame {
+        let error_resilient = if fh.error_resilient_mode { "*" } else { "" };
+        let delta_q = if fh.delta_q_params.delta_q_present {
+            "+delta_q"
+        } else {
+            ""
+        };
+        if fh.show_frame {
+            logln!(
+                "  #{} {}{}, update({}), qidx={}{}, show@{}",
+                rfman.decode_order,
+                av1::stringify::frame_type(fh.frame_type),
+                error_resilient,
+                av1::stringify::ref_frame(fh.refresh_frame_flags),
+                fh.quantization_params.base_q_idx,
+                delta_q,
+                rfman.present_order
+            );
+        } else {
+            logln!(
+                "  #{} {}{}, update({}), qidx={}{}, {}",
+                rfman.decode_order,
+                av1::stringify::frame_type(fh.frame_type),
+                error_resilient,
+                av1::stringify::ref_frame(fh.refresh_frame_flags),
+                fh.quantization_params.base_q_idx,
+                delta_q,
+                if fh.showable_frame {
+                    "showable"
+                } else {
+                    "(refonly)"
+                }
+            );
+        }
+    } else {
+        let show_idx = fh.frame_to_show_map_idx;
+        logln!(
+            "    #{} ({}) show@{}",
+            rfman.frame_buf[show_idx as usize],
+            av1::stringify::ref_frame(1 << show_idx),
+            rfman.present_order,
+        );
+    }
+}
+
+/// build frame record, before reference frame update process
+fn frame_record(
+    rfman: &av1::RefFrameManager,
+    fh: &obu::FrameHeader,
+    obu: &obu::Obu,
+    pts: Option<i64>,
+) -> FrameRecord {
+    let shown = fh.show_frame || fh.show_existing_frame;
+    FrameRecord {
+        decode_idx: if fh.show_existing_frame {
+            rfman.frame_buf[fh.frame_to_show_map_idx as usize]
+        } else {
+            rfman.decode_order
+        },
+        display_idx: if shown {
+            Some(rfman.present_order)
+        } else {
+            None
+        },
+        frame_type: av1::stringify::frame_type(fh.frame_type),
+        show_frame: fh.show_frame,
+        show_existing_frame: fh.show_existing_frame,
+        size_bytes: obu.header_len + obu.obu_size,
+        base_q_idx: if fh.show_existing_frame {
+            None
+        } else {
+            Some(fh.quantization_params.base_q_idx)
+        },
+        delta_q_present: if fh.show_existing_frame {
+            None
+        } else {
+            Some(fh.delta_q_params.delta_q_present)
+        },
+        refresh_mask: fh.refresh_frame_flags,
+        refs: rfman.reference_frames(fh),
+        pts,
+        temporal_id: obu.temporal_id,
+        spatial_id: obu.spatial_id,
+    }
+}
+
+/// accumulate CDEF strengths and loop restoration types/unit sizes of frame
+fn count_filter_params(filters: &mut FilterStats, fh: &obu::FrameHeader, num_planes: usize) {
+    let cdef = &fh.cdef_params;
+    *filters.cdef_damping.entry(cdef.cdef_damping).or_default() += 1;
+    *filters.cdef_bits.entry(cdef.cdef_bits).or_default() += 1;
+    for i in 0..(1 << cdef.cdef_bits) {
+        let strengths = [
+            cdef.cdef_y_pri_strength[i],
+            cdef.cdef_y_sec_strength[i],
+            cdef.cdef_uv_pri_strength[i],
+            cdef.cdef_uv_sec_strength[i],
+        ];
+        let count = if num_planes > 1 { 4 } else { 2 };
+        for (histogram, &strength) in filters.cdef_strength.iter_mut().zip(&strengths).take(count) {
+            *histogram.entry(strength).or_default() += 1;
+        }
+    }
+    let lr = &fh.lr_params;
+    for plane in 0..num_planes {
+        let lr_type = lr.frame_restoration_type[plane];
+        *filters.lr_type[plane].entry(lr_type).or_default() += 1;
+        if lr_type != obu::RESTORE_NONE {
+            let size = lr.loop_restoration_size[plane];
+            *filters.lr_unit_size[plane].entry(size).or_default() += 1;
+        }
+    }
+}
+
+/// count coding tools selected in frame header
+fn count_coding_tools(tools: &mut ToolStats, fh: &obu::FrameHeader) {
+    tools.frames += 1;
+    *tools.tx_mode.entry(fh.tx_mode).or_default() += 1;
+    if fh.reduced_tx_set {
+        tools.reduced_tx_set += 1;
+    }
+    if !fh.frame_is_intra {
+        tools.inter_frames += 1;
+        *tools
+            .interpolation_filter
+            .entry(fh.interpolation_filter)
+            .or_default() += 1;
+        if fh.reference_select {
+            tools.reference_select += 1;
+        }
+    }
+}
+
+/// print usage of interpolation filter, TxMode, reduced_tx_set and reference_select
+fn print_coding_tools(tools: &ToolStats) {
+    fn named(counts: &BTreeMap<u8, u64>, name: fn(u8) -> &'static str) -> String {
+        let counts: Vec<String> = counts
+            .iter()
+            .map(|(&value, count)| format!("{}={}", name(value), count))
+            .collect();
+        counts.join(" ")
+    }
+    if tools.frames == 0 {
+        return;
+    }
+    logln!("  coding tools:");
+    logln!(
+        "    tx_mode {}",
+        named(&tools.tx_mode, av1::stringify::tx_mode)
+    );
+    logln!(
+        "    reduced_tx_set={}/{}",
+        tools.reduced_tx_set,
+        tools.frames
+    );
+    if tools.inter_frames > 0 {
+        logln!(
+            "    interpolation_filter {} (inter frames)",
+            named(
+                &tools.interpolation_filter,
+                av1::stringify::interpolation_filter
+            )
+        );
+        logln!(
+            "    reference_select={}/{} (inter frames)",
+            tools.reference_select,
+            tools.inter_frames
+        );
+    }
+}
+
+/// accumulate ST 2094-40 metadata into HDR summary
+fn count_hdr10plus(hdr: &mut HdrStats, hdr10plus: &obu::St2094_40Metadata) {
+    hdr.hdr10plus_count += 1;
+    hdr.hdr10plus_target_max = cmp::max(
+        hdr.hdr10plus_target_max,
+        hdr10plus.targeted_system_display_maximum_luminance,
+    );
+    for window in hdr10plus.windows.iter() {
+        for &maxscl in window.maxscl.iter() {
+            hdr.hdr10plus_maxscl = cmp::max(hdr.hdr10plus_maxscl, maxscl);
+        }
+    }
+}
+
+/// print HDR metadata summary in cd/m2 and CIE 1931 xy coordinates
+fn print_hdr_summary(hdr: &HdrStats) {
+    let varies = |varies: bool| if varies { ", values vary" } else { "" };
+    if hdr.cll.is_none() && hdr.mdcv.is_none() && hdr.hdr10plus_count == 0 && hdr.t35_other == 0 {
+        return;
+    }
+    logln!("  HDR:");
+    if let Some(ref cll) = hdr.cll {
+        logln!(
+            "    content light level: MaxCLL={} cd/m² MaxFALL={} cd/m² (count={}{})",
+            cll.max_cll,
+            cll.max_fall,
+            hdr.cll_count,
+            varies(hdr.cll_varies)
+        );
+    }
+    if let Some(ref mdcv) = hdr.mdcv {
+        // chromaticity coordinates are 0.16 fixed-point
+        let xy = |x: u16, y: u16| format!("({:.4},{:.4})", x as f64 / 65536.0, y as f64 / 65536.0);
+        let primaries: Vec<String> = ["R", "G", "B"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let (x, y) = (
+                    mdcv.primary_chromaticity_x[i],
+                    mdcv.primary_chromaticity_y[i],
+                );
+                format!("{}{}", name, xy(x, y))
+            })
+            .collect();
+        // luminance_max is 24.8, luminance_min is 18.14 fixed-point
+        logln!(
+            "    mastering display: {} white{} luminance={:.4}-{:.4} cd/m² (count={}{})",
+            primaries.join(" "),
+            xy(
+                mdcv.white_point_chromaticity_x,
+                mdcv.white_point_chromaticity_y
+            ),
+            mdcv.luminance_min as f64 / 16384.0,
+            mdcv.luminance_max as f64 / 256.0,
+            hdr.mdcv_count,
+            varies(hdr.mdcv_varies)
+        );
+    }
+    if hdr.hdr10plus_count > 0 {
+        // maxscl is in 0.00001 of 10000 cd/m2
+        logln!(
+            "    dynamic metadata: HDR10+ (ST 2094-40) count={} targeted_display_max={} cd/m² max_maxscl={:.1} cd/m²",
+            hdr.hdr10plus_count,
+            hdr.hdr10plus_target_max,
+            hdr.hdr10plus_maxscl as f64 / 10.0
+        );
+    }
+    if hdr.t35_other > 0 {
+        logln!("    other ITU-T T.35 metadata count={}", hdr.t35_other);
+    }
+}
+
+/// format histogram as value=count list
+fn histogram<K: std::fmt::Display>(counts: &BTreeMap<K, u64>) -> String {
+    let counts: Vec<String> = counts
+        .iter()
+        .map(|(value, count)| format!("{}={}", value, count))
+        .collect();
+    counts.join(" ")
+}
+
+/// print number of coded frames using superres per denominator
+fn print_superres_usage(stats: &FrameStats) {
+    if !stats
+        .sequence_header
+        .as_ref()
+        .is_some_and(|sh| sh.enable_superres)
+    {
+        return;
+    }
+    let coded_frames: u64 = stats.frame_count.iter().sum();
+    let superres_frames: u64 = stats.superres_denoms.values().sum();
+    let denoms = if superres_frames > 0 {
+        format!(" denom {}", histogram(&stats.superres_denoms))
+    } else {
+        String::new()
+    };
+    logln!(
+        "  superres: frames={}/{} ({:.1}%){}",
+        superres_frames,
+        coded_frames,
+        superres_frames as f64 * 100.0 / cmp::max(coded_frames, 1) as f64,
+        denoms
+    );
+}
+
+/// print film grain usage of shown frames
+fn print_film_grain_stats(film_grain: &[(Option<f64>, obu::FilmGrainParams)]) {
+    if film_grain.is_empty() {
+        return;
+    }
+    let applied: Vec<&obu::FilmGrainParams> = film_grain
+        .iter()
+        .map(|(_, fg)| fg)
+        .filter(|fg| fg.apply_grain)
+        .collect();
+    let updated = applied.iter().filter(|fg| fg.update_grain).count();
+    let seeds: BTreeMap<u16, u64> = applied.iter().fold(BTreeMap::new(), |mut seeds, fg| {
+        *seeds.entry(fg.grain_seed).or_default() += 1;
+        seeds
+    });
+    logln!(
+        "  film grain: apply_grain={}/{} update_grain={} distinct grain_seed={}",
+        applied.len(),
+        film_grain.len(),
+        updated,
+        seeds.len()
+    );
+    if applied.is_empty() {
+        return;
+    }
+    let mut points: [BTreeMap<u8, u64>; 3] = Default::default();
+    for fg in applied.iter() {
+        for (counts, &num) in
+            points
+                .iter_mut()
+                .zip(&[fg.num_y_points, fg.num_cb_points, fg.num_cr_points])
+        {
+            *counts.entry(num).or_default() += 1;
+        }
+    }
+    for (name, counts) in ["num_y_points", "num_cb_points", "num_cr_points"]
+        .iter()
+        .zip(points.iter())
+    {
+        logln!("    {} {}", name, histogram(counts));
+    }
+}
+
+/// print histograms of CDEF and loop restoration params as value=count lists
+fn print_filter_stats(filters: &FilterStats) {
+    if filters.cdef_bits.is_empty() {
+        return;
+    }
+    logln!(
+        "  cdef: damping {} bits {}",
+        histogram(&filters.cdef_damping),
+        histogram(&filters.cdef_bits)
+    );
+    let names = ["y_pri", "y_sec", "uv_pri", "uv_sec"];
+    for (name, counts) in names.iter().zip(&filters.cdef_strength) {
+        if !counts.is_empty() {
+            logln!("    {}_strength {}", name, histogram(counts));
+        }
+    }
+    logln!("  loop restoration:");
+    for (plane, types) in filters.lr_type.iter().enumerate() {
+        if types.is_empty() {
+            continue;
+        }
+        let types: Vec<String> = types
+            .iter()
+            .map(|(&lr_type, count)| {
+                format!("{}={}", av1::stringify::restoration_type(lr_type), count)
+            })
+            .collect();
+        let sizes = &filters.lr_unit_size[plane];
+        logln!(
+            "    {} {}{}",
+            ["Y", "U", "V"][plane],
+            types.join(" "),
+            if sizes.is_empty() {
+                String::new()
+            } else {
+                format!(" unit_size {}", histogram(sizes))
+            }
+        );
+    }
+}
+
+/// print non-identity global motion models in pixels (translation) and scaled matrix
+fn print_global_motion(gmp: &obu::GlobalMotionParams) {
+    let scale = |v: i32| v as f64 / (1 << obu::WARPEDMODEL_PREC_BITS) as f64;
+    for ref_frame in av1::LAST_FRAME..=av1::ALTREF_FRAME {
+        if gmp.gm_type[ref_frame] == obu::IDENTITY {
+            continue;
+        }
+        let params = &gmp.gm_params[ref_frame];
+        logln!(
+            "  gm {}: {} translation=({:.4},{:.4}) matrix=[{:.6} {:.6}; {:.6} {:.6}]",
+            av1::stringify::ref_frame_name(ref_frame),
+            av1::stringify::warp_model(gmp.gm_type[ref_frame]),
+            scale(params[0]),
+            scale(params[1]),
+            scale(params[2]),
+            scale(params[3]),
+            scale(params[4]),
+            scale(params[5])
+        );
+    }
+}
+
+/// print number of inter frames using each warp model per reference
+fn print_global_motion_usage(gm_types: &[[u64; 4]; obu::NUM_REF_FRAMES]) {
+    if gm_types[av1::LAST_FRAME].iter().sum::<u64>() == 0 {
+        return;
+    }
+    logln!("  global motion (inter frames per model):");
+    for (ref_frame, counts) in gm_types.iter().enumerate().skip(av1::LAST_FRAME) {
+        let models: Vec<String> = counts
+            .iter()
+            .enumerate()
+            .map(|(gm_type, count)| {
+                format!("{}={}", av1::stringify::warp_model(gm_type as u8), count)
+            })
+            .collect();
+        logln!(
+            "    {} {}",
+            av1::stringify::ref_frame_name(ref_frame),
+            models.join(" ")
+        );
+    }
+}
+
+/// tile configuration (TileCols x TileRows, spacing, tile widths and heights in pixels)
+fn tile_config(fh: &obu::FrameHeader) -> String {
+    let ti = &fh.tile_info;
+    let sizes = |starts: &[u32], limit: u32| {
+        let sizes: Vec<String> = starts
+            .windows(2)
+            .map(|mi| (cmp::min(mi[1] * MI_SIZE, limit) - mi[0] * MI_SIZE).to_string())
+            .collect();
+        sizes.join(",")
+    };
+    format!(
+        "{}x{} {} widths=[{}] heights=[{}]",
+        ti.tile_cols,
+        ti.tile_rows,
+        if ti.uniform_tile_spacing_flag {
+            "uniform"
+        } else {
+            "explicit"
+        },
+        sizes(&ti.mi_col_starts, fh.frame_size.frame_width),
+        sizes(&ti.mi_row_starts, fh.frame_size.frame_height)
+    )
+}
+
+/// write frame record in AOM analyzer JSON array
+fn write_analyzer_record(rfman: &av1::RefFrameManager, fh: &obu::FrameHeader, config: &AppConfig) {
+    // MiCols, MiRows
+    let mi_cols = 2 * ((fh.frame_size.frame_width + 7) >> 3);
+    let mi_rows = 2 * ((fh.frame_size.frame_height + 7) >> 3);
+    let tile_cols = cmp::max(fh.tile_info.tile_cols as u32, 1);
+    let tile_rows = cmp::max(fh.tile_info.tile_rows as u32, 1);
+    let record = AnalyzerFrameRecord {
+        frame: rfman.decode_order,
+        show_frame: fh.show_frame as u8,
+        frame_type: fh.frame_type,
+        base_q_index: fh.quantization_params.base_q_idx,
+        tile_cols: mi_cols.div_ceil(tile_cols),
+        tile_rows: mi_rows.div_ceil(tile_rows),
+        delta_q_present_flag: fh.delta_q_params.delta_q_present as u8,
+        delta_q_res: 1 << fh.delta_q_params.delta_q_res,
+        config: AnalyzerConfig { mi_size: MI_SIZE },
+        config_string: format!("{} {}", crate_name!(), crate_version!()),
+    };
+    let separator = if config.records.get() > 0 { "," } else { "" };
+    config.records.set(config.records.get() + 1);
+    outln!("{}{}", separator, serde_json::to_string(&record).unwrap());
+}
+
+/// print ffprobe-style section in default ([NAME] key=value [/NAME]) or compact (name|key=value) format
+fn print_ffprobe_section(name: &str, fields: &[(&str, String)], compact: bool) {
+    if compact {
+        let mut line = name.to_lowercase();
+        for (key, value) in fields {
+            line.push_str(&format!("|{}={}", key, value));
+        }
+        outln!("{}", line);
+    } else {
+        outln!("[{}]", name);
+        for (key, value) in fields {
+            outln!("{}={}", key, value);
+        }
+        outln!("[/{}]", name);
+    }
+}
+
+/// get FFmpeg pixel format name
+fn ffprobe_pix_fmt(cc: &obu::ColorConfig) -> String {
+    let depth = match cc.bit_depth {
+        8 => "",
+        10 => "10le",
+        _ => "12le",
+    };
+    let layout = match (cc.mono_chrome, cc.subsampling_x, cc.subsampling_y) {
+        (true, _, _) => "gray",
+        (false, 1, 1) => "yuv420p",
+        (false, 1, 0) => "yuv422p",
+        _ if cc.matrix_coefficients == 0 => "gbrp", // MC_IDENTITY
+        _ => "yuv444p",
+    };
+    format!("{}{}", layout, depth)
+}
+
+/// get FFmpeg color property names (color_space, color_transfer, color_primaries)
+fn ffprobe_color_names(cc: &obu::ColorConfig) -> [&'static str; 3] {
+    let color_space = match cc.matrix_coefficients {
+        0 => "gbr",
+        1 => "bt709",
+        4 => "fcc",
+        5 => "bt470bg",
+        6 => "smpte170m",
+        7 => "smpte240m",
+        8 => "ycgco",
+        9 => "bt2020nc",
+        10 => "bt2020c",
+        11 => "smpte2085",
+        12 => "chroma-derived-nc",
+        13 => "chroma-derived-c",
+        14 => "ictcp",
+        _ => "unknown",
+    };
+    let color_transfer = match cc.transfer_characteristics {
+        1 => "bt709",
+        4 => "gamma22",
+        5 => "gamma28",
+        6 => "smpte170m",
+        7 => "smpte240m",
+        8 => "linear",
+        9 => "log100",
+        10 => "log316",
+        11 => "iec61966-2-4",
+        12 => "bt1361e",
+        13 => "iec61966-2-1",
+        14 => "bt2020-10",
+        15 => "bt2020-12",
+        16 => "smpte2084",
+        17 => "smpte428",
+        18 => "arib-std-b67",
+        _ => "unknown",
+    };
+    let color_primaries = match cc.color_primaries {
+        1 => "bt709",
+        4 => "bt470m",
+        5 => "bt470bg",
+        6 => "smpte170m",
+        7 => "smpte240m",
+        8 => "film",
+        9 => "bt2020",
+        10 => "smpte428",
+        11 => "smpte431",
+        12 => "smpte432",
+        22 => "ebu3213",
+        _ => "unknown",
+    };
+    [color_space, color_transfer, color_primaries]
+}
+
+/// print FRAME section of shown frame for --show-frames
+fn print_ffprobe_frame(
+    fh: &obu::FrameHeader,
+    obu: &obu::Obu,
+    sh: Option<&obu::SequenceHeader>,
+    config: &AppConfig,
+) {
+    let options = match config.ffprobe {
+        Some(options) if options.show_frames => options,
+        _ => return,
+    };
+    if !fh.show_frame && !fh.show_existing_frame {
+        return;
+    }
+    let (pts, pts_time) = match (config.pts.get(), config.timebase.get()) {
+        (Some(pts), Some((num, den))) if den > 0 => (
+            pts.to_string(),
+            format!("{:.6}", pts as f64 * num as f64 / den as f64),
+        ),
+        (Some(pts), _) => (pts.to_string(), "N/A".to_owned()),
+        _ => ("N/A".to_owned(), "N/A".to_owned()),
+    };
+    // frame size of shown existing frame is not loaded, use first frame size
+    let (width, height) = if fh.show_existing_frame {
+        config.stats.borrow().frame_size.unwrap_or((0, 0))
+    } else {
+        (fh.frame_size.upscaled_width, fh.frame_size.frame_height)
+    };
+    let pict_type = match fh.frame_type {
+        obu::KEY_FRAME | obu::INTRA_ONLY_FRAME => "I",
+        obu::INTER_FRAME => "P",
+        _ => "SP",
+    };
+    let fields = [
+        ("media_type", "video".to_owned()),
+        ("stream_index", "0".to_owned()),
+        (
+            "key_frame",
+            ((fh.frame_type == obu::KEY_FRAME) as u8).to_string(),
+        ),
+        ("pts", pts),
+        ("pts_time", pts_time),
+        ("pkt_size", (obu.header_len + obu.obu_size).to_string()),
+        ("width", width.to_string()),
+        ("height", height.to_string()),
+        (
+            "pix_fmt",
+            sh.map_or("unknown".to_owned(), |sh| ffprobe_pix_fmt(&sh.color_config)),
+        ),
+        ("pict_type", pict_type.to_owned()),
+    ];
+    print_ffprobe_section("FRAME", &fields, options.compact);
+}
+
+/// print STREAM section for --show-streams
+fn print_ffprobe_stream(config: &AppConfig) {
+    let options = match config.ffprobe {
+        Some(options) if options.show_streams => options,
+        _ => return,
+    };
+    let stats = config.stats.borrow();
+    let sh = match stats.sequence_header {
+        Some(ref sh) => sh,
+        None => return,
+    };
+    let cc = &sh.color_config;
+    let profile = match sh.seq_profile {
+        0 => "Main",
+        1 => "High",
+        _ => "Professional",
+    };
+    let (width, height) = stats
+        .frame_size
+        .unwrap_or((sh.max_frame_width, sh.max_frame_height));
+    let [color_space, color_transfer, color_primaries] = ffprobe_color_names(cc);
+    let time_base = match config.timebase.get() {
+        Some((num, den)) => {
+            let (mut a, mut b) = (num, den);
+            while b != 0 {
+                (a, b) = (b, a % b);
+            }
+            format!("{}/{}", num / a.max(1), den / a.max(1))
+        }
+        None => "N/A".to_owned(),
+    };
+    let fields = [
+        ("index", "0".to_owned()),
+        ("codec_name", "av1".to_owned()),
+        ("codec_long_name", "Alliance for Open Media AV1".to_owned()),
+        ("profile", profile.to_owned()),
+        ("codec_type", "video".to_owned()),
+        ("width", width.to_string()),
+        ("height", height.to_string()),
+        ("pix_fmt", ffprobe_pix_fmt(cc)),
+        ("level", sh.op[0].seq_level_idx.to_string()),
+        (
+            "color_range",
+            if cc.color_range { "pc" } else { "tv" }.to_owned(),
+        ),
+        ("color_space", color_space.to_owned()),
+        ("color_transfer", color_transfer.to_owned()),
+        ("color_primaries", color_primaries.to_owned()),
+        ("time_base", time_base),
+        ("nb_frames", stats.shown_frames.to_string()),
+    ];
+    print_ffprobe_section("STREAM", &fields, options.compact);
+}
+
+/// write frame record as CSV row, optional values are empty
+fn write_csv_record(record: &FrameRecord) {
+    let opt = |v: Option<i64>| v.map_or(String::new(), |v| v.to_string());
+    let refs: Vec<String> = record.refs.iter().map(|r| r.to_string()).collect();
+    outln!(
+        "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+        record.decode_idx,
+        opt(record.display_idx),
+        record.frame_type,
+        record.show_frame as u8,
+        record.show_existing_frame as u8,
+        record.size_bytes,
+        opt(record.base_q_idx.map(i64::from)),
+        opt(record.delta_q_present.map(i64::from)),
+        record.refresh_mask,
+        refs.join(" "),
+        opt(record.pts),
+        record.temporal_id,
+        record.spatial_id
+    );
+}
+
+/// write record as single line JSON, or YAML sequence item
+fn write_record(record: &StreamRecord, output: FrameOutput) {
+    if output == FrameOutput::Yaml {
+        let yaml = serde_yaml::to_string(record).unwrap();
+        for (i, line) in yaml.lines().enumerate() {
+            outln!("{}{}", if i == 0 { "- " } else { "  " }, line);
+        }
+    } else {
+        outln!("{}", serde_json::to_string(record).unwrap());
+    }
+}
+
+/// write exported table records in JSON/YAML, CSV rows are already written
+fn write_export_records<T: Serialize>(fmt: ExportFormat, records: &[T]) -> io::Result<()> {
+    match fmt {
+        ExportFormat::Csv => {}
+        ExportFormat::Json => {
+            outln!("{}", serde_json::to_string_pretty(records)?);
+        }
+        ExportFormat::Yaml => {
+            let yaml = serde_yaml::to_string(records).map_err(io::Error::other)?;
+            outln!("{}", yaml.trim_end());
+        }
+    }
+    Ok(())
+}
+
+/// print Sequence Header summary for info subcommand
+fn print_sequence_header(sh: &obu::SequenceHeader) {
+    let cc = &sh.color_config;
+    let chroma = match (cc.mono_chrome, cc.subsampling_x, cc.subsampling_y) {
+        (true, _, _) => "4:0:0",
+        (false, 1, 1) => "4:2:0",
+        (false, 1, 0) => "4:2:2",
+        _ => "4:4:4",
+    };
+    logln!(
+        "  profile={} level={} tier={} max_size={}x{} bit_depth={} chroma={} still_picture={} operating_points={} film_grain={}",
+        sh.seq_profile,
+        sh.op[0].seq_level_idx,
+        sh.op[0].seq_tier,
+        sh.max_frame_width,
+        sh.max_frame_height,
+        cc.bit_depth,
+        chroma,
+        sh.still_picture as u8,
+        sh.operating_points_cnt,
+        sh.film_grain_params_present as u8
+    );
+    logln!("  capabilities: {}", sequence_capabilities(sh));
+}
+
+/// describe Sequence Header as human-readable capability list
+fn sequence_capabilities(sh: &obu::SequenceHeader) -> String {
+    let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+    let cc = &sh.color_config;
+    let chroma = match (cc.mono_chrome, cc.subsampling_x, cc.subsampling_y) {
+        (true, _, _) => "monochrome",
+        (false, 1, 1) => "4:2:0",
+        (false, 1, 0) => "4:2:2",
+        _ => "4:4:4",
+    };
+    let mut format = format!("{}-bit {}", cc.bit_depth, chroma);
+    if let Some(color) = color_description(cc) {
+        format += &format!(" {}", color);
+    }
+    if cc.color_range {
+        format += " full range";
+    }
+    let order_hint = if sh.enable_order_hint {
+        format!("on ({} bits)", sh.order_hint_bits)
+    } else {
+        "off".to_string()
+    };
+    let mut caps = vec![
+        format,
+        format!("order hints {}", order_hint),
+        format!("superres {}", on_off(sh.enable_superres)),
+        format!("CDEF {}", on_off(sh.enable_cdef)),
+        format!("loop restoration {}", on_off(sh.enable_restoration)),
+        format!(
+            "film grain {}",
+            if sh.film_grain_params_present {
+                "signaled"
+            } else {
+                "not signaled"
+            }
+        ),
+    ];
+    if sh.still_picture {
+        caps.push("still picture".to_string());
+    }
+    caps.push(format!(
+        "{} profile",
+        av1::stringify::profile(sh.seq_profile)
+    ));
+    caps.push(format!(
+        "level {} {} tier",
+        av1::stringify::level(sh.op[0].seq_level_idx),
+        if sh.op[0].seq_tier == 0 {
+            "Main"
+        } else {
+            "High"
+        }
+    ));
+    caps.join(", ")
+}
+
+/// describe color primaries and transfer characteristics (e.g. "BT.2020/PQ"), None if unspecified
+fn color_description(cc: &obu::ColorConfig) -> Option<String> {
+    const CP_UNSPECIFIED: u8 = 2;
+    const TC_UNSPECIFIED: u8 = 2;
+    if cc.color_primaries == CP_UNSPECIFIED && cc.transfer_characteristics == TC_UNSPECIFIED {
+        return None;
+    }
+    let primaries = match cc.color_primaries {
+        1 => "BT.709".to_string(),
+        5 => "BT.601 625".to_string(),
+        6 => "BT.601 525".to_string(),
+        9 => "BT.2020".to_string(),
+        11 => "DCI-P3".to_string(),
+        12 => "Display P3".to_string(),
+        cp => format!("cp={}", cp),
+    };
+    let transfer = match cc.transfer_characteristics {
+        1 | 6 | 14 | 15 => "SDR".to_string(),
+        8 => "linear".to_string(),
+        13 => "sRGB".to_string(),
+        16 => "PQ".to_string(),
+        18 => "HLG".to_string(),
+        tc => format!("tc={}", tc),
+    };
+    Some(format!("{}/{}", primaries, transfer))
+}
+
+/// print OBU header line in verbose log
+fn print_obu_header(obu: &obu::Obu, config: &AppConfig) {
+    if (config.verbose > 0 || config.trace) && config.show_obu(obu.obu_type) {
+        logln!("  {}", obu);
+    }
+}
+
+/// location of OBU in conformance finding
+fn obu_location(obu: &obu::Obu, config: &AppConfig) -> String {
+    format!(
+        "frame #{} {}",
+        config.stats.borrow().frame_headers,
+        av1::stringify::obu_type(obu.obu_type)
+    )
+}
+
+/// report conformance findings detected while parsing OBU
+fn report_findings(obu: &obu::Obu, config: &AppConfig) {
+    for finding in conformance::take_findings() {
+        config.report(finding.rule, &obu_location(obu, config), &finding.message);
+    }
+}
+
+/// run OBU payload parser, report findings and print traced syntax elements if enabled
+fn parse_syntax<T, F: FnOnce() -> T>(
+    obu: &obu::Obu,
+    config: &AppConfig,
+    trace: bool,
+    parse: F,
+) -> T {
+    if !trace {
+        let result = parse();
+        report_findings(obu, config);
+        return result;
+    }
+    bitio::set_trace(true);
+    let result = parse();
+    for se in bitio::take_trace() {
+        let bits = if se.width > 0 {
+            format!("{:0width$b}", se.bits, width = se.width)
+        } else {
+            String::new()
+        };
+        logln!(
+            "    @{:<6} {:<40} {}({}) {:>16} = {}",
+            se.bit_pos,
+            se.name,
+            se.descriptor,
+            se.width,
+            bits,
+            se.value
+        );
+    }
+    bitio::set_trace(false);
+    report_findings(obu, config);
+    result
+}
+
+/// print OBU payload in offset/hex/ASCII layout
+fn print_hexdump(data: &[u8]) {
+    for (i, chunk) in data.chunks(16).enumerate() {
+        let mut hex = String::new();
+        for (j, b) in chunk.iter().enumerate() {
+            hex.push_str(if j == 8 { "  " } else { " " });
+            hex.push_str(&format!("{:02x}", b));
+        }
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        logln!("    {:08x} {:<49}  |{}|", i * 16, hex, ascii);
+    }
+}
+
+/// parse --filter-obu list (e.g. "SEQUENCE_HEADER,METADATA"), return bitmask of obu_type
+fn parse_obu_filter(list: &str) -> Result<u16, String> {
+    let mut mask = 0;
+    for name in list.split(',').map(str::trim) {
+        let name = name.to_uppercase();
+        let name = name.strip_prefix("OBU_").unwrap_or(&name);
+        match (0..16).find(|&t| av1::stringify::obu_type(t) == name) {
+            Some(obu_type) => mask |= 1 << obu_type,
+            None => return Err(format!("unknown OBU type: {}", name)),
+        }
+    }
+    Ok(mask)
+}
+
+///
+/// process OBU(Open Bitstream Unit), return parsed FrameHeader
+///
+fn process_obu<R: io::Read>(
+    reader: &mut R,
+    seq: &mut av1::Sequence,
+    obu: &obu::Obu,
+    config: &AppConfig,
+) -> Option<obu::FrameHeader> {
+    let reader = &mut io::Read::take(reader, obu.obu_size as u64);
+    report_findings(obu, config);
+    let shown = config.show_obu(obu.obu_type);
+    let report = config.export.is_none() && shown;
+    // dumped bytes are replayed before remaining payload
+    let mut head = Vec::new();
+    if let (Some(limit), true) = (config.hexdump, report) {
+        if (&mut *reader).take(limit).read_to_end(&mut head).is_ok() {
+            print_hexdump(&head);
+        }
+    }
+    let reader = &mut io::Cursor::new(head).chain(reader);
+    let trace = config.trace && report;
+    let verbose = if shown { config.verbose } else { 0 };
+    let show = if shown {
+        config.show
+    } else {
+        ShowSections::default()
+    };
+    if matches!(config.output, FrameOutput::Ndjson | FrameOutput::Yaml) && verbose > 0 {
+        let record = StreamRecord::Obu(ObuRecord {
+            obu_type: av1::stringify::obu_type(obu.obu_type).into_owned(),
+            temporal_id: obu.temporal_id,
+            spatial_id: obu.spatial_id,
+            header_len: obu.header_len,
+            obu_size: obu.obu_size,
+        });
+        write_record(&record, config.output);
+    }
+    {
+        let mut stats = config.stats.borrow_mut();
+        stats.obu_count[obu.obu_type as usize & 0xf] += 1;
+        stats.obu_bytes[obu.obu_type as usize & 0xf] += (obu.header_len + obu.obu_size) as u64;
+        let framing = &mut stats.framing;
+        match obu.obu_type {
+            obu::OBU_TEMPORAL_DELIMITER => {
+                framing.temporal_delimiters += (obu.header_len + obu.obu_size) as u64
+            }
+            obu::OBU_REDUNDANT_FRAME_HEADER => {
+                framing.redundant_frame_headers += (obu.header_len + obu.obu_size) as u64
+            }
+            _ => {
+                let extension_len = obu.obu_extension_flag as u32;
+                framing.obu_headers += 1;
+                framing.extension_headers += extension_len as u64;
+                framing.size_fields += obu.header_len.saturating_sub(1 + extension_len) as u64;
+            }
+        }
+        if obu.obu_type == obu::OBU_TILE_GROUP {
+            // tile groups following FrameHeader or Frame OBU
+            if let Some(frame) = stats.frame_sizes.last_mut() {
+                frame.bytes += (obu.header_len + obu.obu_size) as u64;
+            }
+        }
+        if config.command == Command::Bitrate {
+            let bytes = (obu.header_len + obu.obu_size) as u64;
+            match bitrate_window(config, &stats) {
+                Some(window) => stats.bitrate.entry(window).or_default().1 += bytes,
+                None => stats.untimed_bytes += bytes,
+            }
+        }
+    }
+    match obu.obu_type {
+        obu::OBU_SEQUENCE_HEADER => {
+            if let Some(sh) =
+                parse_syntax(obu, config, trace, || obu::parse_sequence_header(reader))
+            {
+                if show.sh {
+                    logln!("  {:?}", sh);
+                }
+                {
+                    let mut stats = config.stats.borrow_mut();
+                    stats.sequence_headers += 1;
+                    if stats.sequence_header.is_none() {
+                        stats.sequence_header = Some(sh.clone());
+                    }
+                }
+                let codecs = av1::codec_string(&sh);
+                if report && seq.sh.as_ref().map(av1::codec_string).as_ref() != Some(&codecs) {
+                    if config.command != Command::Check {
+                        logln!("  codecs={}", codecs);
+                    }
+                    if config.command == Command::Info {
+                        print_sequence_header(&sh);
+                    }
+                    if let Some(ref declared) = config.codecs {
+                        if !av1::match_codec_string(declared, &codecs) {
+                            config.report(
+                                &conformance::CODEC_STRING,
+                                &obu_location(obu, config),
+                                &format!(
+                                    "codec string mismatch (declared={}, stream={})",
+                                    declared, codecs
+                                ),
+                            );
+                        }
+                    }
+                }
+                seq.sh = Some(sh);
+            } else {
+                config.report(
+                    &conformance::OBU_PAYLOAD,
+                    &obu_location(obu, config),
+                    "invalid SequenceHeader",
+                );
+            }
+        }
+        obu::OBU_FRAME_HEADER | obu::OBU_FRAME => {
+            if config.frame_limit_reached() {
+                // remaining frames in current temporal unit
+                return None;
+            }
+            if seq.sh.is_none() {
+                config.report(
+                    &conformance::NO_SEQUENCE_HEADER,
+                    &obu_location(obu, config),
+                    "no sequence header",
+                );
+                return None;
+            }
+            if let Some(fh) = parse_syntax(obu, config, trace, || {
+                obu::parse_frame_header(reader, seq.sh.as_ref().unwrap(), &mut seq.rfman)
+            }) {
+                let is_key_frame = fh.frame_type == obu::KEY_FRAME && !fh.show_existing_frame;
+                let skipped = config.stats.borrow().frame_headers < config.skip_frames;
+                if shown
+                    && !skipped
+                    && config.frame_filter.matches(&fh)
+                    && (config.print_frames() || (config.command == Command::Index && is_key_frame))
+                {
+                    match config.output {
+                        FrameOutput::Text => print_frame(&seq.rfman, &fh),
+                        FrameOutput::Ndjson | FrameOutput::Yaml => write_record(
+                            &StreamRecord::Frame(frame_record(
+                                &seq.rfman,
+                                &fh,
+                                obu,
+                                config.pts.get(),
+                            )),
+                            config.output,
+                        ),
+                        FrameOutput::Csv => {
+                            write_csv_record(&frame_record(&seq.rfman, &fh, obu, config.pts.get()))
+                        }
+                        FrameOutput::AomAnalyzer if !fh.show_existing_frame => {
+                            write_analyzer_record(&seq.rfman, &fh, config)
+                        }
+                        FrameOutput::AomAnalyzer => {}
+                        FrameOutput::Ffprobe => {
+                            print_ffprobe_frame(&fh, obu, seq.sh.as_ref(), config)
+                        }
+                    }
+                }
+                if config.command == Command::Diff {
+                    config.frames.borrow_mut().push(DiffFrame {
+                        record: frame_record(&seq.rfman, &fh, obu, config.pts.get()),
+                        header: diff::header_value(&fh),
+                    });
+                }
+                if show.fh {
+                    logln!("  {:?}", fh);
+                }
+                if show.fg && !show.fh {
+                    logln!("  {:?}", fh.film_grain_params);
+                }
+                if (show.gm || config.verbose > 0) && !fh.frame_is_intra && !fh.show_existing_frame
+                {
+                    print_global_motion(&fh.global_motion_params);
+                }
+                if show.superres && fh.frame_size.use_superres && !fh.show_existing_frame {
+                    let fs = &fh.frame_size;
+                    logln!(
+                        "  superres: {}x{} upscaled to {}x{} (denom={})",
+                        fs.frame_width,
+                        fs.frame_height,
+                        fs.upscaled_width,
+                        fs.frame_height,
+                        fs.superres_denom
+                    );
+                }
+                if show.tiles && !fh.show_existing_frame {
+                    logln!(
+                        "  tiles: {} context_update_tile_id={}",
+                        tile_config(&fh),
+                        fh.tile_info.context_update_tile_id
+                    );
+                }
+                {
+                    let mut stats = config.stats.borrow_mut();
+                    if fh.show_frame || fh.show_existing_frame {
+                        let pending = mem::take(&mut stats.hdr10plus_pending);
+                        let frame = stats.frame_headers;
+                        stats.hdr10plus_frames.push((frame, pending));
+                    }
+                    stats.frame_headers += 1;
+                    if !fh.show_existing_frame {
+                        stats.frame_count[fh.frame_type as usize & 0x3] += 1;
+                        let bytes = (obu.header_len + obu.obu_size) as u64;
+                        stats.frame_sizes.push(FrameSize {
+                            frame_type: fh.frame_type,
+                            temporal_id: obu.temporal_id,
+                            show_frame: fh.show_frame,
+                            bytes,
+                        });
+                        *stats
+                            .base_q_idx
+                            .entry(fh.quantization_params.base_q_idx)
+                            .or_default() += 1;
+                        if fh.delta_q_params.delta_q_present {
+                            stats.delta_q_frames += 1;
+                        }
+                        *stats.tile_configs.entry(tile_config(&fh)).or_default() += 1;
+                        let num_planes = seq.sh.as_ref().unwrap().color_config.num_planes as usize;
+                        count_filter_params(&mut stats.filters, &fh, num_planes);
+                        count_coding_tools(&mut stats.tools, &fh);
+                        level::measure_level(&mut stats.level, &fh);
+                        if fh.frame_size.use_superres {
+                            *stats
+                                .superres_denoms
+                                .entry(fh.frame_size.superres_denom)
+                                .or_default() += 1;
+                        }
+                        if !fh.frame_is_intra {
+                            let gm_type = &fh.global_motion_params.gm_type;
+                            for (counts, &gm_type) in
+                                stats.gm_types.iter_mut().zip(gm_type).skip(av1::LAST_FRAME)
+                            {
+                                counts[gm_type as usize & 0x3] += 1;
+                            }
+                        }
+                    }
+                    if fh.show_frame || fh.show_existing_frame {
+                        if config.command == Command::Bitrate {
+                            if let Some(window) = bitrate_window(config, &stats) {
+                                stats.bitrate.entry(window).or_default().0 += 1;
+                            }
+                        }
+                        if seq.sh.as_ref().unwrap().film_grain_params_present {
+                            let time = presentation_time(config, &stats);
+                            stats.film_grain.push((time, fh.film_grain_params.clone()));
+                        }
+                        if config.validate_timestamps {
+                            let order_hint = if fh.show_existing_frame {
+                                seq.rfman.ref_order_hint[fh.frame_to_show_map_idx as usize]
+                            } else {
+                                fh.order_hint
+                            };
+                            stats.timestamps.push(ShownTimestamp {
+                                pts: config.pts.get(),
+                                order_hint,
+                                key_frame: fh.frame_type == obu::KEY_FRAME,
+                                show_existing_frame: fh.show_existing_frame,
+                            });
+                        }
+                        stats.shown_frames += 1;
+                        if let Some(pts) = config.pts.get() {
+                            let (min, max) = stats.pts_range.unwrap_or((pts, pts));
+                            stats.pts_range = Some((cmp::min(min, pts), cmp::max(max, pts)));
+                        }
+                    }
+                    if !fh.show_existing_frame && stats.frame_size.is_none() {
+                        let fs = &fh.frame_size;
+                        stats.frame_size = Some((fs.upscaled_width, fs.frame_height));
+                    }
+                }
+
+                // decode_frame_wrapup(): Decode frame wrapup process
+                if fh.show_frame || fh.show_existing_frame {
+                    seq.rfman.output_process(&fh);
+                }
+                if !fh.show_existing_frame {
+                    if show.refs {
+                        logln!("  {:?}", seq.rfman);
+                    }
+                    seq.rfman.update_process(&fh);
+                }
+                return Some(fh);
+            }
+        }
+        obu::OBU_TILE_LIST => {
+            if let Some(tl) = parse_syntax(obu, config, trace, || obu::parse_tile_list(reader)) {
+                if verbose > 2 {
+                    logln!("  {:?}", tl);
+                }
+            } else {
+                config.report(
+                    &conformance::OBU_PAYLOAD,
+                    &obu_location(obu, config),
+                    "invalid TileList",
+                );
+            }
+        }
+        obu::OBU_METADATA => {
+            if let Ok(metadata) =
+                parse_syntax(obu, config, trace, || obu::parse_metadata_obu(reader))
+            {
+                if show.metadata {
+                    logln!("    {:?}", metadata);
+                }
+                match metadata {
+                    obu::MetadataObu::HdrCll(ref cll) => {
+                        let hdr = &mut config.stats.borrow_mut().hdr;
+                        hdr.cll_count += 1;
+                        hdr.cll_varies |= hdr.cll.as_ref().is_some_and(|first| first != cll);
+                        hdr.cll = hdr.cll.take().or_else(|| Some(cll.clone()));
+                    }
+                    obu::MetadataObu::HdrMdcv(ref mdcv) => {
+                        let hdr = &mut config.stats.borrow_mut().hdr;
+                        hdr.mdcv_count += 1;
+                        hdr.mdcv_varies |= hdr.mdcv.as_ref().is_some_and(|first| first != mdcv);
+                        hdr.mdcv = hdr.mdcv.take().or_else(|| Some(mdcv.clone()));
+                    }
+                    obu::MetadataObu::ItutT35(ref t35) => {
+                        let mut stats = config.stats.borrow_mut();
+                        match t35.parse_st2094_40() {
+                            Some(hdr10plus) => {
+                                stats.hdr10plus = true;
+                                stats.hdr10plus_pending += 1;
+                                count_hdr10plus(&mut stats.hdr, &hdr10plus);
+                            }
+                            None => stats.hdr.t35_other += 1,
+                        }
+                    }
+                    _ => {}
+                }
+                match metadata {
+                    obu::MetadataObu::ItutT35(ref t35) if show.metadata => {
+                        if let Some(hdr10plus) = t35.parse_st2094_40() {
+                            logln!("    {:?}", hdr10plus);
+                        }
+                    }
+                    obu::MetadataObu::HdrCll(cll) => seq.hdr_cll = Some(cll),
+                    obu::MetadataObu::HdrMdcv(mdcv) => seq.hdr_mdcv = Some(mdcv),
+                    _ => {}
+                }
+            } else {
+                config.report(
+                    &conformance::OBU_PAYLOAD,
+                    &obu_location(obu, config),
+                    "invalid MetadataObu",
+                );
+            }
+        }
+        _ => {}
+    }
+    None
+}
+
+/// parse IVF format
+fn parse_ivf_format<R: io::Read + io::Seek>(
+    mut reader: R,
+    fname: &str,
+    config: &AppConfig,
+) -> io::Result<()> {
+    // parse IVF header
+    let mut ivf_header = [0; ivf::IVF_HEADER_SIZE];
+    reader.read_exact(&mut ivf_header)?;
+    let hdr = match ivf::parse_ivf_header(&ivf_header) {
+        Ok(hdr) => hdr,
+        Err(msg) => {
+            logln!("{}: {}", fname, msg);
+            return Ok(());
+        }
+    };
+    let codec = String::from_utf8_lossy(&hdr.codec);
+    logln!(
+        "{}: IVF codec={:?} size={}x{} timescale={}/{} length={}",
+        fname,
+        codec,
+        hdr.width,
+        hdr.height,
+        hdr.timescale_num,
+        hdr.timescale_den,
+        hdr.length
+    );
+    if hdr.header_len as usize != ivf::IVF_HEADER_SIZE {
+        // skip extra header bytes
+        logln!("{}: IVF header length={}", fname, hdr.header_len);
+        reader.seek(SeekFrom::Start(hdr.header_len as u64))?;
+    }
+    if hdr.codec != FCC_AV01 {
+        logln!(
+            "{}: unsupport codec(0x{}), enumerate frames only",
+            fname,
+            hex::encode_upper(hdr.codec)
+        );
+        config.set_unsupported();
+    }
+
+    let mut seq = new_sequence(config)?;
+    config
+        .timebase
+        .set(Some((hdr.timescale_den as u64, hdr.timescale_num as u64)));
+    if let Some(target) = config.seek {
+        seek_ivf_keyframe(&mut reader, &hdr, target, &mut seq, fname)?;
+    }
+    let mut nframe = 0;
+    let mut total_size = 0;
+    let (mut min_pts, mut max_pts) = (u64::MAX, 0);
+    let mut prev_pts = None;
+
+    // parse IVF frames, follow frames appended to growing file
+    let mut last_update = time::Instant::now();
+    loop {
+        let frame_pos = reader.stream_position()?;
+        let frame = match ivf::parse_ivf_frame(&mut reader) {
+            Ok(frame) if !config.follow => frame,
+            Ok(frame) => {
+                // wait until whole frame is written
+                let frame_end = frame_pos + (ivf::IVF_FRAME_HEADER_SIZE as u64) + frame.size as u64;
+                let stream_len = reader.seek(SeekFrom::End(0))?;
+                reader.seek(SeekFrom::Start(
+                    frame_pos + ivf::IVF_FRAME_HEADER_SIZE as u64,
+                ))?;
+                if frame_end <= stream_len {
+                    frame
+                } else if follow_wait(last_update, config) {
+                    reader.seek(SeekFrom::Start(frame_pos))?;
+                    continue;
+                } else {
+                    break;
+                }
+            }
+            Err(_) if config.follow && follow_wait(last_update, config) => {
+                reader.seek(SeekFrom::Start(frame_pos))?;
+                continue;
+            }
+            Err(_) => break,
+        };
+        last_update = time::Instant::now();
+        if config.frame_limit_reached() {
+            break;
+        }
+        config.pts.set(Some(frame.pts as i64));
+        if config.verbose > 0 {
+            logln!("IVF F#{} size={}", frame.pts, frame.size);
+        }
+        // check pts discontinuity
+        match prev_pts {
+            Some(prev) if frame.pts == prev => {
+                logln!("{}: IVF F#{} duplicate pts", fname, frame.pts)
+            }
+            Some(prev) if frame.pts < prev => logln!(
+                "{}: IVF F#{} pts jumps backwards from {}",
+                fname,
+                frame.pts,
+                prev
+            ),
+            _ => {}
+        }
+        prev_pts = Some(frame.pts);
+        min_pts = cmp::min(min_pts, frame.pts);
+        max_pts = cmp::max(max_pts, frame.pts);
+        nframe += 1;
+        total_size += frame.size as u64;
+
+        let mut sz = frame.size;
+        let pos = reader.stream_position()?;
+        // parse OBU(open bitstream unit)s
+        while hdr.codec == FCC_AV01 && sz > 0 {
+            let obu = obu::parse_obu_header(&mut reader, sz)?;
+            print_obu_header(&obu, config);
+            sz -= obu.header_len + obu.obu_size;
+            let pos = reader.stream_position()?;
+            process_obu(&mut reader, &mut seq, &obu, config);
+            reader.seek(SeekFrom::Start(pos + obu.obu_size as u64))?;
+        }
+        reader.seek(SeekFrom::Start(pos + frame.size as u64))?;
+    }
+
+    // measured frame rate from pts, nominal values from IVF header
+    if nframe > 1 && min_pts < max_pts && hdr.timescale_num > 0 {
+        let tick = hdr.timescale_den as f64 / hdr.timescale_num as f64; // [sec]
+        let interval = (max_pts - min_pts) as f64 / (nframe - 1) as f64;
+        let duration = ((max_pts - min_pts) as f64 + interval) * tick;
+        logln!(
+            "{}: {} frames, duration={:.3}s fps={:.2} bitrate={:.1}kbps (nominal fps={:.2})",
+            fname,
+            nframe,
+            duration,
+            1.0 / (interval * tick),
+            total_size as f64 * 8.0 / duration / 1000.0,
+            1.0 / tick
+        );
+    }
+    if hdr.codec != FCC_AV01 {
+        logln!(
+            "{}: codec={:?} {} frames, {} bytes",
+            fname,
+            codec,
+            nframe,
+            total_size
+        );
+    }
+    Ok(())
+}
+
+/// check temporal unit contains shown key frame (random access point)
+fn is_random_access_point(data: &[u8], seq: &mut av1::Sequence) -> bool {
+    let sz = data.len() as u64;
+    let mut tu = io::Cursor::new(data);
+    let mut key_frame = false;
+    while tu.position() < sz {
+        let remain = (sz - tu.position()) as u32;
+        let obu = match obu::parse_obu_header(&mut tu, remain) {
+            Ok(obu) => obu,
+            Err(_) => break,
+        };
+        let pos = tu.position();
+        let reader = &mut io::Read::take(&mut tu, obu.obu_size as u64);
+        match (obu.obu_type, &seq.sh) {
+            (obu::OBU_SEQUENCE_HEADER, _) => {
+                if let Some(sh) = obu::parse_sequence_header(reader) {
+                    seq.sh = Some(sh);
+                }
+            }
+            (obu::OBU_FRAME_HEADER, Some(sh)) | (obu::OBU_FRAME, Some(sh)) => {
+                // update reference frames for subsequent frame headers
+                if let Some(fh) = obu::parse_frame_header(reader, sh, &mut seq.rfman) {
+                    key_frame |= fh.frame_type == obu::KEY_FRAME && fh.show_frame;
+                    seq.decode_frame_wrapup(&fh);
+                }
+            }
+            _ => {}
+        }
+        tu.set_position(pos + obu.obu_size as u64);
+    }
+    // findings in scan are reported when frames are parsed again
+    conformance::take_findings();
+    key_frame
+}
+
+/// scan IVF frames and seek to nearest key frame before the target
+fn seek_ivf_keyframe<R: io::Read + io::Seek>(
+    mut reader: R,
+    hdr: &ivf::IvfHeader,
+    target: SeekTarget,
+    seq: &mut av1::Sequence,
+    fname: &str,
+) -> io::Result<()> {
+    let mut scan = av1::Sequence::new();
+    let mut keyframe = None; // (position, pts, Sequence Header)
+    let mut index = 0;
+    loop {
+        let pos = reader.stream_position()?;
+        let frame = match ivf::parse_ivf_frame(&mut reader) {
+            Ok(frame) => frame,
+            Err(_) => break,
+        };
+        let reached = match target {
+            SeekTarget::Time(ms) => {
+                hdr.timescale_num > 0
+                    && frame.pts * hdr.timescale_den as u64 * 1000 > ms * hdr.timescale_num as u64
+            }
+            SeekTarget::Frame(n) => index > n,
+        };
+        if reached {
+            break;
+        }
+        let mut data = vec![0; frame.size as usize];
+        reader.read_exact(&mut data)?;
+        if is_random_access_point(&data, &mut scan) {
+            keyframe = Some((pos, frame.pts, scan.sh.clone()));
+        }
+        index += 1;
+    }
+    match keyframe {
+        Some((pos, pts, sh)) => {
+            logln!("{}: seek to IVF F#{} (key frame)", fname, pts);
+            reader.seek(SeekFrom::Start(pos))?;
+            // Sequence Header may precede the key frame
+            seq.sh = seq.sh.take().or(sh);
+        }
+        None => {
+            logln!("{}: no key frame found before seek position", fname);
+            reader.seek(SeekFrom::Start(hdr.header_len as u64))?;
+        }
+    }
+    Ok(())
+}
+
+/// acceptable distance between chapter start and keyframe [nsec]
+const CHAPTER_KEYFRAME_TOLERANCE_NS: i64 = 500_000_000;
+
+/// print Matroska Segment Information
+fn print_webm_info(info: &mkv::SegmentInfo, fname: &str) {
+    let mut items = Vec::new();
+    if let Some(duration_ns) = info.duration_ns() {
+        items.push(format!("duration={:.3}s", duration_ns / 1_000_000_000.0));
+    }
+    if let Some(ref title) = info.title {
+        items.push(format!("title=\"{}\"", title));
+    }
+    if let Some(ref muxing_app) = info.muxing_app {
+        items.push(format!("muxing_app=\"{}\"", muxing_app));
+    }
+    if let Some(ref writing_app) = info.writing_app {
+        items.push(format!("writing_app=\"{}\"", writing_app));
+    }
+    if !items.is_empty() {
+        logln!("{}: Info {}", fname, items.join(" "));
+    }
+}
+
+/// parse WebM format
+fn parse_webm_format<R: io::Read + io::Seek>(
+    mut reader: R,
+    fname: &str,
+    config: &AppConfig,
+) -> io::Result<()> {
+    // open Matroska/WebM file
+    let mut webm = mkv::open_mkvfile(&mut reader)?;
+
+    let codec_id = mkv::CODEC_V_AV1;
+    let av1_tracks = webm.find_tracks(codec_id);
+    let track_num = match config.mp4.track_id {
+        Some(id) => av1_tracks.iter().cloned().find(|&num| num == id as u64),
+        None => av1_tracks.first().cloned(),
+    };
+    let track_num = match track_num {
+        Some(num) => num,
+        _ => {
+            logln!("{}: Matroska/WebM \"{}\" codec not found", fname, codec_id);
+            config.set_unsupported();
+            return Ok(());
+        }
+    };
+    if let Some(fmt) = config.export {
+        return export_webm_blocks(reader, &mut webm, track_num, fmt, config);
+    }
+    if av1_tracks.len() > 1 {
+        logln!(
+            "{}: {} \"{}\" tracks, select track#{}",
+            fname,
+            av1_tracks.len(),
+            codec_id,
+            track_num
+        );
+    }
+    match webm.get_videosetting(track_num) {
+        Some(video) => logln!(
+            "{}: Matroska/WebM codec=\"{}\" size={}x{}",
+            fname,
+            codec_id,
+            video.pixel_width,
+            video.pixel_height
+        ),
+        None => logln!(
+            "{}: Matroska/WebM codec=\"{}\" size=(unknown)",
+            fname,
+            codec_id
+        ),
+    }
+    print_webm_info(webm.get_info(), fname);
+    let codec_delay = webm.get_codec_delay(track_num);
+    if codec_delay > 0 {
+        logln!(
+            "{}: CodecDelay={:.3}ms on video track",
+            fname,
+            codec_delay as f64 / 1e6
+        );
+    }
+    if config.verbose > 0 {
+        for tag in webm.get_tags() {
+            if tag.track_uid != 0 {
+                logln!(
+                    "  tag[track_uid={}] {}={:?}",
+                    tag.track_uid,
+                    tag.name,
+                    tag.value
+                );
+            } else {
+                logln!("  tag {}={:?}", tag.name, tag.value);
+            }
+        }
+    }
+    for (idx, chapter) in webm.get_chapters().iter().enumerate() {
+        logln!(
+            "{}: chapter#{} start={:.3}s name={:?}",
+            fname,
+            idx + 1,
+            chapter.time_start as f64 / 1e9,
+            chapter.name
+        );
+    }
+    let mut ncrc_errors = report_crc_errors(webm.get_crc_errors(), fname, 0);
+    let mut nskipped = 0;
+
+    let mut start_ns = None; // skip Blocks before keyframe found by scanning
+    match config.seek {
+        Some(SeekTarget::Time(seek_ms)) => {
+            match webm.seek_to_time(&mut reader, seek_ms as i64 * 1_000_000)? {
+                Some(timecode) => logln!(
+                    "{}: seek to Cluster timecode={} ({} cues)",
+                    fname,
+                    timecode,
+                    webm.get_cues().len()
+                ),
+                None => logln!("{}: no Cluster found before {}ms", fname, seek_ms),
+            }
+        }
+        Some(SeekTarget::Frame(index)) => {
+            // scan Blocks to find nearest keyframe Block before the frame index
+            let mut nblock = 0;
+            while let Some(block) = webm.next_block(&mut reader)? {
+                if block.track_num != track_num {
+                    continue;
+                }
+                if nblock > index {
+                    break;
+                }
+                if block.keyframe {
+                    start_ns = Some(block.timestamp_ns);
+                }
+                nblock += 1;
+            }
+            webm.seek_to_time(&mut reader, start_ns.unwrap_or(0))?;
+            if let Some(ns) = start_ns {
+                logln!(
+                    "{}: seek to keyframe Block time={:.3}ms",
+                    fname,
+                    ns as f64 / 1e6
+                );
+            }
+        }
+        None => {}
+    }
+
+    let mut seq = new_sequence(config)?;
+    config
+        .timebase
+        .set(Some((webm.get_timecode_scale(), 1_000_000_000)));
+    let (mut nblock, mut first_ts, mut last_ts) = (0, 0, 0);
+    let mut keyframe_ts = Vec::new(); // timestamps of Blocks starting with KEY_FRAME
+    let (mut ndiscard, mut discard_ns) = (0, 0);
+
+    // parse WebM block, follow Blocks appended to growing file
+    let mut last_update = time::Instant::now();
+    loop {
+        let nblock_prev = nblock;
+        while let Some(block) = webm.next_block(&mut reader)? {
+            if config.frame_limit_reached() {
+                break;
+            }
+            ncrc_errors = report_crc_errors(webm.get_crc_errors(), fname, ncrc_errors);
+            nskipped = report_skipped(webm.get_skipped(), fname, nskipped);
+            if block.track_num != track_num || start_ns.is_some_and(|ns| block.timestamp_ns < ns) {
+                // skip non AV1 track data
+                continue;
+            }
+            if nblock == 0 {
+                first_ts = block.timestamp_ns;
+            }
+            last_ts = block.timestamp_ns;
+            nblock += 1;
+            if let Some(padding) = block.discard_padding {
+                ndiscard += 1;
+                discard_ns += padding;
+            }
+
+            config.pts.set(Some(block.timecode));
+            if config.verbose > 0 {
+                logln!(
+                    "MKV F#{} time={:.3}ms flags=0x{:02x} size={}",
+                    block.timecode,
+                    block.timestamp_ms(),
+                    block.flags,
+                    block.size
+                );
+                for addition in block.additions.iter() {
+                    logln!(
+                        "  BlockAdditional id={} size={}",
+                        addition.add_id,
+                        addition.data.len()
+                    );
+                    if config.verbose > 1 {
+                        if let Some(hdr10plus) = addition.parse_st2094_40() {
+                            logln!("    {:?}", hdr10plus);
+                        }
+                    }
+                }
+            }
+            let mut sz = block.size as u32;
+            let mut first_frame_type = None;
+            // parse OBU(open bitstream unit)s
+            while sz > 0 {
+                let obu = obu::parse_obu_header(&mut reader, sz)?;
+                print_obu_header(&obu, config);
+                sz -= obu.header_len + obu.obu_size;
+                let pos = reader.stream_position()?;
+                if let Some(fh) = process_obu(&mut reader, &mut seq, &obu, config) {
+                    if first_frame_type.is_none() && !fh.show_existing_frame {
+                        first_frame_type = Some(fh.frame_type);
+                    }
+                }
+                reader.seek(SeekFrom::Start(pos + obu.obu_size as u64))?;
+            }
+
+            if first_frame_type == Some(obu::KEY_FRAME) {
+                keyframe_ts.push(block.timestamp_ns);
+            }
+            // keyframe Block must start with KEY_FRAME for random access
+            match first_frame_type {
+                Some(frame_type) if block.keyframe && frame_type != obu::KEY_FRAME => logln!(
+                    "{}: MKV F#{} marked keyframe but contains {}",
+                    fname,
+                    block.timecode,
+                    av1::stringify::frame_type(frame_type)
+                ),
+                _ => {}
+            }
+
+            reader.seek(SeekFrom::Start(block.offset + block.size))?;
+        }
+        ncrc_errors = report_crc_errors(webm.get_crc_errors(), fname, ncrc_errors);
+        nskipped = report_skipped(webm.get_skipped(), fname, nskipped);
+        if nblock_prev < nblock {
+            last_update = time::Instant::now();
+        }
+        if !config.follow || !follow_wait(last_update, config) {
+            break;
+        }
+    }
+
+    // chapter start points should be near random access points
+    for (idx, chapter) in webm.get_chapters().iter().enumerate() {
+        let start = chapter.time_start as i64;
+        let nearest = keyframe_ts.iter().map(|&ts| (ts - start).abs()).min();
+        match nearest {
+            Some(distance) if distance <= CHAPTER_KEYFRAME_TOLERANCE_NS => {}
+            _ => logln!(
+                "{}: chapter#{} start={:.3}s is not near keyframe{}",
+                fname,
+                idx + 1,
+                start as f64 / 1e9,
+                match nearest {
+                    Some(distance) => format!(" (nearest {:.3}s)", distance as f64 / 1e9),
+                    None => String::new(),
+                }
+            ),
+        }
+    }
+
+    // cross-check Colour element with bitstream
+    if let Some(colour) = webm
+        .get_videosetting(track_num)
+        .and_then(|video| video.colour.as_ref())
+    {
+        check_webm_colour(colour, &seq, fname);
+    }
+
+    if ndiscard > 0 {
+        logln!(
+            "{}: DiscardPadding in {} blocks (total {:.3}ms)",
+            fname,
+            ndiscard,
+            discard_ns as f64 / 1e6
+        );
+    }
+
+    // nominal frame rate from DefaultDuration, or estimate from Block timestamps
+    let default_duration = webm
+        .get_default_duration(track_num)
+        .filter(|&duration| duration > 0);
+    let estimated = nblock > 1 && first_ts < last_ts;
+    let interval = match default_duration {
+        Some(duration) => duration as f64,
+        None if estimated => (last_ts - first_ts) as f64 / (nblock - 1) as f64,
+        None => return Ok(()),
+    };
+    let duration = webm
+        .get_duration_ns()
+        .unwrap_or((last_ts - first_ts) as f64 + interval - discard_ns as f64);
+    let fps = 1e9 / interval;
+    logln!(
+        "{}: {} blocks, duration={:.3}s fps={:.2}{}",
+        fname,
+        nblock,
+        duration / 1e9,
+        fps,
+        if default_duration.is_some() {
+            " (DefaultDuration)"
+        } else {
+            ""
+        }
+    );
+
+    // compare with timing_info in Sequence Header
+    if let Some(ti_fps) = seq.sh.as_ref().and_then(timing_info_fps) {
+        let mismatch = (ti_fps - fps).abs() > fps * 0.01;
+        logln!(
+            "{}: timing_info fps={:.2}{}",
+            fname,
+            ti_fps,
+            if mismatch { " (mismatch)" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+/// check consistency between Matroska Colour and ColorConfig/HDR metadata OBUs
+fn check_webm_colour(colour: &mkv::Colour, seq: &av1::Sequence, fname: &str) {
+    let mut mismatches = Vec::new();
+    if let Some(ref sh) = seq.sh {
+        let cc = &sh.color_config;
+        // CP/TC/MC_UNSPECIFIED(2) carries no information
+        let cicp = [
+            ("Primaries", colour.primaries, cc.color_primaries),
+            (
+                "TransferCharacteristics",
+                colour.transfer_characteristics,
+                cc.transfer_characteristics,
+            ),
+            (
+                "MatrixCoefficients",
+                colour.matrix_coefficients,
+                cc.matrix_coefficients,
+            ),
+        ];
+        for &(name, webm_value, av1_value) in cicp.iter() {
+            match webm_value {
+                Some(value) if value != 2 && av1_value != 2 && value != av1_value as u64 => {
+                    mismatches.push(format!("{}={} color_config={}", name, value, av1_value))
+                }
+                _ => {}
+            }
+        }
+        // Range: 1=broadcast, 2=full
+        match colour.range {
+            Some(range @ 1) | Some(range @ 2) if (range == 2) != cc.color_range => mismatches.push(
+                format!("Range={} color_range={}", range, cc.color_range as u8),
+            ),
+            _ => {}
+        }
+    }
+    if let (Some(max_cll), Some(ref cll)) = (colour.max_cll, &seq.hdr_cll) {
+        if max_cll != cll.max_cll as u64 {
+            mismatches.push(format!("MaxCLL={} max_cll={}", max_cll, cll.max_cll));
+        }
+    }
+    if let (Some(max_fall), Some(ref cll)) = (colour.max_fall, &seq.hdr_cll) {
+        if max_fall != cll.max_fall as u64 {
+            mismatches.push(format!("MaxFALL={} max_fall={}", max_fall, cll.max_fall));
+        }
+    }
+    if let (Some(ref mm), Some(ref mdcv)) = (&colour.mastering_metadata, &seq.hdr_mdcv) {
+        // luminance_max is 24.8, luminance_min is 18.14 fixed-point
+        let luminance_max = mdcv.luminance_max as f64 / 256.0;
+        let luminance_min = mdcv.luminance_min as f64 / 16384.0;
+        if (mm.luminance_max - luminance_max).abs() > 1.0 {
+            mismatches.push(format!(
+                "LuminanceMax={} luminance_max={}",
+                mm.luminance_max, luminance_max
+            ));
+        }
+        if (mm.luminance_min - luminance_min).abs() > 0.001 {
+            mismatches.push(format!(
+                "LuminanceMin={} luminance_min={}",
+                mm.luminance_min, luminance_min
+            ));
+        }
+        // chromaticity coordinates are 0.16 fixed-point
+        let chromaticity = [
+            ("R", mm.primary_r_chromaticity, 0),
+            ("G", mm.primary_g_chromaticity, 1),
+            ("B", mm.primary_b_chromaticity, 2),
+        ];
+        for &(name, (x, y), i) in chromaticity.iter() {
+            let av1_x = mdcv.primary_chromaticity_x[i] as f64 / 65536.0;
+            let av1_y = mdcv.primary_chromaticity_y[i] as f64 / 65536.0;
+            if (x - av1_x).abs() > 0.001 || (y - av1_y).abs() > 0.001 {
+                mismatches.push(format!(
+                    "Primary{}Chromaticity=({:.4},{:.4}) primary_chromaticity=({:.4},{:.4})",
+                    name, x, y, av1_x, av1_y
+                ));
+            }
+        }
+        let (x, y) = mm.white_point_chromaticity;
+        let av1_x = mdcv.white_point_chromaticity_x as f64 / 65536.0;
+        let av1_y = mdcv.white_point_chromaticity_y as f64 / 65536.0;
+        if (x - av1_x).abs() > 0.001 || (y - av1_y).abs() > 0.001 {
+            mismatches.push(format!(
+                "WhitePointChromaticity=({:.4},{:.4}) white_point_chromaticity=({:.4},{:.4})",
+                x, y, av1_x, av1_y
+            ));
+        }
+    }
+    for mismatch in mismatches {
+        logln!("{}: Colour mismatch {}", fname, mismatch);
+    }
+}
+
+/// frame rate from timing_info() in Sequence Header
+fn timing_info_fps(sh: &obu::SequenceHeader) -> Option<f64> {
+    let ti = &sh.timing_info;
+    if !sh.timing_info_present_flag || ti.num_units_in_display_tick == 0 {
+        return None;
+    }
+    let ticks = if ti.equal_picture_interval {
+        ti.num_ticks_per_picture.max(1)
+    } else {
+        1
+    };
+    Some(ti.time_scale as f64 / (ti.num_units_in_display_tick as f64 * ticks as f64))
+}
+
+/// process AV1CodecConfigurationBox::configOBUs
+fn process_config_obus(
+    av1cc: &mp4::AV1CodecConfigurationBox,
+    seq: &mut av1::Sequence,
+    config: &AppConfig,
+) -> io::Result<()> {
+    let mut cur = av1cc.config_obus_reader();
+    let mut config_sz = av1cc.config_obus().len() as u32;
+    while config_sz > 0 {
+        let obu = obu::parse_obu_header(&mut cur, config_sz)?;
+        print_obu_header(&obu, config);
+        config_sz -= obu.header_len + obu.obu_size;
+        let pos = cur.position();
+        process_obu(&mut cur, seq, &obu, config);
+        cur.set_position(pos + obu.obu_size as u64);
+    }
+    Ok(())
+}
+
+/// load AV1CodecConfigurationBox payload from initialization segment, av1C box or its payload
+fn load_init_config(path: &str, options: &mp4::Mp4Options) -> io::Result<Vec<u8>> {
+    let mut data = fs::read(path)?;
+    if data.len() >= 8 && data[4..8] == mp4::BOX_FILETYPE {
+        let mp4 = mp4::open_mp4file_with(io::Cursor::new(&data), options)?;
+        return match mp4.get_av1config() {
+            Some((_, av1cc)) => Ok(av1cc.to_bytes()),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "av01 track not found in initialization segment",
+            )),
+        };
+    }
+    if data.len() >= 8 && data[4..8] == mp4::BOX_AV1CODECCONFIG {
+        data.drain(..8);
+    }
+    Ok(data)
+}
+
+/// create Sequence, configOBUs in initialization segment (--init) are processed first
+fn new_sequence(config: &AppConfig) -> io::Result<av1::Sequence> {
+    let mut seq = av1::Sequence::new();
+    if let Some(ref init) = config.init {
+        let payload = load_init_config(init, &config.mp4)?;
+        let av1cc = mp4::read_av1codecconfig(&payload)?;
+        process_config_obus(&av1cc, &mut seq, config)?;
+    }
+    Ok(seq)
+}
+
+/// open MP4 file, or media segment ('styp'/'moof' without 'moov') with initialization segment
+fn open_mp4_input<R: io::Read + io::Seek>(
+    mut reader: R,
+    init: Option<&str>,
+    options: &mp4::Mp4Options,
+) -> io::Result<mp4::IsoBmff> {
+    if !mp4::is_media_segment(&mut reader)? {
+        return mp4::open_mp4file_with(reader, options);
+    }
+    match init {
+        Some(init) => {
+            let init_reader = io::BufReader::new(fs::File::open(init)?);
+            mp4::open_mp4segment_with(init_reader, reader, options)
+        }
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "media segment requires initialization segment (--init)",
+        )),
+    }
+}
+
+/// export MP4 sample table
+fn export_mp4_samples<R: io::Read + io::Seek>(
+    mut reader: R,
+    mp4: &mp4::IsoBmff,
+    seq: &mut av1::Sequence,
+    fmt: ExportFormat,
+    config: &AppConfig,
+) -> io::Result<()> {
+    let mut records = Vec::with_capacity(mp4.get_samples().len());
+    for (index, sample) in mp4.get_samples().iter().enumerate() {
+        reader.seek(SeekFrom::Start(sample.pos))?;
+        let mut frame_types = Vec::new();
+        let mut sz = sample.size;
+        while sz > 0 {
+            let obu_size = cmp::min(sz, u32::MAX as u64) as u32;
+            let obu = obu::parse_obu_header(&mut reader, obu_size)?;
+            sz -= (obu.header_len + obu.obu_size) as u64;
+            let pos = reader.stream_position()?;
+            if let Some(fh) = process_obu(&mut reader, seq, &obu, config) {
+                frame_types.push(if fh.show_existing_frame {
+                    "ShowExistingFrame"
+                } else {
+                    av1::stringify::frame_type(fh.frame_type)
+                });
+            }
+            reader.seek(SeekFrom::Start(pos + obu.obu_size as u64))?;
+        }
+        let record = SampleRecord {
+            index,
+            offset: sample.pos,
+            size: sample.size,
+            dts: sample.dts,
+            pts: sample.pts,
+            sync: sample.is_sync,
+            frame_types,
+        };
+        if fmt == ExportFormat::Csv {
+            if index == 0 {
+                outln!("index,offset,size,dts,pts,sync,frame_types");
+            }
+            outln!(
+                "{},{},{},{},{},{},{}",
+                record.index,
+                record.offset,
+                record.size,
+                record.dts,
+                record.pts,
+                record.sync as u8,
+                record.frame_types.join(" ")
+            );
+        } else {
+            records.push(record);
+        }
+    }
+    write_export_records(fmt, &records)
+}
+
+/// export WebM block table, frame types are parsed in AV1 track only
+fn export_webm_blocks<R: io::Read + io::Seek>(
+    mut reader: R,
+    webm: &mut mkv::Matroska,
+    track_num: u64,
+    fmt: ExportFormat,
+    config: &AppConfig,
+) -> io::Result<()> {
+    let mut seq = new_sequence(config)?;
+    let mut records = Vec::new();
+    let mut index = 0;
+    while let Some(block) = webm.next_block(&mut reader)? {
+        let mut frame_types = Vec::new();
+        let mut sz = block.size;
+        while block.track_num == track_num && sz > 0 {
+            let obu_size = cmp::min(sz, u32::MAX as u64) as u32;
+            let obu = obu::parse_obu_header(&mut reader, obu_size)?;
+            sz -= (obu.header_len + obu.obu_size) as u64;
+            let pos = reader.stream_position()?;
+            if let Some(fh) = process_obu(&mut reader, &mut seq, &obu, config) {
+                frame_types.push(if fh.show_existing_frame {
+                    "ShowExistingFrame"
+                } else {
+                    av1::stringify::frame_type(fh.frame_type)
+                });
+            }
+            reader.seek(SeekFrom::Start(pos + obu.obu_size as u64))?;
+        }
+        reader.seek(SeekFrom::Start(block.offset + block.size))?;
+        let record = BlockRecord {
+            index,
+            track: block.track_num,
+            timecode: block.timecode,
+            timestamp_ns: block.timestamp_ns,
+            flags: block.flags,
+            keyframe: block.keyframe,
+            offset: block.offset,
+            size: block.size,
+            lace_count: block.lace_count,
+            frame_types,
+        };
+        if fmt == ExportFormat::Csv {
+            if index == 0 {
+                outln!("index,track,timecode,timestamp_ns,flags,keyframe,offset,size,lace_count,frame_types");
+            }
+            outln!(
+                "{},{},{},{},{},{},{},{},{},{}",
+                record.index,
+                record.track,
+                record.timecode,
+                record.timestamp_ns,
+                record.flags,
+                record.keyframe as u8,
+                record.offset,
+                record.size,
+                record.lace_count,
+                record.frame_types.join(" ")
+            );
+        } else {
+            records.push(record);
+        }
+        index += 1;
+    }
+    write_export_records(fmt, &records)
+}
+
+/// check 'av01' brand and track in MP4(ISOBMFF) file
+fn check_av01_track(mp4: &mp4::IsoBmff, fname: &str) -> bool {
+    let brand_av01 = mp4::FCC::from(mp4::BRAND_AV01);
+    let ftyp = mp4.get_filetype();
+    if mp4.get_av1config().is_none() {
+        logln!(
+            "{}: ISOBMFF/MP4 {} track not found (brands={})",
+            fname,
+            brand_av01,
+            ftyp.brands()
+        );
+        return false;
+    }
+    if !ftyp.has_brand(mp4::BRAND_AV01) {
+        // 'av01' brand is recommended, but not mandatory
+        logln!(
+            "{}: ISOBMFF/MP4 {} brand not found (brands={})",
+            fname,
+            brand_av01,
+            ftyp.brands()
+        );
+    }
+    true
+}
+
+/// print MP4(ISOBMFF) 'av01' track summary
+fn print_mp4_summary(mp4: &mp4::IsoBmff, fname: &str, config: &AppConfig) {
+    let (av1se, av1cc) = mp4.get_av1config().unwrap();
+    let tkhd = mp4.get_trackheader();
+    let track_size = match tkhd {
+        Some(tkhd) if tkhd.is_identity() => {
+            format!(" tkhd={}x{}", tkhd.width >> 16, tkhd.height >> 16)
+        }
+        Some(tkhd) => format!(
+            " tkhd={}x{} rotation={}",
+            tkhd.width >> 16,
+            tkhd.height >> 16,
+            tkhd.rotation()
+        ),
+        None => "".to_owned(),
+    };
+    let image_sequence = if mp4.get_handler() == mp4::HANDLER_PICTURE
+        || mp4.get_filetype().has_brand(mp4::BRAND_AVIS)
+    {
+        " (image sequence)"
+    } else {
+        ""
+    };
+    logln!(
+        "{}: ISOBMFF/MP4 codec={} size={}x{}{}{}",
+        fname,
+        mp4::FCC::from(mp4::BRAND_AV01),
+        av1se.width,
+        av1se.height,
+        track_size,
+        image_sequence
+    );
+    if config.verbose > 0 {
+        for (key, value) in mp4.get_tags() {
+            logln!("  tag {}={:?}", key, value);
+        }
+    }
+    if config.verbose > 1 {
+        logln!("  {:?}", av1se);
+        logln!("  {:?}", av1cc);
+        if let Some(tkhd) = tkhd {
+            logln!("  {:?}", tkhd);
+        }
+    }
+}
+
+/// report skipped malformed regions after `from`-th, return number of regions
+fn report_skipped(skipped: &[(u64, u64)], fname: &str, from: usize) -> usize {
+    for &(start, end) in &skipped[from..] {
+        logln!(
+            "{}: skip malformed region {}..{} ({} bytes)",
+            fname,
+            start,
+            end,
+            end - start
+        );
+    }
+    skipped.len()
+}
+
+/// report Matroska elements with CRC-32 mismatch
+fn report_crc_errors(errors: &[(u32, u64)], fname: &str, from: usize) -> usize {
+    for &(id, pos) in &errors[from..] {
+        logln!(
+            "{}: CRC-32 mismatch in element 0x{:X} at {}",
+            fname,
+            id,
+            pos
+        );
+    }
+    errors.len()
+}
+
+/// report encrypted 'av01' track
+fn report_protection(mp4: &mp4::IsoBmff, fname: &str, config: &AppConfig) {
+    let sinf = match mp4.get_protection() {
+        Some(sinf) => sinf,
+        None => return,
+    };
+    logln!(
+        "{}: {} track is encrypted (scheme={} version={:#x} default_KID={})",
+        fname,
+        sinf.original_format,
+        sinf.scheme_type,
+        sinf.scheme_version,
+        hex::encode(sinf.default_kid)
+    );
+    if config.verbose > 0 {
+        let layout = mp4.get_aux_layout();
+        logln!(
+            "  saiz samples={} saio offsets={} senc samples={} subsamples={}",
+            layout.saiz_sample_count,
+            layout.saio_entry_count,
+            layout.senc_sample_count,
+            layout.senc_subsamples
+        );
+    }
+    if config.verbose > 1 {
+        logln!("  {:?}", sinf);
+    }
+}
+
+/// parse AV1 Samples in MP4(ISOBMFF) file
+fn process_mp4_samples<R: io::Read + io::Seek>(
+    mut reader: R,
+    samples: &[mp4::Sample],
+    seq: &mut av1::Sequence,
+    tkhd: Option<&mp4::TrackHeaderBox>,
+    track_size_checked: &mut bool,
+    fname: &str,
+    config: &AppConfig,
+) -> io::Result<Option<u8>> {
+    let mut first_frame_type = None;
+    for sample in samples {
+        if config.frame_limit_reached() {
+            break;
+        }
+        config.pts.set(Some(sample.pts));
+        reader.seek(SeekFrom::Start(sample.pos))?;
+        let mut sz = sample.size;
+        // parse OBU(open bitstream unit)s
+        while sz > 0 {
+            let obu_size = cmp::min(sz, u32::MAX as u64) as u32;
+            let obu = obu::parse_obu_header(&mut reader, obu_size)?;
+            print_obu_header(&obu, config);
+            sz -= (obu.header_len + obu.obu_size) as u64;
+            let pos = reader.stream_position()?;
+            let fh = process_obu(&mut reader, seq, &obu, config);
+            if let Some(fh) = fh.as_ref() {
+                if !fh.show_existing_frame {
+                    first_frame_type = first_frame_type.or(Some(fh.frame_type));
+                }
+            }
+            if let (Some(tkhd), Some(sh), Some(fh)) = (tkhd, seq.sh.as_ref(), fh.as_ref()) {
+                if !*track_size_checked && !fh.show_existing_frame {
+                    check_track_size(fname, tkhd, sh, fh);
+                    *track_size_checked = true;
+                }
+            }
+            reader.seek(SeekFrom::Start(pos + obu.obu_size as u64))?;
+        }
+    }
+    Ok(first_frame_type)
+}
+
+/// find index of nearest sync sample before the seek target
+fn seek_sync_sample(samples: &[mp4::Sample], timescale: u32, target: SeekTarget) -> usize {
+    let last = match target {
+        SeekTarget::Time(ms) => samples
+            .iter()
+            .rposition(|sample| sample.pts * 1000 <= ms as i64 * timescale as i64),
+        SeekTarget::Frame(index) => Some(cmp::min(index as usize, samples.len().saturating_sub(1))),
+    };
+    last.and_then(|last| samples[..=last].iter().rposition(|sample| sample.is_sync))
+        .unwrap_or(0)
+}
+
+/// wait for next polling, return false when idle timeout expired or frame limit reached
+fn follow_wait(last_update: time::Instant, config: &AppConfig) -> bool {
+    if config.frame_limit_reached() {
+        return false;
+    }
+    if let Some(timeout) = config.idle_timeout {
+        if last_update.elapsed() >= timeout {
+            return false;
+        }
+    }
+    thread::sleep(FOLLOW_INTERVAL);
+    true
+}
+
+/// check codec parameter string in av1C or playlist with Sequence Header, return false if no Sequence Header
+fn check_codec_string(
+    fname: &str,
+    source: &str,
+    declared: &str,
+    seq: &av1::Sequence,
+    config: &AppConfig,
+) -> bool {
+    let sh = match seq.sh {
+        Some(ref sh) => sh,
+        None => return false,
+    };
+    let codecs = av1::codec_string(sh);
+    if !av1::match_codec_string(declared, &codecs) {
+        config.report(
+            &conformance::CODEC_STRING,
+            fname,
+            &format!(
+                "codec string mismatch ({}={}, stream={})",
+                source, declared, codecs
+            ),
+        );
+    }
+    true
+}
+
+/// parse MP4(ISOBMFF) format
+fn parse_mp4_format<R: io::Read + io::Seek>(
+    mut reader: R,
+    fname: &str,
+    config: &AppConfig,
+) -> io::Result<()> {
+    // open MP4(ISOBMFF) file, or media segment with initialization segment
+    let mut mp4 = open_mp4_input(&mut reader, config.init.as_deref(), &config.mp4)?;
+    if config.verbose > 1 {
+        logln!("  {:?}", mp4.get_filetype());
+    }
+    let mut nskipped = report_skipped(mp4.get_skipped(), fname, 0);
+    if !check_av01_track(&mp4, fname) {
+        config.set_unsupported();
+        return Ok(());
+    }
+
+    if mp4.get_protection().is_some() {
+        // encrypted Samples are not parsed
+        print_mp4_summary(&mp4, fname, config);
+        report_protection(&mp4, fname, config);
+        return Ok(());
+    }
+
+    let mut seq = av1::Sequence::new();
+    config.timebase.set(Some((1, mp4.get_timescale() as u64)));
+    if let Some(fmt) = config.export {
+        process_config_obus(&mp4.get_av1config().unwrap().1, &mut seq, config)?;
+        return export_mp4_samples(reader, &mp4, &mut seq, fmt, config);
+    }
+    print_mp4_summary(&mp4, fname, config);
+    let av1c_codecs = mp4.get_av1config().unwrap().1.codec_string();
+    process_config_obus(&mp4.get_av1config().unwrap().1, &mut seq, config)?;
+    let av1c_checked = check_codec_string(fname, "av1C", &av1c_codecs, &seq, config);
+
+    // parse AV1 Samples
+    let tkhd = mp4.get_trackheader().cloned();
+    let mut track_size_checked = false;
+    let start = match config.seek {
+        Some(target) => {
+            let start = seek_sync_sample(mp4.get_samples(), mp4.get_timescale(), target);
+            logln!("{}: seek to sample#{} (sync sample)", fname, start);
+            start
+        }
+        None => 0,
+    };
+    process_mp4_samples(
+        &mut reader,
+        &mp4.get_samples()[start..],
+        &mut seq,
+        tkhd.as_ref(),
+        &mut track_size_checked,
+        fname,
+        config,
+    )?;
+    if !av1c_checked {
+        // configOBUs without Sequence Header
+        check_codec_string(fname, "av1C", &av1c_codecs, &seq, config);
+    }
+
+    // follow MovieFragmentBoxes appended to growing file
+    let mut last_update = time::Instant::now();
+    while config.follow && follow_wait(last_update, config) {
+        let nsample = mp4.get_samples().len();
+        let pos = mp4.get_parsed_pos();
+        mp4.parse_boxes(&mut reader, pos)?;
+        nskipped = report_skipped(mp4.get_skipped(), fname, nskipped);
+        if nsample < mp4.get_samples().len() {
+            process_mp4_samples(
+                &mut reader,
+                &mp4.get_samples()[nsample..],
+                &mut seq,
+                tkhd.as_ref(),
+                &mut track_size_checked,
+                fname,
+                config,
+            )?;
+            last_update = time::Instant::now();
+        }
+    }
+    Ok(())
+}
 
-use av1parser::*;
-use clap::{App, Arg};
-use std::cmp;
-use std::fs;
-use std::io;
-use std::io::{Seek, SeekFrom};
+/// parse primary image item in AVIF file
+fn parse_avif_format<R: io::Read + io::Seek>(
+    mut reader: R,
+    fname: &str,
+    config: &AppConfig,
+) -> io::Result<()> {
+    let (ftyp_box, image) = mp4::read_avif(&mut reader)?;
+    logln!(
+        "{}: AVIF image brands={} item_ID={} size={}x{}",
+        fname,
+        ftyp_box.brands(),
+        image.item_id,
+        image.width,
+        image.height
+    );
 
-mod av1;
-mod bitio;
-mod ivf;
-mod mkv;
-mod mp4;
-mod obu;
+    let mut seq = new_sequence(config)?;
+    match image.get_av1config() {
+        Some(ref av1cc) => process_config_obus(av1cc, &mut seq, config)?,
+        None => logln!("{}: av1C property not found", fname),
+    }
+    // AV1 Image Item Data
+    let mut data = Vec::new();
+    for &(offset, length) in &image.extents {
+        reader.seek(SeekFrom::Start(offset))?;
+        (&mut reader).take(length).read_to_end(&mut data)?;
+    }
+    process_temporal_unit(data, &mut seq, config)
+}
 
-/// application global config
-struct AppConfig {
-    verbose: u64,
+/// list initialization segment and media segments in DASH segment directory or MPD/M3U8 playlist
+fn list_dash_segments(dir: &str) -> io::Result<playlist::SegmentList> {
+    if playlist::is_playlist(path::Path::new(dir)) {
+        return playlist::open_playlist(path::Path::new(dir));
+    }
+    let mut list = playlist::SegmentList::default();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let name = path.file_name().unwrap().to_string_lossy().to_lowercase();
+        if name.contains("init") || name.ends_with(".mp4") {
+            list.init = Some(path);
+        } else if name.ends_with(".m4s") {
+            list.segments.push(path);
+        }
+    }
+    // sort numbered segments (e.g. seg-2.m4s < seg-10.m4s)
+    list.segments
+        .sort_by_key(|path| (path.as_os_str().len(), path.clone()));
+    Ok(list)
 }
 
-///
-/// process OBU(Open Bitstream Unit)
-///
-fn process_obu<R: io::Read>(
-    reader: &mut R,
+/// report statistics and continuity of completed media segment,
+/// return (last dts, sample duration) and number of continuity problems
+fn report_segment(
+    dir: &str,
+    segment: &path::Path,
+    index: usize,
+    samples: &[mp4::Sample],
+    first_frame_type: Option<u8>,
+    last: Option<(i64, i64)>,
+    timescale: u32,
+) -> (Option<(i64, i64)>, u64) {
+    let (first, last_sample) = match (samples.first(), samples.last()) {
+        (Some(first), Some(last_sample)) => (first, last_sample),
+        _ => {
+            logln!(
+                "{}: segment #{} {} has no samples",
+                dir,
+                index,
+                segment.display()
+            );
+            return (last, 0);
+        }
+    };
+    let delta = if samples.len() > 1 {
+        (last_sample.dts - first.dts) / (samples.len() as i64 - 1)
+    } else {
+        last.map_or(0, |(_, delta)| delta)
+    };
+    let duration = (last_sample.dts - first.dts + delta) as f64 / timescale as f64;
+    let size: u64 = samples.iter().map(|sample| sample.size).sum();
+    logln!(
+        "{}: segment #{} {} samples={} dts={} duration={:.3}s size={}",
+        dir,
+        index,
+        segment.display(),
+        samples.len(),
+        first.dts,
+        duration,
+        size
+    );
+    // cross-segment continuity
+    let mut problems = 0;
+    if !first.is_sync || first_frame_type.is_some_and(|ft| ft != obu::KEY_FRAME) {
+        logln!("{}: segment #{} does not start with key frame", dir, index);
+        problems += 1;
+    }
+    if let Some((last_dts, last_delta)) = last {
+        if first.dts != last_dts + last_delta {
+            logln!(
+                "{}: segment #{} timestamp discontinuity (dts={}, expected {})",
+                dir,
+                index,
+                first.dts,
+                last_dts + last_delta
+            );
+            problems += 1;
+        }
+    }
+    (Some((last_sample.dts, delta)), problems)
+}
+
+/// parse DASH segment directory (initialization segment and .m4s media segments) or MPD/M3U8 playlist
+fn parse_dash_segments(dir: &str, config: &AppConfig) -> io::Result<()> {
+    let list = list_dash_segments(dir)?;
+    let init = match list.init {
+        Some(init) => init,
+        None => {
+            logln!("{}: DASH initialization segment not found", dir);
+            return Ok(());
+        }
+    };
+    let mut reader = io::BufReader::new(fs::File::open(&init)?);
+    let mut mp4 = mp4::open_mp4file_with(&mut reader, &config.mp4)?;
+    let mut nskipped = report_skipped(mp4.get_skipped(), dir, 0);
+    if !check_av01_track(&mp4, dir) {
+        config.set_unsupported();
+        return Ok(());
+    }
+    print_mp4_summary(&mp4, dir, config);
+    if mp4.get_protection().is_some() {
+        // encrypted Samples are not parsed, collect auxiliary information only
+        for segment in list_dash_segments(dir)?.segments {
+            mp4.parse_boxes(io::BufReader::new(fs::File::open(segment)?), 0)?;
+        }
+        report_protection(&mp4, dir, config);
+        return Ok(());
+    }
+
+    let mut seq = av1::Sequence::new();
+    config.timebase.set(Some((1, mp4.get_timescale() as u64)));
+    process_config_obus(&mp4.get_av1config().unwrap().1, &mut seq, config)?;
+    let av1c_codecs = mp4.get_av1config().unwrap().1.codec_string();
+    check_codec_string(dir, "av1C", &av1c_codecs, &seq, config);
+    if let Some(ref codecs) = list.codecs {
+        check_codec_string(dir, "playlist", codecs, &seq, config);
+    }
+    let tkhd = mp4.get_trackheader().cloned();
+    let mut track_size_checked = false;
+
+    // (number of completed segments, position in current segment)
+    let (mut done, mut seg_pos) = (0, 0);
+    // (first sample index, first frame_type) of current segment
+    let (mut seg_sample, mut seg_frame_type) = (0, None);
+    let mut last_dts = None;
+    let mut last_update = time::Instant::now();
+    loop {
+        let segments = list_dash_segments(dir)?.segments;
+        for (idx, segment) in segments.iter().enumerate().skip(done) {
+            if config.frame_limit_reached() {
+                break;
+            }
+            if seg_pos == 0 {
+                if config.verbose > 0 {
+                    logln!("  segment {}", segment.display());
+                }
+                seg_sample = mp4.get_samples().len();
+                seg_frame_type = None;
+            }
+            let mut reader = io::BufReader::new(fs::File::open(segment)?);
+            let nsample = mp4.get_samples().len();
+            seg_pos = mp4.parse_boxes(&mut reader, seg_pos)?;
+            nskipped = report_skipped(mp4.get_skipped(), dir, nskipped);
+            if nsample < mp4.get_samples().len() {
+                let frame_type = process_mp4_samples(
+                    &mut reader,
+                    &mp4.get_samples()[nsample..],
+                    &mut seq,
+                    tkhd.as_ref(),
+                    &mut track_size_checked,
+                    dir,
+                    config,
+                )?;
+                seg_frame_type = seg_frame_type.or(frame_type);
+                last_update = time::Instant::now();
+            }
+            if idx + 1 < segments.len() || !config.follow {
+                // newer segment exists, current segment is complete
+                let (dts, problems) = report_segment(
+                    dir,
+                    segment,
+                    idx,
+                    &mp4.get_samples()[seg_sample..],
+                    seg_frame_type,
+                    last_dts,
+                    mp4.get_timescale(),
+                );
+                last_dts = dts;
+                config.stats.borrow_mut().problems += problems;
+                done = idx + 1;
+                seg_pos = 0;
+            }
+        }
+        if !config.follow || !follow_wait(last_update, config) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// check TrackHeaderBox dimensions against coded/render size
+fn check_track_size(
+    fname: &str,
+    tkhd: &mp4::TrackHeaderBox,
+    sh: &obu::SequenceHeader,
+    fh: &obu::FrameHeader,
+) {
+    let (mut width, mut height) = (tkhd.width >> 16, tkhd.height >> 16);
+    let rotation = tkhd.rotation();
+    if rotation == 90 || rotation == 270 {
+        // tkhd holds dimensions after transformation
+        std::mem::swap(&mut width, &mut height);
+    }
+    if width != sh.max_frame_width || height != sh.max_frame_height {
+        logln!(
+            "{}: tkhd size {}x{} differs from max_frame_size {}x{}",
+            fname,
+            width,
+            height,
+            sh.max_frame_width,
+            sh.max_frame_height
+        );
+    }
+    let render_size = &fh.render_size;
+    if width != render_size.render_width || height != render_size.render_height {
+        logln!(
+            "{}: tkhd size {}x{} differs from render_size {}x{}",
+            fname,
+            width,
+            height,
+            render_size.render_width,
+            render_size.render_height
+        );
+    }
+    if !tkhd.is_identity() && (rotation == 0 || rotation % 90 != 0) {
+        logln!("{}: tkhd has non-trivial transformation matrix", fname);
+    }
+}
+
+/// parse low overhead bitstream format
+fn parse_obu_bitstream<R: io::Read + io::Seek>(
+    mut reader: R,
+    fname: &str,
+    config: &AppConfig,
+) -> io::Result<()> {
+    logln!("{}: Raw stream", fname);
+
+    let mut seq = new_sequence(config)?;
+    let mut fnum = 0;
+
+    // parse OBU(open bitstream unit)s sequence
+    while !config.frame_limit_reached() {
+        let sz = stream::raw_obu_sz(&mut reader)?;
+        let obu = match obu::parse_obu_header(&mut reader, sz) {
+            Ok(obu) => obu,
+            Err(_) => break,
+        };
+        if config.verbose > 0 {
+            if obu.obu_type == obu::OBU_TEMPORAL_DELIMITER {
+                logln!("Raw F#{}", fnum);
+                fnum += 1;
+            }
+            print_obu_header(&obu, config);
+        }
+        let pos = reader.stream_position()?;
+        process_obu(&mut reader, &mut seq, &obu, config);
+        reader.seek(SeekFrom::Start(pos + obu.obu_size as u64))?;
+    }
+    Ok(())
+}
+
+/// parse length delimited bitstream format (AV1 Annex B)
+fn parse_annexb_bitstream<R: io::Read + io::Seek>(
+    mut reader: R,
+    fname: &str,
+    config: &AppConfig,
+) -> io::Result<()> {
+    logln!("{}: Annex B stream", fname);
+
+    let mut seq = new_sequence(config)?;
+    let mut fnum = 0;
+
+    // parse temporal_unit(temporal_unit_size)
+    while let Ok((_, temporal_unit_size)) = obu::leb128(&mut reader) {
+        if config.frame_limit_reached() {
+            break;
+        }
+        if config.verbose > 0 {
+            logln!("AnnexB F#{} size={}", fnum, temporal_unit_size);
+        }
+        fnum += 1;
+        let tu_end = reader.stream_position()? + temporal_unit_size as u64;
+        while reader.stream_position()? < tu_end {
+            // parse frame_unit(frame_unit_size)
+            let (_, frame_unit_size) = obu::leb128(&mut reader)?;
+            let fu_end = reader.stream_position()? + frame_unit_size as u64;
+            while reader.stream_position()? < fu_end {
+                let (_, obu_length) = obu::leb128(&mut reader)?;
+                let obu_end = reader.stream_position()? + obu_length as u64;
+                let obu = obu::parse_obu_header(&mut reader, obu_length)?;
+                print_obu_header(&obu, config);
+                process_obu(&mut reader, &mut seq, &obu, config);
+                reader.seek(SeekFrom::Start(obu_end))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// parse OBUs in temporal unit (low overhead bitstream format)
+fn process_temporal_unit(
+    data: Vec<u8>,
     seq: &mut av1::Sequence,
-    obu: &obu::Obu,
     config: &AppConfig,
-) {
-    let reader = &mut io::Read::take(reader, obu.obu_size as u64);
-    match obu.obu_type {
-        obu::OBU_SEQUENCE_HEADER => {
-            if let Some(sh) = obu::parse_sequence_header(reader) {
-                if config.verbose > 1 {
-                    println!("  {:?}", sh);
+) -> io::Result<()> {
+    let sz = data.len() as u64;
+    let mut tu = io::Cursor::new(data);
+    while tu.position() < sz {
+        let remain = (sz - tu.position()) as u32;
+        let obu = obu::parse_obu_header(&mut tu, remain)?;
+        print_obu_header(&obu, config);
+        let pos = tu.position();
+        process_obu(&mut tu, seq, &obu, config);
+        tu.set_position(pos + obu.obu_size as u64);
+    }
+    Ok(())
+}
+
+/// parse AV1 stream in MPEG-2 Transport Stream
+fn parse_ts_format<R: io::Read>(mut reader: R, fname: &str, config: &AppConfig) -> io::Result<()> {
+    logln!("{}: MPEG-2 TS", fname);
+
+    let mut demuxer = ts::TsDemuxer::new();
+    let mut seq = new_sequence(config)?;
+    config.timebase.set(Some((1, ts::PTS_CLOCK)));
+    let mut fnum = 0;
+
+    while let Some(pes) = demuxer.next_pes(&mut reader)? {
+        if config.frame_limit_reached() {
+            break;
+        }
+        if fnum == 0 {
+            if config.verbose > 0 {
+                for es in demuxer.get_streams() {
+                    logln!(
+                        "  stream PID=0x{:04X} stream_type=0x{:02X}{}",
+                        es.elementary_pid,
+                        es.stream_type,
+                        if es.is_av1 { " (AV1)" } else { "" }
+                    );
                 }
-                seq.sh = Some(sh);
-            } else {
-                println!("  invalid SequenceHeader");
+            }
+            if let Some(pid) = demuxer.get_av1_pid() {
+                logln!("{}: AV1 stream PID=0x{:04X}", fname, pid);
             }
         }
-        obu::OBU_FRAME_HEADER | obu::OBU_FRAME => {
-            if seq.sh.is_none() {
-                if config.verbose > 1 {
-                    println!("  no sequence header");
+        let data = ts::av1_access_unit(&pes.data);
+        config.pts.set(pes.pts.map(|pts| pts as i64));
+        if config.verbose > 0 {
+            match pes.pts {
+                Some(pts) => logln!("TS F#{} pts={} size={}", fnum, pts, data.len()),
+                None => logln!("TS F#{} size={}", fnum, data.len()),
+            }
+        }
+        fnum += 1;
+        process_temporal_unit(data, &mut seq, config)?;
+    }
+    if demuxer.get_av1_pid().is_none() {
+        logln!("{}: AV1 stream not found", fname);
+        config.set_unsupported();
+    } else if demuxer.get_cc_errors() > 0 {
+        logln!(
+            "{}: {} continuity_counter discontinuities",
+            fname,
+            demuxer.get_cc_errors()
+        );
+    }
+    Ok(())
+}
+
+/// parse AV1 RTP packets in rtpdump file
+fn parse_rtpdump_format<R: io::BufRead>(
+    mut reader: R,
+    fname: &str,
+    config: &AppConfig,
+) -> io::Result<()> {
+    let header = rtp::read_rtpdump_header(&mut reader)?;
+    logln!("{}: RTP dump ({})", fname, header);
+
+    let mut depacketizer = rtp::Depacketizer::new();
+    let mut seq = new_sequence(config)?;
+    config.timebase.set(Some((1, rtp::RTP_CLOCK_RATE)));
+    let mut payload_type = None;
+    let mut fnum = 0;
+    let mut eos = false;
+
+    while !eos && !config.frame_limit_reached() {
+        match rtp::read_rtpdump_packet(&mut reader)? {
+            Some(packet) => {
+                // follow first RTP stream
+                let (hdr, _) = rtp::parse_rtp_packet(&packet)?;
+                if *payload_type.get_or_insert(hdr.payload_type) != hdr.payload_type {
+                    continue;
                 }
-                return;
+                depacketizer.push_packet(&packet)?;
             }
-            if let Some(fh) =
-                obu::parse_frame_header(reader, seq.sh.as_ref().unwrap(), &mut seq.rfman)
-            {
-                if !fh.show_existing_frame {
-                    let error_resilient = if fh.error_resilient_mode { "*" } else { "" };
-                    if fh.show_frame {
-                        println!(
-                            "  #{} {}{}, update({}), show@{}",
-                            seq.rfman.decode_order,
-                            av1::stringify::frame_type(fh.frame_type),
-                            error_resilient,
-                            av1::stringify::ref_frame(fh.refresh_frame_flags),
-                            seq.rfman.present_order
-                        );
-                    } else {
-                        println!(
-                            "  #{} {}{}, update({}), {}",
-                            seq.rfman.decode_order,
-                            av1::stringify::frame_type(fh.frame_type),
-                            error_resilient,
-                            av1::stringify::ref_frame(fh.refresh_frame_flags),
-                            if fh.showable_frame {
-                                "showable"
-                            } else {
-                                "(refonly)"
-                            }
-                        );
-                    }
-                } else {
-                    let show_idx = fh.frame_to_show_map_idx;
-                    println!(
-                        "    #{} ({}) show@{}",
-                        seq.rfman.frame_buf[show_idx as usize],
-                        av1::stringify::ref_frame(1 << show_idx),
-                        seq.rfman.present_order,
+            None => {
+                depacketizer.flush();
+                eos = true;
+            }
+        }
+        while let Some(tu) = depacketizer.next_unit() {
+            config.pts.set(Some(tu.pts as i64));
+            if config.verbose > 0 {
+                logln!("RTP F#{} timestamp={} size={}", fnum, tu.pts, tu.data.len());
+            }
+            fnum += 1;
+            process_temporal_unit(tu.data, &mut seq, config)?;
+        }
+    }
+    if let Some(pt) = payload_type {
+        logln!("{}: {} temporal units (PT={})", fname, fnum, pt);
+    }
+    if depacketizer.get_lost_packets() > 0 {
+        logln!(
+            "{}: {} RTP packets lost",
+            fname,
+            depacketizer.get_lost_packets()
+        );
+    }
+    Ok(())
+}
+
+/// forward-only reader for non-seekable input, seek can only skip bytes
+struct ForwardReader<R> {
+    inner: R,
+    pos: u64,
+}
+
+impl<R: io::BufRead> ForwardReader<R> {
+    fn new(inner: R) -> Self {
+        ForwardReader { inner, pos: 0 }
+    }
+}
+
+impl<R: io::BufRead> io::Read for ForwardReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: io::BufRead> io::BufRead for ForwardReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.pos += amt as u64;
+    }
+}
+
+impl<R: io::BufRead> io::Seek for ForwardReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(n) if n >= 0 => Some(self.pos + n as u64),
+            _ => None,
+        };
+        match target {
+            Some(target) if target >= self.pos => {
+                let skip = target - self.pos;
+                self.pos += io::copy(&mut (&mut self.inner).take(skip), &mut io::sink())?;
+                Ok(self.pos)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "backward seek on non-seekable input",
+            )),
+        }
+    }
+}
+
+/// parse input stream in probed format
+fn parse_format<R: io::BufRead + io::Seek>(
+    reader: R,
+    fmt: FileFormat,
+    fname: &str,
+    config: &AppConfig,
+) -> io::Result<()> {
+    if config.seek.is_some()
+        && !matches!(
+            fmt,
+            FileFormat::IVF
+                | FileFormat::WebM
+                | FileFormat::MP4
+                | FileFormat::Gzip
+                | FileFormat::Zstd
+        )
+    {
+        logln!(
+            "{}: seek is not supported in {:?} format, parse from beginning",
+            fname,
+            fmt
+        );
+    }
+    match fmt {
+        FileFormat::IVF => parse_ivf_format(reader, fname, config)?,
+        FileFormat::WebM => parse_webm_format(reader, fname, config)?,
+        FileFormat::MP4 => parse_mp4_format(reader, fname, config)?,
+        FileFormat::AVIF => parse_avif_format(reader, fname, config)?,
+        FileFormat::Bitstream => parse_obu_bitstream(reader, fname, config)?,
+        FileFormat::AnnexB => parse_annexb_bitstream(reader, fname, config)?,
+        FileFormat::MPEGTS => parse_ts_format(reader, fname, config)?,
+        FileFormat::RtpDump => parse_rtpdump_format(reader, fname, config)?,
+        FileFormat::Gzip | FileFormat::Zstd => {
+            // decompress whole input, then probe inner format
+            let buf = compress::decompress(reader, fmt)?;
+            let fmt = probe_buffer(&buf);
+            parse_format(io::Cursor::new(buf), fmt, fname, config)?
+        }
+    };
+    Ok(())
+}
+
+/// process standard input
+fn process_stdin(fname: &str, config: &AppConfig) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+
+    // buffer probe bytes, then replay them before remaining input
+    let mut head = Vec::new();
+    (&mut stdin)
+        .take(PROBE_SIZE as u64)
+        .read_to_end(&mut head)?;
+    if head.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "empty input"));
+    }
+    let fmt = config.format.unwrap_or_else(|| probe_buffer(&head));
+    let mut reader = io::Cursor::new(head).chain(stdin);
+
+    match fmt {
+        FileFormat::WebM | FileFormat::MP4 => {
+            // container parser requires random access, buffer whole input
+            let mut buf = Vec::new();
+            reader.read_to_end(&mut buf)?;
+            parse_format(io::Cursor::new(buf), fmt, fname, config)
+        }
+        _ => parse_format(ForwardReader::new(reader), fmt, fname, config),
+    }
+}
+
+/// process input file
+fn process_file(fname: &str, config: &AppConfig) -> io::Result<()> {
+    if fname == "-" {
+        return process_stdin(fname, config);
+    }
+    if path::Path::new(fname).is_dir() {
+        return parse_dash_segments(fname, config);
+    }
+    if playlist::is_playlist(path::Path::new(fname)) {
+        return parse_dash_segments(fname, config);
+    }
+
+    // open input file as read-only mode
+    let f = fs::OpenOptions::new().read(true).open(fname)?;
+    let mut reader = io::BufReader::new(f);
+
+    // probe media container format
+    let fmt = match config.format {
+        Some(fmt) => fmt,
+        None => probe_fileformat_with_hint(&mut reader, fname)?,
+    };
+    if !matches!(fmt, FileFormat::Gzip | FileFormat::Zstd) {
+        // input size for container overhead
+        config.stats.borrow_mut().input_bytes = Some(reader.get_ref().metadata()?.len());
+    }
+
+    parse_format(reader, fmt, fname, config)
+}
+
+/// print per-file summary of info/stats/check subcommands, return number of problems
+fn print_command_summary(fname: &str, config: &AppConfig) -> u64 {
+    let mut stats = config.stats.borrow_mut();
+    let frames: u64 = stats.frame_count.iter().sum();
+    match config.command {
+        Command::Info => {
+            logln!(
+                "{}: {} frames ({} shown)",
+                fname,
+                frames,
+                stats.shown_frames
+            );
+            print_hdr_summary(&stats.hdr);
+            level::measure_level_rates(&mut stats, config);
+            level::print_level_suggestion(&stats);
+        }
+        Command::Stats => {
+            logln!(
+                "{}: {} frames ({} shown) sequence_headers={}",
+                fname,
+                frames,
+                stats.shown_frames,
+                stats.sequence_headers
+            );
+            for (frame_type, &count) in stats.frame_count.iter().enumerate() {
+                if count > 0 {
+                    logln!(
+                        "  {} count={}",
+                        av1::stringify::frame_type(frame_type as u8),
+                        count
                     );
                 }
-                if config.verbose > 1 {
-                    println!("  {:?}", fh);
-                }
-
-                // decode_frame_wrapup(): Decode frame wrapup process
-                if fh.show_frame || fh.show_existing_frame {
-                    seq.rfman.output_process(&fh);
+            }
+            print_frame_size_stats(&stats.frame_sizes);
+            print_base_q_idx_histogram(&stats.base_q_idx, stats.delta_q_frames);
+            print_global_motion_usage(&stats.gm_types);
+            print_filter_stats(&stats.filters);
+            print_coding_tools(&stats.tools);
+            print_superres_usage(&stats);
+            print_film_grain_stats(&stats.film_grain);
+            print_hdr_summary(&stats.hdr);
+            if !stats.tile_configs.is_empty() {
+                logln!("  tile configurations:");
+                for (tiles, count) in stats.tile_configs.iter() {
+                    logln!("    {} count={}", tiles, count);
                 }
-                if !fh.show_existing_frame {
-                    if config.verbose > 2 {
-                        println!("  {:?}", seq.rfman);
-                    }
-                    seq.rfman.update_process(&fh);
+            }
+            print_byte_share(&stats);
+            print_framing_overhead(&stats, config);
+            for obu_type in 0..stats.obu_count.len() {
+                if stats.obu_count[obu_type] > 0 {
+                    logln!(
+                        "  OBU_{} count={} size={}",
+                        av1::stringify::obu_type(obu_type as u8),
+                        stats.obu_count[obu_type],
+                        stats.obu_bytes[obu_type]
+                    );
                 }
             }
         }
-        obu::OBU_TILE_LIST => {
-            if let Some(tl) = obu::parse_tile_list(reader) {
-                if config.verbose > 2 {
-                    println!("  {:?}", tl);
-                }
+        Command::Summary => print_summary_line(fname, &stats, config),
+        Command::Bitrate => print_bitrate_csv(fname, &stats, config),
+        Command::Check => {
+            let no_key_frame = stats.frame_count[obu::KEY_FRAME as usize] == 0;
+            // every shown frame carries one HDR10+ metadata, once stream uses HDR10+
+            let hdr10plus_frames = if stats.hdr10plus {
+                mem::take(&mut stats.hdr10plus_frames)
             } else {
-                println!("  invalid TileList")
+                Vec::new()
+            };
+            level::measure_level_rates(&mut stats, config);
+            let level_exceeded = match stats.sequence_header {
+                Some(ref sh) if stats.level.max_pic_size > 0 => {
+                    av1::level_limits(sh.op[0].seq_level_idx).map(|limits| {
+                        let exceeded =
+                            limits.exceeded(&stats.level, sh.seq_profile, sh.op[0].seq_tier);
+                        (limits.seq_level_idx, exceeded)
+                    })
+                }
+                _ => None,
+            };
+            drop(stats);
+            if let Some((seq_level_idx, exceeded)) = level_exceeded {
+                if !exceeded.is_empty() {
+                    config.report(
+                        &conformance::LEVEL_LIMITS,
+                        fname,
+                        &format!(
+                            "level {} exceeds {}",
+                            av1::stringify::level(seq_level_idx),
+                            exceeded.join(", ")
+                        ),
+                    );
+                }
             }
-        }
-        obu::OBU_METADATA => {
-            if let Ok(metadata) = obu::parse_metadata_obu(reader) {
-                if config.verbose > 1 {
-                    println!("    {:?}", metadata);
+            if frames == 0 {
+                config.report(&conformance::NO_FRAMES, fname, "no frames");
+            } else if no_key_frame {
+                config.report(&conformance::NO_KEY_FRAME, fname, "no key frame");
+            }
+            for (frame, count) in hdr10plus_frames {
+                let location = format!("frame #{}", frame);
+                if count == 0 {
+                    config.report(
+                        &conformance::HDR10PLUS_MISSING,
+                        &location,
+                        "no ST 2094-40 metadata for shown frame",
+                    );
+                } else if count > 1 {
+                    config.report(
+                        &conformance::HDR10PLUS_DUPLICATE,
+                        &location,
+                        &format!("{} ST 2094-40 metadata for shown frame", count),
+                    );
                 }
+            }
+            stats = config.stats.borrow_mut();
+            let warnings = if stats.warnings > 0 {
+                format!(" ({} warnings)", stats.warnings)
+            } else {
+                String::new()
+            };
+            if stats.problems == 0 {
+                logln!("{}: OK{}", fname, warnings);
             } else {
-                println!("    invalid MetadataObu");
+                logln!("{}: {} problems found{}", fname, stats.problems, warnings);
             }
         }
-        _ => {}
+        _ => {}
+    }
+    stats.problems
+}
+
+/// print min/mean/median/percentiles/max of frame sizes, split by frame type and temporal layer
+fn print_frame_size_stats(frame_sizes: &[FrameSize]) {
+    fn print_distribution(label: &str, mut sizes: Vec<u64>) {
+        if sizes.is_empty() {
+            return;
+        }
+        sizes.sort_unstable();
+        // nearest-rank percentile
+        let percentile = |p: usize| sizes[(p * sizes.len()).div_ceil(100).max(1) - 1];
+        logln!(
+            "    {} count={} min={} mean={:.1} median={} p90={} p95={} p99={} max={}",
+            label,
+            sizes.len(),
+            sizes[0],
+            sizes.iter().sum::<u64>() as f64 / sizes.len() as f64,
+            percentile(50),
+            percentile(90),
+            percentile(95),
+            percentile(99),
+            sizes[sizes.len() - 1]
+        );
+    }
+    if frame_sizes.is_empty() {
+        return;
+    }
+    logln!("  frame size (bytes):");
+    print_distribution("all", frame_sizes.iter().map(|f| f.bytes).collect());
+    for frame_type in 0..4 {
+        print_distribution(
+            av1::stringify::frame_type(frame_type),
+            frame_sizes
+                .iter()
+                .filter(|f| f.frame_type == frame_type)
+                .map(|f| f.bytes)
+                .collect(),
+        );
+    }
+    let max_temporal_id = frame_sizes.iter().map(|f| f.temporal_id).max().unwrap_or(0);
+    if max_temporal_id > 0 {
+        for temporal_id in 0..=max_temporal_id {
+            print_distribution(
+                &format!("temporal_id={}", temporal_id),
+                frame_sizes
+                    .iter()
+                    .filter(|f| f.temporal_id == temporal_id)
+                    .map(|f| f.bytes)
+                    .collect(),
+            );
+        }
     }
 }
 
-/// parse IVF format
-fn parse_ivf_format<R: io::Read + io::Seek>(
-    mut reader: R,
-    fname: &str,
-    config: &AppConfig,
-) -> io::Result<()> {
-    // parse IVF header
-    let mut ivf_header = [0; ivf::IVF_HEADER_SIZE];
-    reader.read_exact(&mut ivf_header)?;
-    match ivf::parse_ivf_header(&ivf_header) {
-        Ok(hdr) => {
-            let codec = String::from_utf8(hdr.codec.to_vec()).unwrap();
-            println!(
-                "{}: IVF codec={:?} size={}x{} timescale={}/{} length={}",
-                fname,
-                codec,
-                hdr.width,
-                hdr.height,
-                hdr.timescale_num,
-                hdr.timescale_den,
-                hdr.length
+/// print fraction of total bytes spent on frame types, no-show frames, metadata, padding and headers
+fn print_byte_share(stats: &FrameStats) {
+    let total: u64 = stats.obu_bytes.iter().sum();
+    if total == 0 {
+        return;
+    }
+    let frame_bytes = |pred: &dyn Fn(&FrameSize) -> bool| -> u64 {
+        stats
+            .frame_sizes
+            .iter()
+            .filter(|f| pred(f))
+            .map(|f| f.bytes)
+            .sum()
+    };
+    // key frames are counted regardless of show_frame
+    let mut shares = vec![("KeyFrame", frame_bytes(&|f| f.frame_type == obu::KEY_FRAME))];
+    for frame_type in 1..4 {
+        shares.push((
+            av1::stringify::frame_type(frame_type),
+            frame_bytes(&|f| f.frame_type == frame_type && f.show_frame),
+        ));
+    }
+    shares.push((
+        "no-show frames",
+        frame_bytes(&|f| f.frame_type != obu::KEY_FRAME && !f.show_frame),
+    ));
+    let metadata = stats.obu_bytes[obu::OBU_METADATA as usize];
+    let padding = stats.obu_bytes[obu::OBU_PADDING as usize];
+    let tile_list = stats.obu_bytes[obu::OBU_TILE_LIST as usize];
+    shares.push(("metadata", metadata));
+    shares.push(("padding", padding));
+    shares.push(("tile lists", tile_list));
+    // SequenceHeader, TemporalDelimiter, show_existing_frame and redundant FrameHeader, reserved OBUs
+    let counted: u64 = shares.iter().map(|share| share.1).sum();
+    shares.push(("headers", total.saturating_sub(counted)));
+    logln!("  byte share (total={}):", total);
+    for (label, bytes) in shares {
+        if bytes > 0 {
+            logln!(
+                "    {} bytes={} ({:.1}%)",
+                label,
+                bytes,
+                bytes as f64 * 100.0 / total as f64
             );
-            if hdr.codec != FCC_AV01 {
-                println!(
-                    "{}: unsupport codec(0x{})",
-                    fname,
-                    hex::encode_upper(hdr.codec)
-                );
-                return Ok(());
-            }
-        }
-        Err(msg) => {
-            println!("{}: {}", fname, msg);
-            return Ok(());
         }
-    };
+    }
+}
 
-    let mut seq = av1::Sequence::new();
+// framing overhead [%] flagged as unusually high
+const FRAMING_OVERHEAD_LIMIT: f64 = 5.0;
+const CONTAINER_OVERHEAD_LIMIT: f64 = 10.0;
 
-    // parse IVF frames
-    while let Ok(frame) = ivf::parse_ivf_frame(&mut reader) {
-        if config.verbose > 0 {
-            println!("IVF F#{} size={}", frame.pts, frame.size);
+/// print bytes spent on OBU headers, size fields, temporal delimiters and container versus payload
+fn print_framing_overhead(stats: &FrameStats, config: &AppConfig) {
+    let total: u64 = stats.obu_bytes.iter().sum();
+    if total == 0 {
+        return;
+    }
+    let framing = &stats.framing;
+    let overhead = framing.obu_headers
+        + framing.extension_headers
+        + framing.size_fields
+        + framing.temporal_delimiters
+        + framing.redundant_frame_headers;
+    let percent = |bytes: u64| bytes as f64 * 100.0 / total as f64;
+    logln!(
+        "  framing overhead: bytes={} ({:.1}%){}",
+        overhead,
+        percent(overhead),
+        if percent(overhead) > FRAMING_OVERHEAD_LIMIT {
+            " unusually high"
+        } else {
+            ""
         }
-        let mut sz = frame.size;
-        let pos = reader.stream_position()?;
-        // parse OBU(open bitstream unit)s
-        while sz > 0 {
-            let obu = obu::parse_obu_header(&mut reader, sz)?;
-            if config.verbose > 0 {
-                println!("  {}", obu);
-            }
-            sz -= obu.header_len + obu.obu_size;
-            let pos = reader.stream_position()?;
-            process_obu(&mut reader, &mut seq, &obu, config);
-            reader.seek(SeekFrom::Start(pos + obu.obu_size as u64))?;
+    );
+    for &(label, bytes) in [
+        ("OBU headers", framing.obu_headers),
+        ("extension headers", framing.extension_headers),
+        ("size fields", framing.size_fields),
+        ("temporal delimiters", framing.temporal_delimiters),
+        ("redundant frame headers", framing.redundant_frame_headers),
+        ("payload", total - overhead),
+    ]
+    .iter()
+    {
+        logln!("    {} bytes={} ({:.1}%)", label, bytes, percent(bytes));
+    }
+    // whole input is not parsed with --frames or --seek
+    if let Some(input_bytes) = stats.input_bytes {
+        if config.max_frames.is_none() && config.seek.is_none() && input_bytes >= total {
+            let container = input_bytes - total;
+            let ratio = container as f64 * 100.0 / input_bytes as f64;
+            logln!(
+                "  container overhead: bytes={} ({:.1}% of {} bytes){}",
+                container,
+                ratio,
+                input_bytes,
+                if ratio > CONTAINER_OVERHEAD_LIMIT {
+                    " unusually high"
+                } else {
+                    ""
+                }
+            );
         }
-        reader.seek(SeekFrom::Start(pos + frame.size as u64))?;
     }
-    Ok(())
 }
 
-/// parse WebM format
-fn parse_webm_format<R: io::Read + io::Seek>(
-    mut reader: R,
-    fname: &str,
-    config: &AppConfig,
-) -> io::Result<()> {
-    // open Matroska/WebM file
-    let mut webm = mkv::open_mkvfile(&mut reader)?;
-
-    let codec_id = mkv::CODEC_V_AV1;
-    let track_num = match webm.find_track(codec_id) {
-        Some(num) => num,
-        _ => {
-            println!("{}: Matroska/WebM \"{}\" codec not found", fname, codec_id);
-            return Ok(());
+/// print histogram of base_q_idx in 16-wide bins
+fn print_base_q_idx_histogram(histogram: &BTreeMap<u8, u64>, delta_q_frames: u64) {
+    let frames: u64 = histogram.values().sum();
+    if frames == 0 {
+        return;
+    }
+    let (min, max) = (
+        histogram.keys().next().unwrap(),
+        histogram.keys().last().unwrap(),
+    );
+    let sum: u64 = histogram.iter().map(|(&q, &n)| q as u64 * n).sum();
+    logln!(
+        "  base_q_idx: min={} mean={:.1} max={} delta_q_present={}",
+        min,
+        sum as f64 / frames as f64,
+        max,
+        delta_q_frames
+    );
+    let mut bins = [0u64; 16];
+    for (&q, &n) in histogram {
+        bins[q as usize / 16] += n;
+    }
+    let peak = *bins.iter().max().unwrap();
+    for (bin, &count) in bins.iter().enumerate() {
+        if count > 0 {
+            logln!(
+                "    {:>3}-{:<3} count={:<6} {}",
+                bin * 16,
+                bin * 16 + 15,
+                count,
+                "#".repeat(cmp::max(1, (count * 40 / peak) as usize))
+            );
         }
-    };
-    match webm.get_videosetting(track_num) {
-        Some(video) => println!(
-            "{}: Matroska/WebM codec=\"{}\" size={}x{}",
-            fname, codec_id, video.pixel_width, video.pixel_height
-        ),
-        None => println!(
-            "{}: Matroska/WebM codec=\"{}\" size=(unknown)",
-            fname, codec_id
-        ),
     }
+}
 
-    let mut seq = av1::Sequence::new();
+/// time window index of current temporal unit, from container timestamp or timing_info frame rate
+fn bitrate_window(config: &AppConfig, stats: &FrameStats) -> Option<i64> {
+    let sec = presentation_time(config, stats)?;
+    Some((sec / config.window).floor() as i64)
+}
 
-    // parse WebM block
-    while let Ok(Some(block)) = webm.next_block(&mut reader) {
-        if block.track_num != track_num {
-            // skip non AV1 track data
-            continue;
+/// presentation time [sec] of next shown frame, from container pts or timing_info
+fn presentation_time(config: &AppConfig, stats: &FrameStats) -> Option<f64> {
+    match (config.pts.get(), config.timebase.get()) {
+        (Some(pts), Some((num, den))) => Some(pts as f64 * num as f64 / den as f64),
+        _ => {
+            let fps = stats.sequence_header.as_ref().and_then(timing_info_fps)?;
+            Some(stats.shown_frames as f64 / fps)
         }
+    }
+}
 
-        if config.verbose > 0 {
-            println!(
-                "MKV F#{} flags=0x{:02x} size={}",
-                block.timecode, block.flags, block.size
+/// print time/bitrate CSV of input for bitrate subcommand, empty windows are included
+fn print_bitrate_csv(fname: &str, stats: &FrameStats, config: &AppConfig) {
+    outln!("time,frames,bytes,kbps");
+    if let (Some(&first), Some(&last)) = (stats.bitrate.keys().next(), stats.bitrate.keys().last())
+    {
+        for window in first..=last {
+            let (frames, bytes) = stats.bitrate.get(&window).cloned().unwrap_or_default();
+            outln!(
+                "{:.3},{},{},{:.1}",
+                window as f64 * config.window,
+                frames,
+                bytes,
+                bytes as f64 * 8.0 / config.window / 1000.0
             );
         }
-        let mut sz = block.size as u32;
-        // parse OBU(open bitstream unit)s
-        while sz > 0 {
-            let obu = obu::parse_obu_header(&mut reader, sz)?;
-            if config.verbose > 0 {
-                println!("  {}", obu);
+    }
+    // configOBUs in av1C/CodecPrivate precede first timestamp
+    if stats.bitrate.is_empty() && stats.untimed_bytes > 0 {
+        logln!(
+            "{}: no timestamp (container pts or timing_info) in {} bytes",
+            fname,
+            stats.untimed_bytes
+        );
+    }
+}
+
+/// check container timestamps of shown frames for monotonicity, frame rate, gaps, drift and show_existing_frame
+fn validate_timestamps(config: &AppConfig) {
+    let stats = config.stats.borrow();
+    let (num, den) = match config.timebase.get() {
+        Some(timebase) => timebase,
+        None => {
+            drop(stats);
+            logln!("  timestamps: no container timestamp");
+            return;
+        }
+    };
+    let sec = |ticks: i64| ticks as f64 * num as f64 / den as f64;
+    // (shown frame #, pts, ShownTimestamp)
+    let frames: Vec<(usize, i64, &ShownTimestamp)> = stats
+        .timestamps
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, ts)| ts.pts.map(|pts| (idx, pts, ts)))
+        .collect();
+    let sh = stats.sequence_header.as_ref();
+    let mut findings: Vec<(&'static conformance::Rule, String, String)> = Vec::new();
+    // monotonicity, show_existing_frame in its own temporal unit
+    for pair in frames.windows(2) {
+        let ((_, prev_pts, _), (idx, pts, ts)) = (pair[0], pair[1]);
+        if pts <= prev_pts {
+            let kind = if ts.show_existing_frame {
+                " (show_existing_frame)"
+            } else {
+                ""
+            };
+            findings.push((
+                &conformance::PTS_MONOTONIC,
+                format!("frame #{}", idx),
+                format!("pts {} is not after previous pts {}{}", pts, prev_pts, kind),
+            ));
+        }
+    }
+    // container frame interval (median of positive deltas) against timing_info
+    let mut deltas: Vec<f64> = frames
+        .windows(2)
+        .map(|pair| sec(pair[1].1 - pair[0].1))
+        .filter(|&delta| delta > 0.0)
+        .collect();
+    deltas.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let measured = deltas.get(deltas.len() / 2).cloned();
+    let nominal = sh
+        .filter(|sh| sh.timing_info.equal_picture_interval)
+        .and_then(timing_info_fps)
+        .map(|fps| 1.0 / fps);
+    if let (Some(measured), Some(nominal)) = (measured, nominal) {
+        if (measured - nominal).abs() > nominal * 0.01 {
+            findings.push((
+                &conformance::FRAME_RATE,
+                "stream".to_string(),
+                format!(
+                    "container frame interval {:.6}s ({:.3} fps) differs from timing_info {:.6}s ({:.3} fps)",
+                    measured,
+                    1.0 / measured,
+                    nominal,
+                    1.0 / nominal
+                ),
+            ));
+        }
+    }
+    // gaps and accumulated drift from reference interval
+    let interval = nominal.or(measured);
+    let (mut gaps, mut drift, mut max_drift) = (0, 0.0f64, 0.0f64);
+    let mut drift_reported = false;
+    if let Some(interval) = interval {
+        for pair in frames.windows(2) {
+            let ((_, prev_pts, _), (idx, pts, ts)) = (pair[0], pair[1]);
+            let delta = sec(pts - prev_pts);
+            if delta <= 0.0 {
+                continue;
+            }
+            if delta > interval * 1.5 {
+                gaps += 1;
+                findings.push((
+                    &conformance::PTS_GAP,
+                    format!("frame #{}", idx),
+                    format!(
+                        "gap of {:.3}s ({} frame intervals missing) after pts {}",
+                        delta,
+                        (delta / interval).round() as i64 - 1,
+                        prev_pts
+                    ),
+                ));
+            } else {
+                drift += delta - interval;
+                if drift.abs() > max_drift.abs() {
+                    max_drift = drift;
+                }
+                if drift.abs() > interval / 2.0 && !drift_reported {
+                    drift_reported = true;
+                    findings.push((
+                        &conformance::PTS_GAP,
+                        format!("frame #{}", idx),
+                        format!(
+                            "timestamps drift {:+.3}s from frame interval {:.6}s",
+                            drift, interval
+                        ),
+                    ));
+                }
+            }
+            // show_existing_frame is presented order_hint distance after previous shown frame
+            let order_hint = sh.filter(|sh| sh.enable_order_hint);
+            if let (true, false, Some(sh)) = (ts.show_existing_frame, ts.key_frame, order_hint) {
+                let prev = pair[0].2;
+                let dist = av1::get_relative_dist(ts.order_hint as i32, prev.order_hint as i32, sh);
+                if dist != (delta / interval).round() as i32 {
+                    findings.push((
+                        &conformance::SHOW_EXISTING_PTS,
+                        format!("frame #{}", idx),
+                        format!(
+                            "show_existing_frame (order_hint={}) at pts {} is {:.3}s after previous shown frame (order_hint={}), expected {} frame intervals",
+                            ts.order_hint, pts, delta, prev.order_hint, dist
+                        ),
+                    ));
+                }
             }
-            sz -= obu.header_len + obu.obu_size;
-            let pos = reader.stream_position()?;
-            process_obu(&mut reader, &mut seq, &obu, config);
-            reader.seek(SeekFrom::Start(pos + obu.obu_size as u64))?;
         }
-
-        reader.seek(SeekFrom::Start(block.offset + block.size))?;
     }
-    Ok(())
+    let summary = format!(
+        "  timestamps: shown={} interval={} timing_info={} gaps={} max_drift={:+.3}s",
+        frames.len(),
+        measured.map_or("-".to_string(), |sec| format!("{:.6}s", sec)),
+        nominal.map_or("-".to_string(), |sec| format!("{:.6}s", sec)),
+        gaps,
+        max_drift
+    );
+    drop(stats);
+    logln!("{}", summary);
+    for (rule, location, message) in findings {
+        config.report(rule, &location, &message);
+    }
 }
 
-/// parse MP4(ISOBMFF) format
-fn parse_mp4_format<R: io::Read + io::Seek>(
-    mut reader: R,
-    fname: &str,
-    config: &AppConfig,
-) -> io::Result<()> {
-    // open MP4(ISOBMFF) file
-    let mp4 = mp4::open_mp4file(&mut reader)?;
-    if config.verbose > 1 {
-        println!("  {:?}", mp4.get_filetype());
+/// duration [sec] from timestamps of shown frames, or from frame rate in timing_info
+fn stream_duration(stats: &FrameStats, config: &AppConfig) -> Option<f64> {
+    match (stats.pts_range, config.timebase.get()) {
+        (Some((min, max)), Some((num, den))) if stats.shown_frames > 1 && min < max => {
+            let interval = (max - min) as f64 / (stats.shown_frames - 1) as f64;
+            Some(((max - min) as f64 + interval) * num as f64 / den as f64)
+        }
+        _ => {
+            let sh = stats.sequence_header.as_ref()?;
+            timing_info_fps(sh).map(|fps| stats.shown_frames as f64 / fps)
+        }
     }
+}
 
-    let brand_av01 = mp4::FCC::from(mp4::BRAND_AV01);
-    let brands = &mp4.get_filetype().compatible_brands;
-    if !brands.iter().any(|b| *b == brand_av01) {
-        println!("{}: ISOBMFF/MP4 {} brand not found", fname, brand_av01);
-        return Ok(());
-    }
-    let (av1se, av1cc) = match mp4.get_av1config() {
-        Some(config) => config,
+/// print one line summary of input for summary subcommand
+fn print_summary_line(fname: &str, stats: &FrameStats, config: &AppConfig) {
+    let sh = match stats.sequence_header {
+        Some(ref sh) => sh,
         None => {
-            println!("{}: ISOBMFF/MP4 {} track not found", fname, brand_av01);
-            return Ok(());
+            logln!("{}: no sequence header", fname);
+            return;
         }
     };
-    println!(
-        "{}: ISOBMFF/MP4 codec={} size={}x{}",
-        fname, brand_av01, av1se.width, av1se.height
+    let (width, height) = stats
+        .frame_size
+        .unwrap_or((sh.max_frame_width, sh.max_frame_height));
+    let frames: u64 = stats.frame_count.iter().sum();
+    let duration = stream_duration(stats, config);
+    let bytes: u64 = stats.obu_bytes.iter().sum();
+    let mut hdr = Vec::new();
+    match sh.color_config.transfer_characteristics {
+        16 => hdr.push("PQ"),  // TC_SMPTE_2084
+        18 => hdr.push("HLG"), // TC_HLG
+        _ => {}
+    }
+    for &(present, name) in [
+        (stats.hdr.mdcv.is_some(), "MDCV"),
+        (stats.hdr.cll.is_some(), "CLL"),
+        (stats.hdr10plus, "HDR10+"),
+    ]
+    .iter()
+    {
+        if present {
+            hdr.push(name);
+        }
+    }
+    logln!(
+        "{}: {}x{} profile={} level={} tier={} bit_depth={} frames={} keyframes={} duration={} bitrate={} hdr={}",
+        fname,
+        width,
+        height,
+        sh.seq_profile,
+        sh.op[0].seq_level_idx,
+        if sh.op[0].seq_tier == 0 { 'M' } else { 'H' },
+        sh.color_config.bit_depth,
+        frames,
+        stats.frame_count[obu::KEY_FRAME as usize],
+        duration.map_or("-".to_string(), |sec| format!("{:.3}s", sec)),
+        duration
+            .filter(|&sec| sec > 0.0)
+            .map_or("-".to_string(), |sec| format!("{:.1}kbps", bytes as f64 * 8.0 / sec / 1000.0)),
+        if hdr.is_empty() {
+            "none".to_string()
+        } else {
+            hdr.join(",")
+        }
     );
-    if config.verbose > 1 {
-        println!("  {:?}", av1se);
-        println!("  {:?}", av1cc);
+}
+
+/// expand {stem} and {name} in --output template with input file name
+fn report_path(template: &str, fname: &str) -> path::PathBuf {
+    let input = path::Path::new(if fname == "-" { "stdin" } else { fname });
+    let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let name = input.file_name().and_then(|s| s.to_str()).unwrap_or("");
+    path::PathBuf::from(template.replace("{stem}", stem).replace("{name}", name))
+}
+
+/// write report into the file, or stdout if None
+fn set_report_file(path: Option<&path::Path>) -> io::Result<()> {
+    let file = match path {
+        Some(path) => Some(io::LineWriter::new(fs::File::create(path)?)),
+        None => None,
+    };
+    let mut report = REPORT_FILE.lock().unwrap();
+    if let Some(mut prev) = report.take() {
+        prev.flush()?;
     }
+    *report = file;
+    Ok(())
+}
 
-    let mut seq = av1::Sequence::new();
+/// write CSV header or opening bracket of JSON array at beginning of report
+fn begin_report(config: &AppConfig) {
+    config.records.set(0);
+    match config.output {
+        FrameOutput::Csv => outln!("{}", FRAME_CSV_HEADER),
+        FrameOutput::AomAnalyzer => outln!("["),
+        _ => {}
+    }
+}
 
-    // process AV1CodecConfigurationBox::configOBUs
-    let mut cur = io::Cursor::new(av1cc.config_obus.clone());
-    let mut config_sz = av1cc.config_obus.len() as u32;
-    while config_sz > 0 {
-        let obu = obu::parse_obu_header(&mut cur, config_sz)?;
-        if config.verbose > 0 {
-            println!("  {}", obu);
+/// write closing bracket of JSON array at end of report
+fn end_report(config: &AppConfig) {
+    if config.output == FrameOutput::AomAnalyzer {
+        outln!("]");
+    }
+}
+
+// extensions of AV1 stream files in directory scan
+const STREAM_EXTENSIONS: [&str; 6] = ["ivf", "webm", "mkv", "mp4", "obu", "avif"];
+
+/// check directory contains DASH media segments (.m4s)
+fn is_dash_directory(dir: &path::Path) -> io::Result<bool> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("m4s"))
+        {
+            return Ok(true);
         }
-        config_sz -= obu.header_len + obu.obu_size;
-        process_obu(&mut cur, &mut seq, &obu, config);
     }
+    Ok(false)
+}
 
-    // parse AV1 Samples
-    for sample in mp4.get_samples() {
-        reader.seek(SeekFrom::Start(sample.pos))?;
-        let mut sz = sample.size;
-        // parse OBU(open bitstream unit)s
-        while sz > 0 {
-            let obu_size = cmp::min(sz, u32::MAX as u64) as u32;
-            let obu = obu::parse_obu_header(&mut reader, obu_size)?;
-            if config.verbose > 0 {
-                println!("  {}", obu);
+/// collect AV1 stream files and DASH segment directories under the directory recursively
+fn scan_directory(dir: &path::Path, files: &mut Vec<String>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            if is_dash_directory(&path)? {
+                files.push(path.to_string_lossy().into_owned());
+            } else {
+                scan_directory(&path, files)?;
             }
-            sz -= (obu.header_len + obu.obu_size) as u64;
-            let pos = reader.stream_position()?;
-            process_obu(&mut reader, &mut seq, &obu, config);
-            reader.seek(SeekFrom::Start(pos + obu.obu_size as u64))?;
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| STREAM_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        {
+            files.push(path.to_string_lossy().into_owned());
         }
     }
     Ok(())
 }
 
-/// parse low overhead bitstream format
-fn parse_obu_bitstream<R: io::Read + io::Seek>(
-    mut reader: R,
-    fname: &str,
-    config: &AppConfig,
-) -> io::Result<()> {
-    println!("{}: Raw stream", fname);
-
-    let mut seq = av1::Sequence::new();
-    let sz = u32::MAX;
-    let mut fnum = 0;
-
-    // parse OBU(open bitstream unit)s sequence
-    while let Ok(obu) = obu::parse_obu_header(&mut reader, sz) {
-        if config.verbose > 0 {
-            if obu.obu_type == obu::OBU_TEMPORAL_DELIMITER {
-                println!("Raw F#{}", fnum);
-                fnum += 1;
+///
+/// expand glob patterns and directories (except DASH segment directory) in input list
+///
+/// return input files, and whether any input is expanded.
+///
+fn expand_inputs<'a, I: Iterator<Item = &'a str>>(inputs: I) -> io::Result<(Vec<String>, bool)> {
+    let mut files = Vec::new();
+    let mut expanded = false;
+    for input in inputs {
+        let path = path::Path::new(input);
+        if !path.exists() && input.contains(['*', '?', '[']) {
+            let paths = glob::glob(input)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+            for path in paths {
+                let path = path?;
+                if path.is_dir() && !is_dash_directory(&path)? {
+                    scan_directory(&path, &mut files)?;
+                } else {
+                    files.push(path.to_string_lossy().into_owned());
+                }
             }
-            println!("  {}", obu);
+            expanded = true;
+        } else if path.is_dir() && !is_dash_directory(path)? {
+            scan_directory(path, &mut files)?;
+            expanded = true;
+        } else {
+            files.push(input.to_string());
         }
-        let pos = reader.stream_position()?;
-        process_obu(&mut reader, &mut seq, &obu, config);
-        reader.seek(SeekFrom::Start(pos + obu.obu_size as u64))?;
     }
-    Ok(())
+    Ok((files, expanded))
 }
 
-/// process input file
-fn process_file(fname: &str, config: &AppConfig) -> io::Result<()> {
-    // open input file as read-only mode
-    let f = fs::OpenOptions::new().read(true).open(fname)?;
-    let mut reader = io::BufReader::new(f);
+/// analysis result of each input for aggregate table
+struct InputSummary {
+    fname: String,
+    frames: u64,
+    shown_frames: u64,
+    problems: u64,
+    status: &'static str, // OK, problems, parse error, unsupported or no frames
+}
 
-    // probe media container format
-    let fmt = probe_fileformat(&mut reader)?;
-    reader.seek(SeekFrom::Start(0))?;
+/// print aggregate table of all inputs
+fn print_aggregate_table(summaries: &[InputSummary]) {
+    let width = summaries
+        .iter()
+        .map(|s| s.fname.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    logln!(
+        "{:<width$} {:>8} {:>8} {:>8} status",
+        "file",
+        "frames",
+        "shown",
+        "problems",
+        width = width
+    );
+    for summary in summaries {
+        logln!(
+            "{:<width$} {:>8} {:>8} {:>8} {}",
+            summary.fname,
+            summary.frames,
+            summary.shown_frames,
+            summary.problems,
+            summary.status,
+            width = width
+        );
+    }
+    logln!(
+        "{} files, {} frames, {} problems",
+        summaries.len(),
+        summaries.iter().map(|s| s.frames).sum::<u64>(),
+        summaries.iter().map(|s| s.problems).sum::<u64>()
+    );
+}
 
-    match fmt {
-        FileFormat::IVF => parse_ivf_format(reader, fname, config)?,
-        FileFormat::WebM => parse_webm_format(reader, fname, config)?,
-        FileFormat::MP4 => parse_mp4_format(reader, fname, config)?,
-        FileFormat::Bitstream => parse_obu_bitstream(reader, fname, config)?,
+/// build conversion/extraction subcommand with --quiet flag
+fn tool_subcommand<'a, 'b>(name: &'a str, about: &'b str) -> App<'a, 'b> {
+    SubCommand::with_name(name)
+        .about(about)
+        .arg(Arg::from_usage(
+            "[quiet] -q --quiet 'Suppress status and report output'",
+        ))
+}
+
+/// build analysis subcommand with input files and parsing options
+fn analysis_subcommand<'a, 'b>(name: &'a str, about: &'b str) -> App<'a, 'b> {
+    SubCommand::with_name(name)
+        .about(about)
+        .arg(Arg::from_usage("<INPUT>... 'Input AV1 bitstream files, DASH segment directories or MPD/M3U8 playlists (\"-\" for stdin)'"))
+        .arg(
+            Arg::from_usage("[format] --format=[FORMAT] 'Force input format instead of probing'")
+                .possible_values(&["ivf", "webm", "mp4", "avif", "obu", "annexb", "ts", "rtpdump"]),
+        )
+        .arg(Arg::from_usage(
+            "[track] --track=[ID] 'Select AV1 track by track ID (MP4) or TrackNumber (WebM)'",
+        ))
+        .arg(Arg::from_usage(
+            "[init] --init=[FILE] 'Initialization segment (or av1C box) for media segments without sequence header'",
+        ))
+        .arg(Arg::from_usage(
+            "[recover] --recover 'Resynchronize after malformed MP4 boxes'",
+        ))
+        .arg(Arg::from_usage(
+            "[frames] --frames=[N] 'Stop parsing each input after N frames'",
+        ))
+        .arg(Arg::from_usage(
+            "[output] -o --output=[FILE] 'Write report of each input into the file ({stem} and {name} are replaced with input file name)'",
+        ))
+        .arg(Arg::from_usage(
+            "[quiet] -q --quiet 'Suppress all output, check exit status (1: parse error, 2: problems found, 3: unsupported format or no frames)'",
+        ))
+}
+
+/// build application config from commandline flags of analysis subcommand
+fn app_config(matches: &clap::ArgMatches, command: Command) -> AppConfig {
+    let export = match matches.value_of("export") {
+        Some("csv") => Some(ExportFormat::Csv),
+        Some("json") => Some(ExportFormat::Json),
+        Some("yaml") => Some(ExportFormat::Yaml),
+        _ => None,
     };
-    Ok(())
+    let verbose = match command {
+        Command::Obus => 1 + matches.occurrences_of("v"),
+        _ => 0,
+    };
+    let ffprobe = if matches.is_present("show-streams") || matches.is_present("show-frames") {
+        Some(FfprobeOptions {
+            show_streams: matches.is_present("show-streams"),
+            show_frames: matches.is_present("show-frames"),
+            compact: matches.value_of("print-format") == Some("compact"),
+        })
+    } else {
+        None
+    };
+    AppConfig {
+        command,
+        output: if matches.is_present("ndjson") {
+            FrameOutput::Ndjson
+        } else if matches.is_present("csv") {
+            FrameOutput::Csv
+        } else if matches.is_present("yaml") {
+            FrameOutput::Yaml
+        } else if matches.is_present("aomanalyzer") {
+            FrameOutput::AomAnalyzer
+        } else if ffprobe.is_some() {
+            FrameOutput::Ffprobe
+        } else {
+            FrameOutput::Text
+        },
+        stats: Default::default(),
+        pts: Default::default(),
+        records: Default::default(),
+        timebase: Default::default(),
+        ffprobe,
+        verbose,
+        show: match matches.values_of("show") {
+            Some(sections) => {
+                let mut show = ShowSections::default();
+                for section in sections {
+                    match section {
+                        "sh" => show.sh = true,
+                        "fh" => show.fh = true,
+                        "gm" => show.gm = true,
+                        "fg" => show.fg = true,
+                        "metadata" => show.metadata = true,
+                        "tiles" => show.tiles = true,
+                        "superres" => show.superres = true,
+                        _ => show.refs = true,
+                    }
+                }
+                show
+            }
+            None => ShowSections::from_verbose(verbose),
+        },
+        hexdump: if matches.is_present("hexdump") {
+            Some(
+                matches
+                    .value_of("hexdump")
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or(u64::MAX),
+            )
+        } else {
+            None
+        },
+        trace: matches.is_present("trace"),
+        quiet: matches.is_present("quiet"),
+        strict: matches.is_present("strict"),
+        window: matches
+            .value_of("window")
+            .and_then(|sec| sec.parse().ok())
+            .filter(|&sec: &f64| sec > 0.0)
+            .unwrap_or(1.0),
+        export,
+        follow: matches.is_present("follow"),
+        idle_timeout: matches
+            .value_of("idle-timeout")
+            .and_then(|sec| sec.parse().ok())
+            .map(time::Duration::from_secs),
+        mp4: mp4::Mp4Options {
+            track_id: matches.value_of("track").and_then(|id| id.parse().ok()),
+            recovery: matches.is_present("recover"),
+        },
+        seek: matches
+            .value_of("seek")
+            .and_then(|target| target.parse().ok()),
+        init: matches.value_of("init").map(String::from),
+        format: matches
+            .value_of("format")
+            .and_then(|name| name.parse().ok()),
+        codecs: matches.value_of("codecs").map(String::from),
+        validate_timestamps: matches.is_present("validate-timestamps"),
+        frames: Default::default(),
+        obu_filter: matches
+            .value_of("filter-obu")
+            .and_then(|list| parse_obu_filter(list).ok()),
+        skip_frames: matches
+            .value_of("skip")
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0),
+        max_frames: matches.value_of("frames").and_then(|n| n.parse().ok()),
+        frame_filter: FrameFilter {
+            only_keyframes: matches.is_present("only-keyframes"),
+            only_shown: matches.is_present("only-shown"),
+            frame_types: matches.values_of("frame-type").map(|types| {
+                types.fold(0, |mask, name| match name {
+                    "key" => mask | 1 << obu::KEY_FRAME,
+                    "inter" => mask | 1 << obu::INTER_FRAME,
+                    "intra_only" => mask | 1 << obu::INTRA_ONLY_FRAME,
+                    _ => mask | 1 << obu::SWITCH_FRAME,
+                })
+            }),
+        },
+    }
 }
 
 /// application entry point
@@ -369,17 +4393,569 @@ fn main() -> std::io::Result<()> {
     let app = App::new(crate_name!())
         .version(crate_version!())
         .about(crate_description!())
-        .arg(Arg::from_usage("<INPUT>... 'Input AV1 bitstream files'").index(1))
-        .arg(Arg::from_usage("[v]... -v --verbose 'Show verbose log'"));
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            analysis_subcommand("info", "Show container and sequence header summary")
+                .arg(Arg::from_usage(
+                    "[av1c] --av1c=[FILE] 'Write av1C (WebCodecs decoder config description) generated from Sequence Header'",
+                )),
+        )
+        .subcommand(
+            analysis_subcommand("frames", "Show frames in decoding order with reference update")
+                .arg(Arg::from_usage(
+                    "[skip] --skip=[N] 'Omit first N frames of each input from output'",
+                ))
+                .arg(Arg::from_usage(
+                    "[ndjson] --ndjson 'Write one JSON object per frame as parsing proceeds'",
+                ))
+                .arg(
+                    Arg::from_usage(
+                        "[csv] --csv 'Write one CSV row per frame (decode_idx, display_idx, frame_type, ...)'",
+                    )
+                    .conflicts_with("ndjson"),
+                )
+                .arg(
+                    Arg::from_usage(
+                        "[yaml] --yaml 'Write frame records (same as --ndjson) as YAML sequence'",
+                    )
+                    .conflicts_with_all(&["ndjson", "csv"]),
+                )
+                .arg(
+                    Arg::from_usage(
+                        "[aomanalyzer] --aomanalyzer 'Write decoded frames as JSON array for AOM analyzer frontend'",
+                    )
+                    .conflicts_with_all(&["ndjson", "csv", "yaml"]),
+                )
+                .arg(
+                    Arg::from_usage("[export] --export=[FORMAT] 'Export MP4 sample/WebM block table'")
+                        .possible_values(&["csv", "json", "yaml"]),
+                )
+                .arg(Arg::from_usage(
+                    "[show-streams] --show-streams 'Write ffprobe-style STREAM section'",
+                ))
+                .arg(Arg::from_usage(
+                    "[show-frames] --show-frames 'Write ffprobe-style FRAME sections of shown frames'",
+                ))
+                .arg(
+                    Arg::from_usage(
+                        "[print-format] --print-format=[FORMAT] 'ffprobe output format of --show-streams/--show-frames'",
+                    )
+                    .possible_values(&["default", "compact"]),
+                )
+                .arg(Arg::from_usage(
+                    "[only-keyframes] --only-keyframes 'Write key frames only in per-frame output'",
+                ))
+                .arg(Arg::from_usage(
+                    "[only-shown] --only-shown 'Write shown frames only in per-frame output'",
+                ))
+                .arg(
+                    Arg::from_usage(
+                        "[frame-type] --frame-type=[TYPES] 'Write frames of comma separated types only in per-frame output'",
+                    )
+                    .possible_values(&["key", "inter", "intra_only", "switch"])
+                    .use_delimiter(true),
+                )
+                .arg(
+                    Arg::from_usage(
+                        "[show] --show=[SECTIONS] 'Show comma separated header sections (obus -v/-vv/-vvv selects them by verbosity)'",
+                    )
+                    .possible_values(&["sh", "fh", "gm", "fg", "metadata", "refs", "tiles", "superres"])
+                    .use_delimiter(true),
+                )
+                .arg(
+                    Arg::from_usage(
+                        "[filter-obu] --filter-obu=[TYPES] 'Show only OBUs of comma separated types (e.g. SEQUENCE_HEADER,METADATA)'",
+                    )
+                    .validator(|list| parse_obu_filter(&list).map(|_| ())),
+                )
+                .arg(
+                    Arg::from_usage(
+                        "[seek] --seek=[POS] 'Start reporting from key frame before timestamp ([HH:]MM:SS[.sss] or MSEC) or frame index (#N)'",
+                    )
+                    .validator(|target| target.parse::<SeekTarget>().map(|_| ())),
+                )
+                .arg(Arg::from_usage(
+                    "[follow] -f --follow 'Follow growing IVF/fragmented MP4/WebM file or DASH segment directory'",
+                ))
+                .arg(Arg::from_usage(
+                    "[idle-timeout] --idle-timeout=[SEC] 'Stop following after SEC seconds without new samples'",
+                )),
+        )
+        .subcommand(
+            analysis_subcommand("obus", "Show OBU headers with frames")
+                .arg(Arg::from_usage("[v]... -v --verbose 'Show syntax elements of OBUs'"))
+                .arg(Arg::from_usage(
+                    "[skip] --skip=[N] 'Omit first N frames of each input from output'",
+                ))
+                .arg(Arg::from_usage(
+                    "[ndjson] --ndjson 'Write one JSON object per OBU and frame as parsing proceeds'",
+                ))
+                .arg(
+                    Arg::from_usage("[hexdump] --hexdump=[N] 'Dump first N bytes (or all) of OBU payloads'")
+                        .min_values(0)
+                        .require_equals(true),
+                )
+                .arg(Arg::from_usage(
+                    "[trace] --trace 'Trace syntax elements with bit position, width, raw bits and value'",
+                ))
+                .arg(
+                    Arg::from_usage(
+                        "[show] --show=[SECTIONS] 'Show comma separated header sections (obus -v/-vv/-vvv selects them by verbosity)'",
+                    )
+                    .possible_values(&["sh", "fh", "gm", "fg", "metadata", "refs", "tiles", "superres"])
+                    .use_delimiter(true),
+                )
+                .arg(
+                    Arg::from_usage(
+                        "[filter-obu] --filter-obu=[TYPES] 'Show only OBUs of comma separated types (e.g. SEQUENCE_HEADER,METADATA)'",
+                    )
+                    .validator(|list| parse_obu_filter(&list).map(|_| ())),
+                ),
+        )
+        .subcommand(
+            analysis_subcommand("stats", "Show frame type and OBU type statistics").arg(
+                Arg::from_usage(
+                    "[film-grain-table] --film-grain-table=[FILE] 'Write film grain params of shown frames in libaom film grain table format'",
+                ),
+            ),
+        )
+        .subcommand(
+            analysis_subcommand("check", "Validate stream and report problems only (exit status 2 on problems)")
+                .arg(Arg::from_usage(
+                    "[codecs] --codecs=[STRING] 'Validate declared codec parameter string (e.g. av01.0.04M.08)'",
+                ))
+                .arg(Arg::from_usage(
+                    "[strict] --strict 'Treat warnings as errors, fail on any finding'",
+                ))
+                .arg(Arg::from_usage(
+                    "[validate-timestamps] --validate-timestamps 'Check container timestamps against timing_info and show_existing_frame'",
+                )),
+        )
+        .subcommand(analysis_subcommand(
+            "index",
+            "List key frames (random access points) in decoding order",
+        ))
+        .subcommand(analysis_subcommand(
+            "summary",
+            "Print one line per input (size, profile/level, bit depth, frames, duration, bitrate, HDR)",
+        ))
+        .subcommand(
+            analysis_subcommand(
+                "bitrate",
+                "Print time/bitrate CSV of frame bytes bucketed by presentation time",
+            )
+            .arg(
+                Arg::from_usage("[window] --window=[SEC] 'Length of time window in seconds'")
+                    .default_value("1"),
+            ),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Compare sequence headers and frames of two streams (exit status 2 on differences)")
+                .arg(Arg::from_usage("<A> 'Input AV1 bitstream file'"))
+                .arg(Arg::from_usage("<B> 'Input AV1 bitstream file compared with A'"))
+                .arg(
+                    Arg::from_usage("[align] --align=[MODE] 'Align frames by decoding order or presentation timestamp'")
+                        .possible_values(&["decode", "pts"])
+                        .default_value("decode"),
+                )
+                .arg(Arg::from_usage(
+                    "[track] --track=[ID] 'Select AV1 track by track ID (MP4) or TrackNumber (WebM)'",
+                ))
+                .arg(Arg::from_usage(
+                    "[frames] --frames=[N] 'Stop parsing each input after N frames'",
+                ))
+                .arg(Arg::from_usage(
+                    "[quiet] -q --quiet 'Suppress all output, check exit status (2: streams differ)'",
+                )),
+        )
+        .subcommand(
+            tool_subcommand("mux", "Mux IVF or raw OBU stream into MP4 or WebM (.webm/.mkv) file")
+                .arg(Arg::from_usage("<INPUT> 'Input IVF, raw OBU stream, Annex B or MPEG-2 TS'"))
+                .arg(Arg::from_usage("<OUTPUT> 'Output MP4 or WebM file'")),
+        )
+        .subcommand(
+            tool_subcommand("extract", "Extract AV1 samples in MP4/WebM file into raw OBU stream or IVF file")
+                .arg(Arg::from_usage("<INPUT> 'Input MP4/WebM file'"))
+                .arg(Arg::from_usage("<OUTPUT> 'Output raw OBU stream (or IVF file with .ivf, \"-\" for stdout)'"))
+                .arg(Arg::from_usage(
+                    "[config-obus] -c --config-obus 'Prepend configOBUs in av1C box (CodecPrivate)'",
+                ))
+                .arg(Arg::from_usage(
+                    "[track] --track=[ID] 'Select AV1 track by track ID'",
+                ))
+                .arg(Arg::from_usage(
+                    "[init] --init=[FILE] 'Initialization segment for MP4 media segment (implies -c)'",
+                )),
+        )
+        .subcommand(
+            tool_subcommand("tracks", "List all tracks in MP4/WebM file")
+                .arg(Arg::from_usage("<INPUT> 'Input MP4/WebM file'")),
+        )
+        .subcommand(
+            tool_subcommand("convert", "Convert IVF/raw OBU stream/Annex B/MPEG-2 TS into raw OBU stream or Annex B")
+                .arg(Arg::from_usage("<INPUT> 'Input IVF, raw OBU stream, Annex B or MPEG-2 TS'"))
+                .arg(Arg::from_usage("<OUTPUT> 'Output raw OBU stream (or Annex B, \"-\" for stdout)'"))
+                .arg(Arg::from_usage(
+                    "[annexb] --annexb 'Output length delimited bitstream (Annex B)'",
+                ))
+                .arg(Arg::from_usage(
+                    "[normalize] --normalize 'Insert TemporalDelimiter and obu_size fields, drop padding OBUs'",
+                )),
+        )
+        .subcommand(
+            tool_subcommand("concat", "Concatenate IVF/raw OBU streams into IVF file (with .ivf) or raw OBU stream")
+                .arg(Arg::from_usage("<INPUT>... 'Input IVF or raw OBU streams'"))
+                .arg(Arg::from_usage("<OUTPUT> 'Output IVF file or raw OBU stream'")),
+        )
+        .subcommand(
+            tool_subcommand("avif", "Convert still picture (or first key frame) into AVIF file")
+                .arg(Arg::from_usage("<INPUT> 'Input IVF, raw OBU stream, Annex B or MPEG-2 TS'"))
+                .arg(Arg::from_usage("<OUTPUT> 'Output AVIF file'")),
+        )
+        .subcommand(
+            tool_subcommand("extractframe", "Extract frame with its reference frames into standalone stream")
+                .arg(Arg::from_usage("<INPUT> 'Input IVF, raw OBU stream, Annex B or MPEG-2 TS'"))
+                .arg(Arg::from_usage("<FRAME> 'Frame number in decoding order (#N)'"))
+                .arg(Arg::from_usage("<OUTPUT> 'Output raw OBU stream or IVF file (.ivf)'")),
+        )
+        .subcommand(
+            tool_subcommand("split", "Split IVF/raw OBU stream into numbered files per temporal unit")
+                .arg(Arg::from_usage("<INPUT> 'Input IVF, raw OBU stream, Annex B or MPEG-2 TS'"))
+                .arg(Arg::from_usage("<PREFIX> 'Output file prefix (<PREFIX>.NNNN.obu)'"))
+                .arg(Arg::from_usage(
+                    "[per-obu] --per-obu 'Write each OBU into <PREFIX>.NNNN.MM.obu'",
+                )),
+        )
+        .subcommand(
+            tool_subcommand("scan", "Scan binary file for embedded AV1 streams (raw OBU stream)")
+                .arg(Arg::from_usage("<INPUT> 'Input binary file'"))
+                .arg(Arg::from_usage(
+                    "[output] -o --output=<PREFIX> 'Write found streams into <PREFIX>.N.obu'",
+                )),
+        )
+        .subcommand(
+            tool_subcommand("ivfrepair", "Fix IVF header length and pts, write corrected copy")
+                .arg(Arg::from_usage("<INPUT> 'Input IVF file'"))
+                .arg(Arg::from_usage("<OUTPUT> 'Output IVF file'"))
+                .arg(Arg::from_usage(
+                    "[renumber-pts] --renumber-pts 'Renumber restarting pts monotonically'",
+                )),
+        )
+        .subcommand(
+            tool_subcommand("metastrip", "Remove metadata OBUs from IVF/raw OBU stream into IVF file (with .ivf) or raw OBU stream")
+                .arg(Arg::from_usage("<INPUT> 'Input IVF, raw OBU stream, Annex B or MPEG-2 TS (MP4/WebM needs extract into IVF first)'"))
+                .arg(Arg::from_usage("<OUTPUT> 'Output IVF file or raw OBU stream'"))
+                .arg(
+                    Arg::from_usage(
+                        "[metadata-type] --metadata-type=[TYPES] 'Remove metadata OBUs of comma separated metadata types'",
+                    )
+                    .possible_values(&["hdr_cll", "hdr_mdcv", "scalability", "itut_t35", "timecode"])
+                    .use_delimiter(true),
+                )
+                .arg(
+                    Arg::from_usage(
+                        "[t35-provider] --t35-provider=[CODES] 'Remove ITU-T T.35 metadata OBUs of comma separated terminal provider codes (e.g. 0x003C)'",
+                    )
+                    .use_delimiter(true),
+                )
+                .arg(Arg::from_usage(
+                    "[hdr10plus] --hdr10plus 'Remove HDR10+ (SMPTE ST 2094-40) metadata OBUs'",
+                )),
+        )
+        .subcommand(
+            tool_subcommand("metainject", "Insert HDR CLL/MDCV metadata OBUs into key frame temporal units and HDR10+ metadata OBUs into each temporal unit, write IVF file (with .ivf) or raw OBU stream")
+                .arg(Arg::from_usage("<INPUT> 'Input IVF, raw OBU stream, Annex B or MPEG-2 TS (MP4/WebM needs extract into IVF first)'"))
+                .arg(Arg::from_usage("<OUTPUT> 'Output IVF file or raw OBU stream'"))
+                .arg(Arg::from_usage(
+                    "[content-light] --content-light=[CLL] 'Content light level as \"MaxCLL,MaxFALL\" in cd/m² (e.g. 1000,400)'",
+                ))
+                .arg(Arg::from_usage(
+                    "[mastering-display] --mastering-display=[MDCV] 'Mastering display as \"G(x,y)B(x,y)R(x,y)WP(x,y)L(max,min)\" in CIE 1931 xy and cd/m²'",
+                ))
+                .arg(Arg::from_usage(
+                    "[json] --json=[FILE] 'Read content_light and mastering_display from JSON file'",
+                ))
+                .arg(Arg::from_usage(
+                    "[hdr10plus-json] --hdr10plus-json=[FILE] 'Read HDR10+ metadata from hdr10plus_tool JSON file, inserted in presentation order'",
+                )),
+        )
+        .subcommand(
+            tool_subcommand("hdr10plus", "Extract HDR10+ (SMPTE ST 2094-40) metadata into hdr10plus_tool JSON file")
+                .arg(Arg::from_usage("<INPUT> 'Input IVF, raw OBU stream, Annex B or MPEG-2 TS'"))
+                .arg(Arg::from_usage("<OUTPUT> 'Output JSON file (\"-\" for stdout)'")),
+        )
+        .subcommand(
+            tool_subcommand("grainstrip", "Rewrite frame headers without film grain synthesis, write IVF file (with .ivf) or raw OBU stream")
+                .arg(Arg::from_usage("<INPUT> 'Input IVF, raw OBU stream, Annex B or MPEG-2 TS (MP4/WebM needs extract into IVF first)'"))
+                .arg(Arg::from_usage("<OUTPUT> 'Output IVF file or raw OBU stream'"))
+                .arg(Arg::from_usage(
+                    "[sequence-header] --sequence-header 'Also clear film_grain_params_present in sequence headers'",
+                )),
+        )
+        .subcommand(
+            tool_subcommand("mkvdump", "Dump EBML element tree in Matroska/WebM file")
+                .arg(Arg::from_usage("<INPUT> 'Input Matroska/WebM file'")),
+        );
 
     // get commandline flags
     let matches = app.get_matches();
-    let config = AppConfig {
-        verbose: matches.occurrences_of("v"),
+    if let Some(matches) = matches.subcommand_matches("diff") {
+        QUIET.store(matches.is_present("quiet"), Ordering::Relaxed);
+        let by_pts = matches.value_of("align") == Some("pts");
+        let (fname_a, fname_b) = (
+            matches.value_of("A").unwrap(),
+            matches.value_of("B").unwrap(),
+        );
+        match diff::diff_streams(fname_a, fname_b, by_pts, matches) {
+            Ok(0) => return Ok(()),
+            Ok(_) => process::exit(EXIT_VIOLATION),
+            Err(err) => {
+                if !matches.is_present("quiet") {
+                    eprintln!("{}", err);
+                }
+                process::exit(EXIT_PARSE_ERROR);
+            }
+        }
+    }
+    if let (_, Some(matches)) = matches.subcommand() {
+        QUIET.store(matches.is_present("quiet"), Ordering::Relaxed);
+    }
+    if let Some(matches) = matches.subcommand_matches("mux") {
+        let input = matches.value_of("INPUT").unwrap();
+        let output = matches.value_of("OUTPUT").unwrap();
+        return mux::mux_file(input, output);
+    }
+    if let Some(matches) = matches.subcommand_matches("extract") {
+        let input = matches.value_of("INPUT").unwrap();
+        let output = matches.value_of("OUTPUT").unwrap();
+        let options = mp4::Mp4Options {
+            track_id: matches.value_of("track").and_then(|id| id.parse().ok()),
+            recovery: false,
+        };
+        let init = matches.value_of("init");
+        let with_config_obus = matches.is_present("config-obus") || init.is_some();
+        return mux::extract_obu(input, output, with_config_obus, init, &options);
+    }
+    if let Some(matches) = matches.subcommand_matches("tracks") {
+        return tracks::list_tracks(matches.value_of("INPUT").unwrap());
+    }
+    if let Some(matches) = matches.subcommand_matches("convert") {
+        let input = matches.value_of("INPUT").unwrap();
+        let output = matches.value_of("OUTPUT").unwrap();
+        return convert::convert_bitstream(
+            input,
+            output,
+            matches.is_present("annexb"),
+            matches.is_present("normalize"),
+        );
+    }
+    if let Some(matches) = matches.subcommand_matches("concat") {
+        let inputs: Vec<&str> = matches.values_of("INPUT").unwrap().collect();
+        return mux::concat_files(&inputs, matches.value_of("OUTPUT").unwrap());
+    }
+    if let Some(matches) = matches.subcommand_matches("avif") {
+        let input = matches.value_of("INPUT").unwrap();
+        return mux::convert_avif(input, matches.value_of("OUTPUT").unwrap());
+    }
+    if let Some(matches) = matches.subcommand_matches("extractframe") {
+        let input = matches.value_of("INPUT").unwrap();
+        let frame = value_t!(matches, "FRAME", i64).unwrap_or_else(|e| e.exit());
+        return split::extract_frame(input, frame, matches.value_of("OUTPUT").unwrap());
+    }
+    if let Some(matches) = matches.subcommand_matches("split") {
+        let input = matches.value_of("INPUT").unwrap();
+        let prefix = matches.value_of("PREFIX").unwrap();
+        return split::split_file(input, prefix, matches.is_present("per-obu"));
+    }
+    if let Some(matches) = matches.subcommand_matches("scan") {
+        let input = matches.value_of("INPUT").unwrap();
+        return scan::scan_file(input, matches.value_of("output"));
+    }
+    if let Some(matches) = matches.subcommand_matches("ivfrepair") {
+        let input = matches.value_of("INPUT").unwrap();
+        let output = matches.value_of("OUTPUT").unwrap();
+        return repair::repair_ivf(input, output, matches.is_present("renumber-pts"));
+    }
+    if let Some(matches) = matches.subcommand_matches("metastrip") {
+        let input = matches.value_of("INPUT").unwrap();
+        let output = matches.value_of("OUTPUT").unwrap();
+        let mut t35_providers = Vec::new();
+        for code in matches.values_of("t35-provider").into_iter().flatten() {
+            let value = match code.strip_prefix("0x") {
+                Some(hex) => u16::from_str_radix(hex, 16),
+                None => code.parse(),
+            };
+            match value {
+                Ok(value) => t35_providers.push(value),
+                Err(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid terminal provider code: {}", code),
+                    ))
+                }
+            }
+        }
+        let filter = rewrite::MetadataFilter {
+            metadata_types: matches
+                .values_of("metadata-type")
+                .into_iter()
+                .flatten()
+                .map(|name| match name {
+                    "hdr_cll" => obu::METADATA_TYPE_HDR_CLL,
+                    "hdr_mdcv" => obu::METADATA_TYPE_HDR_MDCV,
+                    "scalability" => obu::METADATA_TYPE_SCALABILITY,
+                    "itut_t35" => obu::METADATA_TYPE_ITUT_T35,
+                    _ => obu::METADATA_TYPE_TIMECODE,
+                })
+                .collect(),
+            t35_providers,
+            hdr10plus: matches.is_present("hdr10plus"),
+        };
+        return rewrite::strip_metadata(input, output, &filter);
+    }
+    if let Some(matches) = matches.subcommand_matches("metainject") {
+        let input = matches.value_of("INPUT").unwrap();
+        let output = matches.value_of("OUTPUT").unwrap();
+        let mut hdr = match matches.value_of("json") {
+            Some(path) => {
+                let f = io::BufReader::new(fs::File::open(path)?);
+                serde_json::from_reader(f).map_err(|e| {
+                    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, e))
+                })?
+            }
+            None => rewrite::HdrStaticMetadata::default(),
+        };
+        // commandline flags override values in JSON file
+        if let Some(cll) = matches.value_of("content-light") {
+            hdr.content_light = Some(rewrite::ContentLight::parse(cll)?);
+        }
+        if let Some(mdcv) = matches.value_of("mastering-display") {
+            hdr.mastering_display = Some(rewrite::MasteringDisplay::parse(mdcv)?);
+        }
+        let hdr10plus = match matches.value_of("hdr10plus-json") {
+            Some(path) => hdr10plus::read_hdr10plus_json(path)?,
+            None => BTreeMap::new(),
+        };
+        return rewrite::inject_hdr_metadata(input, output, &hdr, &hdr10plus);
+    }
+    if let Some(matches) = matches.subcommand_matches("hdr10plus") {
+        let input = matches.value_of("INPUT").unwrap();
+        let output = matches.value_of("OUTPUT").unwrap();
+        return hdr10plus::extract_hdr10plus(input, output);
+    }
+    if let Some(matches) = matches.subcommand_matches("grainstrip") {
+        let input = matches.value_of("INPUT").unwrap();
+        let output = matches.value_of("OUTPUT").unwrap();
+        return rewrite::remove_film_grain(input, output, matches.is_present("sequence-header"));
+    }
+    if let Some(matches) = matches.subcommand_matches("mkvdump") {
+        return tracks::dump_mkv_elements(matches.value_of("INPUT").unwrap());
+    }
+    let (matches, command) = match matches.subcommand() {
+        ("info", Some(matches)) => (matches, Command::Info),
+        ("frames", Some(matches)) => (matches, Command::Frames),
+        ("obus", Some(matches)) => (matches, Command::Obus),
+        ("stats", Some(matches)) => (matches, Command::Stats),
+        ("check", Some(matches)) => (matches, Command::Check),
+        ("index", Some(matches)) => (matches, Command::Index),
+        ("summary", Some(matches)) => (matches, Command::Summary),
+        ("bitrate", Some(matches)) => (matches, Command::Bitrate),
+        _ => unreachable!("subcommand is required"),
     };
+    let config = app_config(matches, command);
+    TEXT_LOG_STDERR.store(
+        config.output != FrameOutput::Text || command == Command::Bitrate,
+        Ordering::Relaxed,
+    );
+    QUIET.store(config.quiet, Ordering::Relaxed);
+    let template = matches.value_of("output");
+    let mut report = None; // path of current report file
+    if template.is_none() {
+        begin_report(&config);
+    }
 
-    for fname in matches.values_of("INPUT").unwrap() {
-        process_file(fname, &config)?;
+    let (inputs, expanded) = expand_inputs(matches.values_of("INPUT").unwrap())?;
+    let mut summaries = Vec::new();
+    let (mut parse_errors, mut unsupported, mut problems) = (0, 0, 0);
+    for fname in inputs.iter().map(String::as_str) {
+        if let Some(template) = template {
+            let path = report_path(template, fname);
+            if report.as_ref() != Some(&path) {
+                if report.is_some() {
+                    end_report(&config);
+                }
+                set_report_file(Some(&path))?;
+                begin_report(&config);
+                report = Some(path);
+            }
+        }
+        *config.stats.borrow_mut() = Default::default();
+        config.pts.set(None);
+        config.timebase.set(None);
+        let mut status = "OK";
+        // per-file log is replaced with one line summary or CSV
+        QUIET.store(
+            config.quiet || matches!(command, Command::Summary | Command::Bitrate),
+            Ordering::Relaxed,
+        );
+        let result = process_file(fname, &config);
+        QUIET.store(config.quiet, Ordering::Relaxed);
+        if let Err(err) = result {
+            if err.kind() == io::ErrorKind::Unsupported {
+                unsupported += 1;
+                status = "unsupported";
+            } else {
+                parse_errors += 1;
+                status = "parse error";
+            }
+            if !config.quiet {
+                eprintln!("{}: {}", fname, err);
+            }
+        }
+        print_ffprobe_stream(&config);
+        if config.validate_timestamps {
+            validate_timestamps(&config);
+        }
+        let file_problems = print_command_summary(fname, &config);
+        problems += file_problems;
+        let stats = config.stats.borrow();
+        let frames = stats.frame_count.iter().sum();
+        if stats.unsupported {
+            unsupported += 1;
+            status = "unsupported";
+        } else if frames == 0 && status == "OK" {
+            // nothing was recognized as AV1 stream in any mode
+            unsupported += 1;
+            status = "no frames";
+        } else if file_problems > 0 && status == "OK" {
+            status = "problems";
+        }
+        summaries.push(InputSummary {
+            fname: fname.to_string(),
+            frames,
+            shown_frames: stats.shown_frames,
+            problems: file_problems,
+            status,
+        });
+        if let Some(output) = matches.value_of("av1c") {
+            av1c::write_decoder_config(fname, output)?;
+        }
+        if let Some(output) = matches.value_of("film-grain-table") {
+            grain::write_film_grain_table(fname, output, &stats)?;
+        }
+    }
+    end_report(&config);
+    set_report_file(None)?;
+    if expanded {
+        print_aggregate_table(&summaries);
+    }
+    if parse_errors > 0 {
+        process::exit(EXIT_PARSE_ERROR);
+    } else if unsupported > 0 {
+        process::exit(EXIT_UNSUPPORTED);
+    } else if problems > 0 {
+        process::exit(EXIT_VIOLATION);
     }
     Ok(())
 }
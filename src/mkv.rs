@@ -2,32 +2,115 @@
 ///
 /// https://matroska.org/technical/specs/index.html
 ///
+use crate::obu;
 use byteorder::{BigEndian, ByteOrder};
 use std::io;
 use std::io::{Read, SeekFrom};
 
 // Element ID
 const ELEMENT_EBML: u32 = 0x1A45DFA3; // EBML header
+const ELEMENT_EBMLVERSION: u32 = 0x4286; // EBML/EBMLVersion
+const ELEMENT_EBMLREADVERSION: u32 = 0x42F7; // EBML/EBMLReadVersion
+const ELEMENT_EBMLMAXIDLENGTH: u32 = 0x42F2; // EBML/EBMLMaxIDLength
+const ELEMENT_EBMLMAXSIZELENGTH: u32 = 0x42F3; // EBML/EBMLMaxSizeLength
+const ELEMENT_DOCTYPE: u32 = 0x4282; // EBML/DocType
+const ELEMENT_DOCTYPEVERSION: u32 = 0x4287; // EBML/DocTypeVersion
+const ELEMENT_DOCTYPEREADVERSION: u32 = 0x4285; // EBML/DocTypeReadVersion
+const ELEMENT_VOID: u32 = 0xEC; // Void
+const ELEMENT_CRC32: u32 = 0xBF; // CRC-32
 const ELEMENT_SEGMENT: u32 = 0x18538067; // Segment
 const ELEMENT_SEEKHEAD: u32 = 0x114D9B74; // Meta Seek Information
+const ELEMENT_SEEK: u32 = 0x4DBB; // SeekHead/Seek
+const ELEMENT_SEEKID: u32 = 0x53AB; // SeekHead/Seek/SeekID
+const ELEMENT_SEEKPOSITION: u32 = 0x53AC; // SeekHead/Seek/SeekPosition
 const ELEMENT_INFO: u32 = 0x1549A966; // Segment Information
+const ELEMENT_TIMECODESCALE: u32 = 0x2AD7B1; // Info/TimecodeScale
+const ELEMENT_DURATION: u32 = 0x4489; // Info/Duration
+const ELEMENT_TITLE: u32 = 0x7BA9; // Info/Title
+const ELEMENT_MUXINGAPP: u32 = 0x4D80; // Info/MuxingApp
+const ELEMENT_WRITINGAPP: u32 = 0x5741; // Info/WritingApp
 const ELEMENT_CLUSTER: u32 = 0x1F43B675; // Cluster
 const ELEMENT_TIMECODE: u32 = 0xE7; // Cluster/Timecode
 const ELEMENT_SIMPLEBLOCK: u32 = 0xA3; // Cluster/SimpleBlock
 const ELEMENT_BLOCKGROUP: u32 = 0xA0; // Cluster/BlockGroup
+const ELEMENT_BLOCK: u32 = 0xA1; // Cluster/BlockGroup/Block
+const ELEMENT_BLOCKDURATION: u32 = 0x9B; // Cluster/BlockGroup/BlockDuration
+const ELEMENT_REFERENCEBLOCK: u32 = 0xFB; // Cluster/BlockGroup/ReferenceBlock
+const ELEMENT_DISCARDPADDING: u32 = 0x75A2; // Cluster/BlockGroup/DiscardPadding
+const ELEMENT_BLOCKADDITIONS: u32 = 0x75A1; // Cluster/BlockGroup/BlockAdditions
+const ELEMENT_BLOCKMORE: u32 = 0xA6; // Cluster/BlockGroup/BlockAdditions/BlockMore
+const ELEMENT_BLOCKADDID: u32 = 0xEE; // Cluster/BlockGroup/BlockAdditions/BlockMore/BlockAddID
+const ELEMENT_BLOCKADDITIONAL: u32 = 0xA5; // Cluster/BlockGroup/BlockAdditions/BlockMore/BlockAdditional
 const ELEMENT_TRACKS: u32 = 0x1654AE6B; // Track
 const ELEMENT_TRACKENTRY: u32 = 0xAE; // Tracks/TrackEntry
 const ELEMENT_TRACKNUMBER: u32 = 0xD7; // Tracks/TrackEntry/TrackNumber
 const ELEMENT_TRACKTYPE: u32 = 0x83; // Tracks/TrackEntry/TrackType
+const ELEMENT_TRACKUID: u32 = 0x73C5; // Tracks/TrackEntry/TrackUID
+const ELEMENT_FLAGENABLED: u32 = 0xB9; // Tracks/TrackEntry/FlagEnabled
+const ELEMENT_FLAGDEFAULT: u32 = 0x88; // Tracks/TrackEntry/FlagDefault
+const ELEMENT_FLAGLACING: u32 = 0x9C; // Tracks/TrackEntry/FlagLacing
 const ELEMENT_CODECID: u32 = 0x86; // Tracks/TrackEntry/CodecID
+const ELEMENT_CODECPRIVATE: u32 = 0x63A2; // Tracks/TrackEntry/CodecPrivate
+const ELEMENT_NAME: u32 = 0x536E; // Tracks/TrackEntry/Name
+const ELEMENT_LANGUAGE: u32 = 0x22B59C; // Tracks/TrackEntry/Language
+const ELEMENT_DEFAULTDURATION: u32 = 0x23E383; // Tracks/TrackEntry/DefaultDuration
+const ELEMENT_CODECDELAY: u32 = 0x56AA; // Tracks/TrackEntry/CodecDelay
 const ELEMENT_VIDEO: u32 = 0xE0; // Tracks/TrackEntry/Video
 const ELEMENT_PIXELWIDTH: u32 = 0xB0; // Tracks/TrackEntry/Video/PixelWidth
 const ELEMENT_PIXELHEIGHT: u32 = 0xBA; // Tracks/TrackEntry/Video/PixelHeight
+const ELEMENT_COLOUR: u32 = 0x55B0; // Tracks/TrackEntry/Video/Colour
+const ELEMENT_MATRIXCOEFFICIENTS: u32 = 0x55B1; // Video/Colour/MatrixCoefficients
+const ELEMENT_RANGE: u32 = 0x55B9; // Video/Colour/Range
+const ELEMENT_TRANSFERCHARACTERISTICS: u32 = 0x55BA; // Video/Colour/TransferCharacteristics
+const ELEMENT_PRIMARIES: u32 = 0x55BB; // Video/Colour/Primaries
+const ELEMENT_MAXCLL: u32 = 0x55BC; // Video/Colour/MaxCLL
+const ELEMENT_MAXFALL: u32 = 0x55BD; // Video/Colour/MaxFALL
+const ELEMENT_MASTERINGMETADATA: u32 = 0x55D0; // Video/Colour/MasteringMetadata
+const ELEMENT_PRIMARYRCHROMATICITYX: u32 = 0x55D1; // Colour/MasteringMetadata/PrimaryRChromaticityX
+const ELEMENT_PRIMARYRCHROMATICITYY: u32 = 0x55D2; // Colour/MasteringMetadata/PrimaryRChromaticityY
+const ELEMENT_PRIMARYGCHROMATICITYX: u32 = 0x55D3; // Colour/MasteringMetadata/PrimaryGChromaticityX
+const ELEMENT_PRIMARYGCHROMATICITYY: u32 = 0x55D4; // Colour/MasteringMetadata/PrimaryGChromaticityY
+const ELEMENT_PRIMARYBCHROMATICITYX: u32 = 0x55D5; // Colour/MasteringMetadata/PrimaryBChromaticityX
+const ELEMENT_PRIMARYBCHROMATICITYY: u32 = 0x55D6; // Colour/MasteringMetadata/PrimaryBChromaticityY
+const ELEMENT_WHITEPOINTCHROMATICITYX: u32 = 0x55D7; // Colour/MasteringMetadata/WhitePointChromaticityX
+const ELEMENT_WHITEPOINTCHROMATICITYY: u32 = 0x55D8; // Colour/MasteringMetadata/WhitePointChromaticityY
+const ELEMENT_LUMINANCEMAX: u32 = 0x55D9; // Colour/MasteringMetadata/LuminanceMax
+const ELEMENT_LUMINANCEMIN: u32 = 0x55DA; // Colour/MasteringMetadata/LuminanceMin
 const ELEMENT_CUES: u32 = 0x1C53BB6B; // Cueing Data
+const ELEMENT_ATTACHMENTS: u32 = 0x1941A469; // Attachment
+const ELEMENT_CHAPTERS: u32 = 0x1043A770; // Chapters
+const ELEMENT_EDITIONENTRY: u32 = 0x45B9; // Chapters/EditionEntry
+const ELEMENT_CHAPTERATOM: u32 = 0xB6; // Chapters/EditionEntry/ChapterAtom
+const ELEMENT_CHAPTERTIMESTART: u32 = 0x91; // ChapterAtom/ChapterTimeStart
+const ELEMENT_CHAPTERTIMEEND: u32 = 0x92; // ChapterAtom/ChapterTimeEnd
+const ELEMENT_CHAPTERDISPLAY: u32 = 0x80; // ChapterAtom/ChapterDisplay
+const ELEMENT_CHAPSTRING: u32 = 0x85; // ChapterAtom/ChapterDisplay/ChapString
+const ELEMENT_TAGS: u32 = 0x1254C367; // Tagging
+const ELEMENT_TAG: u32 = 0x7373; // Tags/Tag
+const ELEMENT_TARGETS: u32 = 0x63C0; // Tags/Tag/Targets
+const ELEMENT_TAGTRACKUID: u32 = 0x63C5; // Tags/Tag/Targets/TagTrackUID
+const ELEMENT_SIMPLETAG: u32 = 0x67C8; // Tags/Tag/SimpleTag
+const ELEMENT_TAGNAME: u32 = 0x45A3; // Tags/Tag/SimpleTag/TagName
+const ELEMENT_TAGSTRING: u32 = 0x4487; // Tags/Tag/SimpleTag/TagString
+const ELEMENT_CUEPOINT: u32 = 0xBB; // Cues/CuePoint
+const ELEMENT_CUETIME: u32 = 0xB3; // Cues/CuePoint/CueTime
+const ELEMENT_CUETRACKPOSITIONS: u32 = 0xB7; // Cues/CuePoint/CueTrackPositions
+const ELEMENT_CUETRACK: u32 = 0xF7; // Cues/CuePoint/CueTrackPositions/CueTrack
+const ELEMENT_CUECLUSTERPOSITION: u32 = 0xF1; // Cues/CuePoint/CueTrackPositions/CueClusterPosition
+
+// SimpleBlock flags
+const SIMPLEBLOCK_FLAG_KEYFRAME: u8 = 0x80;
+const BLOCK_FLAG_LACING: u8 = 0x06;
+
+// default TimecodeScale (1 millisecond)
+const DEFAULT_TIMECODESCALE: u64 = 1_000_000;
 
 // Codec ID
 pub const CODEC_V_AV1: &str = "V_AV1"; // video/AV1
 
+// BlockAddID
+pub const BLOCKADDID_ITUT_T35: u64 = 4; // ITU-T T.35 metadata (e.g. HDR10+)
+
 /// Element ID (1-4 bytes)
 fn read_elementid<R: io::Read>(mut reader: R) -> io::Result<u32> {
     let mut b0 = [0; 1];
@@ -78,23 +161,135 @@ fn read_varint<R: io::Read>(mut reader: R) -> io::Result<(i64, usize)> {
                 "Unexpected EOF at variable length codeded integer",
             ));
         }
-        for i in 0..lzcnt {
-            value = (value << 8) | buf[i] as i64;
+        for b in buf.iter().take(lzcnt) {
+            value = (value << 8) | *b as i64;
         }
     }
     Ok((value, 1 + lzcnt))
 }
 
-/// Data size (1-8 bytes)
+/// Data size (1-8 bytes), return None for reserved unknown size (all-ones value)
+#[inline]
+fn read_elementsize<R: io::Read>(reader: R) -> io::Result<Option<i64>> {
+    let (value, len) = read_varint(reader)?;
+    if value == (1 << (7 * len)) - 1 {
+        return Ok(None);
+    }
+    Ok(Some(value))
+}
+
+/// Data size (1-8 bytes) of element which shall have known size
 #[inline]
 fn read_datasize<R: io::Read>(reader: R) -> io::Result<i64> {
-    let (value, _) = read_varint(reader)?;
-    Ok(value)
+    read_elementsize(reader)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Unknown data size is not allowed",
+        )
+    })
+}
+
+/// Top-level element in Segment, which terminates unknown-size element
+fn is_level1_element(id: u32) -> bool {
+    matches!(
+        id,
+        ELEMENT_SEEKHEAD
+            | ELEMENT_INFO
+            | ELEMENT_TRACKS
+            | ELEMENT_CLUSTER
+            | ELEMENT_CUES
+            | ELEMENT_ATTACHMENTS
+            | ELEMENT_CHAPTERS
+            | ELEMENT_TAGS
+            | ELEMENT_EBML
+            | ELEMENT_SEGMENT
+    )
+}
+
+/// CRC-32 (ISO 3309, little-endian in CRC-32 element)
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// verify CRC-32 element at the beginning of element data, return false on mismatch
+fn verify_crc32<R: io::Read + io::Seek>(
+    mut reader: R,
+    data_pos: u64,
+    data_end: u64,
+) -> io::Result<bool> {
+    reader.seek(SeekFrom::Start(data_pos))?;
+    let mut crc = [0; 4];
+    let has_crc = read_elementid(&mut reader).ok() == Some(ELEMENT_CRC32)
+        && read_datasize(&mut reader).ok() == Some(4)
+        && reader.read_exact(&mut crc).is_ok();
+    let mut result = true;
+    if has_crc {
+        let mut remain = data_end.saturating_sub(reader.stream_position()?);
+        let mut buf = vec![0; 64 * 1024];
+        let mut value = 0;
+        while remain > 0 {
+            let n = remain.min(buf.len() as u64) as usize;
+            reader.read_exact(&mut buf[..n])?;
+            value = crc32_update(value, &buf[..n]);
+            remain -= n as u64;
+        }
+        result = value == u32::from_le_bytes(crc);
+    }
+    reader.seek(SeekFrom::Start(data_pos))?;
+    Ok(result)
+}
+
+/// find next Cluster ElementID from the position
+fn resync_cluster<R: io::Read + io::Seek>(
+    mut reader: R,
+    from: u64,
+    stream_len: u64,
+) -> io::Result<Option<u64>> {
+    let cluster_id = ELEMENT_CLUSTER.to_be_bytes();
+    let mut buf = vec![0; 64 * 1024];
+    let mut base = from;
+    while base + 4 <= stream_len {
+        let n = (buf.len() as u64).min(stream_len - base) as usize;
+        reader.seek(SeekFrom::Start(base))?;
+        reader.read_exact(&mut buf[..n])?;
+        if let Some(i) = buf[..n].windows(4).position(|w| w == cluster_id) {
+            return Ok(Some(base + i as u64));
+        }
+        if n < buf.len() {
+            break;
+        }
+        base += (n - 3) as u64;
+    }
+    Ok(None)
+}
+
+/// get length of stream
+fn stream_len<R: io::Seek>(mut reader: R) -> io::Result<u64> {
+    let pos = reader.stream_position()?;
+    let len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(pos))?;
+    Ok(len)
 }
 
 /// Unsigned integer (1-8 bytes)
 fn read_uint<R: io::Read>(reader: R, len: i64) -> io::Result<u64> {
-    assert!(0 < len && len <= 8);
+    if !(0 < len && len <= 8) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid unsigned integer size={}", len),
+        ));
+    }
     let mut buf = [0; 8];
     let rlen = reader.take(len as u64).read(&mut buf)?;
     if rlen < len as usize {
@@ -104,17 +299,48 @@ fn read_uint<R: io::Read>(reader: R, len: i64) -> io::Result<u64> {
         ));
     }
     let mut value = buf[0] as u64;
-    for i in 1..(len as usize) {
-        value = value << 8 | buf[i] as u64;
+    for b in buf.iter().take(len as usize).skip(1) {
+        value = value << 8 | *b as u64;
     }
     Ok(value)
 }
 
+/// Signed integer (1-8 bytes)
+fn read_int<R: io::Read>(reader: R, len: i64) -> io::Result<i64> {
+    let value = read_uint(reader, len)?;
+    // sign extension
+    let shift = 64 - 8 * len as u32;
+    Ok(((value << shift) as i64) >> shift)
+}
+
+/// Float (4 or 8 bytes)
+fn read_float<R: io::Read>(mut reader: R, len: i64) -> io::Result<f64> {
+    match len {
+        4 => {
+            let mut buf = [0; 4];
+            reader.read_exact(&mut buf)?;
+            Ok(BigEndian::read_f32(&buf) as f64)
+        }
+        8 => {
+            let mut buf = [0; 8];
+            reader.read_exact(&mut buf)?;
+            Ok(BigEndian::read_f64(&buf))
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid float length",
+        )),
+    }
+}
+
 /// String (1-n bytes)
 fn read_string<R: io::Read>(reader: R, len: i64) -> io::Result<String> {
     assert!(0 < len);
     let mut value = String::new();
     reader.take(len as u64).read_to_string(&mut value)?;
+    // strip null padding
+    let len = value.trim_end_matches('\0').len();
+    value.truncate(len);
     Ok(value)
 }
 
@@ -123,22 +349,196 @@ fn read_string<R: io::Read>(reader: R, len: i64) -> io::Result<String> {
 ///
 #[derive(Debug)]
 pub struct Matroska {
-    tracks: Vec<TrackEntey>,
-    clusters: Vec<Cluster>,
-    curr_cluster: usize,
+    info: SegmentInfo,
+    tracks: Vec<TrackEntry>,
+    cues: Vec<CuePoint>,
+    tags: Vec<SimpleTag>,
+    chapters: Vec<Chapter>,
+    segment_pos: u64,            // position of Segment data
+    first_cluster_pos: u64,      // position of first Cluster element
+    next_cluster_pos: u64,       // position to look for next Cluster (0=end)
+    parsed: Vec<u64>,            // positions of parsed Level1 elements
+    crc_errors: Vec<(u32, u64)>, // (ElementID, position) with CRC-32 mismatch
+    skipped: Vec<(u64, u64)>,    // damaged regions in Clusters
+    curr_cluster: Option<Cluster>,
     curr_offset: u64,
 }
 
 impl Matroska {
     fn new() -> Self {
         Matroska {
+            info: SegmentInfo {
+                timecode_scale: DEFAULT_TIMECODESCALE,
+                ..Default::default()
+            },
             tracks: Vec::new(),
-            clusters: Vec::new(),
-            curr_cluster: 0,
+            cues: Vec::new(),
+            tags: Vec::new(),
+            chapters: Vec::new(),
+            segment_pos: 0,
+            first_cluster_pos: 0,
+            next_cluster_pos: 0,
+            parsed: Vec::new(),
+            crc_errors: Vec::new(),
+            skipped: Vec::new(),
+            curr_cluster: None,
             curr_offset: 0,
         }
     }
 
+    /// get Segment Information
+    pub fn get_info(&self) -> &SegmentInfo {
+        &self.info
+    }
+
+    /// get TimecodeScale in nanoseconds
+    pub fn get_timecode_scale(&self) -> u64 {
+        self.info.timecode_scale
+    }
+
+    /// get Segment duration in nanoseconds
+    pub fn get_duration_ns(&self) -> Option<f64> {
+        self.info.duration_ns()
+    }
+
+    /// get elements with CRC-32 mismatch as (ElementID, position)
+    pub fn get_crc_errors(&self) -> &Vec<(u32, u64)> {
+        &self.crc_errors
+    }
+
+    /// get skipped damaged regions as (start, end) positions
+    pub fn get_skipped(&self) -> &Vec<(u64, u64)> {
+        &self.skipped
+    }
+
+    /// get SimpleTags
+    pub fn get_tags(&self) -> &Vec<SimpleTag> {
+        &self.tags
+    }
+
+    /// get ChapterAtoms (including nested ones)
+    pub fn get_chapters(&self) -> &Vec<Chapter> {
+        &self.chapters
+    }
+
+    /// get CuePoints
+    pub fn get_cues(&self) -> &Vec<CuePoint> {
+        &self.cues
+    }
+
+    ///
+    /// seek to nearest Cluster before the timestamp [nsec]
+    ///
+    /// return timecode of the Cluster, or None if no Cluster found.
+    ///
+    pub fn seek_to_time<R: io::Read + io::Seek>(
+        &mut self,
+        mut reader: R,
+        timestamp_ns: i64,
+    ) -> io::Result<Option<i64>> {
+        let timecode = timestamp_ns / self.info.timecode_scale as i64;
+        self.curr_cluster = None;
+        self.curr_offset = 0;
+        // find Cluster with Cues
+        let cue_pos = self
+            .cues
+            .iter()
+            .rfind(|cue| cue.time as i64 <= timecode)
+            .map(|cue| self.segment_pos + cue.cluster_pos);
+        let cluster_pos = match cue_pos {
+            Some(pos) => Some(pos),
+            None => {
+                // fallback to Cluster Timecode
+                let mut found = None;
+                self.next_cluster_pos = self.first_cluster_pos;
+                while let Some(cluster) = self.next_cluster(&mut reader)? {
+                    if timecode < cluster.timecode {
+                        break;
+                    }
+                    found = Some(cluster.pos);
+                }
+                found
+            }
+        };
+        self.next_cluster_pos = cluster_pos.unwrap_or(self.first_cluster_pos);
+        if cluster_pos.is_none() {
+            return Ok(None);
+        }
+        self.curr_cluster = self.next_cluster(&mut reader)?;
+        Ok(self.curr_cluster.as_ref().map(|cluster| cluster.timecode))
+    }
+
+    /// find next Cluster from next_cluster_pos
+    fn next_cluster<R: io::Read + io::Seek>(
+        &mut self,
+        mut reader: R,
+    ) -> io::Result<Option<Cluster>> {
+        let len = stream_len(&mut reader)?;
+        while self.next_cluster_pos != 0 {
+            let node_pos = self.next_cluster_pos;
+            reader.seek(SeekFrom::Start(node_pos))?;
+            // keep next_cluster_pos at end of (growing) Segment
+            let (node, node_size) = match read_elementid(&mut reader)
+                .and_then(|node| Ok((node, read_elementsize(&mut reader)?)))
+            {
+                Ok(node) => node,
+                Err(_) => break, // end of Segment
+            };
+            let data_pos = reader.stream_position()?;
+            self.next_cluster_pos = match node_size {
+                Some(node_size) => data_pos + node_size as u64,
+                None => data_pos, // walk into children until next Level1 element
+            };
+            match node {
+                ELEMENT_CLUSTER => {
+                    if node_size.is_some()
+                        && self.next_cluster_pos <= len
+                        && !verify_crc32(&mut reader, data_pos, self.next_cluster_pos)?
+                    {
+                        self.crc_errors.push((node, node_pos));
+                    }
+                    let cluster = match Self::read_cluster(&mut reader, node_pos, node_size) {
+                        Ok(cluster) => cluster,
+                        Err(ref err) if err.kind() == io::ErrorKind::InvalidData => {
+                            // skip damaged Cluster
+                            if node_size.is_none() {
+                                self.next_cluster_pos =
+                                    resync_cluster(&mut reader, data_pos, len)?.unwrap_or(len);
+                            }
+                            self.skipped.push((node_pos, self.next_cluster_pos));
+                            continue;
+                        }
+                        Err(err) => return Err(err),
+                    };
+                    if cluster.pos_begin == 0 && len < cluster.pos_end {
+                        // Cluster is still being written
+                        self.next_cluster_pos = node_pos;
+                        break;
+                    }
+                    return Ok(Some(cluster));
+                }
+                ELEMENT_CUES if !self.parsed.contains(&node_pos) => {
+                    let node_size = match node_size {
+                        Some(node_size) => node_size,
+                        None => continue,
+                    };
+                    if len < self.next_cluster_pos {
+                        self.next_cluster_pos = node_pos;
+                        break;
+                    }
+                    self.read_level1(&mut reader, node_pos, node, node_size)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// get all TrackEntries
+    pub fn get_tracks(&self) -> &Vec<TrackEntry> {
+        &self.tracks
+    }
+
     /// find track with CodecID
     pub fn find_track(&self, codec_id: &str) -> Option<u64> {
         self.tracks
@@ -147,6 +547,15 @@ impl Matroska {
             .map(|t| t.track_num)
     }
 
+    /// find all tracks with CodecID
+    pub fn find_tracks(&self, codec_id: &str) -> Vec<u64> {
+        self.tracks
+            .iter()
+            .filter(|t| t.codec_id == codec_id)
+            .map(|t| t.track_num)
+            .collect()
+    }
+
     /// get Video settings
     pub fn get_videosetting(&self, track_num: u64) -> Option<&VideoTrack> {
         self.tracks
@@ -155,58 +564,544 @@ impl Matroska {
             .and_then(|t| t.setting.as_ref())
     }
 
+    /// get DefaultDuration in nanoseconds
+    pub fn get_default_duration(&self, track_num: u64) -> Option<u64> {
+        self.tracks
+            .iter()
+            .find(|t| t.track_num == track_num)
+            .and_then(|t| t.default_duration)
+    }
+
+    /// get CodecDelay in nanoseconds
+    pub fn get_codec_delay(&self, track_num: u64) -> u64 {
+        self.tracks
+            .iter()
+            .find(|t| t.track_num == track_num)
+            .map_or(0, |t| t.codec_delay)
+    }
+
     /// read next block
     pub fn next_block<R: io::Read + io::Seek>(
         &mut self,
         mut reader: R,
     ) -> io::Result<Option<Block>> {
-        if self.curr_offset == 0 {
-            if self.clusters.len() <= self.curr_cluster {
-                return Ok(None); // end of clusters
+        let len = stream_len(&mut reader)?;
+        loop {
+            if self.curr_cluster.is_none() {
+                self.curr_cluster = self.next_cluster(&mut reader)?;
+            }
+            let (timecode, pos_begin, pos_end) = match self.curr_cluster {
+                Some(ref cluster) => (cluster.timecode, cluster.pos_begin, cluster.pos_end),
+                None => return Ok(None), // end of clusters
+            };
+            if self.curr_offset == 0 {
+                self.curr_offset = pos_begin;
+            }
+            if pos_begin == 0 || pos_end <= self.curr_offset {
+                // move to next cluster
+                self.curr_cluster = None;
+                self.curr_offset = 0;
+                continue;
+            }
+            let node_pos = self.curr_offset;
+            reader.seek(SeekFrom::Start(node_pos))?;
+            let (node, node_size) = match read_elementid(&mut reader)
+                .and_then(|node| Ok((node, read_elementsize(&mut reader)?)))
+            {
+                Ok(node) => node,
+                // wait for more data in unknown-size (or growing) Cluster
+                Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(_) => {
+                    self.resync(&mut reader, node_pos, len)?;
+                    continue;
+                }
+            };
+            if is_level1_element(node) {
+                // end of unknown-size Cluster
+                self.next_cluster_pos = node_pos;
+                self.curr_cluster = None;
+                self.curr_offset = 0;
+                continue;
+            }
+            let node_size = match node_size {
+                Some(node_size) => node_size,
+                None => {
+                    // only Segment and Cluster may have unknown size
+                    self.resync(&mut reader, node_pos, len)?;
+                    continue;
+                }
+            };
+            let node_end = reader.stream_position()?.checked_add(node_size as u64);
+            match node_end {
+                Some(node_end) if node_end <= pos_end => self.curr_offset = node_end,
+                _ => {
+                    // element overruns Cluster
+                    self.resync(&mut reader, node_pos, len)?;
+                    continue;
+                }
+            }
+            if len < self.curr_offset {
+                // wait for rest of Block data
+                self.curr_offset = node_pos;
+                return Ok(None);
+            }
+            let block = match node {
+                ELEMENT_SIMPLEBLOCK => Self::read_block(&mut reader, node_size, timecode).map(Some),
+                ELEMENT_BLOCKGROUP => Self::read_blockgroup(&mut reader, node_size, timecode),
+                _ => Ok(None), // Void, CRC-32, etc.
+            };
+            match block {
+                Ok(Some(mut block)) => {
+                    // effective presentation timestamp
+                    block.timestamp_ns = block.timecode * self.info.timecode_scale as i64
+                        - self.get_codec_delay(block.track_num) as i64;
+                    // seek to Block data
+                    reader.seek(SeekFrom::Start(block.offset))?;
+                    return Ok(Some(block));
+                }
+                Ok(None) => {}
+                Err(_) => self.resync(&mut reader, node_pos, len)?,
             }
-            self.curr_offset = self.clusters[self.curr_cluster].pos_begin;
         }
-        reader.seek(SeekFrom::Start(self.curr_offset))?;
-        loop {
-            // seek to SimpleBlock element
+    }
+
+    /// skip damaged region in Cluster until next Cluster
+    fn resync<R: io::Read + io::Seek>(
+        &mut self,
+        mut reader: R,
+        from: u64,
+        stream_len: u64,
+    ) -> io::Result<()> {
+        let next_pos = resync_cluster(&mut reader, from + 1, stream_len)?;
+        self.skipped.push((from, next_pos.unwrap_or(stream_len)));
+        self.next_cluster_pos = next_pos.unwrap_or(stream_len);
+        self.curr_cluster = None;
+        self.curr_offset = 0;
+        Ok(())
+    }
+
+    // (Simple)Block element header
+    fn read_block<R: io::Read + io::Seek>(
+        mut reader: R,
+        node_size: i64,
+        cluster_timecode: i64,
+    ) -> io::Result<Block> {
+        // read (Simple)Block header (4- bytes)
+        let (track_num, len) = read_varint(&mut reader)?;
+        let mut buf = [0; 3];
+        reader.read_exact(&mut buf)?;
+        let tc_offset = BigEndian::read_i16(&buf);
+        let flags = buf[2];
+        let offset = reader.stream_position()?;
+        // number of frames in laced Block (lace header is left in Block data)
+        let lace_count = if flags & BLOCK_FLAG_LACING != 0 {
+            let mut num_frames = [0; 1];
+            reader.read_exact(&mut num_frames)?;
+            reader.seek(SeekFrom::Start(offset))?;
+            num_frames[0] as u32 + 1
+        } else {
+            1
+        };
+        Ok(Block {
+            track_num: track_num as u64,
+            timecode: cluster_timecode + (tc_offset as i64),
+            timestamp_ns: 0,
+            flags,
+            keyframe: flags & SIMPLEBLOCK_FLAG_KEYFRAME != 0,
+            offset,
+            size: (node_size - (len as i64) - 3) as u64,
+            lace_count,
+            duration: None,
+            discard_padding: None,
+            references: Vec::new(),
+            additions: Vec::new(),
+        })
+    }
+
+    // BlockGroup element
+    fn read_blockgroup<R: io::Read + io::Seek>(
+        mut reader: R,
+        node_size: i64,
+        cluster_timecode: i64,
+    ) -> io::Result<Option<Block>> {
+        let limit_pos = reader.stream_position()? + node_size as u64;
+        let mut block = None;
+        let mut duration = None;
+        let mut discard_padding = None;
+        let mut references = Vec::new();
+        let mut additions = Vec::new();
+        while reader.stream_position()? < limit_pos {
+            let node = read_elementid(&mut reader)?;
+            let node_size = read_datasize(&mut reader)?;
+            let node_end = reader.stream_position()? + node_size as u64;
+            match node {
+                ELEMENT_BLOCK => {
+                    block = Some(Self::read_block(&mut reader, node_size, cluster_timecode)?)
+                }
+                ELEMENT_BLOCKDURATION => duration = Some(read_uint(&mut reader, node_size)?),
+                ELEMENT_REFERENCEBLOCK => references.push(read_int(&mut reader, node_size)?),
+                ELEMENT_DISCARDPADDING => discard_padding = Some(read_int(&mut reader, node_size)?),
+                ELEMENT_BLOCKADDITIONS => {
+                    additions = Self::read_blockadditions(&mut reader, node_size)?
+                }
+                _ => {}
+            }
+            reader.seek(SeekFrom::Start(node_end))?;
+        }
+        Ok(block.map(|block| Block {
+            keyframe: references.is_empty(),
+            duration,
+            discard_padding,
+            references,
+            additions,
+            ..block
+        }))
+    }
+
+    // BlockAdditions element
+    fn read_blockadditions<R: io::Read + io::Seek>(
+        mut reader: R,
+        node_size: i64,
+    ) -> io::Result<Vec<BlockAddition>> {
+        let limit_pos = reader.stream_position()? + node_size as u64;
+        let mut additions = Vec::new();
+        while reader.stream_position()? < limit_pos {
+            let node = read_elementid(&mut reader)?;
+            let node_size = read_datasize(&mut reader)?;
+            let node_end = reader.stream_position()? + node_size as u64;
+            if node == ELEMENT_BLOCKMORE {
+                let mut addition = BlockAddition {
+                    add_id: 1, // default value
+                    data: Vec::new(),
+                };
+                while reader.stream_position()? < node_end {
+                    let child = read_elementid(&mut reader)?;
+                    let child_size = read_datasize(&mut reader)?;
+                    match child {
+                        ELEMENT_BLOCKADDID => addition.add_id = read_uint(&mut reader, child_size)?,
+                        ELEMENT_BLOCKADDITIONAL => {
+                            addition.data = vec![0; child_size as usize];
+                            reader.read_exact(&mut addition.data)?;
+                        }
+                        _ => {
+                            reader.seek(SeekFrom::Current(child_size))?;
+                        }
+                    }
+                }
+                additions.push(addition);
+            }
+            reader.seek(SeekFrom::Start(node_end))?;
+        }
+        Ok(additions)
+    }
+
+    // Info element
+    fn read_info<R: io::Read + io::Seek>(
+        &mut self,
+        mut reader: R,
+        node_size: i64,
+    ) -> io::Result<()> {
+        let limit_pos = reader.stream_position()? + node_size as u64;
+        while reader.stream_position()? < limit_pos {
             let node = read_elementid(&mut reader)?;
             let node_size = read_datasize(&mut reader)?;
-            if node != ELEMENT_SIMPLEBLOCK {
+            match node {
+                ELEMENT_TIMECODESCALE => {
+                    self.info.timecode_scale = read_uint(&mut reader, node_size)?
+                }
+                ELEMENT_DURATION => self.info.duration = Some(read_float(&mut reader, node_size)?),
+                ELEMENT_TITLE if 0 < node_size => {
+                    self.info.title = Some(read_string(&mut reader, node_size)?)
+                }
+                ELEMENT_MUXINGAPP if 0 < node_size => {
+                    self.info.muxing_app = Some(read_string(&mut reader, node_size)?)
+                }
+                ELEMENT_WRITINGAPP if 0 < node_size => {
+                    self.info.writing_app = Some(read_string(&mut reader, node_size)?)
+                }
+                _ => {
+                    reader.seek(SeekFrom::Current(node_size))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // SeekHead element, return (ElementID, SeekPosition)s
+    fn read_seekhead<R: io::Read + io::Seek>(
+        mut reader: R,
+        node_size: i64,
+    ) -> io::Result<Vec<(u32, u64)>> {
+        let limit_pos = reader.stream_position()? + node_size as u64;
+        let mut entries = Vec::new();
+        while reader.stream_position()? < limit_pos {
+            let node = read_elementid(&mut reader)?;
+            let node_size = read_datasize(&mut reader)?;
+            let node_end = reader.stream_position()? + node_size as u64;
+            if limit_pos < node_end {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Seek element exceeds SeekHead",
+                ));
+            }
+            if node == ELEMENT_SEEK {
+                let (mut seek_id, mut seek_pos) = (0, 0);
+                while reader.stream_position()? < node_end {
+                    let node = read_elementid(&mut reader)?;
+                    let node_size = read_datasize(&mut reader)?;
+                    if node_end < reader.stream_position()? + node_size as u64 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "SeekID/SeekPosition element exceeds Seek",
+                        ));
+                    }
+                    match node {
+                        ELEMENT_SEEKID => seek_id = read_uint(&mut reader, node_size)? as u32,
+                        ELEMENT_SEEKPOSITION => seek_pos = read_uint(&mut reader, node_size)?,
+                        _ => {
+                            reader.seek(SeekFrom::Current(node_size))?;
+                        }
+                    }
+                }
+                entries.push((seek_id, seek_pos));
+            }
+            reader.seek(SeekFrom::Start(node_end))?;
+        }
+        Ok(entries)
+    }
+
+    // Level1 element (Info, Tracks, Cues)
+    fn read_level1<R: io::Read + io::Seek>(
+        &mut self,
+        mut reader: R,
+        node_pos: u64,
+        node: u32,
+        node_size: i64,
+    ) -> io::Result<()> {
+        self.parsed.push(node_pos);
+        let data_pos = reader.stream_position()?;
+        if !verify_crc32(&mut reader, data_pos, data_pos + node_size as u64)? {
+            self.crc_errors.push((node, node_pos));
+        }
+        match node {
+            ELEMENT_INFO => self.read_info(&mut reader, node_size),
+            ELEMENT_TRACKS => self.read_track(&mut reader, node_size),
+            ELEMENT_CUES => self.read_cues(&mut reader, node_size),
+            ELEMENT_TAGS => self.read_tags(&mut reader, node_size),
+            ELEMENT_CHAPTERS => self.read_chapters(&mut reader, node_size),
+            _ => {
                 reader.seek(SeekFrom::Current(node_size))?;
-                continue;
+                Ok(())
             }
+        }
+    }
 
-            // read SimpleBlock header (4- bytes)
-            let (track_num, len) = read_varint(&mut reader)?;
-            let mut buf = [0; 3];
-            reader.read_exact(&mut buf)?;
-            let tc_offset = BigEndian::read_i16(&buf);
-            let node_size = (node_size - (len as i64) - 3) as u64;
-            let flags = buf[2];
+    // Chapters element
+    fn read_chapters<R: io::Read + io::Seek>(
+        &mut self,
+        mut reader: R,
+        node_size: i64,
+    ) -> io::Result<()> {
+        let limit_pos = reader.stream_position()? + node_size as u64;
+        while reader.stream_position()? < limit_pos {
+            let node = read_elementid(&mut reader)?;
+            let node_size = read_datasize(&mut reader)?;
+            let node_end = reader.stream_position()? + node_size as u64;
+            if node == ELEMENT_EDITIONENTRY {
+                while reader.stream_position()? < node_end {
+                    let node = read_elementid(&mut reader)?;
+                    let node_size = read_datasize(&mut reader)?;
+                    if node == ELEMENT_CHAPTERATOM {
+                        Self::read_chapteratom(&mut reader, node_size, &mut self.chapters)?;
+                    } else {
+                        reader.seek(SeekFrom::Current(node_size))?;
+                    }
+                }
+            }
+            reader.seek(SeekFrom::Start(node_end))?;
+        }
+        Ok(())
+    }
+
+    // ChapterAtom element
+    fn read_chapteratom<R: io::Read + io::Seek>(
+        reader: &mut R,
+        node_size: i64,
+        chapters: &mut Vec<Chapter>,
+    ) -> io::Result<()> {
+        let limit_pos = reader.stream_position()? + node_size as u64;
+        let mut chapter = Chapter::default();
+        let mut nested = Vec::new();
+        while reader.stream_position()? < limit_pos {
+            let node = read_elementid(&mut *reader)?;
+            let node_size = read_datasize(&mut *reader)?;
+            match node {
+                ELEMENT_CHAPTERTIMESTART => {
+                    chapter.time_start = read_uint(&mut *reader, node_size)?
+                }
+                ELEMENT_CHAPTERTIMEEND => {
+                    chapter.time_end = Some(read_uint(&mut *reader, node_size)?)
+                }
+                ELEMENT_CHAPTERDISPLAY => {
+                    // parse nested elements
+                    continue;
+                }
+                ELEMENT_CHAPSTRING if 0 < node_size && chapter.name.is_empty() => {
+                    // use first ChapString
+                    chapter.name = read_string(&mut *reader, node_size)?
+                }
+                ELEMENT_CHAPTERATOM => Self::read_chapteratom(reader, node_size, &mut nested)?,
+                _ => {
+                    reader.seek(SeekFrom::Current(node_size))?;
+                }
+            }
+        }
+        chapters.push(chapter);
+        chapters.append(&mut nested);
+        Ok(())
+    }
 
-            self.curr_offset = reader.stream_position()? + node_size;
-            return Ok(Some(Block {
-                track_num: track_num as u64,
-                timecode: self.clusters[self.curr_cluster].timecode + (tc_offset as i64),
-                flags: flags,
-                offset: self.curr_offset,
-                size: node_size,
-            }));
+    // Tags element
+    fn read_tags<R: io::Read + io::Seek>(
+        &mut self,
+        mut reader: R,
+        node_size: i64,
+    ) -> io::Result<()> {
+        let limit_pos = reader.stream_position()? + node_size as u64;
+        while reader.stream_position()? < limit_pos {
+            let node = read_elementid(&mut reader)?;
+            let node_size = read_datasize(&mut reader)?;
+            let node_end = reader.stream_position()? + node_size as u64;
+            if node == ELEMENT_TAG {
+                let mut track_uid = 0;
+                let mut tags = Vec::new();
+                while reader.stream_position()? < node_end {
+                    let node = read_elementid(&mut reader)?;
+                    let node_size = read_datasize(&mut reader)?;
+                    match node {
+                        ELEMENT_TARGETS => {
+                            // parse nested elements
+                            continue;
+                        }
+                        ELEMENT_TAGTRACKUID => track_uid = read_uint(&mut reader, node_size)?,
+                        ELEMENT_SIMPLETAG => {
+                            Self::read_simpletag(&mut reader, node_size, "", &mut tags)?
+                        }
+                        _ => {
+                            reader.seek(SeekFrom::Current(node_size))?;
+                        }
+                    }
+                }
+                for (name, value) in tags {
+                    self.tags.push(SimpleTag {
+                        track_uid,
+                        name,
+                        value,
+                    });
+                }
+            }
+            reader.seek(SeekFrom::Start(node_end))?;
         }
+        Ok(())
+    }
+
+    // SimpleTag element, nested TagName is joined with '/'
+    fn read_simpletag<R: io::Read + io::Seek>(
+        reader: &mut R,
+        node_size: i64,
+        parent: &str,
+        tags: &mut Vec<(String, String)>,
+    ) -> io::Result<()> {
+        let limit_pos = reader.stream_position()? + node_size as u64;
+        let mut name = String::new();
+        let mut value = String::new();
+        let mut nested = Vec::new();
+        while reader.stream_position()? < limit_pos {
+            let node = read_elementid(&mut *reader)?;
+            let node_size = read_datasize(&mut *reader)?;
+            match node {
+                ELEMENT_TAGNAME if 0 < node_size => name = read_string(&mut *reader, node_size)?,
+                ELEMENT_TAGSTRING if 0 < node_size => value = read_string(&mut *reader, node_size)?,
+                ELEMENT_SIMPLETAG => {
+                    // TagName precedes nested SimpleTag
+                    let parent = format!("{}{}/", parent, name);
+                    Self::read_simpletag(reader, node_size, &parent, &mut nested)?
+                }
+                _ => {
+                    reader.seek(SeekFrom::Current(node_size))?;
+                }
+            }
+        }
+        tags.push((format!("{}{}", parent, name), value));
+        tags.append(&mut nested);
+        Ok(())
+    }
+
+    // Cues element
+    fn read_cues<R: io::Read + io::Seek>(
+        &mut self,
+        mut reader: R,
+        node_size: i64,
+    ) -> io::Result<()> {
+        let limit_pos = reader.stream_position()? + node_size as u64;
+        while reader.stream_position()? < limit_pos {
+            let node = read_elementid(&mut reader)?;
+            let node_size = read_datasize(&mut reader)?;
+            let node_end = reader.stream_position()? + node_size as u64;
+            if node == ELEMENT_CUEPOINT {
+                let mut cue = CuePoint::default();
+                while reader.stream_position()? < node_end {
+                    let node = read_elementid(&mut reader)?;
+                    let node_size = read_datasize(&mut reader)?;
+                    match node {
+                        ELEMENT_CUETIME => cue.time = read_uint(&mut reader, node_size)?,
+                        ELEMENT_CUETRACKPOSITIONS => {
+                            // parse nested elements
+                            continue;
+                        }
+                        ELEMENT_CUETRACK => cue.track_num = read_uint(&mut reader, node_size)?,
+                        ELEMENT_CUECLUSTERPOSITION => {
+                            cue.cluster_pos = read_uint(&mut reader, node_size)?
+                        }
+                        _ => {
+                            reader.seek(SeekFrom::Current(node_size))?;
+                        }
+                    }
+                }
+                self.cues.push(cue);
+            }
+            reader.seek(SeekFrom::Start(node_end))?;
+        }
+        Ok(())
     }
 
     // TrackEntry element
-    fn read_trackentry<R: io::Read + io::Seek>(mut reader: R) -> io::Result<TrackEntey> {
-        let mut entry = TrackEntey::default();
+    fn read_trackentry<R: io::Read + io::Seek>(mut reader: R) -> io::Result<TrackEntry> {
+        let mut entry = TrackEntry::default();
         while let Ok(node) = read_elementid(&mut reader) {
             let node_size = read_datasize(&mut reader)?;
             match node {
                 ELEMENT_TRACKNUMBER => entry.track_num = read_uint(&mut reader, node_size)?,
                 ELEMENT_TRACKTYPE => entry.track_type = read_uint(&mut reader, node_size)?,
                 ELEMENT_CODECID => entry.codec_id = read_string(&mut reader, node_size)?,
+                ELEMENT_NAME if 0 < node_size => {
+                    entry.name = Some(read_string(&mut reader, node_size)?)
+                }
+                ELEMENT_FLAGENABLED => entry.flag_enabled = read_uint(&mut reader, node_size)? != 0,
+                ELEMENT_FLAGDEFAULT => entry.flag_default = read_uint(&mut reader, node_size)? != 0,
+                ELEMENT_LANGUAGE if 0 < node_size => {
+                    entry.language = read_string(&mut reader, node_size)?
+                }
+                ELEMENT_CODECPRIVATE => {
+                    entry.codec_private = vec![0; node_size as usize];
+                    reader.read_exact(&mut entry.codec_private)?;
+                }
+                ELEMENT_DEFAULTDURATION => {
+                    entry.default_duration = Some(read_uint(&mut reader, node_size)?)
+                }
+                ELEMENT_CODECDELAY => entry.codec_delay = read_uint(&mut reader, node_size)?,
                 ELEMENT_VIDEO => {
-                    let mut node_body = Vec::with_capacity(node_size as usize);
-                    node_body.resize(node_size as usize, 0);
+                    let mut node_body = vec![0; node_size as usize];
                     reader.read_exact(&mut node_body)?;
                     let node_body = io::Cursor::new(node_body);
                     let video = Self::read_videoentry(node_body)?;
@@ -228,6 +1123,12 @@ impl Matroska {
             match node {
                 ELEMENT_PIXELWIDTH => video.pixel_width = read_uint(&mut reader, node_size)?,
                 ELEMENT_PIXELHEIGHT => video.pixel_height = read_uint(&mut reader, node_size)?,
+                ELEMENT_COLOUR => {
+                    let mut node_body = vec![0; node_size as usize];
+                    reader.read_exact(&mut node_body)?;
+                    let node_body = io::Cursor::new(node_body);
+                    video.colour = Some(Self::read_colour(node_body)?);
+                }
                 _ => {
                     reader.seek(SeekFrom::Current(node_size))?;
                 }
@@ -236,58 +1137,133 @@ impl Matroska {
         Ok(video)
     }
 
+    // Colour element
+    fn read_colour<R: io::Read + io::Seek>(mut reader: R) -> io::Result<Colour> {
+        let mut colour = Colour::default();
+        while let Ok(node) = read_elementid(&mut reader) {
+            let node_size = read_datasize(&mut reader)?;
+            match node {
+                ELEMENT_MATRIXCOEFFICIENTS => {
+                    colour.matrix_coefficients = Some(read_uint(&mut reader, node_size)?)
+                }
+                ELEMENT_RANGE => colour.range = Some(read_uint(&mut reader, node_size)?),
+                ELEMENT_TRANSFERCHARACTERISTICS => {
+                    colour.transfer_characteristics = Some(read_uint(&mut reader, node_size)?)
+                }
+                ELEMENT_PRIMARIES => colour.primaries = Some(read_uint(&mut reader, node_size)?),
+                ELEMENT_MAXCLL => colour.max_cll = Some(read_uint(&mut reader, node_size)?),
+                ELEMENT_MAXFALL => colour.max_fall = Some(read_uint(&mut reader, node_size)?),
+                ELEMENT_MASTERINGMETADATA => {
+                    let limit_pos = reader.stream_position()? + node_size as u64;
+                    let mut mastering = MasteringMetadata::default();
+                    while reader.stream_position()? < limit_pos {
+                        let node = read_elementid(&mut reader)?;
+                        let node_size = read_datasize(&mut reader)?;
+                        let value = match node {
+                            ELEMENT_PRIMARYRCHROMATICITYX => {
+                                &mut mastering.primary_r_chromaticity.0
+                            }
+                            ELEMENT_PRIMARYRCHROMATICITYY => {
+                                &mut mastering.primary_r_chromaticity.1
+                            }
+                            ELEMENT_PRIMARYGCHROMATICITYX => {
+                                &mut mastering.primary_g_chromaticity.0
+                            }
+                            ELEMENT_PRIMARYGCHROMATICITYY => {
+                                &mut mastering.primary_g_chromaticity.1
+                            }
+                            ELEMENT_PRIMARYBCHROMATICITYX => {
+                                &mut mastering.primary_b_chromaticity.0
+                            }
+                            ELEMENT_PRIMARYBCHROMATICITYY => {
+                                &mut mastering.primary_b_chromaticity.1
+                            }
+                            ELEMENT_WHITEPOINTCHROMATICITYX => {
+                                &mut mastering.white_point_chromaticity.0
+                            }
+                            ELEMENT_WHITEPOINTCHROMATICITYY => {
+                                &mut mastering.white_point_chromaticity.1
+                            }
+                            ELEMENT_LUMINANCEMAX => &mut mastering.luminance_max,
+                            ELEMENT_LUMINANCEMIN => &mut mastering.luminance_min,
+                            _ => {
+                                reader.seek(SeekFrom::Current(node_size))?;
+                                continue;
+                            }
+                        };
+                        *value = read_float(&mut reader, node_size)?;
+                    }
+                    colour.mastering_metadata = Some(mastering);
+                }
+                _ => {
+                    reader.seek(SeekFrom::Current(node_size))?;
+                }
+            }
+        }
+        Ok(colour)
+    }
+
     // Track element
-    fn read_track<R: io::Read + io::Seek>(&mut self, mut reader: R) -> io::Result<()> {
-        let mut pos = reader.stream_position()?;
+    fn read_track<R: io::Read + io::Seek>(
+        &mut self,
+        mut reader: R,
+        node_size: i64,
+    ) -> io::Result<()> {
+        let limit_pos = reader.stream_position()? + node_size as u64;
         // TrackEntry nodes
-        while let Ok(entry) = read_elementid(&mut reader) {
+        while reader.stream_position()? < limit_pos {
+            let entry = read_elementid(&mut reader)?;
+            let entry_size = read_datasize(&mut reader)?;
             if entry != ELEMENT_TRACKENTRY {
-                reader.seek(SeekFrom::Start(pos))?;
-                break;
+                // skip Void, CRC-32, etc.
+                reader.seek(SeekFrom::Current(entry_size))?;
+                continue;
             }
-            let entry_size = read_datasize(&mut reader)? as usize;
 
             // add new track
-            let mut entry_body = Vec::with_capacity(entry_size);
-            entry_body.resize(entry_size, 0);
+            let mut entry_body = vec![0; entry_size as usize];
             reader.read_exact(&mut entry_body)?;
             let entry_body = io::Cursor::new(entry_body);
             self.tracks.push(Self::read_trackentry(entry_body)?);
-
-            pos = reader.stream_position()?;
         }
         Ok(())
     }
 
     // Cluster element
     fn read_cluster<R: io::Read + io::Seek>(
-        &mut self,
         mut reader: R,
-        node_size: i64,
-    ) -> io::Result<()> {
+        node_pos: u64,
+        node_size: Option<i64>,
+    ) -> io::Result<Cluster> {
         let mut pos = reader.stream_position()?;
-        let limit_pos = pos + node_size as u64;
+        let limit_pos = match node_size {
+            Some(node_size) => pos + node_size as u64,
+            None => u64::MAX, // bounded by next Level1 element
+        };
 
         let mut cluster = Cluster {
+            pos: node_pos,
             pos_end: limit_pos,
             ..Default::default()
         };
-        let mut first_block = true;
-
         // Level2 elements
-        while let Ok(node) = read_elementid(&mut reader) {
+        loop {
+            let node = match read_elementid(&mut reader) {
+                Ok(node) => node,
+                Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            };
+            if is_level1_element(node) {
+                break;
+            }
             let node_size = read_datasize(&mut reader)?;
             match node {
                 ELEMENT_TIMECODE => cluster.timecode = read_uint(&mut reader, node_size)? as i64,
-                ELEMENT_SIMPLEBLOCK => {
-                    if first_block {
-                        // store offset of first Block
-                        cluster.pos_begin = pos;
-                        first_block = false;
-                    }
-                    reader.seek(SeekFrom::Current(node_size))?;
+                ELEMENT_SIMPLEBLOCK | ELEMENT_BLOCKGROUP => {
+                    // store offset of first Block, Timecode precedes Blocks
+                    cluster.pos_begin = pos;
+                    break;
                 }
-                ELEMENT_BLOCKGROUP => unimplemented!("BlockGroup"),
                 _ => {
                     reader.seek(SeekFrom::Current(node_size))?;
                 }
@@ -298,20 +1274,64 @@ impl Matroska {
                 break;
             }
         }
-        self.clusters.push(cluster);
-        Ok(())
+        Ok(cluster)
     }
 }
 
 ///
 /// Matroska/TrackEntry
 ///
+#[derive(Debug)]
+pub struct TrackEntry {
+    pub track_num: u64,                // TrackNumber
+    pub track_type: u64,               // TrackType
+    pub codec_id: String,              // CodecID
+    pub codec_private: Vec<u8>,        // CodecPrivate
+    pub name: Option<String>,          // Name
+    pub language: String,              // Language
+    pub flag_enabled: bool,            // FlagEnabled
+    pub flag_default: bool,            // FlagDefault
+    pub default_duration: Option<u64>, // DefaultDuration [nsec]
+    pub codec_delay: u64,              // CodecDelay [nsec]
+    pub setting: Option<VideoTrack>,
+}
+
+impl Default for TrackEntry {
+    fn default() -> Self {
+        TrackEntry {
+            track_num: 0,
+            track_type: 0,
+            codec_id: String::new(),
+            codec_private: Vec::new(),
+            name: None,
+            language: "eng".to_string(), // default Language
+            flag_enabled: true,
+            flag_default: true,
+            default_duration: None,
+            codec_delay: 0,
+            setting: None,
+        }
+    }
+}
+
+///
+/// Matroska/Info
+///
 #[derive(Debug, Default)]
-struct TrackEntey {
-    track_num: u64,
-    track_type: u64,
-    codec_id: String,
-    setting: Option<VideoTrack>,
+pub struct SegmentInfo {
+    pub timecode_scale: u64,         // TimecodeScale [nsec]
+    pub duration: Option<f64>,       // Duration [timecode_scale]
+    pub title: Option<String>,       // Title
+    pub muxing_app: Option<String>,  // MuxingApp
+    pub writing_app: Option<String>, // WritingApp
+}
+
+impl SegmentInfo {
+    /// Segment duration in nanoseconds
+    pub fn duration_ns(&self) -> Option<f64> {
+        self.duration
+            .map(|duration| duration * self.timecode_scale as f64)
+    }
 }
 
 ///
@@ -321,6 +1341,34 @@ struct TrackEntey {
 pub struct VideoTrack {
     pub pixel_width: u64,  // PixelWidth
     pub pixel_height: u64, // PixelHeight
+    pub colour: Option<Colour>,
+}
+
+///
+/// Matroska/TrackEntry/Video/Colour
+///
+#[derive(Debug, Default)]
+pub struct Colour {
+    pub matrix_coefficients: Option<u64>,      // MatrixCoefficients
+    pub range: Option<u64>,                    // Range
+    pub transfer_characteristics: Option<u64>, // TransferCharacteristics
+    pub primaries: Option<u64>,                // Primaries
+    pub max_cll: Option<u64>,                  // MaxCLL [cd/m^2]
+    pub max_fall: Option<u64>,                 // MaxFALL [cd/m^2]
+    pub mastering_metadata: Option<MasteringMetadata>,
+}
+
+///
+/// Matroska/TrackEntry/Video/Colour/MasteringMetadata
+///
+#[derive(Debug, Default)]
+pub struct MasteringMetadata {
+    pub primary_r_chromaticity: (f64, f64), // PrimaryRChromaticityX/Y
+    pub primary_g_chromaticity: (f64, f64), // PrimaryGChromaticityX/Y
+    pub primary_b_chromaticity: (f64, f64), // PrimaryBChromaticityX/Y
+    pub white_point_chromaticity: (f64, f64), // WhitePointChromaticityX/Y
+    pub luminance_max: f64,                 // LuminanceMax [cd/m^2]
+    pub luminance_min: f64,                 // LuminanceMin [cd/m^2]
 }
 
 ///
@@ -329,20 +1377,296 @@ pub struct VideoTrack {
 #[derive(Debug, Default)]
 struct Cluster {
     timecode: i64,
-    pos_begin: u64,
+    pos: u64,       // position of Cluster element
+    pos_begin: u64, // position of first Block
     pos_end: u64,
 }
 
+///
+/// Matroska/Chapters/EditionEntry/ChapterAtom
+///
+#[derive(Debug, Default)]
+pub struct Chapter {
+    pub time_start: u64,       // ChapterTimeStart [nsec]
+    pub time_end: Option<u64>, // ChapterTimeEnd [nsec]
+    pub name: String,          // first ChapString
+}
+
+///
+/// Matroska/Tags/Tag/SimpleTag
+///
+#[derive(Debug, Default)]
+pub struct SimpleTag {
+    pub track_uid: u64, // TagTrackUID (0=all tracks)
+    pub name: String,   // TagName
+    pub value: String,  // TagString
+}
+
+///
+/// Matroska/Cues/CuePoint
+///
+#[derive(Debug, Default)]
+pub struct CuePoint {
+    pub time: u64,        // CueTime [timecode_scale]
+    pub track_num: u64,   // CueTrack
+    pub cluster_pos: u64, // CueClusterPosition (relative to Segment data)
+}
+
 ///
 /// Matroska/(Simple)Block
 ///
 #[derive(Debug)]
 pub struct Block {
     pub track_num: u64,
-    pub timecode: i64,
+    pub timecode: i64,     // [timecode_scale]
+    pub timestamp_ns: i64, // [nsec]
     pub flags: u8,
-    pub offset: u64,
+    pub keyframe: bool, // SimpleBlock keyframe flag, or BlockGroup without ReferenceBlock
+    pub offset: u64,    // offset of Block data
     pub size: u64,
+    pub lace_count: u32,       // number of frames (Xiph/EBML/fixed-size lacing)
+    pub duration: Option<u64>, // BlockGroup/BlockDuration
+    pub discard_padding: Option<i64>, // BlockGroup/DiscardPadding [nsec]
+    pub references: Vec<i64>,  // BlockGroup/ReferenceBlock
+    pub additions: Vec<BlockAddition>, // BlockGroup/BlockAdditions
+}
+
+///
+/// Matroska/BlockGroup/BlockAdditions/BlockMore
+///
+#[derive(Debug)]
+pub struct BlockAddition {
+    pub add_id: u64,   // BlockAddID
+    pub data: Vec<u8>, // BlockAdditional
+}
+
+impl BlockAddition {
+    /// interpret as SMPTE ST 2094-40 dynamic metadata carried in ITU-T T.35 message
+    pub fn parse_st2094_40(&self) -> Option<obu::St2094_40Metadata> {
+        if self.add_id != BLOCKADDID_ITUT_T35 {
+            return None;
+        }
+        obu::parse_itut_t35(&self.data)?.parse_st2094_40()
+    }
+}
+
+impl Block {
+    /// get timestamp in milliseconds
+    pub fn timestamp_ms(&self) -> f64 {
+        self.timestamp_ns as f64 / 1_000_000.0
+    }
+}
+
+///
+/// EBML element in element tree
+///
+#[derive(Debug)]
+pub struct Element {
+    pub id: u32,
+    pub level: usize,      // nesting level (0 = EBML header, Segment)
+    pub offset: u64,       // offset of Element ID
+    pub data_offset: u64,  // offset of Element data
+    pub size: Option<u64>, // data size (None = unknown size)
+}
+
+impl Element {
+    /// get element name
+    pub fn name(&self) -> &'static str {
+        element_name(self.id)
+    }
+}
+
+/// element name of known Element ID
+fn element_name(id: u32) -> &'static str {
+    match id {
+        ELEMENT_EBML => "EBML",
+        ELEMENT_EBMLVERSION => "EBMLVersion",
+        ELEMENT_EBMLREADVERSION => "EBMLReadVersion",
+        ELEMENT_EBMLMAXIDLENGTH => "EBMLMaxIDLength",
+        ELEMENT_EBMLMAXSIZELENGTH => "EBMLMaxSizeLength",
+        ELEMENT_DOCTYPE => "DocType",
+        ELEMENT_DOCTYPEVERSION => "DocTypeVersion",
+        ELEMENT_DOCTYPEREADVERSION => "DocTypeReadVersion",
+        ELEMENT_VOID => "Void",
+        ELEMENT_CRC32 => "CRC-32",
+        ELEMENT_SEGMENT => "Segment",
+        ELEMENT_SEEKHEAD => "SeekHead",
+        ELEMENT_SEEK => "Seek",
+        ELEMENT_SEEKID => "SeekID",
+        ELEMENT_SEEKPOSITION => "SeekPosition",
+        ELEMENT_INFO => "Info",
+        ELEMENT_TIMECODESCALE => "TimecodeScale",
+        ELEMENT_DURATION => "Duration",
+        ELEMENT_TITLE => "Title",
+        ELEMENT_MUXINGAPP => "MuxingApp",
+        ELEMENT_WRITINGAPP => "WritingApp",
+        ELEMENT_CLUSTER => "Cluster",
+        ELEMENT_TIMECODE => "Timecode",
+        ELEMENT_SIMPLEBLOCK => "SimpleBlock",
+        ELEMENT_BLOCKGROUP => "BlockGroup",
+        ELEMENT_BLOCK => "Block",
+        ELEMENT_BLOCKDURATION => "BlockDuration",
+        ELEMENT_REFERENCEBLOCK => "ReferenceBlock",
+        ELEMENT_DISCARDPADDING => "DiscardPadding",
+        ELEMENT_BLOCKADDITIONS => "BlockAdditions",
+        ELEMENT_BLOCKMORE => "BlockMore",
+        ELEMENT_BLOCKADDID => "BlockAddID",
+        ELEMENT_BLOCKADDITIONAL => "BlockAdditional",
+        ELEMENT_TRACKS => "Tracks",
+        ELEMENT_TRACKENTRY => "TrackEntry",
+        ELEMENT_TRACKNUMBER => "TrackNumber",
+        ELEMENT_TRACKTYPE => "TrackType",
+        ELEMENT_TRACKUID => "TrackUID",
+        ELEMENT_FLAGENABLED => "FlagEnabled",
+        ELEMENT_FLAGDEFAULT => "FlagDefault",
+        ELEMENT_FLAGLACING => "FlagLacing",
+        ELEMENT_CODECID => "CodecID",
+        ELEMENT_CODECPRIVATE => "CodecPrivate",
+        ELEMENT_NAME => "Name",
+        ELEMENT_LANGUAGE => "Language",
+        ELEMENT_DEFAULTDURATION => "DefaultDuration",
+        ELEMENT_CODECDELAY => "CodecDelay",
+        ELEMENT_VIDEO => "Video",
+        ELEMENT_PIXELWIDTH => "PixelWidth",
+        ELEMENT_PIXELHEIGHT => "PixelHeight",
+        ELEMENT_COLOUR => "Colour",
+        ELEMENT_MATRIXCOEFFICIENTS => "MatrixCoefficients",
+        ELEMENT_RANGE => "Range",
+        ELEMENT_TRANSFERCHARACTERISTICS => "TransferCharacteristics",
+        ELEMENT_PRIMARIES => "Primaries",
+        ELEMENT_MAXCLL => "MaxCLL",
+        ELEMENT_MAXFALL => "MaxFALL",
+        ELEMENT_MASTERINGMETADATA => "MasteringMetadata",
+        ELEMENT_PRIMARYRCHROMATICITYX => "PrimaryRChromaticityX",
+        ELEMENT_PRIMARYRCHROMATICITYY => "PrimaryRChromaticityY",
+        ELEMENT_PRIMARYGCHROMATICITYX => "PrimaryGChromaticityX",
+        ELEMENT_PRIMARYGCHROMATICITYY => "PrimaryGChromaticityY",
+        ELEMENT_PRIMARYBCHROMATICITYX => "PrimaryBChromaticityX",
+        ELEMENT_PRIMARYBCHROMATICITYY => "PrimaryBChromaticityY",
+        ELEMENT_WHITEPOINTCHROMATICITYX => "WhitePointChromaticityX",
+        ELEMENT_WHITEPOINTCHROMATICITYY => "WhitePointChromaticityY",
+        ELEMENT_LUMINANCEMAX => "LuminanceMax",
+        ELEMENT_LUMINANCEMIN => "LuminanceMin",
+        ELEMENT_CUES => "Cues",
+        ELEMENT_ATTACHMENTS => "Attachments",
+        ELEMENT_CHAPTERS => "Chapters",
+        ELEMENT_EDITIONENTRY => "EditionEntry",
+        ELEMENT_CHAPTERATOM => "ChapterAtom",
+        ELEMENT_CHAPTERTIMESTART => "ChapterTimeStart",
+        ELEMENT_CHAPTERTIMEEND => "ChapterTimeEnd",
+        ELEMENT_CHAPTERDISPLAY => "ChapterDisplay",
+        ELEMENT_CHAPSTRING => "ChapString",
+        ELEMENT_TAGS => "Tags",
+        ELEMENT_TAG => "Tag",
+        ELEMENT_TARGETS => "Targets",
+        ELEMENT_TAGTRACKUID => "TagTrackUID",
+        ELEMENT_SIMPLETAG => "SimpleTag",
+        ELEMENT_TAGNAME => "TagName",
+        ELEMENT_TAGSTRING => "TagString",
+        ELEMENT_CUEPOINT => "CuePoint",
+        ELEMENT_CUETIME => "CueTime",
+        ELEMENT_CUETRACKPOSITIONS => "CueTrackPositions",
+        ELEMENT_CUETRACK => "CueTrack",
+        ELEMENT_CUECLUSTERPOSITION => "CueClusterPosition",
+        _ => "(unknown)",
+    }
+}
+
+/// Master element which contains child elements
+fn is_master_element(id: u32) -> bool {
+    matches!(
+        id,
+        ELEMENT_EBML
+            | ELEMENT_SEGMENT
+            | ELEMENT_SEEKHEAD
+            | ELEMENT_SEEK
+            | ELEMENT_INFO
+            | ELEMENT_CLUSTER
+            | ELEMENT_BLOCKGROUP
+            | ELEMENT_BLOCKADDITIONS
+            | ELEMENT_BLOCKMORE
+            | ELEMENT_TRACKS
+            | ELEMENT_TRACKENTRY
+            | ELEMENT_VIDEO
+            | ELEMENT_COLOUR
+            | ELEMENT_MASTERINGMETADATA
+            | ELEMENT_CUES
+            | ELEMENT_CUEPOINT
+            | ELEMENT_CUETRACKPOSITIONS
+            | ELEMENT_CHAPTERS
+            | ELEMENT_EDITIONENTRY
+            | ELEMENT_CHAPTERATOM
+            | ELEMENT_CHAPTERDISPLAY
+            | ELEMENT_TAGS
+            | ELEMENT_TAG
+            | ELEMENT_TARGETS
+            | ELEMENT_SIMPLETAG
+    )
+}
+
+///
+/// walk EBML element tree in Matroska/WebM file, Block payloads are not interpreted
+///
+pub fn walk_elements<R: io::Read + io::Seek, F: FnMut(&Element)>(
+    reader: &mut R,
+    visit: &mut F,
+) -> io::Result<()> {
+    let len = stream_len(&mut *reader)?;
+    reader.seek(SeekFrom::Start(0))?;
+    walk_children(reader, len, 0, false, visit)
+}
+
+fn walk_children<R: io::Read + io::Seek, F: FnMut(&Element)>(
+    reader: &mut R,
+    limit_pos: u64,
+    level: usize,
+    unknown_size: bool,
+    visit: &mut F,
+) -> io::Result<()> {
+    loop {
+        let offset = reader.stream_position()?;
+        if limit_pos <= offset {
+            break;
+        }
+        let id = match read_elementid(&mut *reader) {
+            Ok(id) => id,
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+        if unknown_size && 1 < level && is_level1_element(id) {
+            // end of unknown-size Cluster
+            reader.seek(SeekFrom::Start(offset))?;
+            break;
+        }
+        let size = read_elementsize(&mut *reader)?.map(|size| size as u64);
+        let data_offset = reader.stream_position()?;
+        visit(&Element {
+            id,
+            level,
+            offset,
+            data_offset,
+            size,
+        });
+        match size {
+            Some(size) if is_master_element(id) => {
+                walk_children(reader, data_offset + size, level + 1, false, visit)?;
+                reader.seek(SeekFrom::Start(data_offset + size))?;
+            }
+            Some(size) => {
+                reader.seek(SeekFrom::Start(data_offset + size))?;
+            }
+            None if is_master_element(id) => {
+                walk_children(reader, limit_pos, level + 1, true, visit)?;
+            }
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Unknown data size is not allowed",
+                ))
+            }
+        }
+    }
+    Ok(())
 }
 
 ///
@@ -368,20 +1692,440 @@ pub fn open_mkvfile<R: io::Read + io::Seek>(mut reader: R) -> io::Result<Matrosk
             "Invalid Segment element",
         ));
     }
-    let _segment_size = read_datasize(&mut reader)?;
+    let _segment_size = read_elementsize(&mut reader)?;
 
-    // Level1 elements
+    // Level1 elements until first Cluster
     let mut mkv = Matroska::new();
-    while let Ok(node) = read_elementid(&mut reader) {
-        let node_size = read_datasize(&mut reader)?;
+    mkv.segment_pos = reader.stream_position()?;
+    let mut seek_entries = Vec::new();
+    loop {
+        let node_pos = reader.stream_position()?;
+        let node = match read_elementid(&mut reader) {
+            Ok(node) => node,
+            Err(_) => break,
+        };
+        if node == ELEMENT_CLUSTER {
+            // Clusters are iterated on demand
+            mkv.first_cluster_pos = node_pos;
+            mkv.next_cluster_pos = node_pos;
+            break;
+        }
+        let node_size = match read_elementsize(&mut reader)? {
+            Some(node_size) => node_size,
+            None => continue, // walk into children until next Level1 element
+        };
         match node {
-            ELEMENT_TRACKS => mkv.read_track(&mut reader)?,
-            ELEMENT_CLUSTER => mkv.read_cluster(&mut reader, node_size)?,
+            ELEMENT_SEEKHEAD => {
+                let data_pos = reader.stream_position()?;
+                if !verify_crc32(&mut reader, data_pos, data_pos + node_size as u64)? {
+                    mkv.crc_errors.push((node, node_pos));
+                }
+                seek_entries.extend(Matroska::read_seekhead(&mut reader, node_size)?);
+            }
             _ => {
-                reader.seek(SeekFrom::Current(node_size))?;
+                let node_end = reader.stream_position()? + node_size as u64;
+                mkv.read_level1(&mut reader, node_pos, node, node_size)?;
+                reader.seek(SeekFrom::Start(node_end))?;
             }
         };
     }
 
+    // jump to Info/Tracks/Cues after Clusters via SeekHead
+    for (seek_id, seek_pos) in seek_entries {
+        let node_pos = mkv.segment_pos + seek_pos;
+        if !(seek_id == ELEMENT_INFO
+            || seek_id == ELEMENT_TRACKS
+            || seek_id == ELEMENT_CUES
+            || seek_id == ELEMENT_TAGS
+            || seek_id == ELEMENT_CHAPTERS)
+            || mkv.parsed.contains(&node_pos)
+        {
+            continue;
+        }
+        reader.seek(SeekFrom::Start(node_pos))?;
+        let node = read_elementid(&mut reader)?;
+        let node_size = read_datasize(&mut reader)?;
+        if node != seek_id {
+            continue; // broken SeekHead
+        }
+        mkv.read_level1(&mut reader, node_pos, node, node_size)?;
+    }
+
     Ok(mkv)
 }
+
+/// append Element ID into buffer
+fn write_elementid(buf: &mut Vec<u8>, id: u32) {
+    let skip = (id.leading_zeros() / 8) as usize;
+    buf.extend_from_slice(&id.to_be_bytes()[skip..]);
+}
+
+/// append data size (shortest length) into buffer
+fn write_datasize(buf: &mut Vec<u8>, size: u64) {
+    let mut len = 1;
+    while len < 8 && (1 << (7 * len)) - 1 <= size {
+        len += 1;
+    }
+    let value = size | 1 << (7 * len);
+    buf.extend_from_slice(&value.to_be_bytes()[8 - len..]);
+}
+
+/// append element into buffer
+fn write_element(buf: &mut Vec<u8>, id: u32, data: &[u8]) {
+    write_elementid(buf, id);
+    write_datasize(buf, data.len() as u64);
+    buf.extend_from_slice(data);
+}
+
+/// append unsigned integer element into buffer
+fn write_uint_element(buf: &mut Vec<u8>, id: u32, value: u64) {
+    let skip = ((value.leading_zeros() / 8) as usize).min(7);
+    write_element(buf, id, &value.to_be_bytes()[skip..]);
+}
+
+/// 8-byte data size for placeholder to be patched
+fn datasize8(size: u64) -> [u8; 8] {
+    (1 << 56 | size).to_be_bytes()
+}
+
+// reserved space for Info and Tracks in front of Clusters
+const RESERVED_SIZE: u64 = 1024;
+
+///
+/// Matroska/WebM writer (single "V_AV1" track)
+///
+pub struct MkvWriter<W> {
+    writer: W,
+    segment_pos: u64,            // position of Segment data
+    reserved_pos: u64,           // position of Void reserved for Info/Tracks
+    cluster: Option<(u64, i64)>, // (position, Timecode) of current Cluster
+    cues: Vec<CuePoint>,
+    first_timecode: i64,
+    last_timecode: i64,
+    interval: i64, // last Block interval [timecode_scale]
+}
+
+impl<W: io::Write + io::Seek> MkvWriter<W> {
+    /// create WebM writer, write EBML header and Segment header
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        let mut ebml = Vec::new();
+        write_uint_element(&mut ebml, ELEMENT_EBMLVERSION, 1);
+        write_uint_element(&mut ebml, ELEMENT_EBMLREADVERSION, 1);
+        write_uint_element(&mut ebml, ELEMENT_EBMLMAXIDLENGTH, 4);
+        write_uint_element(&mut ebml, ELEMENT_EBMLMAXSIZELENGTH, 8);
+        write_element(&mut ebml, ELEMENT_DOCTYPE, b"webm");
+        write_uint_element(&mut ebml, ELEMENT_DOCTYPEVERSION, 4);
+        write_uint_element(&mut ebml, ELEMENT_DOCTYPEREADVERSION, 2);
+        let mut buf = Vec::new();
+        write_element(&mut buf, ELEMENT_EBML, &ebml);
+        // Segment size is patched at finish()
+        write_elementid(&mut buf, ELEMENT_SEGMENT);
+        buf.extend_from_slice(&datasize8(0));
+        writer.write_all(&buf)?;
+        let segment_pos = writer.stream_position()?;
+
+        // SeekHead is patched, Void is replaced with Info/Tracks at finish()
+        writer.write_all(&Self::build_seekhead(&[0, 0, 0]))?;
+        let reserved_pos = writer.stream_position()?;
+        writer.write_all(&Self::build_void(RESERVED_SIZE))?;
+        Ok(MkvWriter {
+            writer,
+            segment_pos,
+            reserved_pos,
+            cluster: None,
+            cues: Vec::new(),
+            first_timecode: 0,
+            last_timecode: 0,
+            interval: 0,
+        })
+    }
+
+    /// write SimpleBlock, start new Cluster at keyframe
+    pub fn write_block(
+        &mut self,
+        data: &[u8],
+        timestamp_ns: u64,
+        keyframe: bool,
+    ) -> io::Result<()> {
+        let timecode = (timestamp_ns / DEFAULT_TIMECODESCALE) as i64;
+        let new_cluster = match self.cluster {
+            Some((_, cluster_timecode)) => {
+                keyframe || timecode - cluster_timecode > i16::MAX as i64
+            }
+            None => {
+                self.first_timecode = timecode;
+                true
+            }
+        };
+        if new_cluster {
+            self.close_cluster()?;
+            let pos = self.writer.stream_position()?;
+            let mut buf = Vec::new();
+            write_elementid(&mut buf, ELEMENT_CLUSTER);
+            buf.extend_from_slice(&datasize8(0));
+            write_uint_element(&mut buf, ELEMENT_TIMECODE, timecode as u64);
+            self.writer.write_all(&buf)?;
+            self.cluster = Some((pos, timecode));
+            if keyframe {
+                self.cues.push(CuePoint {
+                    time: timecode as u64,
+                    track_num: 1,
+                    cluster_pos: pos - self.segment_pos,
+                });
+            }
+        }
+        let cluster_timecode = self.cluster.map_or(0, |(_, timecode)| timecode);
+        let mut buf = Vec::new();
+        write_elementid(&mut buf, ELEMENT_SIMPLEBLOCK);
+        write_datasize(&mut buf, 4 + data.len() as u64);
+        buf.push(0x81); // TrackNumber=1
+        buf.extend_from_slice(&((timecode - cluster_timecode) as i16).to_be_bytes());
+        buf.push(if keyframe {
+            SIMPLEBLOCK_FLAG_KEYFRAME
+        } else {
+            0
+        });
+        self.writer.write_all(&buf)?;
+        self.writer.write_all(data)?;
+        if self.last_timecode < timecode {
+            self.interval = timecode - self.last_timecode;
+        }
+        self.last_timecode = timecode;
+        Ok(())
+    }
+
+    /// finish Clusters, write Cues/Info/Tracks and patch SeekHead
+    pub fn finish(mut self, width: u64, height: u64, codec_private: &[u8]) -> io::Result<W> {
+        self.close_cluster()?;
+        let mut cues_pos = 0;
+        if !self.cues.is_empty() {
+            cues_pos = self.writer.stream_position()? - self.segment_pos;
+            let cues = self.build_cues();
+            self.writer.write_all(&cues)?;
+        }
+        let mut segment_end = self.writer.stream_position()?;
+
+        let info = self.build_info();
+        let tracks = Self::build_tracks(width, height, codec_private);
+        let info_pos = if (info.len() + tracks.len()) as u64 + 9 <= RESERVED_SIZE {
+            self.reserved_pos
+        } else {
+            segment_end += (info.len() + tracks.len()) as u64;
+            segment_end - (info.len() + tracks.len()) as u64
+        };
+        self.writer.seek(SeekFrom::Start(info_pos))?;
+        self.writer.write_all(&info)?;
+        self.writer.write_all(&tracks)?;
+        if info_pos == self.reserved_pos {
+            let used = (info.len() + tracks.len()) as u64;
+            self.writer
+                .write_all(&Self::build_void(RESERVED_SIZE - used))?;
+        }
+
+        let info_pos = info_pos - self.segment_pos;
+        let tracks_pos = info_pos + info.len() as u64;
+        self.writer.seek(SeekFrom::Start(self.segment_pos))?;
+        self.writer
+            .write_all(&Self::build_seekhead(&[info_pos, tracks_pos, cues_pos]))?;
+        self.writer.seek(SeekFrom::Start(self.segment_pos - 8))?;
+        self.writer
+            .write_all(&datasize8(segment_end - self.segment_pos))?;
+        self.writer.seek(SeekFrom::Start(segment_end))?;
+        Ok(self.writer)
+    }
+
+    /// patch size of current Cluster
+    fn close_cluster(&mut self) -> io::Result<()> {
+        if let Some((pos, _)) = self.cluster.take() {
+            let end = self.writer.stream_position()?;
+            // Cluster ID(4) + size(8)
+            self.writer.seek(SeekFrom::Start(pos + 4))?;
+            self.writer.write_all(&datasize8(end - pos - 12))?;
+            self.writer.seek(SeekFrom::Start(end))?;
+        }
+        Ok(())
+    }
+
+    /// SeekHead for Info/Tracks/Cues with fixed size SeekPosition
+    fn build_seekhead(positions: &[u64; 3]) -> Vec<u8> {
+        let mut seekhead = Vec::new();
+        for (&id, &pos) in [ELEMENT_INFO, ELEMENT_TRACKS, ELEMENT_CUES]
+            .iter()
+            .zip(positions.iter())
+        {
+            let mut seek = Vec::new();
+            write_element(&mut seek, ELEMENT_SEEKID, &id.to_be_bytes());
+            write_element(&mut seek, ELEMENT_SEEKPOSITION, &pos.to_be_bytes());
+            write_element(&mut seekhead, ELEMENT_SEEK, &seek);
+        }
+        let mut buf = Vec::new();
+        write_element(&mut buf, ELEMENT_SEEKHEAD, &seekhead);
+        buf
+    }
+
+    /// Void element with total length (9 bytes or more)
+    fn build_void(total: u64) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_elementid(&mut buf, ELEMENT_VOID);
+        buf.extend_from_slice(&datasize8(total - 9));
+        buf.resize(total as usize, 0);
+        buf
+    }
+
+    fn build_info(&self) -> Vec<u8> {
+        let app = format!("{}-{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        let duration = (self.last_timecode - self.first_timecode + self.interval) as f64;
+        let mut info = Vec::new();
+        write_uint_element(&mut info, ELEMENT_TIMECODESCALE, DEFAULT_TIMECODESCALE);
+        write_element(&mut info, ELEMENT_MUXINGAPP, app.as_bytes());
+        write_element(&mut info, ELEMENT_WRITINGAPP, app.as_bytes());
+        write_element(
+            &mut info,
+            ELEMENT_DURATION,
+            &duration.to_bits().to_be_bytes(),
+        );
+        let mut buf = Vec::new();
+        write_element(&mut buf, ELEMENT_INFO, &info);
+        buf
+    }
+
+    fn build_tracks(width: u64, height: u64, codec_private: &[u8]) -> Vec<u8> {
+        let mut video = Vec::new();
+        write_uint_element(&mut video, ELEMENT_PIXELWIDTH, width);
+        write_uint_element(&mut video, ELEMENT_PIXELHEIGHT, height);
+        let mut entry = Vec::new();
+        write_uint_element(&mut entry, ELEMENT_TRACKNUMBER, 1);
+        write_uint_element(&mut entry, ELEMENT_TRACKUID, 1);
+        write_uint_element(&mut entry, ELEMENT_TRACKTYPE, 1); // video
+        write_uint_element(&mut entry, ELEMENT_FLAGLACING, 0);
+        write_element(&mut entry, ELEMENT_CODECID, CODEC_V_AV1.as_bytes());
+        write_element(&mut entry, ELEMENT_CODECPRIVATE, codec_private);
+        write_element(&mut entry, ELEMENT_VIDEO, &video);
+        let mut tracks = Vec::new();
+        write_element(&mut tracks, ELEMENT_TRACKENTRY, &entry);
+        let mut buf = Vec::new();
+        write_element(&mut buf, ELEMENT_TRACKS, &tracks);
+        buf
+    }
+
+    fn build_cues(&self) -> Vec<u8> {
+        let mut cues = Vec::new();
+        for cue in &self.cues {
+            let mut positions = Vec::new();
+            write_uint_element(&mut positions, ELEMENT_CUETRACK, cue.track_num);
+            write_uint_element(&mut positions, ELEMENT_CUECLUSTERPOSITION, cue.cluster_pos);
+            let mut point = Vec::new();
+            write_uint_element(&mut point, ELEMENT_CUETIME, cue.time);
+            write_element(&mut point, ELEMENT_CUETRACKPOSITIONS, &positions);
+            write_element(&mut cues, ELEMENT_CUEPOINT, &point);
+        }
+        let mut buf = Vec::new();
+        write_element(&mut buf, ELEMENT_CUES, &cues);
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNKNOWN_SIZE: [u8; 8] = [0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+
+    /// EBML header, Segment and Tracks with "V_AV1" track #1
+    fn webm_header(segment_size: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_element(&mut buf, ELEMENT_EBML, &[]);
+        write_elementid(&mut buf, ELEMENT_SEGMENT);
+        buf.extend_from_slice(segment_size);
+        let mut entry = Vec::new();
+        write_uint_element(&mut entry, ELEMENT_TRACKNUMBER, 1);
+        write_element(&mut entry, ELEMENT_CODECID, CODEC_V_AV1.as_bytes());
+        let mut tracks = Vec::new();
+        write_element(&mut tracks, ELEMENT_TRACKENTRY, &entry);
+        write_element(&mut buf, ELEMENT_TRACKS, &tracks);
+        buf
+    }
+
+    /// Cluster Timecode and SimpleBlocks of track #1, return Cluster data
+    fn cluster_data(timecode: u64, blocks: &[&[u8]]) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_uint_element(&mut data, ELEMENT_TIMECODE, timecode);
+        for (i, payload) in blocks.iter().enumerate() {
+            let mut block = vec![0x81, 0x00, i as u8, SIMPLEBLOCK_FLAG_KEYFRAME];
+            block.extend_from_slice(payload);
+            write_element(&mut data, ELEMENT_SIMPLEBLOCK, &block);
+        }
+        data
+    }
+
+    /// read (timecode, payload) of all Blocks
+    fn read_blocks(data: &[u8]) -> (Matroska, Vec<(i64, Vec<u8>)>) {
+        let mut reader = io::Cursor::new(data);
+        let mut mkv = open_mkvfile(&mut reader).unwrap();
+        assert_eq!(mkv.find_track(CODEC_V_AV1), Some(1));
+        let mut blocks = Vec::new();
+        while let Some(block) = mkv.next_block(&mut reader).unwrap() {
+            let mut payload = vec![0; block.size as usize];
+            reader.read_exact(&mut payload).unwrap();
+            blocks.push((block.timecode, payload));
+        }
+        (mkv, blocks)
+    }
+
+    #[test]
+    fn unknown_size_value() {
+        // all-ones value of each length is reserved for unknown size
+        for len in 1..=8 {
+            let mut varint = vec![0xff; len];
+            varint[0] = 0xff >> (len - 1);
+            assert_eq!(read_elementsize(&varint[..]).unwrap(), None);
+            varint[len - 1] -= 1;
+            let value = (1i64 << (7 * len)) - 2;
+            assert_eq!(read_elementsize(&varint[..]).unwrap(), Some(value));
+        }
+        assert_eq!(read_elementsize(&[0x40, 0x7f][..]).unwrap(), Some(0x7f));
+        assert!(read_datasize(&[0xff][..]).is_err());
+        assert!(read_elementsize(&[0x00][..]).is_err());
+    }
+
+    #[test]
+    fn unknown_size_cluster() {
+        // unknown-size Segment and Clusters, Cluster ends at next Cluster element
+        let mut data = webm_header(&UNKNOWN_SIZE);
+        for (timecode, blocks) in [(0, [&[0x12, 0x00][..], &[0x34]]), (100, [&[0x56], &[]])] {
+            write_elementid(&mut data, ELEMENT_CLUSTER);
+            data.extend_from_slice(&UNKNOWN_SIZE);
+            data.extend(cluster_data(timecode, &blocks));
+        }
+        let (mkv, blocks) = read_blocks(&data);
+        let expected: Vec<(i64, Vec<u8>)> = vec![
+            (0, vec![0x12, 0x00]),
+            (1, vec![0x34]),
+            (100, vec![0x56]),
+            (101, vec![]),
+        ];
+        assert_eq!(blocks, expected);
+        assert!(mkv.get_skipped().is_empty());
+    }
+
+    #[test]
+    fn resync_damaged_cluster() {
+        let mut data = webm_header(&UNKNOWN_SIZE);
+        // first Cluster has Block with data size overrunning Cluster
+        let mut damaged = cluster_data(0, &[&[0x12, 0x00]]);
+        let len = damaged.len();
+        damaged[len - 7] = 0x90; // SimpleBlock data size
+        write_element(&mut data, ELEMENT_CLUSTER, &damaged);
+        let damaged_pos = data.len() - damaged.len() + 3;
+        // second Cluster starts with invalid ElementID
+        let mut broken = vec![0x00];
+        broken.extend(cluster_data(50, &[&[0x34]]));
+        write_element(&mut data, ELEMENT_CLUSTER, &broken);
+        write_element(&mut data, ELEMENT_CLUSTER, &cluster_data(100, &[&[0x56]]));
+        let (mkv, blocks) = read_blocks(&data);
+        assert_eq!(blocks, vec![(100, vec![0x56])]);
+        let skipped = mkv.get_skipped();
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(skipped[0].0, damaged_pos as u64);
+    }
+}
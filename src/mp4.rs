@@ -2,12 +2,14 @@
 ///
 /// https://aomediacodec.github.io/av1-isobmff/
 ///
+use crate::obu;
 use byteorder::{BigEndian, ByteOrder};
 use std::cmp;
 use std::convert;
 use std::fmt;
 use std::io;
 use std::io::{Read, SeekFrom};
+use std::mem;
 
 pub const BOX_FILETYPE: [u8; 4] = *b"ftyp"; // FileType Box
 const BOX_MEDIADATA: [u8; 4] = *b"mdat"; // Media Data Box
@@ -16,22 +18,72 @@ const BOX_MOVIEHEADER: [u8; 4] = *b"mvhd"; // Movie Header Box
 const BOX_TRACK: [u8; 4] = *b"trak"; // Track Box
 const BOX_TRACKHEADER: [u8; 4] = *b"tkhd"; // Track Header Box
 const BOX_MEDIA: [u8; 4] = *b"mdia"; // Media Box
+const BOX_MEDIAHEADER: [u8; 4] = *b"mdhd"; // Media Header Box
+const BOX_HANDLER: [u8; 4] = *b"hdlr"; // Handler Reference Box
+const BOX_VIDEOMEDIAHEADER: [u8; 4] = *b"vmhd"; // Video Media Header Box
+const BOX_DATAINFORMATION: [u8; 4] = *b"dinf"; // Data Information Box
+const BOX_DATAREFERENCE: [u8; 4] = *b"dref"; // Data Reference Box
+const BOX_DATAENTRYURL: [u8; 4] = *b"url "; // Data Entry Url Box
 const BOX_MEDIAINFORMATION: [u8; 4] = *b"minf"; // Media Information Box
 const BOX_SAMPLETABLE: [u8; 4] = *b"stbl"; // Sample Table Box
 const BOX_SAMPLEDESCRIPTION: [u8; 4] = *b"stsd"; // Sample Description Box
+const BOX_TIMETOSAMPLE: [u8; 4] = *b"stts"; // Decoding Time to Sample Box
+const BOX_COMPOSITIONOFFSET: [u8; 4] = *b"ctts"; // Composition Time to Sample Box
+const BOX_SYNCSAMPLE: [u8; 4] = *b"stss"; // Sync Sample Box
 const BOX_SAMPLETOCHUNK: [u8; 4] = *b"stsc"; // Sample To Chunk Box
 const BOX_SAMPLESIZE: [u8; 4] = *b"stsz"; // Sample Size Box
 const BOX_CHUNKOFFSET: [u8; 4] = *b"stco"; // Chunk Offset Box/32bit
 const BOX_CHUNKOFFSET64: [u8; 4] = *b"co64"; // Chunk Offset Box/64bit
+const BOX_USERDATA: [u8; 4] = *b"udta"; // User Data Box
+const BOX_MOVIEEXTENDS: [u8; 4] = *b"mvex"; // Movie Extends Box
+const BOX_TRACKEXTENDS: [u8; 4] = *b"trex"; // Track Extends Box
+pub const BOX_SEGMENTTYPE: [u8; 4] = *b"styp"; // Segment Type Box
+pub const BOX_MOVIEFRAGMENT: [u8; 4] = *b"moof"; // Movie Fragment Box
+const BOX_TRACKFRAGMENT: [u8; 4] = *b"traf"; // Track Fragment Box
+const BOX_TRACKFRAGMENTHEADER: [u8; 4] = *b"tfhd"; // Track Fragment Header Box
+const BOX_TRACKFRAGMENTDECODETIME: [u8; 4] = *b"tfdt"; // Track Fragment Base Media Decode Time Box
+const BOX_TRACKRUN: [u8; 4] = *b"trun"; // Track Fragment Run Box
+const BOX_META: [u8; 4] = *b"meta"; // Meta Box
+const BOX_ITEMLIST: [u8; 4] = *b"ilst"; // Metadata Item List Box
+const BOX_DATA: [u8; 4] = *b"data"; // Metadata Value Box
 const BOX_AV1SAMPLEENTRY: [u8; 4] = *b"av01"; // AV1 Sample Entry
-const BOX_AV1CODECCONFIG: [u8; 4] = *b"av1C"; // AV1 Codec Configuration Box
+pub const BOX_AV1CODECCONFIG: [u8; 4] = *b"av1C"; // AV1 Codec Configuration Box
+const BOX_ENCRYPTEDVIDEO: [u8; 4] = *b"encv"; // Encrypted Video Sample Entry
+const BOX_PROTECTIONSCHEMEINFO: [u8; 4] = *b"sinf"; // Protection Scheme Info Box
+const BOX_ORIGINALFORMAT: [u8; 4] = *b"frma"; // Original Format Box
+const BOX_SCHEMETYPE: [u8; 4] = *b"schm"; // Scheme Type Box
+const BOX_SCHEMEINFORMATION: [u8; 4] = *b"schi"; // Scheme Information Box
+const BOX_TRACKENCRYPTION: [u8; 4] = *b"tenc"; // Track Encryption Box
+const BOX_SAMPLEENCRYPTION: [u8; 4] = *b"senc"; // Sample Encryption Box
+const BOX_SAMPLEAUXINFOSIZES: [u8; 4] = *b"saiz"; // Sample Auxiliary Information Sizes Box
+const BOX_SAMPLEAUXINFOOFFSETS: [u8; 4] = *b"saio"; // Sample Auxiliary Information Offsets Box
+const BOX_PRIMARYITEM: [u8; 4] = *b"pitm"; // Primary Item Box
+const BOX_ITEMLOCATION: [u8; 4] = *b"iloc"; // Item Location Box
+const BOX_ITEMINFO: [u8; 4] = *b"iinf"; // Item Information Box
+const BOX_ITEMINFOENTRY: [u8; 4] = *b"infe"; // Item Information Entry
+const BOX_ITEMPROPERTIES: [u8; 4] = *b"iprp"; // Item Properties Box
+const BOX_ITEMPROPERTYCONTAINER: [u8; 4] = *b"ipco"; // Item Property Container Box
+const BOX_ITEMPROPERTYASSOCIATION: [u8; 4] = *b"ipma"; // Item Property Association Box
+const BOX_IMAGESPATIALEXTENTS: [u8; 4] = *b"ispe"; // Image Spatial Extents Property
+const BOX_PIXELINFORMATION: [u8; 4] = *b"pixi"; // Pixel Information Property
 
 pub const BRAND_AV01: [u8; 4] = *b"av01";
+pub const BRAND_AVIS: [u8; 4] = *b"avis"; // AVIF image sequence
+pub const BRAND_AVIF: [u8; 4] = *b"avif"; // AVIF image
+const BRAND_MIF1: [u8; 4] = *b"mif1"; // HEIF image
+const BRAND_MIAF: [u8; 4] = *b"miaf"; // MIAF image
+const BRAND_ISOM: [u8; 4] = *b"isom";
+const BRAND_ISO2: [u8; 4] = *b"iso2";
+const BRAND_MP41: [u8; 4] = *b"mp41";
+const BRAND_QT: [u8; 4] = *b"qt  "; // QuickTime movie
+
+const HANDLER_VIDEO: [u8; 4] = *b"vide";
+pub const HANDLER_PICTURE: [u8; 4] = *b"pict"; // image sequence track
 
 ///
 /// Four charactors code (u32)
 ///
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Default, PartialEq)]
 pub struct FCC {
     fcc: [u8; 4],
 }
@@ -43,7 +95,6 @@ impl convert::From<[u8; 4]> for FCC {
 }
 
 impl cmp::PartialEq<[u8; 4]> for FCC {
-    #[must_use]
     fn eq(&self, other: &[u8; 4]) -> bool {
         self.fcc == *other
     }
@@ -126,6 +177,24 @@ pub struct FileTypeBox {
     pub compatible_brands: Vec<FCC>,
 }
 
+impl FileTypeBox {
+    /// check major_brand or compatible_brands contain the brand
+    pub fn has_brand(&self, brand: [u8; 4]) -> bool {
+        self.major_brand == brand || self.compatible_brands.iter().any(|fcc| *fcc == brand)
+    }
+
+    /// list all brands, e.g. "isom,iso2,av01"
+    pub fn brands(&self) -> String {
+        let mut brands = vec![self.major_brand.to_string()];
+        for fcc in &self.compatible_brands {
+            if !brands.contains(&fcc.to_string()) {
+                brands.push(fcc.to_string());
+            }
+        }
+        brands.join(",")
+    }
+}
+
 /// read FileTypeBox
 fn read_ftypbox<R: io::Read>(mut reader: R) -> io::Result<FileTypeBox> {
     let (boxtype, mut payload_size) = read_box(&mut reader)?;
@@ -151,6 +220,87 @@ fn read_ftypbox<R: io::Read>(mut reader: R) -> io::Result<FileTypeBox> {
     })
 }
 
+///
+/// read FileTypeBox after leading 'free'/'skip'/'wide' Boxes,
+/// file without FileTypeBox is treated as QuickTime movie (major_brand='qt  ')
+///
+fn read_leading_ftypbox<R: io::Read + io::Seek>(mut reader: R) -> io::Result<FileTypeBox> {
+    let start = reader.stream_position()?;
+    loop {
+        let pos = reader.stream_position()?;
+        let (boxtype, size) = read_box(&mut reader)?;
+        if boxtype == BOX_FILETYPE {
+            reader.seek(SeekFrom::Start(pos))?;
+            return read_ftypbox(reader);
+        }
+        if !(boxtype == *b"free" || boxtype == *b"skip" || boxtype == *b"wide") {
+            reader.seek(SeekFrom::Start(start))?;
+            return Ok(FileTypeBox {
+                major_brand: FCC::from(BRAND_QT),
+                minor_version: 0,
+                compatible_brands: Vec::new(),
+            });
+        }
+        reader.seek(SeekFrom::Current(size as i64))?;
+    }
+}
+
+///
+/// TrackHeaderBox
+///
+#[derive(Debug, Default, Clone)]
+pub struct TrackHeaderBox {
+    pub track_id: u32,    // ui(32)
+    pub duration: u64,    // ui(32/64)
+    pub matrix: [i32; 9], // int(32)[9]
+    pub width: u32,       // ui(32), fixed-point 16.16
+    pub height: u32,      // ui(32), fixed-point 16.16
+}
+
+impl TrackHeaderBox {
+    /// display rotation angle [degree] from transformation matrix
+    pub fn rotation(&self) -> i32 {
+        let (a, b) = (self.matrix[0] as f64, self.matrix[1] as f64);
+        let angle = b.atan2(a).to_degrees().round() as i32;
+        (angle + 360) % 360
+    }
+
+    /// identity transformation matrix or not
+    pub fn is_identity(&self) -> bool {
+        self.matrix == [0x10000, 0, 0, 0, 0x10000, 0, 0, 0, 0x4000_0000]
+    }
+}
+
+/// read TrackHeaderBox payload
+fn read_tkhdbox<R: io::Read>(mut reader: R) -> io::Result<TrackHeaderBox> {
+    let mut tkhd = TrackHeaderBox::default();
+    let version_flag = read_u32(&mut reader)?;
+    if version_flag >> 24 == 1 {
+        let _creation_time = read_u64(&mut reader)?;
+        let _modification_time = read_u64(&mut reader)?;
+        tkhd.track_id = read_u32(&mut reader)?;
+        let _reserved = read_u32(&mut reader)?;
+        tkhd.duration = read_u64(&mut reader)?;
+    } else {
+        let _creation_time = read_u32(&mut reader)?;
+        let _modification_time = read_u32(&mut reader)?;
+        tkhd.track_id = read_u32(&mut reader)?;
+        let _reserved = read_u32(&mut reader)?;
+        tkhd.duration = read_u32(&mut reader)? as u64;
+    }
+    let _reserved = read_u64(&mut reader)?;
+    let _layer = read_u16(&mut reader)?;
+    let _alternate_group = read_u16(&mut reader)?;
+    let _volume = read_u16(&mut reader)?;
+    let _reserved = read_u16(&mut reader)?;
+    for m in tkhd.matrix.iter_mut() {
+        *m = read_u32(&mut reader)? as i32;
+    }
+    tkhd.width = read_u32(&mut reader)?;
+    tkhd.height = read_u32(&mut reader)?;
+    Ok(tkhd)
+}
+
 ///
 /// AV1SampleEntry(VisualSampleEntry)
 ///
@@ -192,8 +342,8 @@ fn read_av1sampleentry<R: io::Read>(mut reader: R) -> io::Result<AV1SampleEntry>
 ///
 /// AV1CodecConfigurationBox
 ///
-#[derive(Debug, Default)]
-pub struct AV1CodecConfigurationBox {
+#[derive(Debug, Default, Clone)]
+pub struct AV1CodecConfigurationBox<'a> {
     pub seq_profile: u8,                          // ui(3)
     pub seq_level_idx_0: u8,                      // ui(5)
     pub seq_tier_0: u8,                           // ui(1)
@@ -205,17 +355,20 @@ pub struct AV1CodecConfigurationBox {
     pub chroma_sample_position: u8,               // ui(2)
     pub initial_presentation_delay_present: bool, // ui(1)
     pub initial_presentation_delay_minus_one: u8, // ui(4)
-    pub config_obus: Vec<u8>,                     // ui(8)[]
+    pub config_obus: &'a [u8],                    // ui(8)[]
 }
 
-fn read_av1codecconfig<R: io::Read>(
-    mut reader: R,
-    payload_size: u64,
-) -> io::Result<AV1CodecConfigurationBox> {
+/// parse AV1CodecConfigurationBox payload, configOBUs are borrowed from `payload`
+pub fn read_av1codecconfig(payload: &[u8]) -> io::Result<AV1CodecConfigurationBox<'_>> {
     let mut av1cc = AV1CodecConfigurationBox::default();
 
-    let mut bb = [0; 4];
-    reader.read_exact(&mut bb)?;
+    if payload.len() < 4 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Invalid AV1CodecConfigurationBox size",
+        ));
+    }
+    let bb = &payload[..4];
     let (marker, version) = (bb[0] >> 7, bb[0] & 0x7f);
     if marker != 1 || version != 1 {
         return Err(io::Error::new(
@@ -238,45 +391,327 @@ fn read_av1codecconfig<R: io::Read>(
     let _reserved = bb[3] >> 5; // ui(3)
     av1cc.initial_presentation_delay_present = ((bb[3] >> 4) & 1) != 0; // ui(1)
     av1cc.initial_presentation_delay_minus_one = bb[3] & 0xf; // ui(4)
-    if 4 < payload_size {
-        reader
-            .take(payload_size - 4)
-            .read_to_end(&mut av1cc.config_obus)?;
-    }
+    av1cc.config_obus = &payload[4..]; // ui(8)[]
     Ok(av1cc)
 }
 
-/// parse SampleDescriptionBox payload
-fn parse_sampledescription<R: io::Read + io::Seek>(
+impl<'a> AV1CodecConfigurationBox<'a> {
+    /// create AV1CodecConfigurationBox from Sequence Header
+    pub fn from_sequence_header(sh: &obu::SequenceHeader, config_obus: &'a [u8]) -> Self {
+        let cc = &sh.color_config;
+        AV1CodecConfigurationBox {
+            seq_profile: sh.seq_profile,
+            seq_level_idx_0: sh.op[0].seq_level_idx,
+            seq_tier_0: sh.op[0].seq_tier,
+            high_bitdepth: (cc.bit_depth > 8) as u8,
+            twelve_bit: (cc.bit_depth == 12) as u8,
+            monochrome: cc.mono_chrome as u8,
+            chroma_subsampling_x: cc.subsampling_x,
+            chroma_subsampling_y: cc.subsampling_y,
+            chroma_sample_position: cc.chroma_sample_position,
+            initial_presentation_delay_present: false,
+            initial_presentation_delay_minus_one: 0,
+            config_obus,
+        }
+    }
+
+    /// create AV1CodecConfigurationBox from Sequence Header OBU, which becomes configOBUs
+    pub fn from_sequence_header_obu(bytes: &'a [u8]) -> io::Result<Self> {
+        let obu = obu::parse_obu_header(&mut &bytes[..], bytes.len() as u32)?;
+        let end = (obu.header_len + obu.obu_size) as usize;
+        if obu.obu_type != obu::OBU_SEQUENCE_HEADER || bytes.len() < end {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid Sequence Header OBU",
+            ));
+        }
+        let mut payload = &bytes[obu.header_len as usize..end];
+        match obu::parse_sequence_header(&mut payload) {
+            Some(sh) => Ok(Self::from_sequence_header(&sh, &bytes[..end])),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid Sequence Header OBU",
+            )),
+        }
+    }
+
+    /// get codec parameter string with mandatory fields "av01.P.LLT.DD"
+    pub fn codec_string(&self) -> String {
+        let bit_depth = match (self.high_bitdepth, self.twelve_bit) {
+            (0, _) => 8,
+            (_, 0) => 10,
+            _ => 12,
+        };
+        format!(
+            "av01.{}.{:02}{}.{:02}",
+            self.seq_profile,
+            self.seq_level_idx_0,
+            if self.seq_tier_0 == 0 { 'M' } else { 'H' },
+            bit_depth
+        )
+    }
+
+    /// get configOBUs as byte slice
+    pub fn config_obus(&self) -> &'a [u8] {
+        self.config_obus
+    }
+
+    /// get cursor over configOBUs without copying
+    pub fn config_obus_reader(&self) -> io::Cursor<&'a [u8]> {
+        io::Cursor::new(self.config_obus)
+    }
+
+    /// serialize AV1CodecConfigurationBox payload (AV1CodecConfigurationRecord),
+    /// which is also WebCodecs VideoDecoderConfig.description
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(4 + self.config_obus.len());
+        buf.push(0x81); // marker(1)=1, version(7)=1
+        buf.push(self.seq_profile << 5 | self.seq_level_idx_0);
+        buf.push(
+            self.seq_tier_0 << 7
+                | self.high_bitdepth << 6
+                | self.twelve_bit << 5
+                | self.monochrome << 4
+                | self.chroma_subsampling_x << 3
+                | self.chroma_subsampling_y << 2
+                | self.chroma_sample_position,
+        );
+        buf.push(
+            (self.initial_presentation_delay_present as u8) << 4
+                | self.initial_presentation_delay_minus_one,
+        );
+        buf.extend_from_slice(self.config_obus);
+        buf
+    }
+}
+
+///
+/// Common Encryption protection scheme information
+///
+#[derive(Debug, Default, Clone)]
+pub struct ProtectionInfo {
+    pub original_format: FCC,           // frma
+    pub scheme_type: FCC,               // schm
+    pub scheme_version: u32,            // schm
+    pub default_is_protected: u8,       // tenc
+    pub default_per_sample_iv_size: u8, // tenc
+    pub default_kid: [u8; 16],          // tenc
+}
+
+/// parse ProtectionSchemeInfoBox payload
+fn parse_protectionschemeinfo<R: io::Read + io::Seek>(
+    mut reader: R,
+    size: u64,
+) -> io::Result<ProtectionInfo> {
+    let mut sinf = ProtectionInfo::default();
+    let limit = reader.stream_position()? + size;
+    while reader.stream_position()? < limit {
+        let (boxtype, size) = read_box(&mut reader)?;
+        let box_end = reader.stream_position()? + size;
+        if boxtype == BOX_ORIGINALFORMAT {
+            sinf.original_format = read_fcc(&mut reader)?;
+        } else if boxtype == BOX_SCHEMETYPE {
+            let _version_flag = read_u32(&mut reader)?;
+            sinf.scheme_type = read_fcc(&mut reader)?;
+            sinf.scheme_version = read_u32(&mut reader)?;
+        } else if boxtype == BOX_SCHEMEINFORMATION {
+            // parse nested Boxes
+            continue;
+        } else if boxtype == BOX_TRACKENCRYPTION {
+            let _version_flag = read_u32(&mut reader)?;
+            let mut bb = [0; 4];
+            reader.read_exact(&mut bb)?;
+            sinf.default_is_protected = bb[2];
+            sinf.default_per_sample_iv_size = bb[3];
+            reader.read_exact(&mut sinf.default_kid)?;
+        }
+        reader.seek(SeekFrom::Start(box_end))?;
+    }
+    Ok(sinf)
+}
+
+///
+/// Layout of sample auxiliary information for encrypted samples
+///
+#[derive(Debug, Default, Clone)]
+pub struct SampleAuxInfoLayout {
+    pub saiz_sample_count: u32, // number of samples in saiz
+    pub saio_entry_count: u32,  // number of offsets in saio
+    pub senc_sample_count: u32, // number of samples in senc
+    pub senc_subsamples: u64,   // total number of subsamples in senc
+}
+
+/// parse saiz/saio/senc Box payload, accumulate into layout
+fn parse_sampleauxinfo<R: io::Read + io::Seek>(
     mut reader: R,
-) -> io::Result<Option<(AV1SampleEntry, AV1CodecConfigurationBox)>> {
-    let mut payload = None;
+    boxtype: FCC,
+    iv_size: u8,
+    layout: &mut SampleAuxInfoLayout,
+) -> io::Result<()> {
+    let version_flag = read_u32(&mut reader)?;
+    if boxtype == BOX_SAMPLEAUXINFOSIZES {
+        if version_flag & 1 != 0 {
+            let _aux_info_type = read_u32(&mut reader)?;
+            let _aux_info_type_parameter = read_u32(&mut reader)?;
+        }
+        let mut default_sample_info_size = [0; 1];
+        reader.read_exact(&mut default_sample_info_size)?;
+        layout.saiz_sample_count += read_u32(&mut reader)?;
+    } else if boxtype == BOX_SAMPLEAUXINFOOFFSETS {
+        if version_flag & 1 != 0 {
+            let _aux_info_type = read_u32(&mut reader)?;
+            let _aux_info_type_parameter = read_u32(&mut reader)?;
+        }
+        layout.saio_entry_count += read_u32(&mut reader)?;
+    } else if boxtype == BOX_SAMPLEENCRYPTION {
+        let sample_count = read_u32(&mut reader)?;
+        layout.senc_sample_count += sample_count;
+        if version_flag & 0x2 != 0 {
+            // UseSubSampleEncryption
+            for _ in 0..sample_count {
+                reader.seek(SeekFrom::Current(iv_size as i64))?;
+                let subsample_count = read_u16(&mut reader)?;
+                layout.senc_subsamples += subsample_count as u64;
+                // BytesOfClearData(16), BytesOfProtectedData(32)
+                reader.seek(SeekFrom::Current(subsample_count as i64 * 6))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// SampleDescriptionBox summary
+#[derive(Default)]
+struct SampleDescription {
+    codec: FCC,                                   // type of first SampleEntry
+    av1config: Option<(AV1SampleEntry, Vec<u8>)>, // (AV1SampleEntry, av1C payload)
+    protection: Option<ProtectionInfo>,
+}
+
+/// parse SampleDescriptionBox payload
+fn parse_sampledescription<R: io::Read + io::Seek>(mut reader: R) -> io::Result<SampleDescription> {
+    let mut stsd = SampleDescription::default();
     let _version_flag = read_u32(&mut reader)?;
     let entry_count = read_u32(&mut reader)?;
-    for _ in 0..entry_count {
+    for idx in 0..entry_count {
         let (boxtype, size) = read_box(&mut reader)?;
-        if boxtype == BOX_AV1SAMPLEENTRY {
+        let entry_end = reader.stream_position()? + size;
+        if idx == 0 {
+            stsd.codec = boxtype;
+        }
+        if boxtype == BOX_AV1SAMPLEENTRY || boxtype == BOX_ENCRYPTEDVIDEO {
             // read AV1SampleEntry
             let av1se = read_av1sampleentry(&mut reader)?;
-            // read AV1CodecConfigurationBox
-            let (boxtype, size) = read_box(&mut reader)?;
-            if boxtype != BOX_AV1CODECCONFIG {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    format!(
-                        "Invalid AV1CodecConfigurationBox(boxtype={}, size={})",
-                        boxtype, size
-                    ),
-                ));
+            let mut av1cc = None;
+            let mut sinf = None;
+            while reader.stream_position()? < entry_end {
+                let (boxtype, size) = read_box(&mut reader)?;
+                let box_end = reader.stream_position()? + size;
+                if boxtype == BOX_AV1CODECCONFIG {
+                    // read AV1CodecConfigurationBox
+                    let mut payload = vec![0; size as usize];
+                    reader.read_exact(&mut payload)?;
+                    read_av1codecconfig(&payload)?;
+                    av1cc = Some(payload);
+                } else if boxtype == BOX_PROTECTIONSCHEMEINFO {
+                    // read ProtectionSchemeInfoBox
+                    sinf = Some(parse_protectionschemeinfo(&mut reader, size)?);
+                }
+                reader.seek(SeekFrom::Start(box_end))?;
             }
-            let av1cc = read_av1codecconfig(&mut reader, size)?;
-            payload = Some((av1se, av1cc));
-        } else {
-            // ignore unknown SampleEntry
-            reader.seek(SeekFrom::Current(size as i64))?;
+            if boxtype == BOX_ENCRYPTEDVIDEO {
+                match sinf {
+                    Some(ref sinf) if sinf.original_format == BOX_AV1SAMPLEENTRY => {}
+                    _ => {
+                        // ignore encrypted non-AV1 SampleEntry
+                        continue;
+                    }
+                }
+            }
+            let av1cc = match av1cc {
+                Some(av1cc) => av1cc,
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "AV1CodecConfigurationBox not found",
+                    ))
+                }
+            };
+            stsd.av1config = Some((av1se, av1cc));
+            stsd.protection = sinf;
         }
+        // skip remaining or unknown SampleEntry
+        reader.seek(SeekFrom::Start(entry_end))?;
+    }
+    Ok(stsd)
+}
+
+/// parse MediaHeaderBox payload, return (timescale, duration)
+fn parse_mediaheader<R: io::Read>(mut reader: R) -> io::Result<(u32, u64)> {
+    let version_flag = read_u32(&mut reader)?;
+    if version_flag >> 24 == 1 {
+        let _creation_time = read_u64(&mut reader)?;
+        let _modification_time = read_u64(&mut reader)?;
+        let timescale = read_u32(&mut reader)?;
+        let duration = read_u64(&mut reader)?;
+        Ok((timescale, duration))
+    } else {
+        let _creation_time = read_u32(&mut reader)?;
+        let _modification_time = read_u32(&mut reader)?;
+        let timescale = read_u32(&mut reader)?;
+        let duration = read_u32(&mut reader)? as u64;
+        Ok((timescale, duration))
     }
-    Ok(payload)
+}
+
+/// parse HandlerBox payload, return handler_type
+fn parse_handler<R: io::Read>(mut reader: R) -> io::Result<FCC> {
+    let _version_flag = read_u32(&mut reader)?;
+    let _pre_defined = read_u32(&mut reader)?;
+    read_fcc(&mut reader)
+}
+
+/// parse TimeToSampleBox payload
+fn parse_timetosample<R: io::Read>(mut reader: R) -> io::Result<Vec<(u32, u32)>> {
+    let _version_flag = read_u32(&mut reader)?;
+    let entry_count = read_u32(&mut reader)?;
+    let mut stts = Vec::with_capacity(entry_count as usize);
+    for _ in 0..entry_count {
+        let sample_count = read_u32(&mut reader)?;
+        let sample_delta = read_u32(&mut reader)?;
+        stts.push((sample_count, sample_delta));
+    }
+    Ok(stts)
+}
+
+/// parse CompositionOffsetBox payload
+fn parse_compositionoffset<R: io::Read>(mut reader: R) -> io::Result<Vec<(u32, i64)>> {
+    let version_flag = read_u32(&mut reader)?;
+    let entry_count = read_u32(&mut reader)?;
+    let mut ctts = Vec::with_capacity(entry_count as usize);
+    for _ in 0..entry_count {
+        let sample_count = read_u32(&mut reader)?;
+        let sample_offset = read_u32(&mut reader)?;
+        // version 0: unsigned int(32), version 1: signed int(32)
+        let sample_offset = if version_flag >> 24 == 0 {
+            sample_offset as i64
+        } else {
+            sample_offset as i32 as i64
+        };
+        ctts.push((sample_count, sample_offset));
+    }
+    Ok(ctts)
+}
+
+/// parse SyncSampleBox payload
+fn parse_syncsample<R: io::Read>(mut reader: R) -> io::Result<Vec<u32>> {
+    let _version_flag = read_u32(&mut reader)?;
+    let entry_count = read_u32(&mut reader)?;
+    let mut stss = Vec::with_capacity(entry_count as usize);
+    for _ in 0..entry_count {
+        stss.push(read_u32(&mut reader)?);
+    }
+    Ok(stss)
 }
 
 /// parse SampleToChunkBox payload
@@ -329,6 +764,21 @@ fn parse_chunkoffset<R: io::Read>(mut reader: R, boxtype: FCC) -> io::Result<Vec
     Ok(offsets)
 }
 
+///
+/// Track summary
+///
+#[derive(Debug, Clone)]
+pub struct TrackInfo {
+    pub track_id: u32,
+    pub handler: FCC, // handler_type in hdlr
+    pub codec: FCC,   // type of first SampleEntry
+    pub width: u32,   // tkhd width (integer part)
+    pub height: u32,  // tkhd height (integer part)
+    pub timescale: u32,
+    pub duration: u64, // mdhd duration [timescale]
+    pub sample_count: u64,
+}
+
 /// parse TrackBox payload
 fn parse_track<R: io::Read + io::Seek>(
     mut reader: R,
@@ -337,7 +787,14 @@ fn parse_track<R: io::Read + io::Seek>(
 ) -> io::Result<bool> {
     let limit = reader.stream_position()? + size;
     let mut av1config = None;
+    let mut protection = None;
+    let mut aux_layout = SampleAuxInfoLayout::default();
+    let mut tkhd = None;
+    let mut handler = FCC::default();
+    let mut codec = FCC::default();
+    let (mut timescale, mut duration) = (0, 0);
     let (mut stsc, mut stsz, mut stco) = (Vec::new(), Vec::new(), Vec::new());
+    let (mut stts, mut ctts, mut stss) = (Vec::new(), Vec::new(), None);
     loop {
         // read next Box
         let (boxtype, size) = match read_box(&mut reader) {
@@ -352,9 +809,49 @@ fn parse_track<R: io::Read + io::Seek>(
         };
         if boxtype == BOX_MEDIA || boxtype == BOX_MEDIAINFORMATION || boxtype == BOX_SAMPLETABLE {
             // parse nested Boxes
+        } else if boxtype == BOX_TRACKHEADER {
+            // parse TrackHeaderBox
+            let pos = reader.stream_position()?;
+            tkhd = Some(read_tkhdbox(&mut reader)?);
+            reader.seek(SeekFrom::Start(pos + size))?;
+        } else if boxtype == BOX_MEDIAHEADER {
+            // parse MediaHeaderBox
+            let pos = reader.stream_position()?;
+            let mdhd = parse_mediaheader(&mut reader)?;
+            timescale = mdhd.0;
+            duration = mdhd.1;
+            reader.seek(SeekFrom::Start(pos + size))?;
+        } else if boxtype == BOX_HANDLER {
+            // parse HandlerBox
+            let pos = reader.stream_position()?;
+            handler = parse_handler(&mut reader)?;
+            reader.seek(SeekFrom::Start(pos + size))?;
         } else if boxtype == BOX_SAMPLEDESCRIPTION {
             // parse SampleDescriptionBox
-            av1config = parse_sampledescription(&mut reader)?;
+            let stsd = parse_sampledescription(&mut reader)?;
+            codec = stsd.codec;
+            av1config = stsd.av1config;
+            protection = stsd.protection;
+        } else if boxtype == BOX_SAMPLEAUXINFOSIZES
+            || boxtype == BOX_SAMPLEAUXINFOOFFSETS
+            || boxtype == BOX_SAMPLEENCRYPTION
+        {
+            // parse SampleAuxiliaryInformation/SampleEncryptionBox
+            let pos = reader.stream_position()?;
+            let iv_size = protection
+                .as_ref()
+                .map_or(0, |sinf: &ProtectionInfo| sinf.default_per_sample_iv_size);
+            parse_sampleauxinfo(&mut reader, boxtype, iv_size, &mut aux_layout)?;
+            reader.seek(SeekFrom::Start(pos + size))?;
+        } else if boxtype == BOX_TIMETOSAMPLE {
+            // parse TimeToSampleBox
+            stts = parse_timetosample(&mut reader)?;
+        } else if boxtype == BOX_COMPOSITIONOFFSET {
+            // parse CompositionOffsetBox
+            ctts = parse_compositionoffset(&mut reader)?;
+        } else if boxtype == BOX_SYNCSAMPLE {
+            // parse SyncSampleBox
+            stss = Some(parse_syncsample(&mut reader)?);
         } else if boxtype == BOX_SAMPLETOCHUNK {
             // parse SampleToChunkBox
             stsc = parse_sampletochunk(&mut reader)?;
@@ -371,11 +868,51 @@ fn parse_track<R: io::Read + io::Seek>(
             break;
         }
     }
+    let track_id = tkhd.as_ref().map_or(0, |tkhd| tkhd.track_id);
+    mp4.tracks.push(TrackInfo {
+        track_id,
+        handler,
+        codec,
+        width: tkhd.as_ref().map_or(0, |tkhd| tkhd.width >> 16),
+        height: tkhd.as_ref().map_or(0, |tkhd| tkhd.height >> 16),
+        timescale,
+        duration,
+        sample_count: stsz.len() as u64,
+    });
     if av1config.is_none() {
         // This track is not 'av01' video
         return Ok(false);
     }
+    match mp4.select_track {
+        Some(select_track) if select_track != track_id => {
+            // This track is not selected
+            return Ok(false);
+        }
+        _ => {}
+    }
+    if mp4.av1config.is_some() {
+        // use first 'av01' track (e.g. ignore alpha auxiliary track in 'avis')
+        return Ok(false);
+    }
     mp4.av1config = av1config;
+    mp4.protection = protection;
+    mp4.aux_layout = aux_layout;
+    mp4.handler = handler;
+    mp4.trackheader = tkhd;
+    mp4.timescale = timescale;
+
+    // expand (sample_count, value) run-length tables into per-sample values
+    let mut dts_list = stts
+        .iter()
+        .flat_map(|&(count, delta)| (0..count).map(move |_| delta as i64))
+        .scan(0, |dts, delta| {
+            let curr = *dts;
+            *dts += delta;
+            Some(curr)
+        });
+    let mut cts_offsets = ctts
+        .iter()
+        .flat_map(|&(count, offset)| (0..count).map(move |_| offset));
 
     // calculate Sample{pos,size} from stsc/stsz/stco
     let nsample = stsz.len();
@@ -386,7 +923,19 @@ fn parse_track<R: io::Read + io::Seek>(
         let mut pos = stco[stco_idx];
         for _ in 0..(stsc[stsc_idx].1) {
             let size = stsz[stsz_idx] as u64;
-            samples.push(Sample { pos, size });
+            let dts = dts_list.next().unwrap_or(0);
+            let pts = dts + cts_offsets.next().unwrap_or(0);
+            let is_sync = match stss {
+                Some(ref stss) => stss.contains(&(stsz_idx as u32 + 1)),
+                None => true, // all samples are sync samples
+            };
+            samples.push(Sample {
+                pos,
+                size,
+                dts,
+                pts,
+                is_sync,
+            });
             pos += size;
             stsz_idx += 1;
         }
@@ -400,6 +949,297 @@ fn parse_track<R: io::Read + io::Seek>(
     Ok(true)
 }
 
+/// parse Metadata Item List Box payload, return (key, value) of text items
+fn parse_itemlist<R: io::Read + io::Seek>(
+    mut reader: R,
+    size: u64,
+) -> io::Result<Vec<(FCC, String)>> {
+    let limit = reader.stream_position()? + size;
+    let mut tags = Vec::new();
+    while reader.stream_position()? + 8 <= limit {
+        let (key, item_size) = read_box(&mut reader)?;
+        let item_end = reader.stream_position()? + item_size;
+        while reader.stream_position()? + 8 <= item_end {
+            let (boxtype, size) = read_box(&mut reader)?;
+            let box_end = reader.stream_position()? + size;
+            if boxtype == BOX_DATA && 8 <= size {
+                let type_indicator = read_u32(&mut reader)?;
+                let _locale = read_u32(&mut reader)?;
+                if type_indicator == 1 {
+                    // UTF-8 string
+                    let mut value = Vec::new();
+                    (&mut reader).take(size - 8).read_to_end(&mut value)?;
+                    tags.push((key, String::from_utf8_lossy(&value).into_owned()));
+                }
+            }
+            reader.seek(SeekFrom::Start(box_end))?;
+        }
+        reader.seek(SeekFrom::Start(item_end))?;
+    }
+    Ok(tags)
+}
+
+/// parse UserDataBox payload
+fn parse_userdata<R: io::Read + io::Seek>(
+    mut reader: R,
+    size: u64,
+    mp4: &mut IsoBmff,
+) -> io::Result<()> {
+    let limit = reader.stream_position()? + size;
+    while reader.stream_position()? + 8 <= limit {
+        let (boxtype, size) = read_box(&mut reader)?;
+        let box_end = reader.stream_position()? + size;
+        if boxtype == BOX_META {
+            // MetaBox is FullBox in ISOBMFF, but plain Box in QuickTime
+            let pos = reader.stream_position()?;
+            if read_u32(&mut reader)? != 0 {
+                reader.seek(SeekFrom::Start(pos))?;
+            }
+            while reader.stream_position()? + 8 <= box_end {
+                let (boxtype, size) = read_box(&mut reader)?;
+                if boxtype == BOX_ITEMLIST {
+                    let mut tags = parse_itemlist(&mut reader, size)?;
+                    mp4.tags.append(&mut tags);
+                } else {
+                    reader.seek(SeekFrom::Current(size as i64))?;
+                }
+            }
+        } else if boxtype.fcc[0] == 0xA9 && 4 <= size {
+            // QuickTime user data text: size(16), language(16), text
+            let text_size = read_u16(&mut reader)? as u64;
+            let _language = read_u16(&mut reader)?;
+            let mut value = Vec::new();
+            (&mut reader)
+                .take(cmp::min(text_size, size - 4))
+                .read_to_end(&mut value)?;
+            mp4.tags
+                .push((boxtype, String::from_utf8_lossy(&value).into_owned()));
+        }
+        reader.seek(SeekFrom::Start(box_end))?;
+    }
+    Ok(())
+}
+
+///
+/// TrackExtendsBox
+///
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TrackExtendsBox {
+    pub track_id: u32,                         // ui(32)
+    pub default_sample_description_index: u32, // ui(32)
+    pub default_sample_duration: u32,          // ui(32)
+    pub default_sample_size: u32,              // ui(32)
+    pub default_sample_flags: u32,             // ui(32)
+}
+
+/// parse TrackExtendsBox payload
+fn parse_trackextends<R: io::Read>(mut reader: R) -> io::Result<TrackExtendsBox> {
+    let _version_flag = read_u32(&mut reader)?;
+    Ok(TrackExtendsBox {
+        track_id: read_u32(&mut reader)?,
+        default_sample_description_index: read_u32(&mut reader)?,
+        default_sample_duration: read_u32(&mut reader)?,
+        default_sample_size: read_u32(&mut reader)?,
+        default_sample_flags: read_u32(&mut reader)?,
+    })
+}
+
+// sample_is_non_sync_sample flag in sample_flags
+const SAMPLE_FLAG_NON_SYNC: u32 = 0x0001_0000;
+
+/// count Samples in remaining TrackRunBoxes of non-'av01' TrackFragmentBox
+fn count_trackfragment<R: io::Read + io::Seek>(
+    mut reader: R,
+    limit: u64,
+    track_id: u32,
+    mp4: &mut IsoBmff,
+) -> io::Result<()> {
+    while reader.stream_position()? < limit {
+        let (boxtype, size) = read_box(&mut reader)?;
+        let box_end = reader.stream_position()? + size;
+        if boxtype == BOX_TRACKRUN {
+            let _version_flag = read_u32(&mut reader)?;
+            let sample_count = read_u32(&mut reader)?;
+            if let Some(track) = mp4.tracks.iter_mut().find(|t| t.track_id == track_id) {
+                track.sample_count += sample_count as u64;
+            }
+        }
+        reader.seek(SeekFrom::Start(box_end))?;
+    }
+    Ok(())
+}
+
+/// parse TrackFragmentBox, return 'av01' Samples
+fn parse_trackfragment<R: io::Read + io::Seek>(
+    mut reader: R,
+    size: u64,
+    moof_pos: u64,
+    mp4: &mut IsoBmff,
+) -> io::Result<Vec<Sample>> {
+    let limit = reader.stream_position()? + size;
+    let track_id = mp4.trackheader.as_ref().map_or(0, |tkhd| tkhd.track_id);
+    let mut defaults = TrackExtendsBox::default();
+    let mut base_data_offset = moof_pos;
+    let mut samples = Vec::new();
+    while reader.stream_position()? < limit {
+        let (boxtype, size) = read_box(&mut reader)?;
+        let box_end = reader.stream_position()? + size;
+        if boxtype == BOX_TRACKFRAGMENTHEADER {
+            // parse TrackFragmentHeaderBox
+            let tf_flags = read_u32(&mut reader)? & 0xff_ffff;
+            let tfhd_track_id = read_u32(&mut reader)?;
+            if tfhd_track_id != track_id {
+                // This track fragment is not 'av01' video, count Samples only
+                count_trackfragment(&mut reader, limit, tfhd_track_id, mp4)?;
+                return Ok(Vec::new());
+            }
+            if let Some(trex) = mp4.trex.iter().find(|trex| trex.track_id == track_id) {
+                defaults = *trex;
+            }
+            if tf_flags & 0x000001 != 0 {
+                base_data_offset = read_u64(&mut reader)?;
+            }
+            if tf_flags & 0x000002 != 0 {
+                defaults.default_sample_description_index = read_u32(&mut reader)?;
+            }
+            if tf_flags & 0x000008 != 0 {
+                defaults.default_sample_duration = read_u32(&mut reader)?;
+            }
+            if tf_flags & 0x000010 != 0 {
+                defaults.default_sample_size = read_u32(&mut reader)?;
+            }
+            if tf_flags & 0x000020 != 0 {
+                defaults.default_sample_flags = read_u32(&mut reader)?;
+            }
+        } else if boxtype == BOX_TRACKFRAGMENTDECODETIME {
+            // parse TrackFragmentBaseMediaDecodeTimeBox
+            let version_flag = read_u32(&mut reader)?;
+            mp4.fragment_dts = if version_flag >> 24 == 1 {
+                read_u64(&mut reader)? as i64
+            } else {
+                read_u32(&mut reader)? as i64
+            };
+        } else if boxtype == BOX_TRACKRUN {
+            // parse TrackRunBox
+            let version_flag = read_u32(&mut reader)?;
+            let tr_flags = version_flag & 0xff_ffff;
+            let sample_count = read_u32(&mut reader)?;
+            let mut pos = base_data_offset;
+            if tr_flags & 0x000001 != 0 {
+                pos = (base_data_offset as i64 + read_u32(&mut reader)? as i32 as i64) as u64;
+            }
+            let first_sample_flags = if tr_flags & 0x000004 != 0 {
+                Some(read_u32(&mut reader)?)
+            } else {
+                None
+            };
+            for idx in 0..sample_count {
+                let mut duration = defaults.default_sample_duration;
+                let mut size = defaults.default_sample_size;
+                let mut flags = defaults.default_sample_flags;
+                let mut cts_offset = 0;
+                if tr_flags & 0x000100 != 0 {
+                    duration = read_u32(&mut reader)?;
+                }
+                if tr_flags & 0x000200 != 0 {
+                    size = read_u32(&mut reader)?;
+                }
+                if tr_flags & 0x000400 != 0 {
+                    flags = read_u32(&mut reader)?;
+                }
+                if tr_flags & 0x000800 != 0 {
+                    // version 0: unsigned int(32), version 1: signed int(32)
+                    let offset = read_u32(&mut reader)?;
+                    cts_offset = if version_flag >> 24 == 0 {
+                        offset as i64
+                    } else {
+                        offset as i32 as i64
+                    };
+                }
+                if idx == 0 {
+                    flags = first_sample_flags.unwrap_or(flags);
+                }
+                let dts = mp4.fragment_dts;
+                samples.push(Sample {
+                    pos,
+                    size: size as u64,
+                    dts,
+                    pts: dts + cts_offset,
+                    is_sync: flags & SAMPLE_FLAG_NON_SYNC == 0,
+                });
+                pos += size as u64;
+                mp4.fragment_dts += duration as i64;
+            }
+            // next TrackRunBox without data_offset continues from here
+            base_data_offset = pos;
+        } else if boxtype == BOX_SAMPLEAUXINFOSIZES
+            || boxtype == BOX_SAMPLEAUXINFOOFFSETS
+            || boxtype == BOX_SAMPLEENCRYPTION
+        {
+            // parse SampleAuxiliaryInformation/SampleEncryptionBox
+            let iv_size = mp4
+                .protection
+                .as_ref()
+                .map_or(0, |sinf| sinf.default_per_sample_iv_size);
+            parse_sampleauxinfo(&mut reader, boxtype, iv_size, &mut mp4.aux_layout)?;
+        }
+        reader.seek(SeekFrom::Start(box_end))?;
+    }
+    Ok(samples)
+}
+
+/// parse MovieFragmentBox
+fn parse_moviefragment<R: io::Read + io::Seek>(
+    mut reader: R,
+    size: u64,
+    moof_pos: u64,
+    mp4: &mut IsoBmff,
+) -> io::Result<()> {
+    let limit = reader.stream_position()? + size;
+    while reader.stream_position()? < limit {
+        let (boxtype, size) = read_box(&mut reader)?;
+        let box_end = reader.stream_position()? + size;
+        if boxtype == BOX_TRACKFRAGMENT {
+            let samples = parse_trackfragment(&mut reader, size, moof_pos, mp4)?;
+            mp4.samples.extend(samples);
+        }
+        reader.seek(SeekFrom::Start(box_end))?;
+    }
+    Ok(())
+}
+
+/// parse MovieBox
+fn parse_movie<R: io::Read + io::Seek>(
+    mut reader: R,
+    size: u64,
+    mp4: &mut IsoBmff,
+) -> io::Result<()> {
+    let limit = reader.stream_position()? + size;
+    while reader.stream_position()? < limit {
+        let (boxtype, size) = read_box(&mut reader)?;
+        let box_end = reader.stream_position()? + size;
+        if boxtype == BOX_TRACK {
+            // parse TrackBox
+            parse_track(&mut reader, size, mp4)?;
+        } else if boxtype == BOX_USERDATA {
+            // parse UserDataBox
+            parse_userdata(&mut reader, size, mp4)?;
+        } else if boxtype == BOX_MOVIEEXTENDS {
+            // parse nested TrackExtendsBoxes
+            while reader.stream_position()? < box_end {
+                let (boxtype, size) = read_box(&mut reader)?;
+                let pos = reader.stream_position()?;
+                if boxtype == BOX_TRACKEXTENDS {
+                    mp4.trex.push(parse_trackextends(&mut reader)?);
+                }
+                reader.seek(SeekFrom::Start(pos + size))?;
+            }
+        }
+        reader.seek(SeekFrom::Start(box_end))?;
+    }
+    Ok(())
+}
+
 ///
 /// Sample
 ///
@@ -407,6 +1247,9 @@ fn parse_track<R: io::Read + io::Seek>(
 pub struct Sample {
     pub pos: u64,
     pub size: u64,
+    pub dts: i64,      // decoding time [timescale]
+    pub pts: i64,      // composition time [timescale]
+    pub is_sync: bool, // sync sample
 }
 
 ///
@@ -415,8 +1258,21 @@ pub struct Sample {
 #[derive(Debug)]
 pub struct IsoBmff {
     filetype: FileTypeBox,
-    av1config: Option<(AV1SampleEntry, AV1CodecConfigurationBox)>,
+    av1config: Option<(AV1SampleEntry, Vec<u8>)>, // (AV1SampleEntry, av1C payload)
+    protection: Option<ProtectionInfo>,
+    aux_layout: SampleAuxInfoLayout,
+    handler: FCC,
+    tracks: Vec<TrackInfo>,
+    select_track: Option<u32>,
+    trackheader: Option<TrackHeaderBox>,
+    timescale: u32,
     samples: Vec<Sample>,
+    tags: Vec<(FCC, String)>,
+    trex: Vec<TrackExtendsBox>,
+    fragment_dts: i64, // decoding time of next fragmented sample
+    parsed_pos: u64,   // position of next unparsed top-level Box
+    recovery: bool,
+    skipped: Vec<(u64, u64)>,
 }
 
 impl IsoBmff {
@@ -424,7 +1280,20 @@ impl IsoBmff {
         IsoBmff {
             filetype,
             av1config: None,
+            protection: None,
+            aux_layout: SampleAuxInfoLayout::default(),
+            handler: FCC::default(),
+            tracks: Vec::new(),
+            select_track: None,
+            trackheader: None,
+            timescale: 0,
             samples: Vec::new(),
+            tags: Vec::new(),
+            trex: Vec::new(),
+            fragment_dts: 0,
+            parsed_pos: 0,
+            recovery: false,
+            skipped: Vec::new(),
         }
     }
 
@@ -434,43 +1303,817 @@ impl IsoBmff {
     }
 
     /// get (AV1SampleEntry, AV1CodecConfigurationBox)
-    pub fn get_av1config(&self) -> Option<&(AV1SampleEntry, AV1CodecConfigurationBox)> {
-        self.av1config.as_ref()
+    pub fn get_av1config(&self) -> Option<(&AV1SampleEntry, AV1CodecConfigurationBox<'_>)> {
+        // av1C payload has been validated in parse_sampledescription()
+        let (av1se, payload) = self.av1config.as_ref()?;
+        read_av1codecconfig(payload)
+            .ok()
+            .map(|av1cc| (av1se, av1cc))
+    }
+
+    /// get protection scheme of encrypted 'av01' track
+    pub fn get_protection(&self) -> Option<&ProtectionInfo> {
+        self.protection.as_ref()
+    }
+
+    /// get sample auxiliary information layout of encrypted 'av01' track
+    pub fn get_aux_layout(&self) -> &SampleAuxInfoLayout {
+        &self.aux_layout
+    }
+
+    /// get summary of all tracks
+    pub fn get_tracks(&self) -> &Vec<TrackInfo> {
+        &self.tracks
+    }
+
+    /// get handler_type of 'av01' track ('vide' or 'pict')
+    pub fn get_handler(&self) -> FCC {
+        self.handler
+    }
+
+    /// get TrackHeaderBox of 'av01' track
+    pub fn get_trackheader(&self) -> Option<&TrackHeaderBox> {
+        self.trackheader.as_ref()
+    }
+
+    /// get timescale of 'av01' track
+    pub fn get_timescale(&self) -> u32 {
+        self.timescale
+    }
+
+    /// get metadata tags in udta, e.g. ('\u{a9}too', encoder name)
+    pub fn get_tags(&self) -> &Vec<(FCC, String)> {
+        &self.tags
     }
 
     /// get 'av01' Samples
     pub fn get_samples(&self) -> &Vec<Sample> {
         &self.samples
     }
+
+    /// check whether movie has MovieExtendsBox (fragmented MP4)
+    pub fn is_fragmented(&self) -> bool {
+        !self.trex.is_empty()
+    }
+
+    /// get skipped malformed regions (start, end) in recovery mode
+    pub fn get_skipped(&self) -> &Vec<(u64, u64)> {
+        &self.skipped
+    }
+
+    /// skip malformed region from `pos`, return position of next plausible Box
+    fn resync<R: io::Read + io::Seek>(
+        &mut self,
+        reader: R,
+        pos: u64,
+        stream_len: u64,
+    ) -> io::Result<Option<u64>> {
+        let next_pos = resync_box(reader, pos + 1, stream_len)?;
+        self.skipped.push((pos, next_pos.unwrap_or(stream_len)));
+        Ok(next_pos)
+    }
+
+    /// get position of next unparsed top-level Box
+    pub fn get_parsed_pos(&self) -> u64 {
+        self.parsed_pos
+    }
+
+    ///
+    /// parse complete top-level Boxes from `pos` and append new Samples,
+    /// stop before incomplete Box (e.g. file still being written)
+    ///
+    /// return position of next unparsed top-level Box.
+    ///
+    pub fn parse_boxes<R: io::Read + io::Seek>(
+        &mut self,
+        mut reader: R,
+        mut pos: u64,
+    ) -> io::Result<u64> {
+        let stream_len = reader.seek(SeekFrom::End(0))?;
+        while pos + 8 <= stream_len {
+            reader.seek(SeekFrom::Start(pos))?;
+            let (boxtype, size) = match read_box(&mut reader) {
+                Ok(result) => result,
+                Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(ref err) if self.recovery && err.kind() == io::ErrorKind::InvalidData => {
+                    // invalid Box size
+                    match self.resync(&mut reader, pos, stream_len)? {
+                        Some(next_pos) => {
+                            pos = next_pos;
+                            continue;
+                        }
+                        None => break,
+                    }
+                }
+                Err(err) => return Err(err),
+            };
+            let box_end = reader.stream_position()? + size;
+            if box_end > stream_len {
+                if self.recovery && !TOPLEVEL_BOXES.iter().any(|fcc| boxtype == *fcc) {
+                    // unknown Box type with absurd length
+                    if let Some(next_pos) = self.resync(&mut reader, pos, stream_len)? {
+                        pos = next_pos;
+                        continue;
+                    }
+                }
+                break;
+            }
+            let result = if boxtype == BOX_MOVIE {
+                parse_movie(&mut reader, size, self)
+            } else if boxtype == BOX_MOVIEFRAGMENT {
+                let (nsample, fragment_dts) = (self.samples.len(), self.fragment_dts);
+                let result = parse_moviefragment(&mut reader, size, pos, self);
+                if self.samples[nsample..]
+                    .iter()
+                    .any(|sample| sample.pos + sample.size > stream_len)
+                {
+                    // MediaDataBox for this fragment is not written yet
+                    self.samples.truncate(nsample);
+                    self.fragment_dts = fragment_dts;
+                    break;
+                }
+                let track_id = self.trackheader.as_ref().map_or(0, |tkhd| tkhd.track_id);
+                let nfragment = (self.samples.len() - nsample) as u64;
+                if let Some(track) = self.tracks.iter_mut().find(|t| t.track_id == track_id) {
+                    track.sample_count += nfragment;
+                }
+                result
+            } else if boxtype == BOX_USERDATA {
+                parse_userdata(&mut reader, size, self)
+            } else {
+                Ok(())
+            };
+            match result {
+                Ok(()) => {}
+                Err(ref err)
+                    if self.recovery
+                        && (err.kind() == io::ErrorKind::InvalidData
+                            || err.kind() == io::ErrorKind::UnexpectedEof) =>
+                {
+                    // skip malformed Box contents
+                    self.skipped.push((pos, box_end));
+                }
+                Err(err) => return Err(err),
+            }
+            pos = box_end;
+        }
+        self.parsed_pos = pos;
+        Ok(pos)
+    }
+}
+
+///
+/// options for opening ISOBMFF/MP4 file
+///
+#[derive(Debug, Default, Clone)]
+pub struct Mp4Options {
+    pub track_id: Option<u32>, // select 'av01' track by track_ID
+    pub recovery: bool,        // resynchronize after malformed Boxes
 }
 
 ///
 /// open ISOBMFF/MP4 file
 ///
-pub fn open_mp4file<R: io::Read + io::Seek>(mut reader: R) -> io::Result<IsoBmff> {
+pub fn open_mp4file<R: io::Read + io::Seek>(reader: R) -> io::Result<IsoBmff> {
+    open_mp4file_with(reader, &Mp4Options::default())
+}
+
+///
+/// open ISOBMFF/MP4 file with options
+///
+pub fn open_mp4file_with<R: io::Read + io::Seek>(
+    mut reader: R,
+    options: &Mp4Options,
+) -> io::Result<IsoBmff> {
     // read FileTypeBox
-    let ftyp_box = read_ftypbox(&mut reader)?;
+    let ftyp_box = read_leading_ftypbox(&mut reader)?;
     let mut mp4 = IsoBmff::new(ftyp_box);
-    loop {
-        // read next Box
-        let (boxtype, size) = match read_box(&mut reader) {
-            Ok(result) => result,
-            Err(err) => {
-                if err.kind() == io::ErrorKind::UnexpectedEof {
-                    break;
-                } else {
-                    return Err(err);
+    mp4.select_track = options.track_id;
+    mp4.recovery = options.recovery;
+    let pos = reader.stream_position()?;
+    mp4.parse_boxes(&mut reader, pos)?;
+    Ok(mp4)
+}
+
+///
+/// check whether file starts with media segment Box ('styp' or 'moof') instead of 'ftyp'
+///
+pub fn is_media_segment<R: io::Read + io::Seek>(mut reader: R) -> io::Result<bool> {
+    let pos = reader.stream_position()?;
+    let mut b8 = [0; 8];
+    reader.read_exact(&mut b8)?;
+    reader.seek(SeekFrom::Start(pos))?;
+    Ok(b8[4..] == BOX_SEGMENTTYPE || b8[4..] == BOX_MOVIEFRAGMENT)
+}
+
+///
+/// open ISOBMFF/MP4 media segment with its initialization segment
+///
+pub fn open_mp4segment_with<R: io::Read + io::Seek, S: io::Read + io::Seek>(
+    init: R,
+    segment: S,
+    options: &Mp4Options,
+) -> io::Result<IsoBmff> {
+    let mut mp4 = open_mp4file_with(init, options)?;
+    mp4.parse_boxes(segment, 0)?;
+    Ok(mp4)
+}
+
+// known top-level Box types for resynchronization and format probing
+pub const TOPLEVEL_BOXES: [[u8; 4]; 13] = [
+    BOX_FILETYPE,
+    BOX_MEDIADATA,
+    BOX_MOVIE,
+    BOX_MOVIEFRAGMENT,
+    BOX_USERDATA,
+    BOX_META,
+    *b"free",
+    *b"skip",
+    BOX_SEGMENTTYPE,
+    *b"sidx",
+    *b"mfra",
+    *b"uuid",
+    *b"wide",
+];
+
+///
+/// AV1 image item in AVIF file
+///
+#[derive(Debug, Default)]
+pub struct AvifImage {
+    pub item_id: u32,               // pitm
+    pub width: u32,                 // ispe
+    pub height: u32,                // ispe
+    pub av1config: Option<Vec<u8>>, // av1C payload
+    pub extents: Vec<(u64, u64)>,   // iloc (extent_offset, extent_length)
+}
+
+impl AvifImage {
+    /// get AV1CodecConfigurationBox in av1C property
+    pub fn get_av1config(&self) -> Option<AV1CodecConfigurationBox<'_>> {
+        // av1C payload has been validated in parse_avifmeta()
+        let payload = self.av1config.as_ref()?;
+        read_av1codecconfig(payload).ok()
+    }
+}
+
+/// read unsigned integer of `size` bytes (0, 4 or 8)
+fn read_uint<R: io::Read>(reader: R, size: u8) -> io::Result<u64> {
+    match size {
+        0 => Ok(0),
+        4 => read_u32(reader).map(|v| v as u64),
+        8 => read_u64(reader),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid field size={}", size),
+        )),
+    }
+}
+
+/// parse ItemLocationBox payload, return extents of the item
+fn parse_itemlocation<R: io::Read>(mut reader: R, item_id: u32) -> io::Result<Vec<(u64, u64)>> {
+    let version = (read_u32(&mut reader)? >> 24) as u8;
+    let sizes = read_u16(&mut reader)?;
+    let (offset_size, length_size) = ((sizes >> 12) as u8, (sizes >> 8 & 0xf) as u8);
+    let base_offset_size = (sizes >> 4 & 0xf) as u8;
+    let index_size = if version > 0 { (sizes & 0xf) as u8 } else { 0 };
+    let item_count = if version < 2 {
+        read_u16(&mut reader)? as u32
+    } else {
+        read_u32(&mut reader)?
+    };
+    for _ in 0..item_count {
+        let id = if version < 2 {
+            read_u16(&mut reader)? as u32
+        } else {
+            read_u32(&mut reader)?
+        };
+        let construction_method = if version > 0 {
+            read_u16(&mut reader)? & 0xf
+        } else {
+            0
+        };
+        let _data_reference_index = read_u16(&mut reader)?;
+        let base_offset = read_uint(&mut reader, base_offset_size)?;
+        let extent_count = read_u16(&mut reader)?;
+        let mut extents = Vec::with_capacity(extent_count as usize);
+        for _ in 0..extent_count {
+            let _extent_index = read_uint(&mut reader, index_size)?;
+            let extent_offset = read_uint(&mut reader, offset_size)?;
+            let extent_length = read_uint(&mut reader, length_size)?;
+            extents.push((base_offset + extent_offset, extent_length));
+        }
+        if id == item_id {
+            if construction_method != 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unsupported construction_method={}", construction_method),
+                ));
+            }
+            return Ok(extents);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Item location not found(item_ID={})", item_id),
+    ))
+}
+
+/// parse ItemPropertyAssociationBox payload, return property indices (1-origin) of the item
+fn parse_itempropertyassociation<R: io::Read>(mut reader: R, item_id: u32) -> io::Result<Vec<u16>> {
+    let version_flags = read_u32(&mut reader)?;
+    let (version, flags) = ((version_flags >> 24) as u8, version_flags & 0xffffff);
+    let entry_count = read_u32(&mut reader)?;
+    let mut indices = Vec::new();
+    for _ in 0..entry_count {
+        let id = if version < 1 {
+            read_u16(&mut reader)? as u32
+        } else {
+            read_u32(&mut reader)?
+        };
+        let mut association_count = [0; 1];
+        reader.read_exact(&mut association_count)?;
+        for _ in 0..association_count[0] {
+            let property_index = if flags & 1 != 0 {
+                read_u16(&mut reader)? & 0x7fff
+            } else {
+                let mut b = [0; 1];
+                reader.read_exact(&mut b)?;
+                (b[0] & 0x7f) as u16
+            };
+            if id == item_id {
+                indices.push(property_index);
+            }
+        }
+    }
+    Ok(indices)
+}
+
+///
+/// read primary AV1 image item in AVIF file
+///
+pub fn read_avif<R: io::Read + io::Seek>(mut reader: R) -> io::Result<(FileTypeBox, AvifImage)> {
+    let ftyp_box = read_ftypbox(&mut reader)?;
+    let mut pos = reader.stream_position()?;
+    let stream_len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(pos))?;
+    while pos + 8 <= stream_len {
+        let (boxtype, size) = read_box(&mut reader)?;
+        let box_end = reader.stream_position()? + size;
+        if boxtype == BOX_META {
+            let _version_flags = read_u32(&mut reader)?;
+            return Ok((ftyp_box, parse_avifmeta(&mut reader, box_end)?));
+        }
+        pos = reader.seek(SeekFrom::Start(box_end))?;
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "MetaBox not found",
+    ))
+}
+
+/// parse MetaBox children for primary image item
+fn parse_avifmeta<R: io::Read + io::Seek>(mut reader: R, limit: u64) -> io::Result<AvifImage> {
+    let mut image = AvifImage::default();
+    let mut iloc = None;
+    let mut ipma = None;
+    let mut properties = Vec::new(); // (boxtype, payload) in ItemPropertyContainerBox
+    while reader.stream_position()? + 8 <= limit {
+        let (boxtype, size) = read_box(&mut reader)?;
+        let box_end = reader.stream_position()? + size;
+        let mut payload = Vec::new();
+        if boxtype == BOX_PRIMARYITEM {
+            let version = (read_u32(&mut reader)? >> 24) as u8;
+            image.item_id = if version == 0 {
+                read_u16(&mut reader)? as u32
+            } else {
+                read_u32(&mut reader)?
+            };
+        } else if boxtype == BOX_ITEMLOCATION {
+            (&mut reader).take(size).read_to_end(&mut payload)?;
+            iloc = Some(payload);
+        } else if boxtype == BOX_ITEMPROPERTIES {
+            while reader.stream_position()? + 8 <= box_end {
+                let (boxtype, size) = read_box(&mut reader)?;
+                let child_end = reader.stream_position()? + size;
+                if boxtype == BOX_ITEMPROPERTYCONTAINER {
+                    while reader.stream_position()? + 8 <= child_end {
+                        let (boxtype, size) = read_box(&mut reader)?;
+                        let mut payload = Vec::new();
+                        (&mut reader).take(size).read_to_end(&mut payload)?;
+                        properties.push((boxtype, payload));
+                    }
+                } else if boxtype == BOX_ITEMPROPERTYASSOCIATION {
+                    let mut payload = Vec::new();
+                    (&mut reader).take(size).read_to_end(&mut payload)?;
+                    ipma = Some(payload);
                 }
+                reader.seek(SeekFrom::Start(child_end))?;
             }
+        }
+        reader.seek(SeekFrom::Start(box_end))?;
+    }
+
+    if let Some(iloc) = iloc {
+        image.extents = parse_itemlocation(&iloc[..], image.item_id)?;
+    }
+    let indices = match ipma {
+        Some(ipma) => parse_itempropertyassociation(&ipma[..], image.item_id)?,
+        None => Vec::new(),
+    };
+    for index in indices {
+        let (boxtype, payload) = match properties.get_mut((index as usize).wrapping_sub(1)) {
+            Some(property) => property,
+            None => continue,
         };
-        if boxtype == BOX_MOVIE {
-            // parse nested Boxes
-        } else if boxtype == BOX_TRACK {
-            // parse TrackBox
-            parse_track(&mut reader, size, &mut mp4)?;
-        } else {
-            reader.seek(SeekFrom::Current(size as i64))?;
+        if *boxtype == BOX_IMAGESPATIALEXTENTS && payload.len() >= 12 {
+            image.width = BigEndian::read_u32(&payload[4..]);
+            image.height = BigEndian::read_u32(&payload[8..]);
+        } else if *boxtype == BOX_AV1CODECCONFIG {
+            read_av1codecconfig(payload)?;
+            image.av1config = Some(mem::take(payload));
         }
     }
-    Ok(mp4)
+    Ok(image)
+}
+
+/// scan forward for next plausible top-level Box header
+fn resync_box<R: io::Read + io::Seek>(
+    mut reader: R,
+    from: u64,
+    stream_len: u64,
+) -> io::Result<Option<u64>> {
+    let mut buf = vec![0; 64 * 1024];
+    let mut base = from;
+    while base + 8 <= stream_len {
+        let n = cmp::min(buf.len() as u64, stream_len - base) as usize;
+        reader.seek(SeekFrom::Start(base))?;
+        reader.read_exact(&mut buf[..n])?;
+        for i in 0..=(n - 8) {
+            let size = BigEndian::read_u32(&buf[i..]) as u64;
+            let boxtype = &buf[i + 4..i + 8];
+            let pos = base + i as u64;
+            if TOPLEVEL_BOXES.iter().any(|fcc| fcc == boxtype)
+                && (size == 1 || (8 <= size && pos + size <= stream_len))
+            {
+                return Ok(Some(pos));
+            }
+        }
+        if n < buf.len() {
+            break;
+        }
+        base += (n - 7) as u64;
+    }
+    Ok(None)
+}
+
+/// append Box into buffer
+fn write_box(buf: &mut Vec<u8>, boxtype: [u8; 4], payload: &[u8]) {
+    buf.extend_from_slice(&(8 + payload.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&boxtype);
+    buf.extend_from_slice(payload);
+}
+
+/// append FullBox into buffer
+fn write_fullbox(buf: &mut Vec<u8>, boxtype: [u8; 4], version: u8, flags: u32, payload: &[u8]) {
+    buf.extend_from_slice(&(12 + payload.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&boxtype);
+    buf.extend_from_slice(&((version as u32) << 24 | flags).to_be_bytes());
+    buf.extend_from_slice(payload);
+}
+
+///
+/// write AVIF file with single AV1 image item (AV1 Image File Format)
+///
+/// `data` is AV1 Image Item Data, i.e. OBUs of sync sample without TemporalDelimiter OBU.
+///
+pub fn write_avif<W: io::Write>(
+    mut writer: W,
+    width: u32,
+    height: u32,
+    av1cc: &AV1CodecConfigurationBox,
+    data: &[u8],
+) -> io::Result<()> {
+    const ITEM_ID: u16 = 1;
+
+    let mut ftyp = Vec::new();
+    ftyp.extend_from_slice(&BRAND_AVIF); // major_brand
+    ftyp.extend_from_slice(&0u32.to_be_bytes()); // minor_version
+    for brand in &[BRAND_AVIF, BRAND_MIF1, BRAND_MIAF] {
+        ftyp.extend_from_slice(brand); // compatible_brands[]
+    }
+    let mut buf = Vec::new();
+    write_box(&mut buf, BOX_FILETYPE, &ftyp);
+
+    // MetaBox (item data offset depends on MetaBox size)
+    let build_meta = |offset: u32| {
+        let mut meta = Vec::new();
+        {
+            let mut hdlr = Vec::new();
+            hdlr.extend_from_slice(&0u32.to_be_bytes()); // pre_defined
+            hdlr.extend_from_slice(&HANDLER_PICTURE); // handler_type
+            hdlr.extend_from_slice(&[0; 12]); // reserved
+            hdlr.extend_from_slice(b"\0"); // name
+            write_fullbox(&mut meta, BOX_HANDLER, 0, 0, &hdlr);
+        }
+        write_fullbox(&mut meta, BOX_PRIMARYITEM, 0, 0, &ITEM_ID.to_be_bytes());
+        {
+            let mut iloc = Vec::new();
+            iloc.push(0x44); // offset_size=4, length_size=4
+            iloc.push(0x00); // base_offset_size=0, reserved
+            iloc.extend_from_slice(&1u16.to_be_bytes()); // item_count
+            iloc.extend_from_slice(&ITEM_ID.to_be_bytes());
+            iloc.extend_from_slice(&0u16.to_be_bytes()); // data_reference_index
+            iloc.extend_from_slice(&1u16.to_be_bytes()); // extent_count
+            iloc.extend_from_slice(&offset.to_be_bytes()); // extent_offset
+            iloc.extend_from_slice(&(data.len() as u32).to_be_bytes()); // extent_length
+            write_fullbox(&mut meta, BOX_ITEMLOCATION, 0, 0, &iloc);
+        }
+        {
+            let mut infe = Vec::new();
+            infe.extend_from_slice(&ITEM_ID.to_be_bytes());
+            infe.extend_from_slice(&0u16.to_be_bytes()); // item_protection_index
+            infe.extend_from_slice(&BRAND_AV01); // item_type
+            infe.extend_from_slice(b"\0"); // item_name
+            let mut iinf = Vec::new();
+            iinf.extend_from_slice(&1u16.to_be_bytes()); // entry_count
+            write_fullbox(&mut iinf, BOX_ITEMINFOENTRY, 2, 0, &infe);
+            write_fullbox(&mut meta, BOX_ITEMINFO, 0, 0, &iinf);
+        }
+        {
+            let mut ipco = Vec::new();
+            let mut ispe = Vec::new();
+            ispe.extend_from_slice(&width.to_be_bytes()); // image_width
+            ispe.extend_from_slice(&height.to_be_bytes()); // image_height
+            write_fullbox(&mut ipco, BOX_IMAGESPATIALEXTENTS, 0, 0, &ispe);
+            write_box(&mut ipco, BOX_AV1CODECCONFIG, &av1cc.to_bytes());
+            let bit_depth = 8 + 2 * av1cc.high_bitdepth + 2 * av1cc.twelve_bit;
+            let num_channels = if av1cc.monochrome == 1 { 1 } else { 3 };
+            let mut pixi = vec![num_channels];
+            for _ in 0..num_channels {
+                pixi.push(bit_depth); // bits_per_channel
+            }
+            write_fullbox(&mut ipco, BOX_PIXELINFORMATION, 0, 0, &pixi);
+            let mut ipma = Vec::new();
+            ipma.extend_from_slice(&1u32.to_be_bytes()); // entry_count
+            ipma.extend_from_slice(&ITEM_ID.to_be_bytes());
+            ipma.push(3); // association_count
+            ipma.push(1); // ispe
+            ipma.push(0x80 | 2); // av1C (essential)
+            ipma.push(3); // pixi
+            let mut iprp = Vec::new();
+            write_box(&mut iprp, BOX_ITEMPROPERTYCONTAINER, &ipco);
+            write_fullbox(&mut iprp, BOX_ITEMPROPERTYASSOCIATION, 0, 0, &ipma);
+            write_box(&mut meta, BOX_ITEMPROPERTIES, &iprp);
+        }
+        meta
+    };
+    let meta_len = build_meta(0).len() + 12;
+    let offset = buf.len() + meta_len + 8;
+    write_fullbox(&mut buf, BOX_META, 0, 0, &build_meta(offset as u32));
+    write_box(&mut buf, BOX_MEDIADATA, data);
+    writer.write_all(&buf)?;
+    writer.flush()
+}
+
+const UNITY_MATRIX: [u32; 9] = [0x10000, 0, 0, 0, 0x10000, 0, 0, 0, 0x4000_0000];
+
+///
+/// ISOBMFF/MP4 writer (progressive MP4 with single 'av01' track)
+///
+pub struct Mp4Writer<W> {
+    writer: W,
+    mdat_pos: u64,
+    timescale: u32,
+    samples: Vec<(u32, u64, bool)>, // (size, dts, is_sync)
+}
+
+impl<W: io::Write + io::Seek> Mp4Writer<W> {
+    /// create MP4 writer, write FileTypeBox and MediaDataBox header
+    pub fn new(mut writer: W, timescale: u32) -> io::Result<Self> {
+        let mut buf = Vec::new();
+        let mut ftyp = Vec::new();
+        ftyp.extend_from_slice(&BRAND_ISOM); // major_brand
+        ftyp.extend_from_slice(&0x200u32.to_be_bytes()); // minor_version
+        for brand in &[BRAND_ISOM, BRAND_AV01, BRAND_ISO2, BRAND_MP41] {
+            ftyp.extend_from_slice(brand); // compatible_brands[]
+        }
+        write_box(&mut buf, BOX_FILETYPE, &ftyp);
+        writer.write_all(&buf)?;
+        let mdat_pos = writer.stream_position()?;
+        // MediaDataBox with largesize, patched at finish()
+        writer.write_all(&1u32.to_be_bytes())?;
+        writer.write_all(&BOX_MEDIADATA)?;
+        writer.write_all(&0u64.to_be_bytes())?;
+        Ok(Mp4Writer {
+            writer,
+            mdat_pos,
+            timescale,
+            samples: Vec::new(),
+        })
+    }
+
+    /// write Sample into MediaDataBox
+    pub fn write_sample(&mut self, data: &[u8], dts: u64, is_sync: bool) -> io::Result<()> {
+        self.writer.write_all(data)?;
+        self.samples.push((data.len() as u32, dts, is_sync));
+        Ok(())
+    }
+
+    /// finish MediaDataBox and write MovieBox
+    pub fn finish(
+        mut self,
+        width: u16,
+        height: u16,
+        av1cc: &AV1CodecConfigurationBox,
+    ) -> io::Result<W> {
+        let mdat_end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.mdat_pos + 8))?;
+        self.writer
+            .write_all(&(mdat_end - self.mdat_pos).to_be_bytes())?;
+        self.writer.seek(SeekFrom::Start(mdat_end))?;
+
+        let moov = self.build_moov(width, height, av1cc);
+        self.writer.write_all(&moov)?;
+        Ok(self.writer)
+    }
+
+    /// calculate sample durations
+    fn durations(&self) -> Vec<u32> {
+        let mut durations: Vec<u32> = self
+            .samples
+            .windows(2)
+            .map(|w| w[1].1.saturating_sub(w[0].1) as u32)
+            .collect();
+        if !self.samples.is_empty() {
+            // duration of last sample is unknown, assume same as previous one
+            durations.push(*durations.last().unwrap_or(&1));
+        }
+        durations
+    }
+
+    fn build_moov(&self, width: u16, height: u16, av1cc: &AV1CodecConfigurationBox) -> Vec<u8> {
+        let durations = self.durations();
+        let duration: u64 = durations.iter().map(|&d| d as u64).sum();
+        let chunk_offset = self.mdat_pos + 16;
+
+        // SampleTableBox
+        let mut stbl = Vec::new();
+        {
+            // AV1SampleEntry
+            let mut av01 = Vec::new();
+            av01.extend_from_slice(&[0; 6]); // reserved
+            av01.extend_from_slice(&1u16.to_be_bytes()); // data_reference_index
+            av01.extend_from_slice(&[0; 16]); // pre_defined, reserved
+            av01.extend_from_slice(&width.to_be_bytes());
+            av01.extend_from_slice(&height.to_be_bytes());
+            av01.extend_from_slice(&0x0048_0000u32.to_be_bytes()); // horizresolution
+            av01.extend_from_slice(&0x0048_0000u32.to_be_bytes()); // vertresolution
+            av01.extend_from_slice(&0u32.to_be_bytes()); // reserved
+            av01.extend_from_slice(&1u16.to_be_bytes()); // frame_count
+            av01.extend_from_slice(&[0; 32]); // compressorname
+            av01.extend_from_slice(&0x0018u16.to_be_bytes()); // depth
+            av01.extend_from_slice(&0xffffu16.to_be_bytes()); // pre_defined
+            write_box(&mut av01, BOX_AV1CODECCONFIG, &av1cc.to_bytes());
+            let mut stsd = Vec::new();
+            stsd.extend_from_slice(&1u32.to_be_bytes()); // entry_count
+            write_box(&mut stsd, BOX_AV1SAMPLEENTRY, &av01);
+            write_fullbox(&mut stbl, BOX_SAMPLEDESCRIPTION, 0, 0, &stsd);
+        }
+        {
+            // TimeToSampleBox
+            let mut entries: Vec<(u32, u32)> = Vec::new();
+            for &delta in &durations {
+                match entries.last_mut() {
+                    Some(entry) if entry.1 == delta => entry.0 += 1,
+                    _ => entries.push((1, delta)),
+                }
+            }
+            let mut stts = Vec::new();
+            stts.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+            for (sample_count, sample_delta) in entries {
+                stts.extend_from_slice(&sample_count.to_be_bytes());
+                stts.extend_from_slice(&sample_delta.to_be_bytes());
+            }
+            write_fullbox(&mut stbl, BOX_TIMETOSAMPLE, 0, 0, &stts);
+        }
+        if self.samples.iter().any(|s| !s.2) {
+            // SyncSampleBox
+            let sync_samples: Vec<u32> = (1..=self.samples.len() as u32)
+                .filter(|&n| self.samples[n as usize - 1].2)
+                .collect();
+            let mut stss = Vec::new();
+            stss.extend_from_slice(&(sync_samples.len() as u32).to_be_bytes());
+            for sample_number in sync_samples {
+                stss.extend_from_slice(&sample_number.to_be_bytes());
+            }
+            write_fullbox(&mut stbl, BOX_SYNCSAMPLE, 0, 0, &stss);
+        }
+        {
+            // SampleToChunkBox (all samples in single chunk)
+            let mut stsc = Vec::new();
+            stsc.extend_from_slice(&1u32.to_be_bytes()); // entry_count
+            stsc.extend_from_slice(&1u32.to_be_bytes()); // first_chunk
+            stsc.extend_from_slice(&(self.samples.len() as u32).to_be_bytes());
+            stsc.extend_from_slice(&1u32.to_be_bytes()); // sample_description_index
+            write_fullbox(&mut stbl, BOX_SAMPLETOCHUNK, 0, 0, &stsc);
+        }
+        {
+            // SampleSizeBox
+            let mut stsz = Vec::new();
+            stsz.extend_from_slice(&0u32.to_be_bytes()); // sample_size
+            stsz.extend_from_slice(&(self.samples.len() as u32).to_be_bytes());
+            for sample in &self.samples {
+                stsz.extend_from_slice(&sample.0.to_be_bytes());
+            }
+            write_fullbox(&mut stbl, BOX_SAMPLESIZE, 0, 0, &stsz);
+        }
+        {
+            // ChunkOffsetBox/ChunkLargeOffsetBox
+            let mut stco = Vec::new();
+            stco.extend_from_slice(&1u32.to_be_bytes()); // entry_count
+            if chunk_offset <= u32::MAX as u64 {
+                stco.extend_from_slice(&(chunk_offset as u32).to_be_bytes());
+                write_fullbox(&mut stbl, BOX_CHUNKOFFSET, 0, 0, &stco);
+            } else {
+                stco.extend_from_slice(&chunk_offset.to_be_bytes());
+                write_fullbox(&mut stbl, BOX_CHUNKOFFSET64, 0, 0, &stco);
+            }
+        }
+
+        // MediaInformationBox
+        let mut minf = Vec::new();
+        write_fullbox(&mut minf, BOX_VIDEOMEDIAHEADER, 0, 1, &[0; 8]);
+        {
+            let mut dref = Vec::new();
+            dref.extend_from_slice(&1u32.to_be_bytes()); // entry_count
+            write_fullbox(&mut dref, BOX_DATAENTRYURL, 0, 1, &[]); // self-contained
+            let mut dinf = Vec::new();
+            write_fullbox(&mut dinf, BOX_DATAREFERENCE, 0, 0, &dref);
+            write_box(&mut minf, BOX_DATAINFORMATION, &dinf);
+        }
+        write_box(&mut minf, BOX_SAMPLETABLE, &stbl);
+
+        // MediaBox
+        let mut mdia = Vec::new();
+        {
+            let mut mdhd = Vec::new();
+            mdhd.extend_from_slice(&[0; 8]); // creation_time, modification_time
+            mdhd.extend_from_slice(&self.timescale.to_be_bytes());
+            mdhd.extend_from_slice(&(duration as u32).to_be_bytes());
+            mdhd.extend_from_slice(&0x55c4u16.to_be_bytes()); // language='und'
+            mdhd.extend_from_slice(&0u16.to_be_bytes()); // pre_defined
+            write_fullbox(&mut mdia, BOX_MEDIAHEADER, 0, 0, &mdhd);
+            let mut hdlr = Vec::new();
+            hdlr.extend_from_slice(&0u32.to_be_bytes()); // pre_defined
+            hdlr.extend_from_slice(&HANDLER_VIDEO); // handler_type
+            hdlr.extend_from_slice(&[0; 12]); // reserved
+            hdlr.extend_from_slice(b"VideoHandler\0"); // name
+            write_fullbox(&mut mdia, BOX_HANDLER, 0, 0, &hdlr);
+        }
+        write_box(&mut mdia, BOX_MEDIAINFORMATION, &minf);
+
+        // TrackBox
+        let mut trak = Vec::new();
+        {
+            let mut tkhd = Vec::new();
+            tkhd.extend_from_slice(&[0; 8]); // creation_time, modification_time
+            tkhd.extend_from_slice(&1u32.to_be_bytes()); // track_ID
+            tkhd.extend_from_slice(&0u32.to_be_bytes()); // reserved
+            tkhd.extend_from_slice(&(duration as u32).to_be_bytes());
+            tkhd.extend_from_slice(&[0; 8]); // reserved
+            tkhd.extend_from_slice(&[0; 8]); // layer, alternate_group, volume, reserved
+            for m in &UNITY_MATRIX {
+                tkhd.extend_from_slice(&m.to_be_bytes());
+            }
+            tkhd.extend_from_slice(&((width as u32) << 16).to_be_bytes());
+            tkhd.extend_from_slice(&((height as u32) << 16).to_be_bytes());
+            // flags: track_enabled | track_in_movie
+            write_fullbox(&mut trak, BOX_TRACKHEADER, 0, 3, &tkhd);
+        }
+        write_box(&mut trak, BOX_MEDIA, &mdia);
+
+        // MovieBox
+        let mut moov = Vec::new();
+        {
+            let mut mvhd = Vec::new();
+            mvhd.extend_from_slice(&[0; 8]); // creation_time, modification_time
+            mvhd.extend_from_slice(&self.timescale.to_be_bytes());
+            mvhd.extend_from_slice(&(duration as u32).to_be_bytes());
+            mvhd.extend_from_slice(&0x0001_0000u32.to_be_bytes()); // rate
+            mvhd.extend_from_slice(&0x0100u16.to_be_bytes()); // volume
+            mvhd.extend_from_slice(&[0; 10]); // reserved
+            for m in &UNITY_MATRIX {
+                mvhd.extend_from_slice(&m.to_be_bytes());
+            }
+            mvhd.extend_from_slice(&[0; 24]); // pre_defined
+            mvhd.extend_from_slice(&2u32.to_be_bytes()); // next_track_ID
+            write_fullbox(&mut moov, BOX_MOVIEHEADER, 0, 0, &mvhd);
+        }
+        write_box(&mut moov, BOX_TRACK, &trak);
+
+        let mut buf = Vec::new();
+        write_box(&mut buf, BOX_MOVIE, &moov);
+        buf
+    }
 }
@@ -2,7 +2,8 @@
 // https://aomedia.org/av1-bitstream-and-decoding-process-specification/
 //
 use crate::av1;
-use crate::bitio::BitReader;
+use crate::bitio::{BitReader, BitWriter};
+use crate::conformance;
 use std::cmp;
 use std::fmt;
 use std::io;
@@ -39,7 +40,29 @@ const SUPERRES_NUM: usize = 8; // Numerator for upscaling ratio
 const SUPERRES_DENOM_MIN: usize = 9; // Smallest denominator for upscaling ratio
 const SUPERRS_DENOM_BITS: usize = 3; // Number of bits sent to specify denominator of upscaling ratio
 const MAX_LOOP_FILTER: i32 = 63; // Maximum value used for loop filtering
-const WARPEDMODEL_PREC_BITS: usize = 16; // Internal precision of warped motion models
+#[allow(non_upper_case_globals)]
+const Remap_Lr_Type: [u8; 4] = [
+    RESTORE_NONE,
+    RESTORE_SWITCHABLE,
+    RESTORE_WIENER,
+    RESTORE_SGRPROJ,
+];
+#[allow(non_upper_case_globals)]
+const Segmentation_Feature_Bits: [usize; SEG_LVL_MAX] = [8, 6, 6, 6, 6, 3, 0, 0];
+#[allow(non_upper_case_globals)]
+const Segmentation_Feature_Signed: [usize; SEG_LVL_MAX] = [1, 1, 1, 1, 1, 0, 0, 0];
+#[allow(non_upper_case_globals)]
+const Segmentation_Feature_Max: [i32; SEG_LVL_MAX] = [
+    255,
+    MAX_LOOP_FILTER,
+    MAX_LOOP_FILTER,
+    MAX_LOOP_FILTER,
+    MAX_LOOP_FILTER,
+    7,
+    0,
+    0,
+];
+pub const WARPEDMODEL_PREC_BITS: usize = 16; // Internal precision of warped motion models
 const GM_ABS_TRANS_BITS: usize = 12; // Number of bits encoded for translational components of global motion models, if part of a ROTZOOM or AFFINE model
 const GM_ABS_TRANS_ONLY_BITS: usize = 9; // Number of bits encoded for translational components of global motion models, if part of a TRANSLATION model
 const GM_ABS_ALPHA_BITS: usize = 12; // Number of bits encoded for non-translational components of global motion models
@@ -69,34 +92,44 @@ pub const INTRA_ONLY_FRAME: u8 = 2;
 pub const SWITCH_FRAME: u8 = 3;
 
 // interpolation_filter
-const SWITCHABLE: u8 = 4;
+pub const EIGHTTAP: u8 = 0;
+pub const EIGHTTAP_SMOOTH: u8 = 1;
+pub const EIGHTTAP_SHARP: u8 = 2;
+pub const BILINEAR: u8 = 3;
+pub const SWITCHABLE: u8 = 4;
 
 // Loop restoration type (FrameRestorationType, not lr_type)
-const RESTORE_NONE: u8 = 0;
-const RESTORE_SWITCHABLE: u8 = 3;
-const RESTORE_WIENER: u8 = 1;
-const RESTORE_SGRPROJ: u8 = 2;
+pub const RESTORE_NONE: u8 = 0;
+pub const RESTORE_SWITCHABLE: u8 = 3;
+pub const RESTORE_WIENER: u8 = 1;
+pub const RESTORE_SGRPROJ: u8 = 2;
 
 // TxMode
-const ONLY_4X4: u8 = 0;
-const TX_MODE_LARGEST: u8 = 1;
-const TX_MODE_SELECT: u8 = 2;
+pub const ONLY_4X4: u8 = 0;
+pub const TX_MODE_LARGEST: u8 = 1;
+pub const TX_MODE_SELECT: u8 = 2;
 
-const IDENTITY: u8 = 0; // Warp model is just an identity transform
-const TRANSLATION: u8 = 1; // Warp model is a pure translation
-const ROTZOOM: u8 = 2; // Warp model is a rotation + symmetric zoom + translation
-const AFFINE: u8 = 3; // Warp model is a general afane transform
+pub const IDENTITY: u8 = 0; // Warp model is just an identity transform
+pub const TRANSLATION: u8 = 1; // Warp model is a pure translation
+pub const ROTZOOM: u8 = 2; // Warp model is a rotation + symmetric zoom + translation
+pub const AFFINE: u8 = 3; // Warp model is a general afane transform
 
 // OBU Metadata Type
-const METADATA_TYPE_HDR_CLL: u32 = 1;
-const METADATA_TYPE_HDR_MDCV: u32 = 2;
-const METADATA_TYPE_SCALABILITY: u32 = 3;
-const METADATA_TYPE_ITUT_T35: u32 = 4;
-const METADATA_TYPE_TIMECODE: u32 = 5;
+pub const METADATA_TYPE_HDR_CLL: u32 = 1;
+pub const METADATA_TYPE_HDR_MDCV: u32 = 2;
+pub const METADATA_TYPE_SCALABILITY: u32 = 3;
+pub const METADATA_TYPE_ITUT_T35: u32 = 4;
+pub const METADATA_TYPE_TIMECODE: u32 = 5;
 
 // scalability_mode_idc
 const SCALABILITY_SS: u8 = 14;
 
+// ITU-T T.35 for SMPTE ST 2094-40 (HDR10+)
+const T35_COUNTRY_CODE_USA: u8 = 0xB5;
+const T35_PROVIDER_CODE_SAMSUNG: u16 = 0x003C;
+const T35_PROVIDER_ORIENTED_CODE_ST2094_40: u16 = 0x0001;
+const ST2094_40_APPLICATION_IDENTIFIER: u8 = 4;
+
 ///
 /// OBU(Open Bitstream Unit)
 ///
@@ -114,20 +147,75 @@ pub struct Obu {
     pub header_len: u32,
 }
 
+impl Obu {
+    ///
+    /// OBU header for payload, with obu_size field and without obu_extension_header()
+    ///
+    pub fn new(obu_type: u8, obu_size: u32) -> Obu {
+        let mut obu = Obu {
+            obu_type,
+            obu_extension_flag: false,
+            obu_has_size_field: true,
+            temporal_id: 0,
+            spatial_id: 0,
+            obu_size: 0,
+            header_len: 0,
+        };
+        obu.set_obu_size(obu_size);
+        obu
+    }
+
+    /// update obu_size (and header_len) for edited payload
+    pub fn set_obu_size(&mut self, obu_size: u32) {
+        self.obu_size = obu_size;
+        self.header_len = 1 + self.obu_extension_flag as u32;
+        if self.obu_has_size_field {
+            self.header_len += leb128_size(obu_size);
+        }
+    }
+
+    ///
+    /// write obu_header(), obu_extension_header() and obu_size
+    ///
+    pub fn write_header<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut bw = BitWriter::new(w);
+        // obu_header()
+        bw.f(1, 0u8)?; // obu_forbidden_bit
+        bw.f(4, self.obu_type)?; // obu_type
+        bw.f(1, self.obu_extension_flag)?; // obu_extension_flag
+        bw.f(1, self.obu_has_size_field)?; // obu_has_size_field
+        bw.f(1, 0u8)?; // obu_reserved_1bit
+        if self.obu_extension_flag {
+            // obu_extension_header()
+            bw.f(3, self.temporal_id)?; // temporal_id
+            bw.f(2, self.spatial_id)?; // spatial_id
+            bw.f(3, 0u8)?; // extension_header_reserved_3bits
+        }
+        if self.obu_has_size_field {
+            bw.leb128(self.obu_size)?; // obu_size
+        }
+        Ok(())
+    }
+
+    ///
+    /// write OBU with payload, obu_size is recomputed from payload length
+    ///
+    pub fn write<W: io::Write>(&mut self, w: &mut W, payload: &[u8]) -> io::Result<()> {
+        if payload.len() > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "OBU payload exceeds leb128() range",
+            ));
+        }
+        self.set_obu_size(payload.len() as u32);
+        self.write_header(w)?;
+        w.write_all(payload)
+    }
+}
+
 impl fmt::Display for Obu {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let obu_type = match self.obu_type {
-            OBU_SEQUENCE_HEADER => "SEQUENCE_HEADER".to_owned(),
-            OBU_TEMPORAL_DELIMITER => "TEMPORAL_DELIMITER".to_owned(),
-            OBU_FRAME_HEADER => "FRAME_HEADER".to_owned(),
-            OBU_TILE_GROUP => "TILE_GROUP".to_owned(),
-            OBU_FRAME => "FRAME".to_owned(),
-            OBU_METADATA => "METADATA".to_owned(),
-            OBU_REDUNDANT_FRAME_HEADER => "REDUNDANT_FRAME_HEADER".to_owned(),
-            OBU_TILE_LIST => "TILE_LIST".to_owned(),
-            OBU_PADDING => "PADDING".to_owned(),
-            _ => format!("Reserved({})", self.obu_type), // Reserved
-        };
+        let obu_type = av1::stringify::obu_type(self.obu_type);
         if self.obu_extension_flag {
             write!(
                 f,
@@ -142,24 +230,25 @@ impl fmt::Display for Obu {
 }
 
 // Color config
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Serialize)]
 pub struct ColorConfig {
     pub bit_depth: u8,  // BitDepth
     pub num_planes: u8, // NumPlanes
     // color_config()
-    pub mono_chrome: bool,            // f(1)
-    pub color_primaries: u8,          // f(8)
-    pub transfer_characteristics: u8, // f(8)
-    pub matrix_coefficients: u8,      // f(8)
-    pub color_range: bool,            // f(1)
-    pub subsampling_x: u8,            // f(1)
-    pub subsampling_y: u8,            // f(1)
-    pub chroma_sample_position: u8,   // f(2)
-    pub separate_uv_delta_q: bool,    // f(1)
+    pub mono_chrome: bool,                    // f(1)
+    pub color_description_present_flag: bool, // f(1)
+    pub color_primaries: u8,                  // f(8)
+    pub transfer_characteristics: u8,         // f(8)
+    pub matrix_coefficients: u8,              // f(8)
+    pub color_range: bool,                    // f(1)
+    pub subsampling_x: u8,                    // f(1)
+    pub subsampling_y: u8,                    // f(1)
+    pub chroma_sample_position: u8,           // f(2)
+    pub separate_uv_delta_q: bool,            // f(1)
 }
 
 /// Timing info
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Serialize)]
 pub struct TimingInfo {
     // timing_info()
     pub num_units_in_display_tick: u32, // f(32)
@@ -168,20 +257,43 @@ pub struct TimingInfo {
     pub num_ticks_per_picture: u32,     // uvlc()
 }
 
+/// Decoder model info
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct DecoderModelInfo {
+    // decoder_model_info()
+    pub buffer_delay_length: u8, // f(5) buffer_delay_length_minus_1 + 1
+    pub num_units_in_decoding_tick: u32, // f(32)
+    pub buffer_removal_time_length: u8, // f(5) buffer_removal_time_length_minus_1 + 1
+    pub frame_presentation_time_length: u8, // f(5) frame_presentation_time_length_minus_1 + 1
+}
+
+/// Operating parameters info
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct OperatingParametersInfo {
+    // operating_parameters_info()
+    pub decoder_buffer_delay: u32, // f(n)
+    pub encoder_buffer_delay: u32, // f(n)
+    pub low_delay_mode_flag: bool, // f(1)
+}
+
 ///
 /// operating point in Sequence Header OBU
 ///
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Serialize)]
 pub struct OperatingPoint {
-    pub operating_point_idc: u16, // f(12)
-    pub seq_level_idx: u8,        // f(5)
-    pub seq_tier: u8,             // f(1)
+    pub operating_point_idc: u16,                           // f(12)
+    pub seq_level_idx: u8,                                  // f(5)
+    pub seq_tier: u8,                                       // f(1)
+    pub decoder_model_present_for_this_op: bool,            // f(1)
+    pub operating_parameters_info: OperatingParametersInfo, // operating_parameters_info()
+    pub initial_display_delay_present_for_this_op: bool,    // f(1)
+    pub initial_display_delay: u8, // f(4) initial_display_delay_minus_1 + 1
 }
 
 ///
 /// Sequence header OBU
 ///
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct SequenceHeader {
     pub seq_profile: u8,                          // f(3)
     pub still_picture: bool,                      // f(1)
@@ -189,9 +301,10 @@ pub struct SequenceHeader {
     pub timing_info_present_flag: bool,           // f(1)
     pub timing_info: TimingInfo,                  // timing_info()
     pub decoder_model_info_present_flag: bool,    // f(1)
+    pub decoder_model_info: DecoderModelInfo,     // decoder_model_info()
     pub initial_display_delay_present_flag: bool, // f(1)
     pub operating_points_cnt: u8,                 // f(5)
-    pub op: [OperatingPoint; 1],                  // OperatingPoint
+    pub op: Vec<OperatingPoint>,                  // OperatingPoint
     pub frame_width_bits: u8,                     // f(4)
     pub frame_height_bits: u8,                    // f(4)
     pub max_frame_width: u32,                     // f(n)
@@ -220,18 +333,19 @@ pub struct SequenceHeader {
 }
 
 /// Frame size
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct FrameSize {
     // frame_size()
     pub frame_width: u32,  // FrameWidth
     pub frame_height: u32, // FrameHeight
     // superres_params()
     pub use_superres: bool,  // f(1)
+    pub superres_denom: u8,  // SuperresDenom
     pub upscaled_width: u32, // UpscaledWidth
 }
 
 /// Render size
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct RenderSize {
     // render_size()
     pub render_width: u32,  // RenderWidth
@@ -239,28 +353,36 @@ pub struct RenderSize {
 }
 
 /// Loop filter params
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct LoopFilterParams {
     // loop_filter_params()
     pub loop_filter_level: [u8; 4],                          // f(6)
     pub loop_filter_sharpness: u8,                           // f(3)
     pub loop_filter_delta_enabled: bool,                     // f(1)
+    pub loop_filter_delta_update: bool,                      // f(1)
+    pub update_ref_delta: [bool; TOTAL_REFS_PER_FRAME],      // f(1)
     pub loop_filter_ref_deltas: [i32; TOTAL_REFS_PER_FRAME], // su(1+6)
+    pub update_mode_delta: [bool; 2],                        // f(1)
     pub loop_filter_mode_deltas: [i32; 2],                   // su(1+6)
 }
 
 /// Tile info
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct TileInfo {
-    pub tile_cols: u16, // TileCols
-    pub tile_rows: u16, // TileRows
+    pub tile_cols: u16,          // TileCols
+    pub tile_rows: u16,          // TileRows
+    pub mi_col_starts: Vec<u32>, // MiColStarts[]
+    pub mi_row_starts: Vec<u32>, // MiRowStarts[]
+    pub tile_cols_log2: usize,   // TileColsLog2
+    pub tile_rows_log2: usize,   // TileRowsLog2
     // tile_info()
-    pub context_update_tile_id: u32, // f(TileRowsLog2+TileColsLog2)
-    pub tile_size_bytes: usize,      // TileSizeBytes
+    pub uniform_tile_spacing_flag: bool, // f(1)
+    pub context_update_tile_id: u32,     // f(TileRowsLog2+TileColsLog2)
+    pub tile_size_bytes: usize,          // TileSizeBytes
 }
 
 /// Quantization params
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct QuantizationParams {
     pub deltaq_y_dc: i32, // DeltaQYDc
     pub deltaq_u_dc: i32, // DeltaQUDc
@@ -276,17 +398,19 @@ pub struct QuantizationParams {
 }
 
 /// Segmentation params
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct SegmentationParams {
     // segmentation_params()
-    pub segmentation_enabled: bool,         // f(1)
-    pub segmentation_update_map: bool,      // f(1)
-    pub segmentation_temporal_update: bool, // f(1)
-    pub segmentation_update_data: bool,     // f(1)
+    pub segmentation_enabled: bool,                           // f(1)
+    pub segmentation_update_map: bool,                        // f(1)
+    pub segmentation_temporal_update: bool,                   // f(1)
+    pub segmentation_update_data: bool,                       // f(1)
+    pub feature_enabled: [[bool; SEG_LVL_MAX]; MAX_SEGMENTS], // f(1)
+    pub feature_value: [[i32; SEG_LVL_MAX]; MAX_SEGMENTS],    // su(1+bitsToRead) or f(bitsToRead)
 }
 
 /// Quantizer index delta parameters
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct DeltaQParams {
     // delta_q_params()
     pub delta_q_present: bool, // f(1)
@@ -294,7 +418,7 @@ pub struct DeltaQParams {
 }
 
 /// Loop filter delta parameters
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct DeltaLfParams {
     // delta_lf_params()
     pub delta_lf_present: bool, // f(1)
@@ -303,7 +427,7 @@ pub struct DeltaLfParams {
 }
 
 /// CDEF params
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct CdefParams {
     // cdef_params()
     pub cdef_damping: u8,              // f(2)
@@ -315,15 +439,15 @@ pub struct CdefParams {
 }
 
 /// Loop restoration params
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct LrParams {
     pub uses_lr: bool,                   // UsesLr
     pub frame_restoration_type: [u8; 3], // FrameRestorationType[]
-    pub loop_restoration_size: [u8; 3],  // LoopRestorationSize[]
+    pub loop_restoration_size: [u16; 3], // LoopRestorationSize[]
 }
 
 /// Skip mode params
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct SkipModeParams {
     pub skip_mode_frame: [u8; 2], // SkipModeFrame[]
     // skip_mode_params()
@@ -331,7 +455,7 @@ pub struct SkipModeParams {
 }
 
 /// Global motion params
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct GlobalMotionParams {
     pub gm_type: [u8; NUM_REF_FRAMES],              // GmType[]
     pub gm_params: [[i32; 6]; NUM_REF_FRAMES],      // gm_params[]
@@ -341,55 +465,58 @@ pub struct GlobalMotionParams {
 ///
 /// Frame header OBU
 ///
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct FrameHeader {
     // uncompressed_header()
-    pub show_existing_frame: bool,                // f(1)
-    pub frame_to_show_map_idx: u8,                // f(3)
-    pub display_frame_id: u16,                    // f(idLen)
-    pub frame_type: u8,                           // f(2)
-    pub frame_is_intra: bool,                     // FrameIsIntra
-    pub show_frame: bool,                         // f(1)
-    pub showable_frame: bool,                     // f(1)
-    pub error_resilient_mode: bool,               // f(1)
-    pub disable_cdf_update: bool,                 // f(1)
-    pub allow_screen_content_tools: bool,         // f(1)
-    pub force_integer_mv: bool,                   // f(1)
-    pub current_frame_id: u16,                    // f(idLen)
-    pub frame_size_override_flag: bool,           // f(1)
-    pub order_hint: u8,                           // f(OrderHintBits)
-    pub primary_ref_frame: u8,                    // f(3)
-    pub refresh_frame_flags: u8,                  // f(8)
-    pub ref_order_hint: [u8; NUM_REF_FRAMES],     // f(OrderHintBits)
-    pub frame_size: FrameSize,                    // frame_size()
-    pub render_size: RenderSize,                  // render_size()
-    pub allow_intrabc: bool,                      // f(1)
-    pub last_frame_idx: u8,                       // f(3)
-    pub gold_frame_idx: u8,                       // f(3)
-    pub ref_frame_idx: [u8; NUM_REF_FRAMES],      // f(3)
-    pub allow_high_precision_mv: bool,            // f(1)
-    pub interpolation_filter: u8,                 // f(2)
-    pub is_motion_mode_switchable: bool,          // f(1)
-    pub use_ref_frame_mvs: bool,                  // f(1)
-    pub disable_frame_end_update_cdf: bool,       // f(1)
-    pub order_hints: [u8; NUM_REF_FRAMES],        // OrderHints
-    pub tile_info: TileInfo,                      // tile_info()
-    pub quantization_params: QuantizationParams,  // quantization_params()
-    pub segmentation_params: SegmentationParams,  // segmentation_params()
-    pub delta_q_params: DeltaQParams,             // delta_q_params()
-    pub delta_lf_params: DeltaLfParams,           // delta_lf_params()
-    pub coded_lossless: bool,                     // CodedLossless
-    pub all_lossless: bool,                       // AllLossless
-    pub loop_filter_params: LoopFilterParams,     // loop_filter_params()
-    pub cdef_params: CdefParams,                  // cdef_params()
-    pub lr_params: LrParams,                      // lr_params()
-    pub tx_mode: u8,                              // TxMode
-    pub skip_mode_params: SkipModeParams,         // skip_mode_params()
-    pub global_motion_params: GlobalMotionParams, // global_motion_params()
-    pub film_grain_params: FilmGrainParams,       // film_grain_params()
-    pub reference_select: bool,                   // f(1)
-    pub allow_warped_motion: bool,                // f(1)
-    pub reduced_tx_set: bool,                     // f(1)
+    pub show_existing_frame: bool,                     // f(1)
+    pub frame_to_show_map_idx: u8,                     // f(3)
+    pub display_frame_id: u16,                         // f(idLen)
+    pub frame_type: u8,                                // f(2)
+    pub frame_is_intra: bool,                          // FrameIsIntra
+    pub show_frame: bool,                              // f(1)
+    pub showable_frame: bool,                          // f(1)
+    pub error_resilient_mode: bool,                    // f(1)
+    pub disable_cdf_update: bool,                      // f(1)
+    pub allow_screen_content_tools: bool,              // f(1)
+    pub force_integer_mv: bool,                        // f(1)
+    pub current_frame_id: u16,                         // f(idLen)
+    pub frame_size_override_flag: bool,                // f(1)
+    pub order_hint: u8,                                // f(OrderHintBits)
+    pub primary_ref_frame: u8,                         // f(3)
+    pub refresh_frame_flags: u8,                       // f(8)
+    pub ref_order_hint: [u8; NUM_REF_FRAMES],          // f(OrderHintBits)
+    pub frame_size: FrameSize,                         // frame_size()
+    pub render_size: RenderSize,                       // render_size()
+    pub allow_intrabc: bool,                           // f(1)
+    pub last_frame_idx: u8,                            // f(3)
+    pub gold_frame_idx: u8,                            // f(3)
+    pub ref_frame_idx: [u8; NUM_REF_FRAMES],           // f(3)
+    pub delta_frame_id_minus_1: [u16; NUM_REF_FRAMES], // f(n)
+    pub allow_high_precision_mv: bool,                 // f(1)
+    pub interpolation_filter: u8,                      // f(2)
+    pub is_motion_mode_switchable: bool,               // f(1)
+    pub use_ref_frame_mvs: bool,                       // f(1)
+    pub disable_frame_end_update_cdf: bool,            // f(1)
+    pub order_hints: [u8; NUM_REF_FRAMES],             // OrderHints
+    pub tile_info: TileInfo,                           // tile_info()
+    pub quantization_params: QuantizationParams,       // quantization_params()
+    pub segmentation_params: SegmentationParams,       // segmentation_params()
+    pub delta_q_params: DeltaQParams,                  // delta_q_params()
+    pub delta_lf_params: DeltaLfParams,                // delta_lf_params()
+    pub coded_lossless: bool,                          // CodedLossless
+    pub all_lossless: bool,                            // AllLossless
+    pub loop_filter_params: LoopFilterParams,          // loop_filter_params()
+    pub cdef_params: CdefParams,                       // cdef_params()
+    pub lr_params: LrParams,                           // lr_params()
+    pub tx_mode: u8,                                   // TxMode
+    pub skip_mode_params: SkipModeParams,              // skip_mode_params()
+    pub global_motion_params: GlobalMotionParams,      // global_motion_params()
+    pub film_grain_params: FilmGrainParams,            // film_grain_params()
+    pub reference_select: bool,                        // f(1)
+    pub allow_warped_motion: bool,                     // f(1)
+    pub reduced_tx_set: bool,                          // f(1)
+    #[serde(skip)]
+    pub header_bits: u64,            // length of uncompressed_header() in bits
 }
 
 ///
@@ -413,7 +540,7 @@ pub struct TileListEntry {
 }
 
 /// Film grain synthesis parameters
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct FilmGrainParams {
     pub apply_grain: bool,              // f(1)
     pub grain_seed: u16,                // f(16)
@@ -474,13 +601,13 @@ pub enum MetadataObu {
     Timecode(TimecodeMetadata),
 }
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct HdrCllMetadata {
     pub max_cll: u16,  // f(16)
     pub max_fall: u16, // f(16)
 }
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct HdrMdcvMetadata {
     pub primary_chromaticity_x: [u16; 3],
     pub primary_chromaticity_y: [u16; 3],
@@ -520,6 +647,28 @@ pub struct TimecodeMetadata {
     pub time_offset_value: u32,    // f(time_offset_length), 5 bits <= 31
 }
 
+// SMPTE ST 2094-40 dynamic metadata (HDR10+)
+#[derive(Debug, Default)]
+pub struct St2094_40Metadata {
+    pub application_version: u8,                                     // u(8)
+    pub num_windows: u8,                                             // u(2)
+    pub targeted_system_display_maximum_luminance: u32,              // u(27)
+    pub targeted_system_display_actual_peak_luminance: Vec<Vec<u8>>, // u(4)
+    pub windows: Vec<St2094_40Window>,
+    pub mastering_display_actual_peak_luminance: Vec<Vec<u8>>, // u(4)
+}
+
+#[derive(Debug, Default)]
+pub struct St2094_40Window {
+    pub maxscl: [u32; 3],                    // u(17)
+    pub average_maxrgb: u32,                 // u(17)
+    pub distribution_maxrgb: Vec<(u8, u32)>, // (percentage u(7), percentile u(17))
+    pub fraction_bright_pixels: u16,         // u(10)
+    pub knee_point: Option<(u16, u16)>,      // (x u(12), y u(12))
+    pub bezier_curve_anchors: Vec<u16>,      // u(10)
+    pub color_saturation_weight: Option<u8>, // u(6)
+}
+
 /// return (MiCols, MiRows)
 fn compute_image_size(fs: &FrameSize) -> (u32, u32) {
     (
@@ -528,8 +677,22 @@ fn compute_image_size(fs: &FrameSize) -> (u32, u32) {
     )
 }
 
-/// return (Leb128Bytes, leb128())
-fn leb128<R: io::Read>(bs: &mut R) -> io::Result<(u32, u32)> {
+/// number of bytes of leb128() for value
+pub fn leb128_size(value: u32) -> u32 {
+    let mut size = 1;
+    while size < 5 && value >> (7 * size) != 0 {
+        size += 1;
+    }
+    size
+}
+
+/// write leb128() in minimum number of bytes
+pub fn write_leb128<W: io::Write>(w: &mut W, value: u32) -> io::Result<()> {
+    BitWriter::new(w).leb128(value)
+}
+
+/// return (Leb128Bytes, leb128()) without range check of value
+pub fn read_leb128<R: io::Read>(bs: &mut R) -> io::Result<(u32, u64)> {
     let mut value: u64 = 0;
     let mut leb128bytes = 0;
     for i in 0..8 {
@@ -542,7 +705,19 @@ fn leb128<R: io::Read>(bs: &mut R) -> io::Result<(u32, u32)> {
             break;
         }
     }
-    assert!(value < (1u64 << 32));
+    Ok((leb128bytes, value))
+}
+
+/// return (Leb128Bytes, leb128())
+pub fn leb128<R: io::Read>(bs: &mut R) -> io::Result<(u32, u32)> {
+    let (leb128bytes, value) = read_leb128(bs)?;
+    if value >= (1u64 << 32) {
+        conformance::report(&conformance::LEB128_RANGE, format!("leb128()={}", value));
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "leb128() value out of range",
+        ));
+    }
     Ok((leb128bytes, value as u32))
 }
 
@@ -550,11 +725,11 @@ fn leb128<R: io::Read>(bs: &mut R) -> io::Result<(u32, u32)> {
 /// parse trailing_bits()
 ///
 fn trailing_bits<R: io::Read>(br: &mut BitReader<R>) -> Option<()> {
-    let trailing_one_bit = br.f::<u8>(1)?;
+    let trailing_one_bit = br.name("trailing_one_bit").f::<u8>(1)?;
     if trailing_one_bit != 1 {
         return None;
     }
-    while let Some(trailing_zero_bit) = br.f::<u8>(1) {
+    while let Some(trailing_zero_bit) = br.name("trailing_zero_bit").f::<u8>(1) {
         if trailing_zero_bit != 0 {
             return None;
         }
@@ -571,9 +746,9 @@ fn parse_color_config<R: io::Read>(
 ) -> Option<ColorConfig> {
     let mut cc = ColorConfig::default();
 
-    let high_bitdepth = br.f::<bool>(1)?; // f(1)
+    let high_bitdepth = br.name("high_bitdepth").f::<bool>(1)?; // f(1)
     if sh.seq_profile == 2 && high_bitdepth {
-        let twelve_bit = br.f::<bool>(1)?; // f(1)
+        let twelve_bit = br.name("twelve_bit").f::<bool>(1)?; // f(1)
         cc.bit_depth = if twelve_bit { 12 } else { 10 }
     } else if sh.seq_profile <= 2 {
         cc.bit_depth = if high_bitdepth { 10 } else { 8 }
@@ -581,21 +756,21 @@ fn parse_color_config<R: io::Read>(
     if sh.seq_profile == 1 {
         cc.mono_chrome = false;
     } else {
-        cc.mono_chrome = br.f::<bool>(1)?; // f(1)
+        cc.mono_chrome = br.name("mono_chrome").f::<bool>(1)?; // f(1)
     }
     cc.num_planes = if cc.mono_chrome { 1 } else { 3 };
-    let color_description_present_flag = br.f::<bool>(1)?; // f(1)
-    if color_description_present_flag {
-        cc.color_primaries = br.f::<u8>(8)?; // f(8)
-        cc.transfer_characteristics = br.f::<u8>(8)?; // f(8)
-        cc.matrix_coefficients = br.f::<u8>(8)?; // f(8)
+    cc.color_description_present_flag = br.name("color_description_present_flag").f::<bool>(1)?; // f(1)
+    if cc.color_description_present_flag {
+        cc.color_primaries = br.name("color_primaries").f::<u8>(8)?; // f(8)
+        cc.transfer_characteristics = br.name("transfer_characteristics").f::<u8>(8)?; // f(8)
+        cc.matrix_coefficients = br.name("matrix_coefficients").f::<u8>(8)?; // f(8)
     } else {
         cc.color_primaries = CP_UNSPECIFIED;
         cc.transfer_characteristics = TC_UNSPECIFIED;
         cc.matrix_coefficients = MC_UNSPECIFIED;
     }
     if cc.mono_chrome {
-        cc.color_range = br.f::<bool>(1)?; // f(1)
+        cc.color_range = br.name("color_range").f::<bool>(1)?; // f(1)
         cc.subsampling_x = 1;
         cc.subsampling_y = 1;
         cc.chroma_sample_position = CSP_UNKNOWN;
@@ -608,9 +783,8 @@ fn parse_color_config<R: io::Read>(
         cc.color_range = true;
         cc.subsampling_x = 0;
         cc.subsampling_y = 0;
-        return Some(cc);
     } else {
-        cc.color_range = br.f::<bool>(1)?; // f(1)
+        cc.color_range = br.name("color_range").f::<bool>(1)?; // f(1)
         if sh.seq_profile == 0 {
             cc.subsampling_x = 1;
             cc.subsampling_y = 1;
@@ -619,9 +793,9 @@ fn parse_color_config<R: io::Read>(
             cc.subsampling_y = 0;
         } else {
             if cc.bit_depth == 12 {
-                cc.subsampling_x = br.f::<u8>(1)?; // f(1)
+                cc.subsampling_x = br.name("subsampling_x").f::<u8>(1)?; // f(1)
                 if cc.subsampling_x != 0 {
-                    cc.subsampling_y = br.f::<u8>(1)?; // f(1)
+                    cc.subsampling_y = br.name("subsampling_y").f::<u8>(1)?; // f(1)
                 } else {
                     cc.subsampling_y = 0;
                 }
@@ -631,31 +805,163 @@ fn parse_color_config<R: io::Read>(
             }
         }
         if cc.subsampling_x != 0 && cc.subsampling_y != 0 {
-            cc.chroma_sample_position = br.f::<u8>(2)?; // f(2)
+            cc.chroma_sample_position = br
+                .name("chroma_sample_position") // f(2)
+                .f::<u8>(2)?;
         }
     }
-    cc.separate_uv_delta_q = br.f::<bool>(1)?; // f(1)
+    cc.separate_uv_delta_q = br.name("separate_uv_delta_q").f::<bool>(1)?; // f(1)
 
     Some(cc)
 }
 
+///
+/// write color_config()
+///
+fn write_color_config<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    sh: &SequenceHeader,
+    cc: &ColorConfig,
+) -> io::Result<()> {
+    bw.f(1, cc.bit_depth > 8)?; // high_bitdepth f(1)
+    if sh.seq_profile == 2 && cc.bit_depth > 8 {
+        bw.f(1, cc.bit_depth == 12)?; // twelve_bit f(1)
+    }
+    if sh.seq_profile != 1 {
+        bw.f(1, cc.mono_chrome)?; // f(1)
+    }
+    bw.f(1, cc.color_description_present_flag)?; // f(1)
+    if cc.color_description_present_flag {
+        bw.f(8, cc.color_primaries)?; // f(8)
+        bw.f(8, cc.transfer_characteristics)?; // f(8)
+        bw.f(8, cc.matrix_coefficients)?; // f(8)
+    }
+    if cc.mono_chrome {
+        return bw.f(1, cc.color_range); // f(1)
+    } else if cc.color_primaries == CP_BT_709
+        && cc.transfer_characteristics == TC_SRGB
+        && cc.matrix_coefficients == MC_IDENTITY
+    {
+        // color_range = 1, subsampling_x = subsampling_y = 0
+    } else {
+        bw.f(1, cc.color_range)?; // f(1)
+        if sh.seq_profile > 1 && cc.bit_depth == 12 {
+            bw.f(1, cc.subsampling_x)?; // f(1)
+            if cc.subsampling_x != 0 {
+                bw.f(1, cc.subsampling_y)?; // f(1)
+            }
+        }
+        if cc.subsampling_x != 0 && cc.subsampling_y != 0 {
+            bw.f(2, cc.chroma_sample_position)?; // f(2)
+        }
+    }
+    bw.f(1, cc.separate_uv_delta_q) // f(1)
+}
+
 ///
 /// parse timing_info()
 ///
 fn parse_timing_info<R: io::Read>(br: &mut BitReader<R>) -> Option<TimingInfo> {
     let mut ti = TimingInfo::default();
 
-    ti.num_units_in_display_tick = br.f::<u32>(32)?; // f(32)
-    ti.time_scale = br.f::<u32>(32)?; // f(32)
-    ti.equal_picture_interval = br.f::<bool>(1)?; // f(1)
+    ti.num_units_in_display_tick = br.name("num_units_in_display_tick").f::<u32>(32)?; // f(32)
+    ti.time_scale = br.name("time_scale").f::<u32>(32)?; // f(32)
+    ti.equal_picture_interval = br.name("equal_picture_interval").f::<bool>(1)?; // f(1)
     if ti.equal_picture_interval {
-        ti.num_ticks_per_picture = 0 + 1; // uvlc()
-        unimplemented!("uvlc() for num_ticks_per_picture_minus_1");
+        ti.num_ticks_per_picture = br
+            .name("num_ticks_per_picture_minus_1")
+            .uvlc()?
+            .checked_add(1)?; // uvlc()
     }
 
     Some(ti)
 }
 
+///
+/// parse decoder_model_info()
+///
+fn parse_decoder_model_info<R: io::Read>(br: &mut BitReader<R>) -> Option<DecoderModelInfo> {
+    let mut dmi = DecoderModelInfo::default();
+
+    dmi.buffer_delay_length = br.name("buffer_delay_length_minus_1").f::<u8>(5)? + 1; // f(5)
+    dmi.num_units_in_decoding_tick = br.name("num_units_in_decoding_tick").f::<u32>(32)?; // f(32)
+    dmi.buffer_removal_time_length = br.name("buffer_removal_time_length_minus_1").f::<u8>(5)? + 1; // f(5)
+    dmi.frame_presentation_time_length = br
+        .name("frame_presentation_time_length_minus_1")
+        .f::<u8>(5)?
+        + 1; // f(5)
+
+    Some(dmi)
+}
+
+///
+/// parse operating_parameters_info(op)
+///
+fn parse_operating_parameters_info<R: io::Read>(
+    br: &mut BitReader<R>,
+    n: usize,
+) -> Option<OperatingParametersInfo> {
+    let mut opi = OperatingParametersInfo::default();
+
+    opi.decoder_buffer_delay = br.name("decoder_buffer_delay").f::<u32>(n)?; // f(n)
+    opi.encoder_buffer_delay = br.name("encoder_buffer_delay").f::<u32>(n)?; // f(n)
+    opi.low_delay_mode_flag = br.name("low_delay_mode_flag").f::<bool>(1)?; // f(1)
+
+    Some(opi)
+}
+
+///
+/// write timing_info()
+///
+fn write_timing_info<W: io::Write>(bw: &mut BitWriter<W>, ti: &TimingInfo) -> io::Result<()> {
+    bw.f(32, ti.num_units_in_display_tick)?; // f(32)
+    bw.f(32, ti.time_scale)?; // f(32)
+    bw.f(1, ti.equal_picture_interval)?; // f(1)
+    if ti.equal_picture_interval {
+        let num_ticks_per_picture_minus_1 =
+            minus(ti.num_ticks_per_picture, 1, "num_ticks_per_picture")?;
+        bw.uvlc(num_ticks_per_picture_minus_1)?; // uvlc()
+    }
+    Ok(())
+}
+
+///
+/// write decoder_model_info()
+///
+fn write_decoder_model_info<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    dmi: &DecoderModelInfo,
+) -> io::Result<()> {
+    let buffer_delay_length_minus_1 = minus(dmi.buffer_delay_length, 1, "buffer_delay_length")?;
+    bw.f(5, buffer_delay_length_minus_1)?; // f(5)
+    bw.f(32, dmi.num_units_in_decoding_tick)?; // f(32)
+    let buffer_removal_time_length_minus_1 = minus(
+        dmi.buffer_removal_time_length,
+        1,
+        "buffer_removal_time_length",
+    )?;
+    bw.f(5, buffer_removal_time_length_minus_1)?; // f(5)
+    let frame_presentation_time_length_minus_1 = minus(
+        dmi.frame_presentation_time_length,
+        1,
+        "frame_presentation_time_length",
+    )?;
+    bw.f(5, frame_presentation_time_length_minus_1) // f(5)
+}
+
+///
+/// write operating_parameters_info(op)
+///
+fn write_operating_parameters_info<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    opi: &OperatingParametersInfo,
+    n: usize,
+) -> io::Result<()> {
+    bw.f(n, opi.decoder_buffer_delay)?; // f(n)
+    bw.f(n, opi.encoder_buffer_delay)?; // f(n)
+    bw.f(1, opi.low_delay_mode_flag) // f(1)
+}
+
 ///
 /// parse frame_size() (include superres_params())
 ///
@@ -668,25 +974,32 @@ fn parse_frame_size<R: io::Read>(
 
     // frame_size()
     if fh.frame_size_override_flag {
-        fs.frame_width = br.f::<u32>(sh.frame_width_bits as usize)? + 1; // f(n)
-        fs.frame_height = br.f::<u32>(sh.frame_height_bits as usize)? + 1; // f(n)
+        fs.frame_width = br
+            .name("frame_width_minus_1")
+            .f::<u32>(sh.frame_width_bits as usize)?
+            + 1; // f(n)
+        fs.frame_height = br
+            .name("frame_height_minus_1")
+            .f::<u32>(sh.frame_height_bits as usize)?
+            + 1; // f(n)
     } else {
         fs.frame_width = sh.max_frame_width;
         fs.frame_height = sh.max_frame_height;
     }
     // superres_params()
     if sh.enable_superres {
-        fs.use_superres = br.f::<bool>(1)?; // f(1)
+        fs.use_superres = br.name("use_superres").f::<bool>(1)?; // f(1)
     } else {
         fs.use_superres = false;
     }
     let supreres_denom;
     if fs.use_superres {
-        let coded_denom = br.f::<usize>(SUPERRS_DENOM_BITS)?; // f(SUPERRES_DENOM_BITS)
+        let coded_denom = br.name("coded_denom").f::<usize>(SUPERRS_DENOM_BITS)?; // f(SUPERRES_DENOM_BITS)
         supreres_denom = coded_denom + SUPERRES_DENOM_MIN;
     } else {
         supreres_denom = SUPERRES_NUM;
     }
+    fs.superres_denom = supreres_denom as u8;
     fs.upscaled_width = fs.frame_width;
     fs.frame_width = ((fs.upscaled_width as usize * SUPERRES_NUM + (supreres_denom / 2))
         / supreres_denom) as u32;
@@ -701,10 +1014,11 @@ fn parse_frame_size<R: io::Read>(
 fn parse_render_size<R: io::Read>(br: &mut BitReader<R>, fs: &FrameSize) -> Option<RenderSize> {
     let mut rs = RenderSize::default();
 
-    let render_and_frame_size_different = br.f::<bool>(1)?; // f(1)
+    let render_and_frame_size_different =
+        br.name("render_and_frame_size_different").f::<bool>(1)?; // f(1)
     if render_and_frame_size_different {
-        rs.render_width = br.f::<u32>(16)? + 1; // f(16)
-        rs.render_height = br.f::<u32>(16)? + 1; // f(16)
+        rs.render_width = br.name("render_width_minus_1").f::<u32>(16)? + 1; // f(16)
+        rs.render_height = br.name("render_height_minus_1").f::<u32>(16)? + 1; // f(16)
     } else {
         rs.render_width = fs.upscaled_width;
         rs.render_height = fs.frame_height;
@@ -713,19 +1027,79 @@ fn parse_render_size<R: io::Read>(br: &mut BitReader<R>, fs: &FrameSize) -> Opti
     Some(rs)
 }
 
+/// write frame_size() and superres_params()
+fn write_frame_size<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    sh: &SequenceHeader,
+    fh: &FrameHeader,
+) -> io::Result<()> {
+    let fs = &fh.frame_size;
+    if fh.frame_size_override_flag {
+        bw.f(
+            sh.frame_width_bits as usize,
+            minus(fs.upscaled_width, 1, "frame_width")?,
+        )?; // frame_width_minus_1 f(n)
+        bw.f(
+            sh.frame_height_bits as usize,
+            minus(fs.frame_height, 1, "frame_height")?,
+        )?; // frame_height_minus_1 f(n)
+    }
+    if sh.enable_superres {
+        bw.f(1, fs.use_superres)?; // f(1)
+    }
+    if fs.use_superres {
+        let coded_denom = minus(
+            fs.superres_denom,
+            SUPERRES_DENOM_MIN as u8,
+            "superres_denom",
+        )?;
+        bw.f(SUPERRS_DENOM_BITS, coded_denom)?; // f(SUPERRES_DENOM_BITS)
+    }
+    Ok(())
+}
+
+/// write render_size()
+fn write_render_size<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    fs: &FrameSize,
+    rs: &RenderSize,
+) -> io::Result<()> {
+    let render_and_frame_size_different =
+        rs.render_width != fs.upscaled_width || rs.render_height != fs.frame_height;
+    bw.f(1, render_and_frame_size_different)?; // f(1)
+    if render_and_frame_size_different {
+        bw.f(16, minus(rs.render_width, 1, "render_width")?)?; // render_width_minus_1 f(16)
+        bw.f(16, minus(rs.render_height, 1, "render_height")?)?; // render_height_minus_1 f(16)
+    }
+    Ok(())
+}
+
 /// read_interpolation_filter()
 fn read_interpolation_filter<R: io::Read>(br: &mut BitReader<R>) -> Option<u8> {
-    let is_filter_switchable = br.f::<bool>(1)?; // f(1)
+    let is_filter_switchable = br.name("is_filter_switchable").f::<bool>(1)?; // f(1)
     let interpolation_filter;
     if is_filter_switchable {
         interpolation_filter = SWITCHABLE;
     } else {
-        interpolation_filter = br.f::<u8>(2)?; // f(2)
+        interpolation_filter = br.name("interpolation_filter").f::<u8>(2)?; // f(2)
     }
 
     Some(interpolation_filter)
 }
 
+/// write_interpolation_filter()
+fn write_interpolation_filter<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    interpolation_filter: u8,
+) -> io::Result<()> {
+    let is_filter_switchable = interpolation_filter == SWITCHABLE;
+    bw.f(1, is_filter_switchable)?; // f(1)
+    if !is_filter_switchable {
+        bw.f(2, interpolation_filter)?; // f(2)
+    }
+    Ok(())
+}
+
 ///
 /// parse loop_filter_params()
 ///
@@ -752,29 +1126,35 @@ fn parse_loop_filter_params<R: io::Read>(
         }
         return Some(lfp);
     }
-    lfp.loop_filter_level[0] = br.f::<u8>(6)?; // f(6)
-    lfp.loop_filter_level[1] = br.f::<u8>(6)?; // f(6)
+    lfp.loop_filter_level[0] = br.name("loop_filter_level").f::<u8>(6)?; // f(6)
+    lfp.loop_filter_level[1] = br.name("loop_filter_level").f::<u8>(6)?; // f(6)
     if cc.num_planes > 1 {
         if lfp.loop_filter_level[0] != 0 || lfp.loop_filter_level[1] != 0 {
-            lfp.loop_filter_level[2] = br.f::<u8>(6)?; // f(6)
-            lfp.loop_filter_level[3] = br.f::<u8>(6)?; // f(6)
+            lfp.loop_filter_level[2] = br.name("loop_filter_level").f::<u8>(6)?; // f(6)
+            lfp.loop_filter_level[3] = br
+                .name("loop_filter_level") // f(6)
+                .f::<u8>(6)?;
         }
     }
-    lfp.loop_filter_sharpness = br.f::<u8>(3)?; // f(3)
-    lfp.loop_filter_delta_enabled = br.f::<bool>(1)?; // f(1)
+    lfp.loop_filter_sharpness = br.name("loop_filter_sharpness").f::<u8>(3)?; // f(3)
+    lfp.loop_filter_delta_enabled = br.name("loop_filter_delta_enabled").f::<bool>(1)?; // f(1)
     if lfp.loop_filter_delta_enabled {
-        let loop_filter_delta_update = br.f::<bool>(1)?; // f(1)
-        if loop_filter_delta_update {
+        lfp.loop_filter_delta_update = br.name("loop_filter_delta_update").f::<bool>(1)?; // f(1)
+        if lfp.loop_filter_delta_update {
             for i in 0..TOTAL_REFS_PER_FRAME {
-                let update_ref_delta = br.f::<bool>(1)?; // f(1)
-                if update_ref_delta {
-                    lfp.loop_filter_ref_deltas[i] = br.su(1 + 6)?; // su(1+6)
+                lfp.update_ref_delta[i] = br.name("update_ref_delta").f::<bool>(1)?; // f(1)
+                if lfp.update_ref_delta[i] {
+                    lfp.loop_filter_ref_deltas[i] = br
+                        .name("loop_filter_ref_deltas") // su(1+6)
+                        .su(1 + 6)?;
                 }
             }
             for i in 0..2 {
-                let update_mode_delta = br.f::<bool>(1)?; // f(1)
-                if update_mode_delta {
-                    lfp.loop_filter_mode_deltas[i] = br.su(1 + 6)?; // su(1+6)
+                lfp.update_mode_delta[i] = br.name("update_mode_delta").f::<bool>(1)?; // f(1)
+                if lfp.update_mode_delta[i] {
+                    lfp.loop_filter_mode_deltas[i] = br
+                        .name("loop_filter_mode_deltas") // su(1+6)
+                        .su(1 + 6)?;
                 }
             }
         }
@@ -783,6 +1163,113 @@ fn parse_loop_filter_params<R: io::Read>(
     Some(lfp)
 }
 
+/// write loop_filter_params()
+fn write_loop_filter_params<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    cc: &ColorConfig,
+    fh: &FrameHeader,
+) -> io::Result<()> {
+    let lfp = &fh.loop_filter_params;
+    if fh.coded_lossless || fh.allow_intrabc {
+        return Ok(());
+    }
+    bw.f(6, lfp.loop_filter_level[0])?; // f(6)
+    bw.f(6, lfp.loop_filter_level[1])?; // f(6)
+    if cc.num_planes > 1 && (lfp.loop_filter_level[0] != 0 || lfp.loop_filter_level[1] != 0) {
+        bw.f(6, lfp.loop_filter_level[2])?; // f(6)
+        bw.f(6, lfp.loop_filter_level[3])?; // f(6)
+    }
+    bw.f(3, lfp.loop_filter_sharpness)?; // f(3)
+    bw.f(1, lfp.loop_filter_delta_enabled)?; // f(1)
+    if lfp.loop_filter_delta_enabled {
+        bw.f(1, lfp.loop_filter_delta_update)?; // f(1)
+        if lfp.loop_filter_delta_update {
+            for i in 0..TOTAL_REFS_PER_FRAME {
+                bw.f(1, lfp.update_ref_delta[i])?; // f(1)
+                if lfp.update_ref_delta[i] {
+                    bw.su(1 + 6, lfp.loop_filter_ref_deltas[i])?; // su(1+6)
+                }
+            }
+            for i in 0..2 {
+                bw.f(1, lfp.update_mode_delta[i])?; // f(1)
+                if lfp.update_mode_delta[i] {
+                    bw.su(1 + 6, lfp.loop_filter_mode_deltas[i])?; // su(1+6)
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// tile_log2: Tile size calculation function
+fn tile_log2(blk_size: u32, target: u32) -> usize {
+    let mut k = 0;
+    while (blk_size << k) < target {
+        k += 1;
+    }
+    k
+}
+
+// superblock layout and tile count limits in tile_info()
+struct TileLimits {
+    mi_cols: u32,
+    mi_rows: u32,
+    sb_cols: u32,
+    sb_rows: u32,
+    sb_shift: u32,
+    max_tile_width_sb: u32,
+    min_log2_tile_cols: usize,
+    max_log2_tile_cols: usize,
+    max_log2_tile_rows: usize,
+    min_log2_tiles: usize,
+}
+
+impl TileLimits {
+    fn new(sh: &SequenceHeader, fs: &FrameSize) -> TileLimits {
+        let (mi_cols, mi_rows) = compute_image_size(fs);
+        let sb_cols = if sh.use_128x128_superblock {
+            (mi_cols + 31) >> 5
+        } else {
+            (mi_cols + 15) >> 4
+        };
+        let sb_rows = if sh.use_128x128_superblock {
+            (mi_rows + 31) >> 5
+        } else {
+            (mi_rows + 15) >> 4
+        };
+        let sb_shift = if sh.use_128x128_superblock { 5 } else { 4 };
+        let sb_size = sb_shift + 2;
+        let max_tile_width_sb = MAX_TILE_WIDTH >> sb_size;
+        let max_tile_area_sb = MAX_TILE_AREA >> (2 * sb_size);
+        let min_log2_tile_cols = tile_log2(max_tile_width_sb, sb_cols);
+        TileLimits {
+            mi_cols,
+            mi_rows,
+            sb_cols,
+            sb_rows,
+            sb_shift,
+            max_tile_width_sb,
+            min_log2_tile_cols,
+            max_log2_tile_cols: tile_log2(1, cmp::min(sb_cols, MAX_TILE_COLS)),
+            max_log2_tile_rows: tile_log2(1, cmp::min(sb_rows, MAX_TILE_ROWS)),
+            min_log2_tiles: cmp::max(
+                min_log2_tile_cols,
+                tile_log2(max_tile_area_sb, sb_rows * sb_cols),
+            ),
+        }
+    }
+
+    // maxTileHeightSb for non-uniform tile spacing
+    fn max_tile_height_sb(&self, widest_tile_sb: u32) -> u32 {
+        let max_tile_area_sb = if self.min_log2_tiles > 0 {
+            (self.sb_rows * self.sb_cols) >> (self.min_log2_tiles + 1)
+        } else {
+            self.sb_rows * self.sb_cols
+        };
+        cmp::max(max_tile_area_sb / widest_tile_sb, 1)
+    }
+}
+
 ///
 /// parse tile_info()
 ///
@@ -793,44 +1280,26 @@ fn parse_tile_info<R: io::Read>(
 ) -> Option<TileInfo> {
     let mut ti = TileInfo::default();
 
-    // tile_log2: Tile size calculation function
-    let tile_log2 = |blk_size, target| {
-        let mut k = 0;
-        while (blk_size << k) < target {
-            k += 1;
-        }
-        k
-    };
-
-    let (mi_cols, mi_rows) = compute_image_size(fs);
-    let sb_cols = if sh.use_128x128_superblock {
-        (mi_cols + 31) >> 5
-    } else {
-        (mi_cols + 15) >> 4
-    };
-    let sb_rows = if sh.use_128x128_superblock {
-        (mi_rows + 31) >> 5
-    } else {
-        (mi_rows + 15) >> 4
-    };
-    let sb_shift = if sh.use_128x128_superblock { 5 } else { 4 };
-    let sb_size = sb_shift + 2;
-    let max_tile_width_sb = MAX_TILE_WIDTH >> sb_size;
-    let max_tile_area_sb = MAX_TILE_AREA >> (2 * sb_size);
-    let min_log2_tile_cols = tile_log2(max_tile_width_sb, sb_cols);
-    let max_log2_tile_cols = tile_log2(1, cmp::min(sb_cols, MAX_TILE_COLS));
-    let max_log2_tile_rows = tile_log2(1, cmp::min(sb_rows, MAX_TILE_ROWS));
-    let min_log2_tiles = cmp::max(
+    let limits = TileLimits::new(sh, fs);
+    let TileLimits {
+        mi_cols,
+        mi_rows,
+        sb_cols,
+        sb_rows,
+        sb_shift,
+        max_tile_width_sb,
         min_log2_tile_cols,
-        tile_log2(max_tile_area_sb, sb_rows * sb_cols),
-    );
+        max_log2_tile_cols,
+        max_log2_tile_rows,
+        min_log2_tiles,
+    } = limits;
 
-    let uniform_tile_spacing_flag = br.f::<bool>(1)?; // f(1)
+    ti.uniform_tile_spacing_flag = br.name("uniform_tile_spacing_flag").f::<bool>(1)?; // f(1)
     let (mut tile_cols_log2, mut tile_rows_log2): (usize, usize);
-    if uniform_tile_spacing_flag {
+    if ti.uniform_tile_spacing_flag {
         tile_cols_log2 = min_log2_tile_cols;
         while tile_cols_log2 < max_log2_tile_cols {
-            let increment_tile_cols_log2 = br.f::<bool>(1)?; // f(1)
+            let increment_tile_cols_log2 = br.name("increment_tile_cols_log2").f::<bool>(1)?; // f(1)
             if increment_tile_cols_log2 {
                 tile_cols_log2 += 1;
             } else {
@@ -840,18 +1309,18 @@ fn parse_tile_info<R: io::Read>(
         let tile_width_sb = (sb_cols + (1 << tile_cols_log2) - 1) >> tile_cols_log2;
         let (mut i, mut start_sb) = (0, 0);
         while start_sb < sb_cols {
-            // MiColStarts[i] = startSb << sbShift
+            ti.mi_col_starts.push(start_sb << sb_shift); // MiColStarts[i]
             i += 1;
             start_sb += tile_width_sb;
         }
-        // MiColStarts[i] = MiCols
+        ti.mi_col_starts.push(mi_cols); // MiColStarts[i]
         ti.tile_cols = i;
 
         let min_log2_tile_rows =
             cmp::max(min_log2_tiles as isize - tile_cols_log2 as isize, 0) as usize;
         tile_rows_log2 = min_log2_tile_rows;
         while tile_rows_log2 < max_log2_tile_rows {
-            let increment_tile_rows_log2 = br.f::<bool>(1)?; // f(1)
+            let increment_tile_rows_log2 = br.name("increment_tile_rows_log2").f::<bool>(1)?; // f(1)
             if increment_tile_rows_log2 {
                 tile_rows_log2 += 1;
             } else {
@@ -861,50 +1330,52 @@ fn parse_tile_info<R: io::Read>(
         let tile_height_sb = (sb_rows + (1 << tile_rows_log2) - 1) >> tile_rows_log2;
         let (mut i, mut start_sb) = (0, 0);
         while start_sb < sb_rows {
-            // MiRowStarts[i] = startSb << sbShift
+            ti.mi_row_starts.push(start_sb << sb_shift); // MiRowStarts[i]
             i += 1;
             start_sb += tile_height_sb;
         }
-        // MiRowStarts[i] = MiRows
+        ti.mi_row_starts.push(mi_rows); // MiRowStarts[i]
         ti.tile_rows = i;
     } else {
         let mut widest_tile_sb = 0;
         let (mut i, mut start_sb) = (0, 0);
         while start_sb < sb_cols {
-            // MiColStarts[i] = startSb << sbShift
+            ti.mi_col_starts.push(start_sb << sb_shift); // MiColStarts[i]
             let max_width = cmp::min(sb_cols - start_sb, max_tile_width_sb);
-            let width_in_sbs = br.ns(max_width)? + 1; // ns(maxWidth)
+            let width_in_sbs = br.name("width_in_sbs_minus_1").ns(max_width)? + 1; // ns(maxWidth)
             let size_sb = width_in_sbs;
             widest_tile_sb = cmp::max(size_sb, widest_tile_sb);
             start_sb += size_sb;
             i += 1;
         }
-        // MiColStarts[i] = MiCols
+        ti.mi_col_starts.push(mi_cols); // MiColStarts[i]
         ti.tile_cols = i;
         tile_cols_log2 = tile_log2(1, ti.tile_cols as u32);
 
-        let max_tile_area_sb = if min_log2_tiles > 0 {
-            (sb_rows * sb_cols) >> (min_log2_tiles + 1)
-        } else {
-            sb_rows * sb_cols
-        };
-        let max_tile_height_sb = cmp::max(max_tile_area_sb / widest_tile_sb, 1);
+        let max_tile_height_sb = limits.max_tile_height_sb(widest_tile_sb);
         let (mut start_sb, mut i) = (0, 0);
         while start_sb < sb_rows {
-            // MiRowStarts[i] = startSb << sbShift
+            ti.mi_row_starts.push(start_sb << sb_shift); // MiRowStarts[i]
             let max_height = cmp::min(sb_rows - start_sb, max_tile_height_sb);
-            let height_in_sbs = br.ns(max_height)? + 1; // ns(maxHeight)
+            let height_in_sbs = br.name("height_in_sbs_minus_1").ns(max_height)? + 1; // ns(maxHeight)
             let size_sb = height_in_sbs;
             start_sb += size_sb;
             i += 1;
         }
-        // MiRowStarts[i] = MiRows
+        ti.mi_row_starts.push(mi_rows); // MiRowStarts[i]
         ti.tile_rows = i;
         tile_rows_log2 = tile_log2(1, ti.tile_rows as u32);
     }
+    ti.tile_cols_log2 = tile_cols_log2;
+    ti.tile_rows_log2 = tile_rows_log2;
     if tile_cols_log2 > 0 || tile_rows_log2 > 0 {
-        ti.context_update_tile_id = br.f::<u32>(tile_cols_log2 + tile_rows_log2)?; // f(TileRowsLog2+TileColsLog2)
-        ti.tile_size_bytes = br.f::<usize>(2)? + 1; // f(2)
+        ti.context_update_tile_id = br
+            .name("context_update_tile_id")
+            .f::<u32>(tile_cols_log2 + tile_rows_log2)?; // f(TileRowsLog2+TileColsLog2)
+        ti.tile_size_bytes = br
+            .name("tile_size_bytes_minus_1") // f(2)
+            .f::<usize>(2)?
+            + 1;
     } else {
         ti.context_update_tile_id = 0;
     }
@@ -912,6 +1383,69 @@ fn parse_tile_info<R: io::Read>(
     Some(ti)
 }
 
+/// write tile_info()
+fn write_tile_info<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    sh: &SequenceHeader,
+    fs: &FrameSize,
+    ti: &TileInfo,
+) -> io::Result<()> {
+    let limits = TileLimits::new(sh, fs);
+    // size of i-th tile in superblocks
+    let tile_size_sb = |starts: &[u32], count: u16, sb_count: u32, i: usize| {
+        let start_sb = starts[i] >> limits.sb_shift;
+        let end_sb = if i + 1 < count as usize {
+            starts[i + 1] >> limits.sb_shift
+        } else {
+            sb_count
+        };
+        (start_sb, end_sb - start_sb)
+    };
+
+    bw.f(1, ti.uniform_tile_spacing_flag)?; // f(1)
+    if ti.uniform_tile_spacing_flag {
+        for tile_cols_log2 in limits.min_log2_tile_cols..limits.max_log2_tile_cols {
+            let increment_tile_cols_log2 = tile_cols_log2 < ti.tile_cols_log2;
+            bw.f(1, increment_tile_cols_log2)?; // f(1)
+            if !increment_tile_cols_log2 {
+                break;
+            }
+        }
+        let min_log2_tile_rows = limits.min_log2_tiles.saturating_sub(ti.tile_cols_log2);
+        for tile_rows_log2 in min_log2_tile_rows..limits.max_log2_tile_rows {
+            let increment_tile_rows_log2 = tile_rows_log2 < ti.tile_rows_log2;
+            bw.f(1, increment_tile_rows_log2)?; // f(1)
+            if !increment_tile_rows_log2 {
+                break;
+            }
+        }
+    } else {
+        let mut widest_tile_sb = 0;
+        for i in 0..ti.tile_cols as usize {
+            let (start_sb, size_sb) =
+                tile_size_sb(&ti.mi_col_starts, ti.tile_cols, limits.sb_cols, i);
+            let max_width = cmp::min(limits.sb_cols - start_sb, limits.max_tile_width_sb);
+            bw.ns(max_width, minus(size_sb, 1, "width_in_sbs")?)?; // width_in_sbs_minus_1 ns(maxWidth)
+            widest_tile_sb = cmp::max(size_sb, widest_tile_sb);
+        }
+        let max_tile_height_sb = limits.max_tile_height_sb(widest_tile_sb);
+        for i in 0..ti.tile_rows as usize {
+            let (start_sb, size_sb) =
+                tile_size_sb(&ti.mi_row_starts, ti.tile_rows, limits.sb_rows, i);
+            let max_height = cmp::min(limits.sb_rows - start_sb, max_tile_height_sb);
+            bw.ns(max_height, minus(size_sb, 1, "height_in_sbs")?)?; // height_in_sbs_minus_1 ns(maxHeight)
+        }
+    }
+    if ti.tile_cols_log2 > 0 || ti.tile_rows_log2 > 0 {
+        bw.f(
+            ti.tile_cols_log2 + ti.tile_rows_log2,
+            ti.context_update_tile_id,
+        )?; // f(TileRowsLog2+TileColsLog2)
+        bw.f(2, minus(ti.tile_size_bytes as u32, 1, "tile_size_bytes")?)?; // tile_size_bytes_minus_1 f(2)
+    }
+    Ok(())
+}
+
 ///
 /// parse quantization_params()
 ///
@@ -921,12 +1455,12 @@ fn parse_quantization_params<R: io::Read>(
 ) -> Option<QuantizationParams> {
     let mut qp = QuantizationParams::default();
 
-    qp.base_q_idx = br.f::<u8>(8)?; // f(8)
+    qp.base_q_idx = br.name("base_q_idx").f::<u8>(8)?; // f(8)
     qp.deltaq_y_dc = read_delta_q(br)?; // read_delta_q()
     if cc.num_planes > 1 {
         let diff_uv_delta;
         if cc.separate_uv_delta_q {
-            diff_uv_delta = br.f::<bool>(1)?; // f(1)
+            diff_uv_delta = br.name("diff_uv_delta").f::<bool>(1)?; // f(1)
         } else {
             diff_uv_delta = false;
         }
@@ -945,31 +1479,73 @@ fn parse_quantization_params<R: io::Read>(
         qp.deltaq_v_dc = 0;
         qp.deltaq_v_ac = 0;
     }
-    qp.using_qmatrix = br.f::<bool>(1)?; // f(1)
+    qp.using_qmatrix = br.name("using_qmatrix").f::<bool>(1)?; // f(1)
     if qp.using_qmatrix {
-        qp.qm_y = br.f::<u8>(4)?; // f(4)
-        qp.qm_u = br.f::<u8>(4)?; // f(4)
+        qp.qm_y = br.name("qm_y").f::<u8>(4)?; // f(4)
+        qp.qm_u = br.name("qm_u").f::<u8>(4)?; // f(4)
         if !cc.separate_uv_delta_q {
             qp.qm_v = qp.qm_u;
         } else {
-            qp.qm_v = br.f::<u8>(4)?; // f(4)
+            qp.qm_v = br.name("qm_v").f::<u8>(4)?; // f(4)
         }
     }
 
     Some(qp)
 }
 
+/// write quantization_params()
+fn write_quantization_params<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    cc: &ColorConfig,
+    qp: &QuantizationParams,
+) -> io::Result<()> {
+    bw.f(8, qp.base_q_idx)?; // f(8)
+    write_delta_q(bw, qp.deltaq_y_dc)?; // write_delta_q()
+    if cc.num_planes > 1 {
+        let diff_uv_delta = cc.separate_uv_delta_q
+            && (qp.deltaq_v_dc != qp.deltaq_u_dc || qp.deltaq_v_ac != qp.deltaq_u_ac);
+        if cc.separate_uv_delta_q {
+            bw.f(1, diff_uv_delta)?; // f(1)
+        }
+        write_delta_q(bw, qp.deltaq_u_dc)?; // write_delta_q()
+        write_delta_q(bw, qp.deltaq_u_ac)?; // write_delta_q()
+        if diff_uv_delta {
+            write_delta_q(bw, qp.deltaq_v_dc)?; // write_delta_q()
+            write_delta_q(bw, qp.deltaq_v_ac)?; // write_delta_q()
+        }
+    }
+    bw.f(1, qp.using_qmatrix)?; // f(1)
+    if qp.using_qmatrix {
+        bw.f(4, qp.qm_y)?; // f(4)
+        bw.f(4, qp.qm_u)?; // f(4)
+        if cc.separate_uv_delta_q {
+            bw.f(4, qp.qm_v)?; // f(4)
+        }
+    }
+    Ok(())
+}
+
 /// Delta quantizer
 fn read_delta_q<R: io::Read>(br: &mut BitReader<R>) -> Option<i32> {
-    let delta_coded = br.f::<bool>(1)?; // f(1)
+    let delta_coded = br.name("delta_coded").f::<bool>(1)?; // f(1)
     let delta_q;
     if delta_coded {
-        delta_q = br.su(1 + 6)?; // su(1+6)
+        delta_q = br.name("delta_q").su(1 + 6)?; // su(1+6)
     } else {
         delta_q = 0;
     }
 
-    Some(delta_q as i32)
+    Some(delta_q)
+}
+
+/// write_delta_q()
+fn write_delta_q<W: io::Write>(bw: &mut BitWriter<W>, delta_q: i32) -> io::Result<()> {
+    let delta_coded = delta_q != 0;
+    bw.f(1, delta_coded)?; // f(1)
+    if delta_coded {
+        bw.su(1 + 6, delta_q)?; // su(1+6)
+    }
+    Ok(())
 }
 
 ///
@@ -981,40 +1557,28 @@ fn parse_segmentation_params<R: io::Read>(
 ) -> Option<SegmentationParams> {
     let mut sp = SegmentationParams::default();
 
-    #[allow(non_upper_case_globals)]
-    const Segmentation_Feature_Bits: [usize; SEG_LVL_MAX] = [8, 6, 6, 6, 6, 3, 0, 0];
-    #[allow(non_upper_case_globals)]
-    const Segmentation_Feature_Signed: [usize; SEG_LVL_MAX] = [1, 1, 1, 1, 1, 0, 0, 0];
-    #[allow(non_upper_case_globals)]
-    const Segmentation_Feature_Max: [i32; SEG_LVL_MAX] = [
-        255,
-        MAX_LOOP_FILTER,
-        MAX_LOOP_FILTER,
-        MAX_LOOP_FILTER,
-        MAX_LOOP_FILTER,
-        7,
-        0,
-        0,
-    ];
-
-    sp.segmentation_enabled = br.f::<bool>(1)?; // f(1)
+    sp.segmentation_enabled = br.name("segmentation_enabled").f::<bool>(1)?; // f(1)
     if sp.segmentation_enabled {
         if fh.primary_ref_frame == PRIMARY_REF_NONE {
             sp.segmentation_update_map = true;
             sp.segmentation_temporal_update = false;
             sp.segmentation_update_data = true;
         } else {
-            sp.segmentation_update_map = br.f::<bool>(1)?; // f(1)
+            sp.segmentation_update_map = br.name("segmentation_update_map").f::<bool>(1)?; // f(1)
             if sp.segmentation_update_map {
-                sp.segmentation_temporal_update = br.f::<bool>(1)?; // f(1)
+                sp.segmentation_temporal_update =
+                    br.name("segmentation_temporal_update").f::<bool>(1)?; // f(1)
             }
-            sp.segmentation_update_data = br.f::<bool>(1)?; // f(1)
+            sp.segmentation_update_data = br
+                .name("segmentation_update_data") // f(1)
+                .f::<bool>(1)?;
         }
         if sp.segmentation_update_data {
-            for _ in 0..MAX_SEGMENTS {
+            for i in 0..MAX_SEGMENTS {
                 for j in 0..SEG_LVL_MAX {
                     let feature_value;
-                    let feature_enabled = br.f::<bool>(1)?; // f(1)
+                    let feature_enabled = br.name("feature_enabled").f::<bool>(1)?; // f(1)
+                    sp.feature_enabled[i][j] = feature_enabled;
 
                     // FeatureEnabled[i][j] = feature_enabled
                     let mut clipped_value = 0;
@@ -1022,12 +1586,13 @@ fn parse_segmentation_params<R: io::Read>(
                         let bits_to_read = Segmentation_Feature_Bits[j];
                         let limit = Segmentation_Feature_Max[j];
                         if Segmentation_Feature_Signed[j] == 1 {
-                            feature_value = br.su(1 + bits_to_read)?; // su(1+bitsToRead)
+                            feature_value = br.name("feature_value").su(1 + bits_to_read)?; // su(1+bitsToRead)
                             clipped_value = cmp::max(-limit, cmp::min(limit, feature_value));
                         } else {
-                            feature_value = br.f::<u32>(bits_to_read)? as i32; // f(bitsToRead)
+                            feature_value = br.name("feature_value").f::<u32>(bits_to_read)? as i32; // f(bitsToRead)
                             clipped_value = cmp::max(0, cmp::min(limit, feature_value));
                         }
+                        sp.feature_value[i][j] = feature_value;
                     }
                     let _ = clipped_value; // FeatureData[i][j] = clippedValue
                 }
@@ -1043,6 +1608,41 @@ fn parse_segmentation_params<R: io::Read>(
     Some(sp)
 }
 
+/// write segmentation_params()
+fn write_segmentation_params<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    fh: &FrameHeader,
+) -> io::Result<()> {
+    let sp = &fh.segmentation_params;
+    bw.f(1, sp.segmentation_enabled)?; // f(1)
+    if !sp.segmentation_enabled {
+        return Ok(());
+    }
+    if fh.primary_ref_frame != PRIMARY_REF_NONE {
+        bw.f(1, sp.segmentation_update_map)?; // f(1)
+        if sp.segmentation_update_map {
+            bw.f(1, sp.segmentation_temporal_update)?; // f(1)
+        }
+        bw.f(1, sp.segmentation_update_data)?; // f(1)
+    }
+    if sp.segmentation_update_data {
+        for i in 0..MAX_SEGMENTS {
+            for j in 0..SEG_LVL_MAX {
+                bw.f(1, sp.feature_enabled[i][j])?; // f(1)
+                if sp.feature_enabled[i][j] {
+                    let bits_to_write = Segmentation_Feature_Bits[j];
+                    if Segmentation_Feature_Signed[j] == 1 {
+                        bw.su(1 + bits_to_write, sp.feature_value[i][j])?; // su(1+bitsToRead)
+                    } else {
+                        bw.f(bits_to_write, sp.feature_value[i][j] as u32)?; // f(bitsToRead)
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 ///
 /// parse delta_q_params()
 ///
@@ -1055,15 +1655,30 @@ fn parse_delta_q_params<R: io::Read>(
     dqp.delta_q_res = 0;
     dqp.delta_q_present = false;
     if qp.base_q_idx > 0 {
-        dqp.delta_q_present = br.f::<bool>(1)?; // f(1)
+        dqp.delta_q_present = br.name("delta_q_present").f::<bool>(1)?; // f(1)
     }
     if dqp.delta_q_present {
-        dqp.delta_q_res = br.f::<u8>(2)?; // f(2)
+        dqp.delta_q_res = br.name("delta_q_res").f::<u8>(2)?; // f(2)
     }
 
     Some(dqp)
 }
 
+/// write delta_q_params()
+fn write_delta_q_params<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    qp: &QuantizationParams,
+    dqp: &DeltaQParams,
+) -> io::Result<()> {
+    if qp.base_q_idx > 0 {
+        bw.f(1, dqp.delta_q_present)?; // f(1)
+    }
+    if dqp.delta_q_present {
+        bw.f(2, dqp.delta_q_res)?; // f(2)
+    }
+    Ok(())
+}
+
 ///
 /// parse delta_lf_params()
 ///
@@ -1078,17 +1693,32 @@ fn parse_delta_lf_params<R: io::Read>(
     dlfp.delta_lf_multi = false;
     if fh.delta_q_params.delta_q_present {
         if !fh.allow_intrabc {
-            dlfp.delta_lf_present = br.f::<bool>(1)?; // f(1)
+            dlfp.delta_lf_present = br.name("delta_lf_present").f::<bool>(1)?; // f(1)
         }
         if dlfp.delta_lf_present {
-            dlfp.delta_lf_res = br.f::<u8>(2)?; // f(2)
-            dlfp.delta_lf_multi = br.f::<bool>(1)?; // f(1)
+            dlfp.delta_lf_res = br.name("delta_lf_res").f::<u8>(2)?; // f(2)
+            dlfp.delta_lf_multi = br.name("delta_lf_multi").f::<bool>(1)?; // f(1)
         }
     }
 
     Some(dlfp)
 }
 
+/// write delta_lf_params()
+fn write_delta_lf_params<W: io::Write>(bw: &mut BitWriter<W>, fh: &FrameHeader) -> io::Result<()> {
+    let dlfp = &fh.delta_lf_params;
+    if fh.delta_q_params.delta_q_present {
+        if !fh.allow_intrabc {
+            bw.f(1, dlfp.delta_lf_present)?; // f(1)
+        }
+        if dlfp.delta_lf_present {
+            bw.f(2, dlfp.delta_lf_res)?; // f(2)
+            bw.f(1, dlfp.delta_lf_multi)?; // f(1)
+        }
+    }
+    Ok(())
+}
+
 ///
 /// parse cdef_params()
 ///
@@ -1108,17 +1738,17 @@ fn parse_cdef_params<R: io::Read>(
         cdefp.cdef_damping = 3;
         return Some(cdefp);
     }
-    cdefp.cdef_damping = br.f::<u8>(2)? + 3; // f(2)
-    cdefp.cdef_bits = br.f::<u8>(2)?; // f(2)
+    cdefp.cdef_damping = br.name("cdef_damping_minus_3").f::<u8>(2)? + 3; // f(2)
+    cdefp.cdef_bits = br.name("cdef_bits").f::<u8>(2)?; // f(2)
     for i in 0..(1 << cdefp.cdef_bits) {
-        cdefp.cdef_y_pri_strength[i] = br.f::<u8>(4)?; // f(4)
-        cdefp.cdef_y_sec_strength[i] = br.f::<u8>(2)?; // f(2)
+        cdefp.cdef_y_pri_strength[i] = br.name("cdef_y_pri_strength").f::<u8>(4)?; // f(4)
+        cdefp.cdef_y_sec_strength[i] = br.name("cdef_y_sec_strength").f::<u8>(2)?; // f(2)
         if cdefp.cdef_y_sec_strength[i] == 3 {
             cdefp.cdef_y_sec_strength[i] += 1;
         }
         if sh.color_config.num_planes > 1 {
-            cdefp.cdef_uv_pri_strength[i] = br.f::<u8>(4)?; // f(4)
-            cdefp.cdef_uv_sec_strength[i] = br.f::<u8>(2)?; // f(2)
+            cdefp.cdef_uv_pri_strength[i] = br.name("cdef_uv_pri_strength").f::<u8>(4)?; // f(4)
+            cdefp.cdef_uv_sec_strength[i] = br.name("cdef_uv_sec_strength").f::<u8>(2)?; // f(2)
             if cdefp.cdef_uv_sec_strength[i] == 3 {
                 cdefp.cdef_uv_sec_strength[i] += 1;
             }
@@ -1128,6 +1758,31 @@ fn parse_cdef_params<R: io::Read>(
     Some(cdefp)
 }
 
+/// write cdef_params()
+fn write_cdef_params<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    sh: &SequenceHeader,
+    fh: &FrameHeader,
+) -> io::Result<()> {
+    let cdefp = &fh.cdef_params;
+    if fh.coded_lossless || fh.allow_intrabc || !sh.enable_cdef {
+        return Ok(());
+    }
+    // cdef_*_sec_strength 4 is coded as 3
+    let sec_strength = |strength: u8| if strength == 4 { 3 } else { strength };
+    bw.f(2, minus(cdefp.cdef_damping, 3, "cdef_damping")?)?; // cdef_damping_minus_3 f(2)
+    bw.f(2, cdefp.cdef_bits)?; // f(2)
+    for i in 0..(1 << cdefp.cdef_bits) {
+        bw.f(4, cdefp.cdef_y_pri_strength[i])?; // f(4)
+        bw.f(2, sec_strength(cdefp.cdef_y_sec_strength[i]))?; // f(2)
+        if sh.color_config.num_planes > 1 {
+            bw.f(4, cdefp.cdef_uv_pri_strength[i])?; // f(4)
+            bw.f(2, sec_strength(cdefp.cdef_uv_sec_strength[i]))?; // f(2)
+        }
+    }
+    Ok(())
+}
+
 ///
 /// parse lr_params()
 ///
@@ -1138,14 +1793,6 @@ fn parse_lr_params<R: io::Read>(
 ) -> Option<LrParams> {
     let mut lrp = LrParams::default();
 
-    #[allow(non_upper_case_globals)]
-    const Remap_Lr_Type: [u8; 4] = [
-        RESTORE_NONE,
-        RESTORE_SWITCHABLE,
-        RESTORE_WIENER,
-        RESTORE_SGRPROJ,
-    ];
-
     if fh.all_lossless || fh.allow_intrabc || !sh.enable_restoration {
         lrp.frame_restoration_type[0] = RESTORE_NONE;
         lrp.frame_restoration_type[1] = RESTORE_NONE;
@@ -1156,7 +1803,7 @@ fn parse_lr_params<R: io::Read>(
     lrp.uses_lr = false;
     let mut use_chroma_lr = false;
     for i in 0..sh.color_config.num_planes as usize {
-        let lr_type = br.f::<usize>(2)?; // f(2)
+        let lr_type = br.name("lr_type").f::<usize>(2)?; // f(2)
         lrp.frame_restoration_type[i] = Remap_Lr_Type[lr_type];
         if lrp.frame_restoration_type[i] != RESTORE_NONE {
             lrp.uses_lr = true;
@@ -1168,20 +1815,20 @@ fn parse_lr_params<R: io::Read>(
     if lrp.uses_lr {
         let mut lr_unit_shift;
         if sh.use_128x128_superblock {
-            lr_unit_shift = br.f::<u8>(1)?; // f(1)
+            lr_unit_shift = br.name("lr_unit_shift").f::<u8>(1)?; // f(1)
             lr_unit_shift += 1;
         } else {
-            lr_unit_shift = br.f::<u8>(1)?; // f(1)
+            lr_unit_shift = br.name("lr_unit_shift").f::<u8>(1)?; // f(1)
             if lr_unit_shift != 0 {
-                let lr_unit_extra_shift = br.f::<u8>(1)?; // f(1)
+                let lr_unit_extra_shift = br.name("lr_unit_extra_shift").f::<u8>(1)?; // f(1)
                 lr_unit_shift += lr_unit_extra_shift;
             }
         }
-        lrp.loop_restoration_size[0] = (RESTORATION_TILESIZE_MAX >> (2 - lr_unit_shift)) as u8;
+        lrp.loop_restoration_size[0] = (RESTORATION_TILESIZE_MAX >> (2 - lr_unit_shift)) as u16;
         let lr_uv_shift;
         if sh.color_config.subsampling_x != 0 && sh.color_config.subsampling_y != 0 && use_chroma_lr
         {
-            lr_uv_shift = br.f::<u8>(1)?; // f(1)
+            lr_uv_shift = br.name("lr_uv_shift").f::<u8>(1)?; // f(1)
         } else {
             lr_uv_shift = 0;
         }
@@ -1192,23 +1839,74 @@ fn parse_lr_params<R: io::Read>(
     Some(lrp)
 }
 
-/// read_tx_mode()
-fn read_tx_mode<R: io::Read>(br: &mut BitReader<R>, fh: &FrameHeader) -> Option<u8> {
-    let tx_mode: u8;
-    if fh.coded_lossless {
-        tx_mode = ONLY_4X4;
-    } else {
-        let tx_mode_select = br.f::<bool>(1)?; // f(1)
-        if tx_mode_select {
-            tx_mode = TX_MODE_SELECT;
-        } else {
-            tx_mode = TX_MODE_LARGEST;
-        }
+/// write lr_params()
+fn write_lr_params<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    sh: &SequenceHeader,
+    fh: &FrameHeader,
+) -> io::Result<()> {
+    let lrp = &fh.lr_params;
+    if fh.all_lossless || fh.allow_intrabc || !sh.enable_restoration {
+        return Ok(());
+    }
+    let mut use_chroma_lr = false;
+    for i in 0..sh.color_config.num_planes as usize {
+        let lr_type = Remap_Lr_Type
+            .iter()
+            .position(|&t| t == lrp.frame_restoration_type[i])
+            .ok_or_else(|| invalid_syntax("frame_restoration_type"))?;
+        bw.f(2, lr_type as u8)?; // f(2)
+        if i > 0 && lrp.frame_restoration_type[i] != RESTORE_NONE {
+            use_chroma_lr = true;
+        }
+    }
+    if lrp.uses_lr {
+        // LoopRestorationSize[0] = RESTORATION_TILESIZE_MAX >> (2 - lr_unit_shift)
+        let lr_unit_shift = (lrp.loop_restoration_size[0] as usize
+            / (RESTORATION_TILESIZE_MAX >> 2))
+            .trailing_zeros() as u8;
+        if sh.use_128x128_superblock {
+            bw.f(1, minus(lr_unit_shift, 1, "lr_unit_shift")?)?; // f(1)
+        } else {
+            bw.f(1, lr_unit_shift != 0)?; // f(1)
+            if lr_unit_shift != 0 {
+                bw.f(1, lr_unit_shift - 1)?; // lr_unit_extra_shift f(1)
+            }
+        }
+        if sh.color_config.subsampling_x != 0 && sh.color_config.subsampling_y != 0 && use_chroma_lr
+        {
+            let lr_uv_shift = lrp.loop_restoration_size[1] != lrp.loop_restoration_size[0];
+            bw.f(1, lr_uv_shift)?; // f(1)
+        }
+    }
+    Ok(())
+}
+
+/// read_tx_mode()
+fn read_tx_mode<R: io::Read>(br: &mut BitReader<R>, fh: &FrameHeader) -> Option<u8> {
+    let tx_mode: u8;
+    if fh.coded_lossless {
+        tx_mode = ONLY_4X4;
+    } else {
+        let tx_mode_select = br.name("tx_mode_select").f::<bool>(1)?; // f(1)
+        if tx_mode_select {
+            tx_mode = TX_MODE_SELECT;
+        } else {
+            tx_mode = TX_MODE_LARGEST;
+        }
     }
 
     Some(tx_mode)
 }
 
+/// write_tx_mode()
+fn write_tx_mode<W: io::Write>(bw: &mut BitWriter<W>, fh: &FrameHeader) -> io::Result<()> {
+    if !fh.coded_lossless {
+        bw.f(1, fh.tx_mode == TX_MODE_SELECT)?; // tx_mode_select f(1)
+    }
+    Ok(())
+}
+
 ///
 /// parse skip_mode_params()
 ///
@@ -1220,67 +1918,71 @@ fn parse_skip_mode_params<R: io::Read>(
 ) -> Option<SkipModeParams> {
     let mut smp = SkipModeParams::default();
 
-    let skip_mode_allowed;
+    if let Some(skip_mode_frame) = skip_mode_frame(sh, fh, rfman) {
+        smp.skip_mode_frame = skip_mode_frame;
+        smp.skip_mode_present = br.name("skip_mode_present").f::<bool>(1)?; // f(1)
+    } else {
+        smp.skip_mode_present = false;
+    }
+
+    Some(smp)
+}
+
+/// SkipModeFrame[] if skipModeAllowed
+fn skip_mode_frame(
+    sh: &SequenceHeader,
+    fh: &FrameHeader,
+    rfman: &av1::RefFrameManager,
+) -> Option<[u8; 2]> {
     if fh.frame_is_intra || !fh.reference_select || !sh.enable_order_hint {
-        skip_mode_allowed = false;
+        return None;
+    }
+    let mut forward_idx = -1;
+    let mut backward_idx = -1;
+    let (mut forward_hint, mut backward_hint) = (0, 0);
+    for i in 0..REFS_PER_FRAME {
+        let ref_hint = rfman.ref_order_hint[fh.ref_frame_idx[i] as usize] as i32;
+        if av1::get_relative_dist(ref_hint, fh.order_hint as i32, sh) < 0 {
+            if forward_idx < 0 || av1::get_relative_dist(ref_hint, forward_hint, sh) > 0 {
+                forward_idx = i as i32;
+                forward_hint = ref_hint;
+            }
+        } else if av1::get_relative_dist(ref_hint, fh.order_hint as i32, sh) > 0 {
+            if backward_idx < 0 || av1::get_relative_dist(ref_hint, backward_hint, sh) < 0 {
+                backward_idx = i as i32;
+                backward_hint = ref_hint;
+            }
+        }
+    }
+    if forward_idx < 0 {
+        return None;
+    }
+    let second_idx = if backward_idx >= 0 {
+        backward_idx
     } else {
-        let mut forward_idx = -1;
-        let mut backward_idx = -1;
-        let (mut forward_hint, mut backward_hint) = (0, 0);
+        let mut second_forward_id = -1;
+        let mut second_forward_hint = 0;
         for i in 0..REFS_PER_FRAME {
             let ref_hint = rfman.ref_order_hint[fh.ref_frame_idx[i] as usize] as i32;
-            if av1::get_relative_dist(ref_hint, fh.order_hint as i32, sh) < 0 {
-                if forward_idx < 0 || av1::get_relative_dist(ref_hint, forward_hint, sh) > 0 {
-                    forward_idx = i as i32;
-                    forward_hint = ref_hint;
-                }
-            } else if av1::get_relative_dist(ref_hint, fh.order_hint as i32, sh) > 0 {
-                if backward_idx < 0 || av1::get_relative_dist(ref_hint, backward_hint, sh) < 0 {
-                    backward_idx = i as i32;
-                    backward_hint = ref_hint;
+            if av1::get_relative_dist(ref_hint, forward_hint, sh) < 0 {
+                if second_forward_id < 0
+                    || av1::get_relative_dist(ref_hint, second_forward_hint, sh) > 0
+                {
+                    second_forward_id = i as i32;
+                    second_forward_hint = ref_hint;
                 }
             }
         }
-        if forward_idx < 0 {
-            skip_mode_allowed = false;
-        } else if backward_idx >= 0 {
-            skip_mode_allowed = true;
-            smp.skip_mode_frame[0] =
-                (LAST_FRAME as i32 + cmp::min(forward_idx, backward_idx)) as u8;
-            smp.skip_mode_frame[1] =
-                (LAST_FRAME as i32 + cmp::max(forward_idx, backward_idx)) as u8;
-        } else {
-            let mut second_forward_id = -1;
-            let mut second_forward_hint = 0;
-            for i in 0..REFS_PER_FRAME {
-                let ref_hint = rfman.ref_order_hint[fh.ref_frame_idx[i] as usize] as i32;
-                if av1::get_relative_dist(ref_hint, forward_hint, sh) < 0 {
-                    if second_forward_id < 0
-                        || av1::get_relative_dist(ref_hint, second_forward_hint, sh) > 0
-                    {
-                        second_forward_id = i as i32;
-                        second_forward_hint = ref_hint;
-                    }
-                }
-            }
-            if second_forward_id < 0 {
-                skip_mode_allowed = false;
-            } else {
-                skip_mode_allowed = true;
-                smp.skip_mode_frame[0] =
-                    (LAST_FRAME as i32 + cmp::min(forward_idx, second_forward_id)) as u8;
-                smp.skip_mode_frame[1] =
-                    (LAST_FRAME as i32 + cmp::max(forward_idx, second_forward_id)) as u8;
-            }
+        if second_forward_id < 0 {
+            return None;
         }
-    }
-    if skip_mode_allowed {
-        smp.skip_mode_present = br.f::<bool>(1)?; // f(1)
-    } else {
-        smp.skip_mode_present = false;
-    }
+        second_forward_id
+    };
 
-    Some(smp)
+    Some([
+        (LAST_FRAME as i32 + cmp::min(forward_idx, second_idx)) as u8,
+        (LAST_FRAME as i32 + cmp::max(forward_idx, second_idx)) as u8,
+    ])
 }
 
 ///
@@ -1292,6 +1994,7 @@ fn parse_global_motion_params<R: io::Read>(
 ) -> Option<GlobalMotionParams> {
     let mut gmp = GlobalMotionParams::default();
 
+    gmp.prev_gm_params = fh.global_motion_params.prev_gm_params;
     for ref_ in LAST_FRAME..=ALTREF_FRAME {
         gmp.gm_type[ref_] = IDENTITY;
         for i in 0..6 {
@@ -1306,14 +2009,14 @@ fn parse_global_motion_params<R: io::Read>(
         return Some(gmp);
     }
     for ref_ in LAST_FRAME..=ALTREF_FRAME {
-        let is_global = br.f::<bool>(1)?; // f(1)
+        let is_global = br.name("is_global").f::<bool>(1)?; // f(1)
         let type_;
         if is_global {
-            let is_rot_zoom = br.f::<bool>(1)?; // f(1)
+            let is_rot_zoom = br.name("is_rot_zoom").f::<bool>(1)?; // f(1)
             if is_rot_zoom {
                 type_ = ROTZOOM;
             } else {
-                let is_translation = br.f::<bool>(1)?; // f(1)
+                let is_translation = br.name("is_translation").f::<bool>(1)?; // f(1)
                 type_ = if is_translation { TRANSLATION } else { AFFINE };
             }
         } else {
@@ -1332,15 +2035,48 @@ fn parse_global_motion_params<R: io::Read>(
                 gmp.gm_params[ref_][5] = gmp.gm_params[ref_][2];
             }
         }
-        if type_ > TRANSLATION {
-            gmp.gm_params[ref_][0] = read_global_param(br, type_, ref_, 1, fh)?;
-            gmp.gm_params[ref_][1] = read_global_param(br, type_, ref_, 0, fh)?;
+        if type_ >= TRANSLATION {
+            gmp.gm_params[ref_][0] = read_global_param(br, type_, ref_, 0, fh)?;
+            gmp.gm_params[ref_][1] = read_global_param(br, type_, ref_, 1, fh)?;
         }
     }
 
     Some(gmp)
 }
 
+/// write global_motion_params()
+fn write_global_motion_params<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    fh: &FrameHeader,
+) -> io::Result<()> {
+    if fh.frame_is_intra {
+        return Ok(());
+    }
+    for ref_ in LAST_FRAME..=ALTREF_FRAME {
+        let type_ = fh.global_motion_params.gm_type[ref_];
+        bw.f(1, type_ != IDENTITY)?; // is_global f(1)
+        if type_ != IDENTITY {
+            bw.f(1, type_ == ROTZOOM)?; // is_rot_zoom f(1)
+            if type_ != ROTZOOM {
+                bw.f(1, type_ == TRANSLATION)?; // is_translation f(1)
+            }
+        }
+        if type_ >= ROTZOOM {
+            write_global_param(bw, type_, ref_, 2, fh)?;
+            write_global_param(bw, type_, ref_, 3, fh)?;
+            if type_ == AFFINE {
+                write_global_param(bw, type_, ref_, 4, fh)?;
+                write_global_param(bw, type_, ref_, 5, fh)?;
+            }
+        }
+        if type_ >= TRANSLATION {
+            write_global_param(bw, type_, ref_, 0, fh)?;
+            write_global_param(bw, type_, ref_, 1, fh)?;
+        }
+    }
+    Ok(())
+}
+
 /// read_global_param() return gm_params[ref][idx]
 fn read_global_param<R: io::Read>(
     br: &mut BitReader<R>,
@@ -1349,17 +2085,7 @@ fn read_global_param<R: io::Read>(
     idx: usize,
     fh: &FrameHeader,
 ) -> Option<i32> {
-    let mut abs_bits = GM_ABS_ALPHA_BITS;
-    let mut prec_bits = GM_ALPHA_PREC_BITS;
-    if idx < 2 {
-        if type_ == TRANSLATION {
-            abs_bits = GM_ABS_TRANS_ONLY_BITS - if fh.allow_high_precision_mv { 0 } else { 1 };
-            prec_bits = GM_TRANS_ONLY_PREC_BITS - if fh.allow_high_precision_mv { 0 } else { 1 };
-        } else {
-            abs_bits = GM_ABS_TRANS_BITS;
-            prec_bits = GM_TRANS_PREC_BITS;
-        }
-    }
+    let (abs_bits, prec_bits) = global_param_bits(type_, idx, fh);
     let prec_diff = WARPEDMODEL_PREC_BITS - prec_bits;
     let round = if (idx % 3) == 2 {
         1 << WARPEDMODEL_PREC_BITS
@@ -1374,6 +2100,40 @@ fn read_global_param<R: io::Read>(
     Some(gm_params)
 }
 
+/// write_global_param(), inverse of read_global_param()
+fn write_global_param<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    type_: u8,
+    ref_: usize,
+    idx: usize,
+    fh: &FrameHeader,
+) -> io::Result<()> {
+    let (abs_bits, prec_bits) = global_param_bits(type_, idx, fh);
+    let prec_diff = WARPEDMODEL_PREC_BITS - prec_bits;
+    let round = if (idx % 3) == 2 {
+        1 << WARPEDMODEL_PREC_BITS
+    } else {
+        0
+    };
+    let sub = if (idx % 3) == 2 { 1 << prec_bits } else { 0 };
+    let mx = 1 << abs_bits;
+    let r = (fh.global_motion_params.prev_gm_params[ref_][idx] >> prec_diff) - sub;
+    let x = (fh.global_motion_params.gm_params[ref_][idx] - round) >> prec_diff;
+    encode_signed_subexp_with_ref(bw, -mx, mx + 1, r, x)
+}
+
+/// (absBits, precBits) of gm_params[ref][idx]
+fn global_param_bits(type_: u8, idx: usize, fh: &FrameHeader) -> (usize, usize) {
+    if idx >= 2 {
+        (GM_ABS_ALPHA_BITS, GM_ALPHA_PREC_BITS)
+    } else if type_ == TRANSLATION {
+        let hp = if fh.allow_high_precision_mv { 0 } else { 1 };
+        (GM_ABS_TRANS_ONLY_BITS - hp, GM_TRANS_ONLY_PREC_BITS - hp)
+    } else {
+        (GM_ABS_TRANS_BITS, GM_TRANS_PREC_BITS)
+    }
+}
+
 /// decode_signed_subexp_with_ref()
 fn decode_signed_subexp_with_ref<R: io::Read>(
     br: &mut BitReader<R>,
@@ -1408,15 +2168,15 @@ fn decode_subexp<R: io::Read>(br: &mut BitReader<R>, num_syms: i32) -> Option<i3
         let b2 = if i != 0 { k + i - 1 } else { k };
         let a = 1 << b2;
         if num_syms <= mk + 3 * a {
-            let subexp_final_bits = br.ns((num_syms - mk) as u32)? as i32; // ns(numSyms-mk)
+            let subexp_final_bits = br.name("subexp_final_bits").ns((num_syms - mk) as u32)? as i32; // ns(numSyms-mk)
             return Some(subexp_final_bits + mk);
         } else {
-            let subexp_more_bits = br.f::<bool>(1)?; // f(1)
+            let subexp_more_bits = br.name("subexp_more_bits").f::<bool>(1)?; // f(1)
             if subexp_more_bits {
                 i += 1;
                 mk += a;
             } else {
-                let subexp_bits = br.ns(b2)? as i32; // ns(b2)
+                let subexp_bits = br.name("subexp_bits").f::<u32>(b2 as usize)? as i32; // f(b2)
                 return Some(subexp_bits + mk);
             }
         }
@@ -1435,6 +2195,65 @@ fn inverse_recenter(r: i32, v: i32) -> i32 {
     }
 }
 
+/// encode_signed_subexp_with_ref(), inverse of decode_signed_subexp_with_ref()
+fn encode_signed_subexp_with_ref<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    low: i32,
+    high: i32,
+    r: i32,
+    x: i32,
+) -> io::Result<()> {
+    encode_unsigned_subexp_with_ref(bw, high - low, r - low, x - low)
+}
+
+/// encode_unsigned_subexp_with_ref(), inverse of decode_unsigned_subexp_with_ref()
+fn encode_unsigned_subexp_with_ref<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    mx: i32,
+    r: i32,
+    x: i32,
+) -> io::Result<()> {
+    if (r << 1) <= mx {
+        encode_subexp(bw, mx, recenter(r, x))
+    } else {
+        encode_subexp(bw, mx, recenter(mx - 1 - r, mx - 1 - x))
+    }
+}
+
+/// encode_subexp(), inverse of decode_subexp()
+fn encode_subexp<W: io::Write>(bw: &mut BitWriter<W>, num_syms: i32, x: i32) -> io::Result<()> {
+    let mut i = 0;
+    let mut mk = 0;
+    let k = 3;
+    loop {
+        let b2 = if i != 0 { k + i - 1 } else { k };
+        let a = 1 << b2;
+        if num_syms <= mk + 3 * a {
+            return bw.ns((num_syms - mk) as u32, (x - mk) as u32); // subexp_final_bits ns(numSyms-mk)
+        }
+        let subexp_more_bits = x >= mk + a;
+        bw.f(1, subexp_more_bits)?; // f(1)
+        if subexp_more_bits {
+            i += 1;
+            mk += a;
+        } else {
+            return bw.f(b2 as usize, (x - mk) as u32); // subexp_bits f(b2)
+        }
+    }
+}
+
+/// recenter(), inverse of inverse_recenter()
+#[inline]
+fn recenter(r: i32, x: i32) -> i32 {
+    if x > 2 * r {
+        x
+    } else if x >= r {
+        (x - r) << 1
+    } else {
+        ((r - x) << 1) - 1
+    }
+}
+
 ///
 /// parse film_grain_params()
 ///
@@ -1442,48 +2261,74 @@ fn parse_film_grain_params<R: io::Read>(
     br: &mut BitReader<R>,
     sh: &SequenceHeader,
     fh: &FrameHeader,
+    rfman: &av1::RefFrameManager,
 ) -> Option<FilmGrainParams> {
     let mut fgp = FilmGrainParams::default();
 
-    if !sh.film_grain_params_present || (!fh.show_frame && fh.showable_frame) {
+    if !sh.film_grain_params_present || (!fh.show_frame && !fh.showable_frame) {
         // reset_grain_params()
         return Some(fgp);
     }
 
-    fgp.apply_grain = br.f::<bool>(1)?; // f(1)
+    fgp.apply_grain = br.name("apply_grain").f::<bool>(1)?; // f(1)
     if !fgp.apply_grain {
         // reset_grain_params()
         return Some(fgp);
     }
 
-    fgp.grain_seed = br.f::<u16>(16)?; // f(16)
+    fgp.grain_seed = br.name("grain_seed").f::<u16>(16)?; // f(16)
 
     fgp.update_grain = if fh.frame_type == INTER_FRAME {
-        br.f::<bool>(1)? // f(1)
+        br.name("update_grain").f::<bool>(1)? // f(1)
     } else {
         true // 1
     };
 
     if !fgp.update_grain {
-        fgp.film_grain_params_ref_idx = br.f::<u8>(3)?;
+        fgp.film_grain_params_ref_idx = br.name("film_grain_params_ref_idx").f::<u8>(3)?;
+
+        // It is a requirement of bitstream conformance that film_grain_params_ref_idx is equal to ref_frame_idx[j]
+        // for some value of j in the range 0 to REFS_PER_FRAME - 1.
+        if !fh.ref_frame_idx.contains(&fgp.film_grain_params_ref_idx) {
+            conformance::report(
+                &conformance::FILM_GRAIN_REF_IDX,
+                format!(
+                    "film_grain_params_ref_idx={} ref_frame_idx={:?}",
+                    fgp.film_grain_params_ref_idx, fh.ref_frame_idx
+                ),
+            );
+        }
 
-        assert!(fgp.film_grain_params_ref_idx <= (REFS_PER_FRAME - 1) as u8);
+        // load_grain_params(film_grain_params_ref_idx), update_grain and film_grain_params_ref_idx are kept
+        let ref_idx = fgp.film_grain_params_ref_idx;
+        let temp_grain_seed = fgp.grain_seed;
+        fgp = rfman.saved_film_grain_params[ref_idx as usize].clone();
+        fgp.grain_seed = temp_grain_seed;
+        fgp.update_grain = false;
+        fgp.film_grain_params_ref_idx = ref_idx;
+        return Some(fgp);
     }
 
-    fgp.num_y_points = br.f::<u8>(4)?;
+    fgp.num_y_points = br.name("num_y_points").f::<u8>(4)?;
 
-    assert!(fgp.num_y_points <= 14);
+    if fgp.num_y_points > 14 {
+        conformance::report(
+            &conformance::FILM_GRAIN_NUM_POINTS,
+            format!("num_y_points={}", fgp.num_y_points),
+        );
+    }
 
     for _ in 0..fgp.num_y_points {
-        fgp.point_y_value.push(br.f::<u8>(8)?); // f(8)
-        fgp.point_y_scaling.push(br.f::<u8>(8)?); // f(8)
+        fgp.point_y_value.push(br.name("point_y_value").f::<u8>(8)?); // f(8)
+        fgp.point_y_scaling
+            .push(br.name("point_y_scaling").f::<u8>(8)?); // f(8)
     }
 
     let cc = sh.color_config;
     fgp.chroma_scaling_from_luma = if cc.mono_chrome {
         false // 0
     } else {
-        br.f::<bool>(1)? // f(1)
+        br.name("chroma_scaling_from_luma").f::<bool>(1)? // f(1)
     };
 
     if sh.color_config.mono_chrome
@@ -1493,26 +2338,50 @@ fn parse_film_grain_params<R: io::Read>(
         fgp.num_cb_points = 0;
         fgp.num_cr_points = 0;
     } else {
-        fgp.num_cb_points = br.f::<u8>(4)?; // f(4)
+        fgp.num_cb_points = br.name("num_cb_points").f::<u8>(4)?; // f(4)
 
         for _ in 0..fgp.num_cb_points {
-            fgp.point_cb_value.push(br.f::<u8>(8)?); // f(8)
-            fgp.point_cb_scaling.push(br.f::<u8>(8)?); // f(8)
+            fgp.point_cb_value
+                .push(br.name("point_cb_value").f::<u8>(8)?); // f(8)
+            fgp.point_cb_scaling
+                .push(br.name("point_cb_scaling").f::<u8>(8)?); // f(8)
         }
 
-        fgp.num_cr_points = br.f::<u8>(4)?; // f(4)
+        fgp.num_cr_points = br.name("num_cr_points").f::<u8>(4)?; // f(4)
 
         for _ in 0..fgp.num_cr_points {
-            fgp.point_cr_value.push(br.f::<u8>(8)?); // f(8)
-            fgp.point_cr_scaling.push(br.f::<u8>(8)?); // f(8)
+            fgp.point_cr_value
+                .push(br.name("point_cr_value").f::<u8>(8)?); // f(8)
+            fgp.point_cr_scaling
+                .push(br.name("point_cr_scaling").f::<u8>(8)?); // f(8)
         }
     }
 
-    assert!(fgp.num_cb_points <= 10);
-    assert!(fgp.num_cr_points <= 10);
+    if fgp.num_cb_points > 10 || fgp.num_cr_points > 10 {
+        conformance::report(
+            &conformance::FILM_GRAIN_NUM_POINTS,
+            format!(
+                "num_cb_points={} num_cr_points={}",
+                fgp.num_cb_points, fgp.num_cr_points
+            ),
+        );
+    }
+    let points = [
+        ("point_y_value", &fgp.point_y_value),
+        ("point_cb_value", &fgp.point_cb_value),
+        ("point_cr_value", &fgp.point_cr_value),
+    ];
+    for (name, values) in points.iter() {
+        if let Some(i) = (1..values.len()).find(|&i| values[i] <= values[i - 1]) {
+            conformance::report(
+                &conformance::FILM_GRAIN_POINT_VALUE,
+                format!("{}[{}]={} after {}", name, i, values[i], values[i - 1]),
+            );
+        }
+    }
 
-    fgp.grain_scaling_minus_8 = br.f::<u8>(2)?; // f(2)
-    fgp.ar_coeff_lag = br.f::<u8>(2)?; // f(2)
+    fgp.grain_scaling_minus_8 = br.name("grain_scaling_minus_8").f::<u8>(2)?; // f(2)
+    fgp.ar_coeff_lag = br.name("ar_coeff_lag").f::<u8>(2)?; // f(2)
     let num_pos_luma = 2 * fgp.ar_coeff_lag * (fgp.ar_coeff_lag + 1);
     let num_pos_chroma;
 
@@ -1520,7 +2389,8 @@ fn parse_film_grain_params<R: io::Read>(
         num_pos_chroma = num_pos_luma + 1;
 
         for _ in 0..num_pos_luma {
-            fgp.ar_coeffs_y_plus_128.push(br.f::<u8>(8)?); // f(8)
+            fgp.ar_coeffs_y_plus_128
+                .push(br.name("ar_coeffs_y_plus_128").f::<u8>(8)?); // f(8)
         }
     } else {
         num_pos_chroma = num_pos_luma;
@@ -1528,37 +2398,112 @@ fn parse_film_grain_params<R: io::Read>(
 
     if fgp.chroma_scaling_from_luma || fgp.num_cb_points != 0 {
         for _ in 0..num_pos_chroma {
-            fgp.ar_coeffs_cb_plus_128.push(br.f::<u8>(8)?); // f(8)
+            fgp.ar_coeffs_cb_plus_128
+                .push(br.name("ar_coeffs_cb_plus_128").f::<u8>(8)?); // f(8)
         }
     }
 
     if fgp.chroma_scaling_from_luma || fgp.num_cr_points != 0 {
         for _ in 0..num_pos_chroma {
-            fgp.ar_coeffs_cr_plus_128.push(br.f::<u8>(8)?); // f(8)
+            fgp.ar_coeffs_cr_plus_128
+                .push(br.name("ar_coeffs_cr_plus_128").f::<u8>(8)?); // f(8)
         }
     }
 
-    fgp.ar_coeff_shift_minus_6 = br.f::<u8>(2)?; // f(2)
-    fgp.grain_scale_shift = br.f::<u8>(2)?; // f(2)
+    fgp.ar_coeff_shift_minus_6 = br.name("ar_coeff_shift_minus_6").f::<u8>(2)?; // f(2)
+    fgp.grain_scale_shift = br.name("grain_scale_shift").f::<u8>(2)?; // f(2)
 
     if fgp.num_cb_points != 0 {
-        fgp.cb_mult = br.f::<u8>(8)?; // f(8)
-        fgp.cb_luma_mult = br.f::<u8>(8)?; // f(8)
-        fgp.cb_offset = br.f::<u16>(9)?; // f(9)
+        fgp.cb_mult = br.name("cb_mult").f::<u8>(8)?; // f(8)
+        fgp.cb_luma_mult = br.name("cb_luma_mult").f::<u8>(8)?; // f(8)
+        fgp.cb_offset = br.name("cb_offset").f::<u16>(9)?; // f(9)
     }
 
     if fgp.num_cr_points != 0 {
-        fgp.cr_mult = br.f::<u8>(8)?; // f(8)
-        fgp.cr_luma_mult = br.f::<u8>(8)?; // f(8)
-        fgp.cr_offset = br.f::<u16>(9)?; // f(9)
+        fgp.cr_mult = br.name("cr_mult").f::<u8>(8)?; // f(8)
+        fgp.cr_luma_mult = br.name("cr_luma_mult").f::<u8>(8)?; // f(8)
+        fgp.cr_offset = br.name("cr_offset").f::<u16>(9)?; // f(9)
     }
 
-    fgp.overlap_flag = br.f::<bool>(1)?; // f(1)
-    fgp.clip_to_restricted_range = br.f::<bool>(1)?; // f(1)
+    fgp.overlap_flag = br.name("overlap_flag").f::<bool>(1)?; // f(1)
+    fgp.clip_to_restricted_range = br.name("clip_to_restricted_range").f::<bool>(1)?; // f(1)
 
     Some(fgp)
 }
 
+/// write film_grain_params()
+fn write_film_grain_params<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    sh: &SequenceHeader,
+    fh: &FrameHeader,
+) -> io::Result<()> {
+    let fgp = &fh.film_grain_params;
+    if !sh.film_grain_params_present || (!fh.show_frame && !fh.showable_frame) {
+        return Ok(());
+    }
+    bw.f(1, fgp.apply_grain)?; // f(1)
+    if !fgp.apply_grain {
+        return Ok(());
+    }
+    bw.f(16, fgp.grain_seed)?; // f(16)
+    if fh.frame_type == INTER_FRAME {
+        bw.f(1, fgp.update_grain)?; // f(1)
+    }
+    if !fgp.update_grain {
+        bw.f(3, fgp.film_grain_params_ref_idx)?; // f(3)
+        return Ok(());
+    }
+    bw.f(4, fgp.num_y_points)?; // f(4)
+    for (&value, &scaling) in fgp.point_y_value.iter().zip(&fgp.point_y_scaling) {
+        bw.f(8, value)?; // point_y_value f(8)
+        bw.f(8, scaling)?; // point_y_scaling f(8)
+    }
+    let cc = sh.color_config;
+    if !cc.mono_chrome {
+        bw.f(1, fgp.chroma_scaling_from_luma)?; // f(1)
+    }
+    if !(cc.mono_chrome
+        || fgp.chroma_scaling_from_luma
+        || (cc.subsampling_x == 1 && cc.subsampling_y == 1 && fgp.num_y_points == 0))
+    {
+        bw.f(4, fgp.num_cb_points)?; // f(4)
+        for (&value, &scaling) in fgp.point_cb_value.iter().zip(&fgp.point_cb_scaling) {
+            bw.f(8, value)?; // point_cb_value f(8)
+            bw.f(8, scaling)?; // point_cb_scaling f(8)
+        }
+        bw.f(4, fgp.num_cr_points)?; // f(4)
+        for (&value, &scaling) in fgp.point_cr_value.iter().zip(&fgp.point_cr_scaling) {
+            bw.f(8, value)?; // point_cr_value f(8)
+            bw.f(8, scaling)?; // point_cr_scaling f(8)
+        }
+    }
+    bw.f(2, fgp.grain_scaling_minus_8)?; // f(2)
+    bw.f(2, fgp.ar_coeff_lag)?; // f(2)
+    let ar_coeffs = fgp
+        .ar_coeffs_y_plus_128
+        .iter()
+        .chain(&fgp.ar_coeffs_cb_plus_128)
+        .chain(&fgp.ar_coeffs_cr_plus_128);
+    for &coeff in ar_coeffs {
+        bw.f(8, coeff)?; // ar_coeffs_*_plus_128 f(8)
+    }
+    bw.f(2, fgp.ar_coeff_shift_minus_6)?; // f(2)
+    bw.f(2, fgp.grain_scale_shift)?; // f(2)
+    if fgp.num_cb_points != 0 {
+        bw.f(8, fgp.cb_mult)?; // f(8)
+        bw.f(8, fgp.cb_luma_mult)?; // f(8)
+        bw.f(9, fgp.cb_offset)?; // f(9)
+    }
+    if fgp.num_cr_points != 0 {
+        bw.f(8, fgp.cr_mult)?; // f(8)
+        bw.f(8, fgp.cr_luma_mult)?; // f(8)
+        bw.f(9, fgp.cr_offset)?; // f(9)
+    }
+    bw.f(1, fgp.overlap_flag)?; // f(1)
+    bw.f(1, fgp.clip_to_restricted_range)?; // f(1)
+    Ok(())
+}
+
 /// setup_past_independence()
 fn setup_past_independence(fh: &mut FrameHeader) {
     // FeatureData[i][j]
@@ -1609,10 +2554,27 @@ pub fn parse_obu_header<R: io::Read>(bs: &mut R, sz: u32) -> io::Result<Obu> {
     let obu_type = (b1[0] >> 3) & 0b1111; // f(4)
     let obu_extension_flag = (b1[0] >> 2) & 1; // f(1)
     let obu_has_size_field = (b1[0] >> 1) & 1; // f(1)
+    let obu_reserved_1bit = b1[0] & 1; // f(1)
+    if obu_reserved_1bit != 0 {
+        conformance::report(
+            &conformance::OBU_RESERVED_BITS,
+            "obu_reserved_1bit=1".to_string(),
+        );
+    }
     let (temporal_id, spatial_id) = if obu_extension_flag == 1 {
         // parse obu_extension_header()
         let mut b2 = [0; 1];
         bs.read_exact(&mut b2)?;
+        let extension_header_reserved_3bits = b2[0] & 0b111; // f(3)
+        if extension_header_reserved_3bits != 0 {
+            conformance::report(
+                &conformance::OBU_RESERVED_BITS,
+                format!(
+                    "extension_header_reserved_3bits={}",
+                    extension_header_reserved_3bits
+                ),
+            );
+        }
         ((b2[0] >> 5) & 0b111, (b2[0] >> 3) & 0b11) // f(3),f(2)
     } else {
         (0, 0)
@@ -1656,68 +2618,118 @@ pub fn parse_sequence_header<R: io::Read>(bs: &mut R) -> Option<SequenceHeader>
     let mut br = BitReader::new(bs);
     let mut sh = SequenceHeader::default();
 
-    sh.seq_profile = br.f::<u8>(3)?; // f(3)
-    sh.still_picture = br.f::<bool>(1)?; // f(1)
-    sh.reduced_still_picture_header = br.f::<bool>(1)?; // f(1)
+    sh.seq_profile = br.name("seq_profile").f::<u8>(3)?; // f(3)
+    sh.still_picture = br.name("still_picture").f::<bool>(1)?; // f(1)
+    sh.reduced_still_picture_header = br.name("reduced_still_picture_header").f::<bool>(1)?; // f(1)
+    if sh.seq_profile > 2 {
+        conformance::report(
+            &conformance::SEQ_PROFILE,
+            format!("seq_profile={}", sh.seq_profile),
+        );
+    }
+    if sh.reduced_still_picture_header && !sh.still_picture {
+        conformance::report(
+            &conformance::REDUCED_STILL_PICTURE,
+            "reduced_still_picture_header=1 still_picture=0".to_string(),
+        );
+    }
     if sh.reduced_still_picture_header {
         sh.timing_info_present_flag = false;
         sh.decoder_model_info_present_flag = false;
         sh.initial_display_delay_present_flag = false;
         sh.operating_points_cnt = 1;
-        sh.op[0].operating_point_idc = 0;
-        sh.op[0].seq_level_idx = br.f::<u8>(5)?; // f(5)
-        sh.op[0].seq_tier = 0;
+        let mut op = OperatingPoint::default();
+        op.operating_point_idc = 0;
+        op.seq_level_idx = br.name("seq_level_idx").f::<u8>(5)?; // f(5)
+        op.seq_tier = 0;
+        sh.op.push(op);
         // decoder_model_present_for_this_op[0] = 0
         // initial_display_delay_present_for_this_op[0] = 0
-        assert!(true);
     } else {
-        sh.timing_info_present_flag = br.f::<bool>(1)?; // f(1)
+        sh.timing_info_present_flag = br.name("timing_info_present_flag").f::<bool>(1)?; // f(1)
         if sh.timing_info_present_flag {
             sh.timing_info = parse_timing_info(&mut br)?; // timing_info()
-            sh.decoder_model_info_present_flag = br.f::<bool>(1)?; // f(1)
+            sh.decoder_model_info_present_flag =
+                br.name("decoder_model_info_present_flag").f::<bool>(1)?; // f(1)
             if sh.decoder_model_info_present_flag {
-                unimplemented!("decoder_model_info()");
+                sh.decoder_model_info = parse_decoder_model_info(&mut br)?; // decoder_model_info()
             }
         } else {
             sh.decoder_model_info_present_flag = false;
         }
-        sh.initial_display_delay_present_flag = br.f::<bool>(1)?; // f(1)
-        sh.operating_points_cnt = br.f::<u8>(5)? + 1; // f(5)
-        assert_eq!(sh.operating_points_cnt, 1); // FIXME: support single operating point
-        for i in 0..(sh.operating_points_cnt) as usize {
-            sh.op[i].operating_point_idc = br.f::<u16>(12)?; // f(12)
-            sh.op[i].seq_level_idx = br.f::<u8>(5)?; // f(5)
-            if sh.op[i].seq_level_idx > 7 {
-                sh.op[i].seq_tier = br.f::<u8>(1)?; // f(1)
+        sh.initial_display_delay_present_flag =
+            br.name("initial_display_delay_present_flag").f::<bool>(1)?; // f(1)
+        sh.operating_points_cnt = br.name("operating_points_cnt_minus_1").f::<u8>(5)? + 1; // f(5)
+        for _ in 0..sh.operating_points_cnt {
+            let mut op = OperatingPoint::default();
+            op.operating_point_idc = br.name("operating_point_idc").f::<u16>(12)?; // f(12)
+            op.seq_level_idx = br.name("seq_level_idx").f::<u8>(5)?; // f(5)
+            if op.seq_level_idx > 7 {
+                op.seq_tier = br.name("seq_tier").f::<u8>(1)?; // f(1)
             } else {
-                sh.op[i].seq_tier = 0;
+                op.seq_tier = 0;
             }
             if sh.decoder_model_info_present_flag {
-                unimplemented!("decoder_model_info_present_flag==1");
+                op.decoder_model_present_for_this_op = br
+                    .name("decoder_model_present_for_this_op") // f(1)
+                    .f::<bool>(1)?;
+                if op.decoder_model_present_for_this_op {
+                    let n = sh.decoder_model_info.buffer_delay_length as usize;
+                    op.operating_parameters_info = parse_operating_parameters_info(&mut br, n)?;
+                    // operating_parameters_info(op)
+                }
+            } else {
+                op.decoder_model_present_for_this_op = false;
             }
             if sh.initial_display_delay_present_flag {
-                unimplemented!("initial_display_delay_present_flag==1");
+                op.initial_display_delay_present_for_this_op = br
+                    .name("initial_display_delay_present_for_this_op") // f(1)
+                    .f::<bool>(1)?;
+                if op.initial_display_delay_present_for_this_op {
+                    op.initial_display_delay =
+                        br.name("initial_display_delay_minus_1").f::<u8>(4)? + 1;
+                    // f(4)
+                }
             }
+            sh.op.push(op);
         }
     }
     // operatingPoint = choose_operating_point()
     // OperatingPointIdc = operating_point_idc[operatingPoint]
-    sh.frame_width_bits = br.f::<u8>(4)? + 1; // f(4)
-    sh.frame_height_bits = br.f::<u8>(4)? + 1; // f(4)
-    sh.max_frame_width = br.f::<u32>(sh.frame_width_bits as usize)? + 1; // f(n)
-    sh.max_frame_height = br.f::<u32>(sh.frame_height_bits as usize)? + 1; // f(n)
+    sh.frame_width_bits = br.name("frame_width_bits_minus_1").f::<u8>(4)? + 1; // f(4)
+    sh.frame_height_bits = br.name("frame_height_bits_minus_1").f::<u8>(4)? + 1; // f(4)
+    sh.max_frame_width = br
+        .name("max_frame_width_minus_1")
+        .f::<u32>(sh.frame_width_bits as usize)?
+        + 1; // f(n)
+    sh.max_frame_height = br
+        .name("max_frame_height_minus_1")
+        .f::<u32>(sh.frame_height_bits as usize)?
+        + 1; // f(n)
     if sh.reduced_still_picture_header {
         sh.frame_id_numbers_present_flag = false;
     } else {
-        sh.frame_id_numbers_present_flag = br.f::<bool>(1)?; // f(1)
+        sh.frame_id_numbers_present_flag = br
+            .name("frame_id_numbers_present_flag") // f(1)
+            .f::<bool>(1)?;
     }
     if sh.frame_id_numbers_present_flag {
-        sh.delta_frame_id_length = br.f::<u8>(4)? + 2; // f(4)
-        sh.additional_frame_id_length = br.f::<u8>(3)? + 1; // f(3)
+        sh.delta_frame_id_length = br.name("delta_frame_id_length_minus_2").f::<u8>(4)? + 2; // f(4)
+        sh.additional_frame_id_length =
+            br.name("additional_frame_id_length_minus_1").f::<u8>(3)? + 1; // f(3)
+        if sh.additional_frame_id_length + sh.delta_frame_id_length > 16 {
+            conformance::report(
+                &conformance::FRAME_ID_LENGTH,
+                format!(
+                    "additional_frame_id_length={} delta_frame_id_length={}",
+                    sh.additional_frame_id_length, sh.delta_frame_id_length
+                ),
+            );
+        }
     }
-    sh.use_128x128_superblock = br.f::<bool>(1)?; // f(1)
-    sh.enable_filter_intra = br.f::<bool>(1)?; // f(1)
-    sh.enable_intra_edge_filter = br.f::<bool>(1)?; // f(1)
+    sh.use_128x128_superblock = br.name("use_128x128_superblock").f::<bool>(1)?; // f(1)
+    sh.enable_filter_intra = br.name("enable_filter_intra").f::<bool>(1)?; // f(1)
+    sh.enable_intra_edge_filter = br.name("enable_intra_edge_filter").f::<bool>(1)?; // f(1)
     if sh.reduced_still_picture_header {
         sh.enable_interintra_compound = false;
         sh.enable_masked_compound = false;
@@ -1730,50 +2742,205 @@ pub fn parse_sequence_header<R: io::Read>(bs: &mut R) -> Option<SequenceHeader>
         sh.seq_force_integer_mv = SELECT_INTEGER_MV;
         sh.order_hint_bits = 0;
     } else {
-        sh.enable_interintra_compound = br.f::<bool>(1)?; // f(1)
-        sh.enable_masked_compound = br.f::<bool>(1)?; // f(1)
-        sh.enable_warped_motion = br.f::<bool>(1)?; // f(1)
-        sh.enable_dual_filter = br.f::<bool>(1)?; // f(1)
-        sh.enable_order_hint = br.f::<bool>(1)?; // f(1)
+        sh.enable_interintra_compound = br.name("enable_interintra_compound").f::<bool>(1)?; // f(1)
+        sh.enable_masked_compound = br.name("enable_masked_compound").f::<bool>(1)?; // f(1)
+        sh.enable_warped_motion = br.name("enable_warped_motion").f::<bool>(1)?; // f(1)
+        sh.enable_dual_filter = br.name("enable_dual_filter").f::<bool>(1)?; // f(1)
+        sh.enable_order_hint = br.name("enable_order_hint").f::<bool>(1)?; // f(1)
         if sh.enable_order_hint {
-            sh.enable_jnt_comp = br.f::<bool>(1)?; // f(1)
-            sh.enable_ref_frame_mvs = br.f::<bool>(1)?; // f(1)
+            sh.enable_jnt_comp = br.name("enable_jnt_comp").f::<bool>(1)?; // f(1)
+            sh.enable_ref_frame_mvs = br
+                .name("enable_ref_frame_mvs") // f(1)
+                .f::<bool>(1)?;
         } else {
             sh.enable_jnt_comp = false;
             sh.enable_ref_frame_mvs = false;
         }
-        let seq_choose_screen_content_tools = br.f::<bool>(1)?; // f(1)
+        let seq_choose_screen_content_tools =
+            br.name("seq_choose_screen_content_tools").f::<bool>(1)?; // f(1)
         if seq_choose_screen_content_tools {
             sh.seq_force_screen_content_tools = SELECT_SCREEN_CONTENT_TOOLS;
         } else {
-            sh.seq_force_screen_content_tools = br.f::<u8>(1)?; // f(1)
+            sh.seq_force_screen_content_tools =
+                br.name("seq_force_screen_content_tools").f::<u8>(1)?; // f(1)
         }
         if sh.seq_force_screen_content_tools > 0 {
-            let seq_choose_integer_mv = br.f::<u8>(1)?; // f(1)
+            let seq_choose_integer_mv = br.name("seq_choose_integer_mv").f::<u8>(1)?; // f(1)
             if seq_choose_integer_mv > 0 {
                 sh.seq_force_integer_mv = SELECT_INTEGER_MV;
             } else {
-                sh.seq_force_integer_mv = br.f::<u8>(1)?; // f(1)
+                sh.seq_force_integer_mv = br
+                    .name("seq_force_integer_mv") // f(1)
+                    .f::<u8>(1)?;
             }
         } else {
             sh.seq_force_integer_mv = SELECT_INTEGER_MV;
         }
         if sh.enable_order_hint {
-            sh.order_hint_bits = br.f::<u8>(3)? + 1; // f(3)
+            sh.order_hint_bits = br
+                .name("order_hint_bits_minus_1") // f(3)
+                .f::<u8>(3)?
+                + 1;
         } else {
             sh.order_hint_bits = 0;
         }
     }
-    sh.enable_superres = br.f::<bool>(1)?; // f(1)
-    sh.enable_cdef = br.f::<bool>(1)?; // f(1)
-    sh.enable_restoration = br.f::<bool>(1)?; // f(1)
+    sh.enable_superres = br.name("enable_superres").f::<bool>(1)?; // f(1)
+    sh.enable_cdef = br.name("enable_cdef").f::<bool>(1)?; // f(1)
+    sh.enable_restoration = br.name("enable_restoration").f::<bool>(1)?; // f(1)
     sh.color_config = parse_color_config(&mut br, &sh)?; // color_config()
-    sh.film_grain_params_present = br.f::<bool>(1)?; // f(1)
+    sh.film_grain_params_present = br.name("film_grain_params_present").f::<bool>(1)?; // f(1)
     trailing_bits(&mut br)?;
 
     Some(sh)
 }
 
+///
+/// write sequence_header_obu() payload, reproduce bitstream of parsed SequenceHeader
+///
+pub fn write_sequence_header(sh: &SequenceHeader) -> io::Result<Vec<u8>> {
+    let mut bw = BitWriter::new(Vec::new());
+    write_sequence_header_bits(&mut bw, sh)?;
+    bw.into_inner()
+}
+
+/// error for syntax element which is not supported by writer
+fn unsupported_syntax(syntax: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{} is not supported for writing", syntax),
+    )
+}
+
+/// error for syntax element value which cannot be written
+fn invalid_syntax(syntax: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid {} for writing", syntax),
+    )
+}
+
+/// coded value of syntax element with offset (e.g. xxx_minus_1), value must not be less than offset
+fn minus<T>(value: T, offset: T, syntax: &str) -> io::Result<T>
+where
+    T: Copy + PartialOrd + std::ops::Sub<Output = T>,
+{
+    if value < offset {
+        return Err(invalid_syntax(syntax));
+    }
+    Ok(value - offset)
+}
+
+fn write_sequence_header_bits<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    sh: &SequenceHeader,
+) -> io::Result<()> {
+    bw.f(3, sh.seq_profile)?; // f(3)
+    bw.f(1, sh.still_picture)?; // f(1)
+    bw.f(1, sh.reduced_still_picture_header)?; // f(1)
+    if sh.op.is_empty() || sh.op.len() != sh.operating_points_cnt as usize {
+        return Err(invalid_syntax("operating_points_cnt"));
+    }
+    if sh.reduced_still_picture_header {
+        bw.f(5, sh.op[0].seq_level_idx)?; // f(5)
+    } else {
+        bw.f(1, sh.timing_info_present_flag)?; // f(1)
+        if sh.timing_info_present_flag {
+            write_timing_info(bw, &sh.timing_info)?; // timing_info()
+            bw.f(1, sh.decoder_model_info_present_flag)?; // f(1)
+            if sh.decoder_model_info_present_flag {
+                write_decoder_model_info(bw, &sh.decoder_model_info)?; // decoder_model_info()
+            }
+        }
+        bw.f(1, sh.initial_display_delay_present_flag)?; // f(1)
+        let operating_points_cnt_minus_1 =
+            minus(sh.operating_points_cnt, 1, "operating_points_cnt")?;
+        bw.f(5, operating_points_cnt_minus_1)?; // f(5)
+        for op in sh.op.iter() {
+            bw.f(12, op.operating_point_idc)?; // f(12)
+            bw.f(5, op.seq_level_idx)?; // f(5)
+            if op.seq_level_idx > 7 {
+                bw.f(1, op.seq_tier)?; // f(1)
+            }
+            if sh.decoder_model_info_present_flag {
+                bw.f(1, op.decoder_model_present_for_this_op)?; // f(1)
+                if op.decoder_model_present_for_this_op {
+                    let n = sh.decoder_model_info.buffer_delay_length as usize;
+                    let opi = &op.operating_parameters_info;
+                    write_operating_parameters_info(bw, opi, n)?; // operating_parameters_info(op)
+                }
+            }
+            if sh.initial_display_delay_present_flag {
+                bw.f(1, op.initial_display_delay_present_for_this_op)?; // f(1)
+                if op.initial_display_delay_present_for_this_op {
+                    let initial_display_delay_minus_1 =
+                        minus(op.initial_display_delay, 1, "initial_display_delay")?;
+                    bw.f(4, initial_display_delay_minus_1)?; // f(4)
+                }
+            }
+        }
+    }
+    let frame_width_bits_minus_1 = minus(sh.frame_width_bits, 1, "frame_width_bits")?;
+    let frame_height_bits_minus_1 = minus(sh.frame_height_bits, 1, "frame_height_bits")?;
+    bw.f(4, frame_width_bits_minus_1)?; // f(4)
+    bw.f(4, frame_height_bits_minus_1)?; // f(4)
+    let max_frame_width_minus_1 = minus(sh.max_frame_width, 1, "max_frame_width")?;
+    let max_frame_height_minus_1 = minus(sh.max_frame_height, 1, "max_frame_height")?;
+    bw.f(sh.frame_width_bits as usize, max_frame_width_minus_1)?; // f(n)
+    bw.f(sh.frame_height_bits as usize, max_frame_height_minus_1)?; // f(n)
+    if !sh.reduced_still_picture_header {
+        bw.f(1, sh.frame_id_numbers_present_flag)?; // f(1)
+    }
+    if sh.frame_id_numbers_present_flag {
+        let delta_frame_id_length_minus_2 =
+            minus(sh.delta_frame_id_length, 2, "delta_frame_id_length")?;
+        let additional_frame_id_length_minus_1 = minus(
+            sh.additional_frame_id_length,
+            1,
+            "additional_frame_id_length",
+        )?;
+        bw.f(4, delta_frame_id_length_minus_2)?; // f(4)
+        bw.f(3, additional_frame_id_length_minus_1)?; // f(3)
+    }
+    bw.f(1, sh.use_128x128_superblock)?; // f(1)
+    bw.f(1, sh.enable_filter_intra)?; // f(1)
+    bw.f(1, sh.enable_intra_edge_filter)?; // f(1)
+    if !sh.reduced_still_picture_header {
+        bw.f(1, sh.enable_interintra_compound)?; // f(1)
+        bw.f(1, sh.enable_masked_compound)?; // f(1)
+        bw.f(1, sh.enable_warped_motion)?; // f(1)
+        bw.f(1, sh.enable_dual_filter)?; // f(1)
+        bw.f(1, sh.enable_order_hint)?; // f(1)
+        if sh.enable_order_hint {
+            bw.f(1, sh.enable_jnt_comp)?; // f(1)
+            bw.f(1, sh.enable_ref_frame_mvs)?; // f(1)
+        }
+        let seq_choose_screen_content_tools =
+            sh.seq_force_screen_content_tools == SELECT_SCREEN_CONTENT_TOOLS;
+        bw.f(1, seq_choose_screen_content_tools)?; // f(1)
+        if !seq_choose_screen_content_tools {
+            bw.f(1, sh.seq_force_screen_content_tools)?; // f(1)
+        }
+        if sh.seq_force_screen_content_tools > 0 {
+            let seq_choose_integer_mv = sh.seq_force_integer_mv == SELECT_INTEGER_MV;
+            bw.f(1, seq_choose_integer_mv)?; // f(1)
+            if !seq_choose_integer_mv {
+                bw.f(1, sh.seq_force_integer_mv)?; // f(1)
+            }
+        }
+        if sh.enable_order_hint {
+            let order_hint_bits_minus_1 = minus(sh.order_hint_bits, 1, "order_hint_bits")?;
+            bw.f(3, order_hint_bits_minus_1)?; // f(3)
+        }
+    }
+    bw.f(1, sh.enable_superres)?; // f(1)
+    bw.f(1, sh.enable_cdef)?; // f(1)
+    bw.f(1, sh.enable_restoration)?; // f(1)
+    write_color_config(bw, sh, &sh.color_config)?; // color_config()
+    bw.f(1, sh.film_grain_params_present)?; // f(1)
+    bw.trailing_bits()
+}
+
 ///
 /// parse frame_header
 ///
@@ -1791,8 +2958,10 @@ pub fn parse_frame_header<R: io::Read>(
     } else {
         0
     } as usize;
-    assert!(id_len <= 16);
-    assert!(NUM_REF_FRAMES <= 8);
+    if id_len > 16 {
+        return None;
+    }
+    const { assert!(NUM_REF_FRAMES <= 8) };
     let all_frames = ((1usize << NUM_REF_FRAMES) - 1) as u8; // 0xff
     if sh.reduced_still_picture_header {
         fh.show_existing_frame = false;
@@ -1801,28 +2970,33 @@ pub fn parse_frame_header<R: io::Read>(
         fh.show_frame = true;
         fh.showable_frame = false;
     } else {
-        fh.show_existing_frame = br.f::<bool>(1)?; // f(1)
+        fh.show_existing_frame = br.name("show_existing_frame").f::<bool>(1)?; // f(1)
         if fh.show_existing_frame {
-            fh.frame_to_show_map_idx = br.f::<u8>(3)?; // f(3)
+            fh.frame_to_show_map_idx = br.name("frame_to_show_map_idx").f::<u8>(3)?; // f(3)
             if sh.decoder_model_info_present_flag && !sh.timing_info.equal_picture_interval {
                 unimplemented!("temporal_point_info()");
             }
             fh.refresh_frame_flags = 0;
             if sh.frame_id_numbers_present_flag {
-                fh.display_frame_id = br.f::<u16>(id_len)?; // f(idLen)
+                fh.display_frame_id = br
+                    .name("display_frame_id") // f(idLen)
+                    .f::<u16>(id_len)?;
             }
             fh.frame_type = rfman.ref_frame_type[fh.frame_to_show_map_idx as usize];
             if fh.frame_type == KEY_FRAME {
                 fh.refresh_frame_flags = all_frames;
             }
             if sh.film_grain_params_present {
-                unimplemented!("load_grain_params()");
+                // load_grain_params(frame_to_show_map_idx)
+                fh.film_grain_params =
+                    rfman.saved_film_grain_params[fh.frame_to_show_map_idx as usize].clone();
             }
+            fh.header_bits = br.position();
             return Some(fh);
         }
-        fh.frame_type = br.f::<u8>(2)?; // f(2)
+        fh.frame_type = br.name("frame_type").f::<u8>(2)?; // f(2)
         fh.frame_is_intra = fh.frame_type == INTRA_ONLY_FRAME || fh.frame_type == KEY_FRAME;
-        fh.show_frame = br.f::<bool>(1)?; // f(1)
+        fh.show_frame = br.name("show_frame").f::<bool>(1)?; // f(1)
         if fh.show_frame
             && sh.decoder_model_info_present_flag
             && !sh.timing_info.equal_picture_interval
@@ -1832,12 +3006,14 @@ pub fn parse_frame_header<R: io::Read>(
         if fh.show_frame {
             fh.showable_frame = fh.frame_type != KEY_FRAME;
         } else {
-            fh.showable_frame = br.f::<bool>(1)?; // f(1)
+            fh.showable_frame = br.name("showable_frame").f::<bool>(1)?; // f(1)
         }
         if fh.frame_type == SWITCH_FRAME || (fh.frame_type == KEY_FRAME && fh.show_frame) {
             fh.error_resilient_mode = true;
         } else {
-            fh.error_resilient_mode = br.f::<bool>(1)?; // f(1)
+            fh.error_resilient_mode = br
+                .name("error_resilient_mode") // f(1)
+                .f::<bool>(1)?;
         }
     }
     if fh.frame_type == KEY_FRAME && fh.show_frame {
@@ -1849,15 +3025,17 @@ pub fn parse_frame_header<R: io::Read>(
             fh.order_hints[LAST_FRAME + i] = 0;
         }
     }
-    fh.disable_cdf_update = br.f::<bool>(1)?; // f(1)
+    fh.disable_cdf_update = br.name("disable_cdf_update").f::<bool>(1)?; // f(1)
     if sh.seq_force_screen_content_tools == SELECT_SCREEN_CONTENT_TOOLS {
-        fh.allow_screen_content_tools = br.f::<bool>(1)?; // f(1)
+        fh.allow_screen_content_tools = br
+            .name("allow_screen_content_tools") // f(1)
+            .f::<bool>(1)?;
     } else {
         fh.allow_screen_content_tools = sh.seq_force_screen_content_tools != 0;
     }
     if fh.allow_screen_content_tools {
         if sh.seq_force_integer_mv == SELECT_INTEGER_MV {
-            fh.force_integer_mv = br.f::<bool>(1)?; // f(1)
+            fh.force_integer_mv = br.name("force_integer_mv").f::<bool>(1)?; // f(1)
         } else {
             fh.force_integer_mv = sh.seq_force_integer_mv != 0;
         }
@@ -1869,7 +3047,7 @@ pub fn parse_frame_header<R: io::Read>(
     }
     if sh.frame_id_numbers_present_flag {
         let _prev_frame_id = fh.current_frame_id;
-        fh.current_frame_id = br.f::<u16>(id_len)?; // f(idLen)
+        fh.current_frame_id = br.name("current_frame_id").f::<u16>(id_len)?; // f(idLen)
         rfman.mark_ref_frames(id_len, sh, &fh);
     } else {
         fh.current_frame_id = 0;
@@ -1879,13 +3057,15 @@ pub fn parse_frame_header<R: io::Read>(
     } else if sh.reduced_still_picture_header {
         fh.frame_size_override_flag = false;
     } else {
-        fh.frame_size_override_flag = br.f::<bool>(1)?; // f(1)
+        fh.frame_size_override_flag = br
+            .name("frame_size_override_flag") // f(1)
+            .f::<bool>(1)?;
     }
-    fh.order_hint = br.f::<u8>(sh.order_hint_bits as usize)?; // f(OrderHintBits)
+    fh.order_hint = br.name("order_hint").f::<u8>(sh.order_hint_bits as usize)?; // f(OrderHintBits)
     if fh.frame_is_intra || fh.error_resilient_mode {
         fh.primary_ref_frame = PRIMARY_REF_NONE;
     } else {
-        fh.primary_ref_frame = br.f::<u8>(3)?; // f(3)
+        fh.primary_ref_frame = br.name("primary_ref_frame").f::<u8>(3)?; // f(3)
     }
     if sh.decoder_model_info_present_flag {
         unimplemented!("decoder_model_info_present_flag==1");
@@ -1896,12 +3076,14 @@ pub fn parse_frame_header<R: io::Read>(
     if fh.frame_type == SWITCH_FRAME || (fh.frame_type == KEY_FRAME && fh.show_frame) {
         fh.refresh_frame_flags = all_frames;
     } else {
-        fh.refresh_frame_flags = br.f::<u8>(8)?; // f(8)
+        fh.refresh_frame_flags = br.name("refresh_frame_flags").f::<u8>(8)?; // f(8)
     }
     if !fh.frame_is_intra || fh.refresh_frame_flags != all_frames {
         if fh.error_resilient_mode && sh.enable_order_hint {
             for i in 0..NUM_REF_FRAMES {
-                fh.ref_order_hint[i] = br.f::<u8>(sh.order_hint_bits as usize)?; // f(OrderHintBits)
+                fh.ref_order_hint[i] = br
+                    .name("ref_order_hint")
+                    .f::<u8>(sh.order_hint_bits as usize)?; // f(OrderHintBits)
                 if fh.ref_order_hint[i] != rfman.ref_order_hint[i] {
                     rfman.ref_valid[i] = false;
                 }
@@ -1914,7 +3096,7 @@ pub fn parse_frame_header<R: io::Read>(
         if fh.allow_screen_content_tools
             && fh.frame_size.upscaled_width == fh.frame_size.frame_width
         {
-            fh.allow_intrabc = br.f::<bool>(1)?; // f(1)
+            fh.allow_intrabc = br.name("allow_intrabc").f::<bool>(1)?; // f(1)
         }
     } else {
         if fh.frame_type == INTRA_ONLY_FRAME {
@@ -1923,32 +3105,40 @@ pub fn parse_frame_header<R: io::Read>(
             if fh.allow_screen_content_tools
                 && fh.frame_size.upscaled_width == fh.frame_size.frame_width
             {
-                fh.allow_intrabc = br.f::<bool>(1)?; // f(1)
+                fh.allow_intrabc = br.name("allow_intrabc").f::<bool>(1)?; // f(1)
             }
         } else {
             let frame_refs_short_signaling;
             if !sh.enable_order_hint {
                 frame_refs_short_signaling = false;
             } else {
-                frame_refs_short_signaling = br.f::<bool>(1)?; // f(1)
+                frame_refs_short_signaling = br.name("frame_refs_short_signaling").f::<bool>(1)?; // f(1)
                 if frame_refs_short_signaling {
-                    fh.last_frame_idx = br.f::<u8>(3)?; // f(3)
-                    fh.gold_frame_idx = br.f::<u8>(3)?; // f(3)
+                    fh.last_frame_idx = br.name("last_frame_idx").f::<u8>(3)?; // f(3)
+                    fh.gold_frame_idx = br.name("gold_frame_idx").f::<u8>(3)?; // f(3)
                     unimplemented!("set_frame_refs()");
                 }
             }
             for i in 0..REFS_PER_FRAME {
                 if !frame_refs_short_signaling {
-                    fh.ref_frame_idx[i] = br.f::<u8>(3)?; // f(3)
+                    fh.ref_frame_idx[i] = br.name("ref_frame_idx").f::<u8>(3)?; // f(3)
 
                     // ref_frame_idx[i] specifies which reference frames are used by inter frames.
                     // It is a requirement of bitstream conformance that RefValid[ref_frame_idx[i]] is equal to 1,
                     // and that the selected reference frames match the current frame in bit depth, profile,
                     // chroma subsampling, and color space.
-                    assert!(rfman.ref_valid[fh.ref_frame_idx[i] as usize]);
+                    if !rfman.ref_valid[fh.ref_frame_idx[i] as usize] {
+                        conformance::report(
+                            &conformance::REF_VALID,
+                            format!("RefValid[ref_frame_idx[{}]={}]=0", i, fh.ref_frame_idx[i]),
+                        );
+                    }
                 }
                 if sh.frame_id_numbers_present_flag {
-                    let delta_frame_id = br.f::<u16>(sh.delta_frame_id_length as usize)? + 1; // f(n)
+                    fh.delta_frame_id_minus_1[i] = br
+                        .name("delta_frame_id_minus_1")
+                        .f::<u16>(sh.delta_frame_id_length as usize)?; // f(n)
+                    let delta_frame_id = fh.delta_frame_id_minus_1[i] + 1;
                     let expected_frame_id =
                         (fh.current_frame_id + (1 << id_len) - delta_frame_id) % (1 << id_len);
 
@@ -1956,10 +3146,16 @@ pub fn parse_frame_header<R: io::Read>(
                     // It is a requirement of bitstream conformance that whenever expectedFrameId[i] is calculated,
                     // the value matches RefFrameId[ref_frame_idx[i]] (this contains the value of current_frame_id
                     // at the time that the frame indexed by ref_frame_idx was stored).
-                    assert_eq!(
-                        expected_frame_id,
-                        rfman.ref_frame_id[fh.ref_frame_idx[i] as usize]
-                    );
+                    let ref_frame_id = rfman.ref_frame_id[fh.ref_frame_idx[i] as usize];
+                    if expected_frame_id != ref_frame_id {
+                        conformance::report(
+                            &conformance::EXPECTED_FRAME_ID,
+                            format!(
+                                "expectedFrameId[{}]={} RefFrameId[{}]={}",
+                                i, expected_frame_id, fh.ref_frame_idx[i], ref_frame_id
+                            ),
+                        );
+                    }
                 }
             }
             if fh.frame_size_override_flag && !fh.error_resilient_mode {
@@ -1971,14 +3167,18 @@ pub fn parse_frame_header<R: io::Read>(
             if fh.force_integer_mv {
                 fh.allow_high_precision_mv = false;
             } else {
-                fh.allow_high_precision_mv = br.f::<bool>(1)?; // f(1)
+                fh.allow_high_precision_mv = br
+                    .name("allow_high_precision_mv") // f(1)
+                    .f::<bool>(1)?;
             }
             fh.interpolation_filter = read_interpolation_filter(&mut br)?; // read_interpolation_filter()
-            fh.is_motion_mode_switchable = br.f::<bool>(1)?; // f(1)
+            fh.is_motion_mode_switchable = br.name("is_motion_mode_switchable").f::<bool>(1)?; // f(1)
             if fh.error_resilient_mode || !sh.enable_ref_frame_mvs {
                 fh.use_ref_frame_mvs = false;
             } else {
-                fh.use_ref_frame_mvs = br.f::<bool>(1)?; // f(1)
+                fh.use_ref_frame_mvs = br
+                    .name("use_ref_frame_mvs") // f(1)
+                    .f::<bool>(1)?;
             }
         }
     }
@@ -1997,7 +3197,9 @@ pub fn parse_frame_header<R: io::Read>(
     if sh.reduced_still_picture_header || fh.disable_cdf_update {
         fh.disable_frame_end_update_cdf = true;
     } else {
-        fh.disable_frame_end_update_cdf = br.f::<bool>(1)?; // f(1)
+        fh.disable_frame_end_update_cdf = br
+            .name("disable_frame_end_update_cdf") // f(1)
+            .f::<bool>(1)?;
     }
     if fh.primary_ref_frame == PRIMARY_REF_NONE {
         // init_non_coeff_cdfs()
@@ -2035,22 +3237,194 @@ pub fn parse_frame_header<R: io::Read>(
         if fh.frame_is_intra {
             fh.reference_select = false;
         } else {
-            fh.reference_select = br.f::<bool>(1)?; // f(1)
+            fh.reference_select = br.name("reference_select").f::<bool>(1)?; // f(1)
         }
     }
     fh.skip_mode_params = parse_skip_mode_params(&mut br, sh, &fh, rfman)?; // skip_mode_params()
     if fh.frame_is_intra || fh.error_resilient_mode || !sh.enable_warped_motion {
         fh.allow_warped_motion = false;
     } else {
-        fh.allow_warped_motion = br.f::<bool>(1)?; // f(1)
+        fh.allow_warped_motion = br.name("allow_warped_motion").f::<bool>(1)?; // f(1)
     }
-    fh.reduced_tx_set = br.f::<bool>(1)?; // f(1)
+    fh.reduced_tx_set = br.name("reduced_tx_set").f::<bool>(1)?; // f(1)
     fh.global_motion_params = parse_global_motion_params(&mut br, &fh)?; // global_motion_params()
-    fh.film_grain_params = parse_film_grain_params(&mut br, sh, &fh)?; // film_grain_params()
+    fh.film_grain_params = parse_film_grain_params(&mut br, sh, &fh, rfman)?; // film_grain_params()
+    fh.header_bits = br.position();
 
     Some(fh)
 }
 
+///
+/// write frame_header_obu() payload, reproduce bitstream of parsed FrameHeader
+///
+/// rfman is reference state of parse_frame_header(), before reference frame update process.
+///
+pub fn write_frame_header(
+    fh: &FrameHeader,
+    sh: &SequenceHeader,
+    rfman: &av1::RefFrameManager,
+) -> io::Result<Vec<u8>> {
+    let mut bw = BitWriter::new(Vec::new());
+    write_frame_header_bits(&mut bw, fh, sh, rfman)?;
+    bw.trailing_bits()?;
+    bw.into_inner()
+}
+
+///
+/// write frame_obu() payload, frame header of FrameHeader followed by tile group data
+///
+/// tile_group is copied untouched, it starts at byte (header_bits + 7) / 8 of parsed OBU_FRAME payload.
+///
+pub fn write_frame(
+    fh: &FrameHeader,
+    sh: &SequenceHeader,
+    rfman: &av1::RefFrameManager,
+    tile_group: &[u8],
+) -> io::Result<Vec<u8>> {
+    let mut bw = BitWriter::new(Vec::new());
+    write_frame_header_bits(&mut bw, fh, sh, rfman)?;
+    bw.byte_alignment()?;
+    let mut payload = bw.into_inner()?;
+    payload.extend_from_slice(tile_group);
+    Ok(payload)
+}
+
+fn write_frame_header_bits<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    fh: &FrameHeader,
+    sh: &SequenceHeader,
+    rfman: &av1::RefFrameManager,
+) -> io::Result<()> {
+    // uncompressed_header()
+    let id_len = if sh.frame_id_numbers_present_flag {
+        sh.additional_frame_id_length + sh.delta_frame_id_length
+    } else {
+        0
+    } as usize;
+    let all_frames = ((1usize << NUM_REF_FRAMES) - 1) as u8; // 0xff
+    let implicit_error_resilient =
+        fh.frame_type == SWITCH_FRAME || (fh.frame_type == KEY_FRAME && fh.show_frame);
+    if !sh.reduced_still_picture_header {
+        bw.f(1, fh.show_existing_frame)?; // f(1)
+        if fh.show_existing_frame {
+            bw.f(3, fh.frame_to_show_map_idx)?; // f(3)
+            if sh.decoder_model_info_present_flag && !sh.timing_info.equal_picture_interval {
+                return Err(unsupported_syntax("temporal_point_info()"));
+            }
+            if sh.frame_id_numbers_present_flag {
+                bw.f(id_len, fh.display_frame_id)?; // f(idLen)
+            }
+            return Ok(());
+        }
+        bw.f(2, fh.frame_type)?; // f(2)
+        bw.f(1, fh.show_frame)?; // f(1)
+        if fh.show_frame
+            && sh.decoder_model_info_present_flag
+            && !sh.timing_info.equal_picture_interval
+        {
+            return Err(unsupported_syntax("temporal_point_info()"));
+        }
+        if !fh.show_frame {
+            bw.f(1, fh.showable_frame)?; // f(1)
+        }
+        if !implicit_error_resilient {
+            bw.f(1, fh.error_resilient_mode)?; // f(1)
+        }
+    }
+    bw.f(1, fh.disable_cdf_update)?; // f(1)
+    if sh.seq_force_screen_content_tools == SELECT_SCREEN_CONTENT_TOOLS {
+        bw.f(1, fh.allow_screen_content_tools)?; // f(1)
+    }
+    if fh.allow_screen_content_tools && sh.seq_force_integer_mv == SELECT_INTEGER_MV {
+        // coded value is not kept for intra frames, force_integer_mv is set to 1 after read
+        bw.f(1, fh.force_integer_mv && !fh.frame_is_intra)?; // f(1)
+    }
+    if sh.frame_id_numbers_present_flag {
+        bw.f(id_len, fh.current_frame_id)?; // f(idLen)
+    }
+    if fh.frame_type != SWITCH_FRAME && !sh.reduced_still_picture_header {
+        bw.f(1, fh.frame_size_override_flag)?; // f(1)
+    }
+    bw.f(sh.order_hint_bits as usize, fh.order_hint)?; // f(OrderHintBits)
+    if !fh.frame_is_intra && !fh.error_resilient_mode {
+        bw.f(3, fh.primary_ref_frame)?; // f(3)
+    }
+    if sh.decoder_model_info_present_flag {
+        return Err(unsupported_syntax("decoder_model_info_present_flag==1"));
+    }
+    if !implicit_error_resilient {
+        bw.f(8, fh.refresh_frame_flags)?; // f(8)
+    }
+    if (!fh.frame_is_intra || fh.refresh_frame_flags != all_frames)
+        && fh.error_resilient_mode
+        && sh.enable_order_hint
+    {
+        for &ref_order_hint in fh.ref_order_hint.iter() {
+            bw.f(sh.order_hint_bits as usize, ref_order_hint)?; // f(OrderHintBits)
+        }
+    }
+    if fh.frame_is_intra {
+        write_frame_size(bw, sh, fh)?; // frame_size()
+        write_render_size(bw, &fh.frame_size, &fh.render_size)?; // render_size()
+        if fh.allow_screen_content_tools
+            && fh.frame_size.upscaled_width == fh.frame_size.frame_width
+        {
+            bw.f(1, fh.allow_intrabc)?; // f(1)
+        }
+    } else {
+        if sh.enable_order_hint {
+            bw.f(1, 0u8)?; // frame_refs_short_signaling f(1)
+        }
+        for i in 0..REFS_PER_FRAME {
+            bw.f(3, fh.ref_frame_idx[i])?; // f(3)
+            if sh.frame_id_numbers_present_flag {
+                bw.f(
+                    sh.delta_frame_id_length as usize,
+                    fh.delta_frame_id_minus_1[i],
+                )?; // f(n)
+            }
+        }
+        if fh.frame_size_override_flag && !fh.error_resilient_mode {
+            return Err(unsupported_syntax("frame_size_with_refs()"));
+        } else {
+            write_frame_size(bw, sh, fh)?; // frame_size()
+            write_render_size(bw, &fh.frame_size, &fh.render_size)?; // render_size()
+        }
+        if !fh.force_integer_mv {
+            bw.f(1, fh.allow_high_precision_mv)?; // f(1)
+        }
+        write_interpolation_filter(bw, fh.interpolation_filter)?; // read_interpolation_filter()
+        bw.f(1, fh.is_motion_mode_switchable)?; // f(1)
+        if !fh.error_resilient_mode && sh.enable_ref_frame_mvs {
+            bw.f(1, fh.use_ref_frame_mvs)?; // f(1)
+        }
+    }
+    if !sh.reduced_still_picture_header && !fh.disable_cdf_update {
+        bw.f(1, fh.disable_frame_end_update_cdf)?; // f(1)
+    }
+    write_tile_info(bw, sh, &fh.frame_size, &fh.tile_info)?; // tile_info()
+    write_quantization_params(bw, &sh.color_config, &fh.quantization_params)?; // quantization_params()
+    write_segmentation_params(bw, fh)?; // segmentation_params()
+    write_delta_q_params(bw, &fh.quantization_params, &fh.delta_q_params)?; // delta_q_params()
+    write_delta_lf_params(bw, fh)?; // delta_lf_params()
+    write_loop_filter_params(bw, &sh.color_config, fh)?; // loop_filter_params()
+    write_cdef_params(bw, sh, fh)?; // cdef_params()
+    write_lr_params(bw, sh, fh)?; // lr_params()
+    write_tx_mode(bw, fh)?; // read_tx_mode()
+    if !fh.frame_is_intra {
+        bw.f(1, fh.reference_select)?; // f(1)
+    }
+    if skip_mode_frame(sh, fh, rfman).is_some() {
+        bw.f(1, fh.skip_mode_params.skip_mode_present)?; // f(1)
+    }
+    if !fh.frame_is_intra && !fh.error_resilient_mode && sh.enable_warped_motion {
+        bw.f(1, fh.allow_warped_motion)?; // f(1)
+    }
+    bw.f(1, fh.reduced_tx_set)?; // f(1)
+    write_global_motion_params(bw, fh)?; // global_motion_params()
+    write_film_grain_params(bw, sh, fh) // film_grain_params()
+}
+
 ///
 /// parse tile_list_obu()
 ///
@@ -2058,9 +3432,11 @@ pub fn parse_tile_list<R: io::Read>(bs: &mut R) -> Option<TileList> {
     let mut br = BitReader::new(bs);
     let mut tl = TileList::default();
 
-    tl.output_frame_width_in_tiles_minus_1 = br.f::<u8>(8)?;
-    tl.output_frame_height_in_tiles_minus_1 = br.f::<u8>(8)?;
-    tl.tile_count_minus_1 = br.f::<u16>(16)?;
+    tl.output_frame_width_in_tiles_minus_1 =
+        br.name("output_frame_width_in_tiles_minus_1").f::<u8>(8)?;
+    tl.output_frame_height_in_tiles_minus_1 =
+        br.name("output_frame_height_in_tiles_minus_1").f::<u8>(8)?;
+    tl.tile_count_minus_1 = br.name("tile_count_minus_1").f::<u16>(16)?;
 
     for _ in 0..=tl.tile_count_minus_1 {
         tl.tile_list_entries.push(parse_tile_list_entry(&mut br)?);
@@ -2075,10 +3451,10 @@ pub fn parse_tile_list<R: io::Read>(bs: &mut R) -> Option<TileList> {
 fn parse_tile_list_entry<R: io::Read>(br: &mut BitReader<R>) -> Option<TileListEntry> {
     let mut tle = TileListEntry::default();
 
-    tle.anchor_frame_idx = br.f::<u8>(8)?;
-    tle.anchor_tile_row = br.f::<u8>(8)?;
-    tle.anchor_tile_col = br.f::<u8>(8)?;
-    tle.tile_data_size_minus_1 = br.f::<u16>(16)?;
+    tle.anchor_frame_idx = br.name("anchor_frame_idx").f::<u8>(8)?;
+    tle.anchor_tile_row = br.name("anchor_tile_row").f::<u8>(8)?;
+    tle.anchor_tile_col = br.name("anchor_tile_col").f::<u8>(8)?;
+    tle.tile_data_size_minus_1 = br.name("tile_data_size_minus_1").f::<u16>(16)?;
 
     Some(tle)
 }
@@ -2109,18 +3485,55 @@ pub fn parse_metadata_obu<R: io::Read>(bs: &mut R) -> io::Result<MetadataObu> {
     }
 }
 
+///
+/// write metadata_obu() payload, support HDR CLL, HDR MDCV and ITU-T T.35 metadata
+///
+pub fn write_metadata_obu(metadata: &MetadataObu) -> io::Result<Vec<u8>> {
+    let mut bw = BitWriter::new(Vec::new());
+    match *metadata {
+        MetadataObu::HdrCll(ref meta) => {
+            bw.leb128(METADATA_TYPE_HDR_CLL)?; // metadata_type
+            write_hdr_cll_metadata(&mut bw, meta)?;
+        }
+        MetadataObu::HdrMdcv(ref meta) => {
+            bw.leb128(METADATA_TYPE_HDR_MDCV)?; // metadata_type
+            write_hdr_mdcv_metadata(&mut bw, meta)?;
+        }
+        MetadataObu::ItutT35(ref meta) => {
+            bw.leb128(METADATA_TYPE_ITUT_T35)?; // metadata_type
+            write_itu_t_t35_metadata(&mut bw, meta)?;
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "unsupported metadata_type for writing",
+            ))
+        }
+    }
+    bw.trailing_bits()?;
+    bw.into_inner()
+}
+
 ///
 /// parse metadata_hdr_cll()
 ///
 fn parse_hdr_cll_metadata<R: io::Read>(br: &mut BitReader<R>) -> Option<MetadataObu> {
     let mut meta = HdrCllMetadata::default();
 
-    meta.max_cll = br.f::<u16>(16)?; // f(16)
-    meta.max_fall = br.f::<u16>(16)?; // f(16)
+    meta.max_cll = br.name("max_cll").f::<u16>(16)?; // f(16)
+    meta.max_fall = br.name("max_fall").f::<u16>(16)?; // f(16)
 
     Some(MetadataObu::HdrCll(meta))
 }
 
+fn write_hdr_cll_metadata<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    meta: &HdrCllMetadata,
+) -> io::Result<()> {
+    bw.f(16, meta.max_cll)?; // f(16)
+    bw.f(16, meta.max_fall) // f(16)
+}
+
 ///
 /// parse metadata_hdr_mdcv()
 ///
@@ -2128,25 +3541,41 @@ fn parse_hdr_mdcv_metadata<R: io::Read>(br: &mut BitReader<R>) -> Option<Metadat
     let mut meta = HdrMdcvMetadata::default();
 
     for i in 0..3 {
-        meta.primary_chromaticity_x[i] = br.f::<u16>(16)?; // f(16)
-        meta.primary_chromaticity_y[i] = br.f::<u16>(16)?; // f(16)
+        meta.primary_chromaticity_x[i] = br.name("primary_chromaticity_x").f::<u16>(16)?; // f(16)
+        meta.primary_chromaticity_y[i] = br
+            .name("primary_chromaticity_y") // f(16)
+            .f::<u16>(16)?;
     }
 
-    meta.white_point_chromaticity_x = br.f::<u16>(16)?; // f(16)
-    meta.white_point_chromaticity_y = br.f::<u16>(16)?; // f(16)
-    meta.luminance_max = br.f::<u32>(32)?; // f(32)
-    meta.luminance_min = br.f::<u32>(32)?; // f(32)
+    meta.white_point_chromaticity_x = br.name("white_point_chromaticity_x").f::<u16>(16)?; // f(16)
+    meta.white_point_chromaticity_y = br.name("white_point_chromaticity_y").f::<u16>(16)?; // f(16)
+    meta.luminance_max = br.name("luminance_max").f::<u32>(32)?; // f(32)
+    meta.luminance_min = br.name("luminance_min").f::<u32>(32)?; // f(32)
 
     Some(MetadataObu::HdrMdcv(meta))
 }
 
+fn write_hdr_mdcv_metadata<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    meta: &HdrMdcvMetadata,
+) -> io::Result<()> {
+    for i in 0..3 {
+        bw.f(16, meta.primary_chromaticity_x[i])?; // f(16)
+        bw.f(16, meta.primary_chromaticity_y[i])?; // f(16)
+    }
+    bw.f(16, meta.white_point_chromaticity_x)?; // f(16)
+    bw.f(16, meta.white_point_chromaticity_y)?; // f(16)
+    bw.f(32, meta.luminance_max)?; // f(32)
+    bw.f(32, meta.luminance_min) // f(32)
+}
+
 ///
 /// parse metadata_scalability()
 ///
 fn parse_scalability_metadata<R: io::Read>(br: &mut BitReader<R>) -> Option<MetadataObu> {
     let mut meta = ScalabilityMetadata::default();
 
-    meta.scalability_mode_idc = br.f::<u8>(8)?; // f(8)
+    meta.scalability_mode_idc = br.name("scalability_mode_idc").f::<u8>(8)?; // f(8)
     if meta.scalability_mode_idc == SCALABILITY_SS {
         meta.scalability_structure = parse_scalability_structure(br);
     }
@@ -2160,38 +3589,55 @@ fn parse_scalability_metadata<R: io::Read>(br: &mut BitReader<R>) -> Option<Meta
 fn parse_scalability_structure<R: io::Read>(br: &mut BitReader<R>) -> Option<ScalabilityStructure> {
     let mut ss = ScalabilityStructure::default();
 
-    ss.spatial_layers_cnt_minus_1 = br.f::<u8>(2)?; // f(2)
-    ss.spatial_layer_dimensions_present_flag = br.f::<bool>(1)?; // f(1)
-    ss.spatial_layer_description_present_flag = br.f::<bool>(1)?; // f(1)
-    ss.temporal_group_description_present_flag = br.f::<bool>(1)?; // f(1)
-    ss.scalability_structure_reserved_3bits = br.f::<u8>(3)?; // f(3)
+    ss.spatial_layers_cnt_minus_1 = br.name("spatial_layers_cnt_minus_1").f::<u8>(2)?; // f(2)
+    ss.spatial_layer_dimensions_present_flag = br
+        .name("spatial_layer_dimensions_present_flag")
+        .f::<bool>(1)?; // f(1)
+    ss.spatial_layer_description_present_flag = br
+        .name("spatial_layer_description_present_flag")
+        .f::<bool>(1)?; // f(1)
+    ss.temporal_group_description_present_flag = br
+        .name("temporal_group_description_present_flag")
+        .f::<bool>(1)?; // f(1)
+    ss.scalability_structure_reserved_3bits =
+        br.name("scalability_structure_reserved_3bits").f::<u8>(3)?; // f(3)
 
     if ss.spatial_layer_dimensions_present_flag {
         for _ in 0..=ss.spatial_layers_cnt_minus_1 {
-            ss.spatial_layer_max_width.push(br.f::<u16>(16)?); // f(16)
-            ss.spatial_layer_max_height.push(br.f::<u16>(16)?); // f(16)
+            ss.spatial_layer_max_width
+                .push(br.name("spatial_layer_max_width").f::<u16>(16)?); // f(16)
+            ss.spatial_layer_max_height
+                .push(br.name("spatial_layer_max_height").f::<u16>(16)?); // f(16)
         }
     }
 
     if ss.spatial_layer_description_present_flag {
         for _ in 0..=ss.spatial_layers_cnt_minus_1 {
-            ss.spatial_layer_ref_id.push(br.f::<u8>(8)?); // f(8)
+            ss.spatial_layer_ref_id
+                .push(br.name("spatial_layer_ref_id").f::<u8>(8)?); // f(8)
         }
     }
 
     if ss.temporal_group_description_present_flag {
-        ss.temporal_group_size = br.f::<u8>(8)?; // f(8)
+        ss.temporal_group_size = br.name("temporal_group_size").f::<u8>(8)?; // f(8)
 
         for i in 0..ss.temporal_group_size as usize {
-            ss.temporal_group_temporal_id.push(br.f::<u8>(3)?); // f(3)
-            ss.temporal_group_temporal_switching_up_point_flag
-                .push(br.f::<bool>(1)?); // f(1)
-            ss.temporal_group_spatial_switching_up_point_flag
-                .push(br.f::<bool>(1)?); // f(1)
-            ss.temporal_group_ref_cnt.push(br.f::<u8>(3)?); // f(3)
+            ss.temporal_group_temporal_id
+                .push(br.name("temporal_group_temporal_id").f::<u8>(3)?); // f(3)
+            ss.temporal_group_temporal_switching_up_point_flag.push(
+                br.name("temporal_group_temporal_switching_up_point_flag")
+                    .f::<bool>(1)?,
+            ); // f(1)
+            ss.temporal_group_spatial_switching_up_point_flag.push(
+                br.name("temporal_group_spatial_switching_up_point_flag")
+                    .f::<bool>(1)?,
+            ); // f(1)
+            ss.temporal_group_ref_cnt
+                .push(br.name("temporal_group_ref_cnt").f::<u8>(3)?); // f(3)
 
             for _ in 0..ss.temporal_group_ref_cnt[i] {
-                ss.temporal_group_ref_pic_diff[i].push(br.f::<u8>(8)?); // f(8)
+                ss.temporal_group_ref_pic_diff[i]
+                    .push(br.name("temporal_group_ref_pic_diff").f::<u8>(8)?); // f(8)
             }
         }
     }
@@ -2205,61 +3651,479 @@ fn parse_scalability_structure<R: io::Read>(br: &mut BitReader<R>) -> Option<Sca
 fn parse_itu_t_t35_metadata<R: io::Read>(br: &mut BitReader<R>) -> Option<MetadataObu> {
     let mut meta = ItutT35Metadata::default();
 
-    meta.itu_t_t35_country_code = br.f::<u8>(8)?; // f(8)
+    meta.itu_t_t35_country_code = br.name("itu_t_t35_country_code").f::<u8>(8)?; // f(8)
 
     meta.itu_t_t35_country_code_extension_byte = if meta.itu_t_t35_country_code == 0xFF {
-        br.f::<u8>(8) // f(8)
+        br.name("itu_t_t35_country_code_extension_byte").f::<u8>(8) // f(8)
     } else {
         None
     };
 
-    while let Some(byte) = br.f::<u8>(8) {
+    while let Some(byte) = br.name("byte").f::<u8>(8) {
         meta.itu_t_t35_payload_bytes.push(byte);
     }
 
     Some(MetadataObu::ItutT35(meta))
 }
 
+/// itu_t_t35_payload_bytes are written as is, without trailing_bits()
+fn write_itu_t_t35_metadata<W: io::Write>(
+    bw: &mut BitWriter<W>,
+    meta: &ItutT35Metadata,
+) -> io::Result<()> {
+    bw.f(8, meta.itu_t_t35_country_code)?; // f(8)
+    if meta.itu_t_t35_country_code == 0xFF {
+        let ext = meta.itu_t_t35_country_code_extension_byte.unwrap_or(0);
+        bw.f(8, ext)?; // f(8)
+    }
+    for &byte in meta.itu_t_t35_payload_bytes.iter() {
+        bw.f(8, byte)?; // f(8)
+    }
+    Ok(())
+}
+
+///
+/// parse ITU-T T.35 message (e.g. Matroska BlockAdditional)
+///
+pub fn parse_itut_t35(data: &[u8]) -> Option<ItutT35Metadata> {
+    let mut br = BitReader::new(data);
+    match parse_itu_t_t35_metadata(&mut br)? {
+        MetadataObu::ItutT35(meta) => Some(meta),
+        _ => None,
+    }
+}
+
+impl ItutT35Metadata {
+    /// terminal provider code (first 2 bytes of payload)
+    pub fn terminal_provider_code(&self) -> Option<u16> {
+        match self.itu_t_t35_payload_bytes[..] {
+            [hi, lo, ..] => Some((hi as u16) << 8 | lo as u16),
+            _ => None,
+        }
+    }
+
+    ///
+    /// parse payload as SMPTE ST 2094-40 dynamic metadata (HDR10+)
+    ///
+    pub fn parse_st2094_40(&self) -> Option<St2094_40Metadata> {
+        if self.itu_t_t35_country_code != T35_COUNTRY_CODE_USA {
+            return None;
+        }
+        let mut br = BitReader::new(&self.itu_t_t35_payload_bytes[..]);
+        if br.f::<u16>(16)? != T35_PROVIDER_CODE_SAMSUNG
+            || br.f::<u16>(16)? != T35_PROVIDER_ORIENTED_CODE_ST2094_40
+            || br.f::<u8>(8)? != ST2094_40_APPLICATION_IDENTIFIER
+        {
+            return None;
+        }
+        let mut meta = St2094_40Metadata::default();
+        meta.application_version = br.f::<u8>(8)?; // u(8)
+        meta.num_windows = br.f::<u8>(2)?; // u(2)
+        if meta.num_windows == 0 {
+            return None;
+        }
+        for _ in 1..meta.num_windows {
+            // elliptical processing window parameters (153 bits), skipped
+            for _ in 0..6 {
+                br.f::<u16>(16)?;
+            }
+            br.f::<u8>(8)?; // rotation_angle
+            for _ in 0..3 {
+                br.f::<u16>(16)?;
+            }
+            br.f::<u8>(1)?; // overlap_process_option
+        }
+        meta.targeted_system_display_maximum_luminance = br.f::<u32>(27)?; // u(27)
+        let targeted_system_display_actual_peak_luminance_flag = br.f::<bool>(1)?; // u(1)
+        if targeted_system_display_actual_peak_luminance_flag {
+            meta.targeted_system_display_actual_peak_luminance = parse_peak_luminance(&mut br)?;
+        }
+        for _ in 0..meta.num_windows {
+            let mut win = St2094_40Window::default();
+            for i in 0..3 {
+                win.maxscl[i] = br.f::<u32>(17)?; // u(17)
+            }
+            win.average_maxrgb = br.f::<u32>(17)?; // u(17)
+            let num_distribution_maxrgb_percentiles = br.f::<u8>(4)?; // u(4)
+            for _ in 0..num_distribution_maxrgb_percentiles {
+                let percentage = br.f::<u8>(7)?; // u(7)
+                let percentile = br.f::<u32>(17)?; // u(17)
+                win.distribution_maxrgb.push((percentage, percentile));
+            }
+            win.fraction_bright_pixels = br.f::<u16>(10)?; // u(10)
+            meta.windows.push(win);
+        }
+        let mastering_display_actual_peak_luminance_flag = br.f::<bool>(1)?; // u(1)
+        if mastering_display_actual_peak_luminance_flag {
+            meta.mastering_display_actual_peak_luminance = parse_peak_luminance(&mut br)?;
+        }
+        for win in meta.windows.iter_mut() {
+            let tone_mapping_flag = br.f::<bool>(1)?; // u(1)
+            if tone_mapping_flag {
+                let knee_point_x = br.f::<u16>(12)?; // u(12)
+                let knee_point_y = br.f::<u16>(12)?; // u(12)
+                win.knee_point = Some((knee_point_x, knee_point_y));
+                let num_bezier_curve_anchors = br.f::<u8>(4)?; // u(4)
+                for _ in 0..num_bezier_curve_anchors {
+                    win.bezier_curve_anchors.push(br.f::<u16>(10)?); // u(10)
+                }
+            }
+            let color_saturation_mapping_flag = br.f::<bool>(1)?; // u(1)
+            if color_saturation_mapping_flag {
+                win.color_saturation_weight = Some(br.f::<u8>(6)?); // u(6)
+            }
+        }
+        Some(meta)
+    }
+}
+
+impl St2094_40Metadata {
+    ///
+    /// write SMPTE ST 2094-40 dynamic metadata (HDR10+) as ITU-T T.35 message,
+    /// processing window parameters are not kept in parsed metadata then single window only
+    ///
+    pub fn to_itut_t35(&self) -> io::Result<ItutT35Metadata> {
+        if self.num_windows != 1 || self.windows.len() != 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "ST 2094-40 metadata with multiple processing windows is not supported",
+            ));
+        }
+        let mut bw = BitWriter::new(Vec::new());
+        self.write_bits(&mut bw)?;
+        bw.byte_alignment()?;
+        Ok(ItutT35Metadata {
+            itu_t_t35_country_code: T35_COUNTRY_CODE_USA,
+            itu_t_t35_country_code_extension_byte: None,
+            itu_t_t35_payload_bytes: bw.into_inner()?,
+        })
+    }
+
+    fn write_bits<W: io::Write>(&self, bw: &mut BitWriter<W>) -> io::Result<()> {
+        bw.f(16, T35_PROVIDER_CODE_SAMSUNG)?; // itu_t_t35_terminal_provider_code
+        bw.f(16, T35_PROVIDER_ORIENTED_CODE_ST2094_40)?; // itu_t_t35_terminal_provider_oriented_code
+        bw.f(8, ST2094_40_APPLICATION_IDENTIFIER)?; // application_identifier
+        bw.f(8, self.application_version)?; // u(8)
+        bw.f(2, self.num_windows)?; // u(2)
+        bw.f(27, self.targeted_system_display_maximum_luminance)?; // u(27)
+        let peak = &self.targeted_system_display_actual_peak_luminance;
+        bw.f(1, !peak.is_empty())?; // targeted_system_display_actual_peak_luminance_flag
+        if !peak.is_empty() {
+            write_peak_luminance(bw, peak)?;
+        }
+        for win in self.windows.iter() {
+            for i in 0..3 {
+                bw.f(17, win.maxscl[i])?; // u(17)
+            }
+            bw.f(17, win.average_maxrgb)?; // u(17)
+            bw.f(4, win.distribution_maxrgb.len() as u32)?; // num_distribution_maxrgb_percentiles
+            for &(percentage, percentile) in win.distribution_maxrgb.iter() {
+                bw.f(7, percentage)?; // u(7)
+                bw.f(17, percentile)?; // u(17)
+            }
+            bw.f(10, win.fraction_bright_pixels)?; // u(10)
+        }
+        let peak = &self.mastering_display_actual_peak_luminance;
+        bw.f(1, !peak.is_empty())?; // mastering_display_actual_peak_luminance_flag
+        if !peak.is_empty() {
+            write_peak_luminance(bw, peak)?;
+        }
+        for win in self.windows.iter() {
+            bw.f(1, win.knee_point.is_some())?; // tone_mapping_flag
+            if let Some((knee_point_x, knee_point_y)) = win.knee_point {
+                bw.f(12, knee_point_x)?; // u(12)
+                bw.f(12, knee_point_y)?; // u(12)
+                bw.f(4, win.bezier_curve_anchors.len() as u32)?; // num_bezier_curve_anchors
+                for &anchor in win.bezier_curve_anchors.iter() {
+                    bw.f(10, anchor)?; // u(10)
+                }
+            }
+            bw.f(1, win.color_saturation_weight.is_some())?; // color_saturation_mapping_flag
+            if let Some(weight) = win.color_saturation_weight {
+                bw.f(6, weight)?; // u(6)
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_peak_luminance<W: io::Write>(bw: &mut BitWriter<W>, table: &[Vec<u8>]) -> io::Result<()> {
+    bw.f(5, table.len() as u32)?; // num_rows
+    bw.f(5, table.first().map_or(0, |row| row.len() as u32))?; // num_cols
+    for row in table.iter() {
+        for &value in row.iter() {
+            bw.f(4, value)?; // u(4)
+        }
+    }
+    Ok(())
+}
+
+/// parse actual peak luminance table (rows x cols)
+fn parse_peak_luminance<R: io::Read>(br: &mut BitReader<R>) -> Option<Vec<Vec<u8>>> {
+    let num_rows = br.f::<u8>(5)?; // u(5)
+    let num_cols = br.f::<u8>(5)?; // u(5)
+    let mut table = Vec::with_capacity(num_rows as usize);
+    for _ in 0..num_rows {
+        let mut row = Vec::with_capacity(num_cols as usize);
+        for _ in 0..num_cols {
+            row.push(br.f::<u8>(4)?); // u(4)
+        }
+        table.push(row);
+    }
+    Some(table)
+}
+
 ///
 /// parse metadata_timecode()
 ///
 fn parse_timecode_metadata<R: io::Read>(br: &mut BitReader<R>) -> Option<MetadataObu> {
     let mut meta = TimecodeMetadata::default();
 
-    meta.counting_type = br.f::<u8>(5)?; // f(5)
-    meta.full_timestamp_flag = br.f::<bool>(1)?; // f(1)
-    meta.discontinuity_flag = br.f::<bool>(1)?; // f(1)
-    meta.cnt_dropped_flag = br.f::<bool>(1)?; // f(1)
-    meta.n_frames = br.f::<u16>(9)?; // f(9)
+    meta.counting_type = br.name("counting_type").f::<u8>(5)?; // f(5)
+    meta.full_timestamp_flag = br.name("full_timestamp_flag").f::<bool>(1)?; // f(1)
+    meta.discontinuity_flag = br.name("discontinuity_flag").f::<bool>(1)?; // f(1)
+    meta.cnt_dropped_flag = br.name("cnt_dropped_flag").f::<bool>(1)?; // f(1)
+    meta.n_frames = br.name("n_frames").f::<u16>(9)?; // f(9)
 
     if meta.full_timestamp_flag {
-        meta.seconds_value = br.f::<u8>(6)?; // f(6)
-        meta.minutes_value = br.f::<u8>(6)?; // f(6)
-        meta.hours_value = br.f::<u8>(5)?; // f(5)
+        meta.seconds_value = br.name("seconds_value").f::<u8>(6)?; // f(6)
+        meta.minutes_value = br.name("minutes_value").f::<u8>(6)?; // f(6)
+        meta.hours_value = br.name("hours_value").f::<u8>(5)?; // f(5)
     } else {
-        meta.seconds_flag = br.f::<bool>(1)?; // f(1)
+        meta.seconds_flag = br.name("seconds_flag").f::<bool>(1)?; // f(1)
 
         if meta.seconds_flag {
-            meta.seconds_value = br.f::<u8>(6)?; // f(6)
-            meta.minutes_flag = br.f::<bool>(1)?; // f(1)
+            meta.seconds_value = br.name("seconds_value").f::<u8>(6)?; // f(6)
+            meta.minutes_flag = br.name("minutes_flag").f::<bool>(1)?; // f(1)
 
             if meta.minutes_flag {
-                meta.minutes_value = br.f::<u8>(6)?; // f(6)
-                meta.hours_flag = br.f::<bool>(1)?; // f(1)
+                meta.minutes_value = br.name("minutes_value").f::<u8>(6)?; // f(6)
+                meta.hours_flag = br.name("hours_flag").f::<bool>(1)?; // f(1)
 
                 if meta.hours_flag {
-                    meta.hours_value = br.f::<u8>(5)?; // f(5)
+                    meta.hours_value = br.name("hours_value").f::<u8>(5)?; // f(5)
                 }
             }
         }
     }
 
-    meta.time_offset_length = br.f::<u8>(5)?; // f(5)
+    meta.time_offset_length = br.name("time_offset_length").f::<u8>(5)?; // f(5)
 
     if meta.time_offset_length > 0 {
-        meta.time_offset_value = br.f::<u32>(meta.time_offset_length as usize)?;
-        // f(time_offset_length)
+        meta.time_offset_value = br
+            .name("time_offset_value")
+            .f::<u32>(meta.time_offset_length as usize)?; // f(time_offset_length)
     }
 
     Some(MetadataObu::Timecode(meta))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::{self, TemporalUnitReader};
+    use std::fs;
+    use std::path;
+
+    type Obus = Vec<(Obu, Vec<u8>)>; // (Obu, OBU bytes including obu_header)
+
+    /// OBUs of IVF and raw OBU streams in streams/ directory
+    fn stream_obus() -> Vec<(path::PathBuf, Obus)> {
+        let dir = path::Path::new(env!("CARGO_MANIFEST_DIR")).join("streams");
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == "ivf" || ext == "obu")
+            })
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+        paths
+            .into_iter()
+            .map(|path| {
+                let mut reader = io::BufReader::new(fs::File::open(&path).unwrap());
+                let fmt =
+                    crate::probe_fileformat_with_hint(&mut reader, path.to_str().unwrap()).unwrap();
+                let mut tu_reader = TemporalUnitReader::new(reader, fmt).unwrap();
+                let mut obus = Vec::new();
+                while let Some(tu) = tu_reader.next_unit().unwrap() {
+                    let mut data = &tu.data[..];
+                    while !data.is_empty() {
+                        let sz = data.len() as u32;
+                        obus.push(stream::read_obu(&mut data, sz).unwrap());
+                    }
+                }
+                (path, obus)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sequence_header_roundtrip() {
+        let mut count = 0;
+        for (path, obus) in stream_obus() {
+            for (obu, bytes) in obus {
+                if obu.obu_type != OBU_SEQUENCE_HEADER {
+                    continue;
+                }
+                let payload = &bytes[obu.header_len as usize..];
+                let sh = parse_sequence_header(&mut &payload[..]).unwrap();
+                let written = write_sequence_header(&sh).unwrap();
+                assert_eq!(written, payload, "{}", path.display());
+                let reparsed = parse_sequence_header(&mut &written[..]).unwrap();
+                assert_eq!(format!("{:?}", reparsed), format!("{:?}", sh));
+                count += 1;
+            }
+        }
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn frame_header_roundtrip() {
+        let mut count = 0;
+        for (path, obus) in stream_obus() {
+            let mut seq = av1::Sequence::new();
+            for (obu, bytes) in obus {
+                let payload = &bytes[obu.header_len as usize..];
+                match obu.obu_type {
+                    OBU_SEQUENCE_HEADER => seq.sh = parse_sequence_header(&mut &payload[..]),
+                    OBU_FRAME_HEADER | OBU_FRAME => {
+                        let sh = seq.sh.clone().unwrap();
+                        let fh =
+                            parse_frame_header(&mut &payload[..], &sh, &mut seq.rfman).unwrap();
+                        let written = if obu.obu_type == OBU_FRAME && !fh.show_existing_frame {
+                            let tile_group = &payload[fh.header_bits.div_ceil(8) as usize..];
+                            write_frame(&fh, &sh, &seq.rfman, tile_group).unwrap()
+                        } else {
+                            write_frame_header(&fh, &sh, &seq.rfman).unwrap()
+                        };
+                        assert_eq!(written, payload, "{} {}", path.display(), obu);
+                        // reference state update of parse_frame_header() is idempotent
+                        let reparsed =
+                            parse_frame_header(&mut &written[..], &sh, &mut seq.rfman).unwrap();
+                        assert_eq!(format!("{:?}", reparsed), format!("{:?}", fh));
+                        seq.decode_frame_wrapup(&fh);
+                        count += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn sequence_header_decoder_model_roundtrip() {
+        // timing_info_present_flag=1, decoder_model_info_present_flag=1,
+        // initial_display_delay_present_flag=1, operating point #0 has both parameters
+        let payload = [
+            0x04, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x79, 0x78, 0x00, 0x00, 0x00, 0x09,
+            0xcf, 0x00, 0x00, 0x21, 0x03, 0xe8, 0x07, 0xd0, 0x65, 0xdc, 0xfc, 0xfc, 0x01, 0x80,
+            0x08,
+        ];
+        let mut sh = parse_sequence_header(&mut &payload[..]).unwrap();
+        assert_eq!(write_sequence_header(&sh).unwrap(), payload);
+
+        // add operating point without decoder model and initial display delay
+        sh.operating_points_cnt = 2;
+        sh.op.push(OperatingPoint {
+            operating_point_idc: 0x101,
+            seq_level_idx: 4,
+            ..Default::default()
+        });
+        let written = write_sequence_header(&sh).unwrap();
+        let reparsed = parse_sequence_header(&mut &written[..]).unwrap();
+        assert_eq!(format!("{:?}", reparsed), format!("{:?}", sh));
+    }
+
+    #[test]
+    fn sequence_header_invalid_value() {
+        let sh = SequenceHeader {
+            timing_info_present_flag: true,
+            timing_info: TimingInfo {
+                equal_picture_interval: true,
+                ..Default::default()
+            },
+            operating_points_cnt: 1,
+            op: vec![OperatingPoint::default()],
+            frame_width_bits: 16,
+            frame_height_bits: 16,
+            max_frame_width: 1920,
+            max_frame_height: 1080,
+            ..Default::default()
+        };
+        let invalid = |sh: &SequenceHeader| {
+            write_sequence_header(sh).unwrap_err().kind() == io::ErrorKind::InvalidInput
+        };
+        // num_ticks_per_picture_minus_1
+        assert!(invalid(&sh));
+        let mut sh = SequenceHeader {
+            timing_info_present_flag: false,
+            ..sh
+        };
+        assert!(write_sequence_header(&sh).is_ok());
+        sh.operating_points_cnt = 2;
+        assert!(invalid(&sh));
+        sh.operating_points_cnt = 1;
+        sh.max_frame_width = 0;
+        assert!(invalid(&sh));
+    }
+
+    #[test]
+    fn frame_header_invalid_value() {
+        let (_, obus) = stream_obus().into_iter().next().unwrap();
+        let mut sh = None;
+        let mut payload = None;
+        for (obu, bytes) in obus {
+            let data = bytes[obu.header_len as usize..].to_vec();
+            match obu.obu_type {
+                OBU_SEQUENCE_HEADER => sh = parse_sequence_header(&mut &data[..]),
+                OBU_FRAME_HEADER | OBU_FRAME => {
+                    payload = Some(data);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let (sh, payload) = (sh.unwrap(), payload.unwrap());
+        let invalid = |modify: &dyn Fn(&mut FrameHeader)| {
+            let mut rfman = av1::RefFrameManager::new();
+            let mut fh = parse_frame_header(&mut &payload[..], &sh, &mut rfman).unwrap();
+            assert!(write_frame_header(&fh, &sh, &rfman).is_ok());
+            modify(&mut fh);
+            write_frame_header(&fh, &sh, &rfman).unwrap_err().kind() == io::ErrorKind::InvalidInput
+        };
+        // render_width_minus_1
+        assert!(invalid(&|fh| fh.render_size.render_width = 0));
+        // render_height_minus_1
+        assert!(invalid(&|fh| fh.render_size.render_height = 0));
+        // FrameRestorationType
+        if sh.enable_restoration {
+            assert!(invalid(&|fh| fh.lr_params.frame_restoration_type[0] = 0xff));
+        }
+        // cdef_damping_minus_3
+        if sh.enable_cdef {
+            assert!(invalid(&|fh| fh.cdef_params.cdef_damping = 0));
+        }
+    }
+
+    #[test]
+    fn frame_header_unsupported_syntax() {
+        let sh = SequenceHeader {
+            enable_order_hint: true,
+            order_hint_bits: 7,
+            ..Default::default()
+        };
+        let fh = FrameHeader {
+            frame_type: INTER_FRAME,
+            show_frame: true,
+            frame_size_override_flag: true,
+            ..Default::default()
+        };
+        let rfman = av1::RefFrameManager::new();
+        let err = write_frame_header(&fh, &sh, &rfman).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
@@ -0,0 +1,288 @@
+//
+// DASH MPD / HLS playlist for local segment files
+//
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const MAX_TEMPLATE_SEGMENTS: u64 = 100_000; // limit of $Number$ enumeration without SegmentTimeline
+
+///
+/// initialization segment and media segments in playback order
+///
+#[derive(Debug, Default)]
+pub struct SegmentList {
+    pub init: Option<PathBuf>,
+    pub segments: Vec<PathBuf>,
+    pub codecs: Option<String>, // declared codec parameter string
+}
+
+/// resolve URI relative to playlist directory, remote URI is not supported
+fn resolve_uri(base: &Path, uri: &str) -> io::Result<PathBuf> {
+    if uri.contains("://") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("remote segment URI is not supported: {}", uri),
+        ));
+    }
+    // strip query string and fragment
+    let path = uri.split(['?', '#']).next().unwrap_or(uri);
+    Ok(base.join(path))
+}
+
+/// get attribute value in XML start tag
+fn xml_attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let key = format!("{}=\"", name);
+    let mut pos = 0;
+    while let Some(i) = tag[pos..].find(&key) {
+        let start = pos + i;
+        pos = start + key.len();
+        // attribute name must follow whitespace
+        if tag[..start].ends_with(char::is_whitespace) {
+            let end = tag[pos..].find('"')? + pos;
+            return Some(&tag[pos..end]);
+        }
+    }
+    None
+}
+
+/// find XML element of the name, return (start tag, element body)
+fn xml_element<'a>(xml: &'a str, name: &str) -> Option<(&'a str, &'a str)> {
+    let open = format!("<{}", name);
+    let mut pos = 0;
+    while let Some(i) = xml[pos..].find(&open) {
+        let start = pos + i;
+        pos = start + open.len();
+        // element name must end here
+        if !xml[pos..].starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/') {
+            continue;
+        }
+        let tag_end = xml[start..].find('>')? + start + 1;
+        let tag = &xml[start..tag_end];
+        if tag.ends_with("/>") {
+            return Some((tag, ""));
+        }
+        let close = format!("</{}>", name);
+        let body_end = xml[tag_end..]
+            .find(&close)
+            .map_or(xml.len(), |i| tag_end + i);
+        return Some((tag, &xml[tag_end..body_end]));
+    }
+    None
+}
+
+/// iterate XML start tags of the name
+fn xml_tags<'a>(xml: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    let mut rest = xml;
+    std::iter::from_fn(move || {
+        let (tag, _) = xml_element(rest, name)?;
+        let pos = rest.find(tag)? + tag.len();
+        rest = &rest[pos..];
+        Some(tag)
+    })
+}
+
+/// expand $RepresentationID$, $Bandwidth$, $Number$ and $Time$ identifiers in SegmentTemplate
+fn expand_template(
+    template: &str,
+    rep_id: &str,
+    bandwidth: &str,
+    number: u64,
+    time: u64,
+) -> String {
+    let mut result = String::new();
+    let mut parts = template.split('$');
+    result.push_str(parts.next().unwrap_or(""));
+    let mut is_identifier = true;
+    for part in parts {
+        if is_identifier {
+            // identifier with optional format tag, e.g. $Number%05d$
+            let (name, width) = match part.find("%0") {
+                Some(i) => (
+                    &part[..i],
+                    part[i + 2..].trim_end_matches('d').parse().unwrap_or(0),
+                ),
+                None => (part, 0),
+            };
+            match name {
+                "" => result.push('$'),
+                "RepresentationID" => result.push_str(rep_id),
+                "Bandwidth" => result.push_str(bandwidth),
+                "Number" => result.push_str(&format!("{:0width$}", number, width = width)),
+                "Time" => result.push_str(&format!("{:0width$}", time, width = width)),
+                _ => result.push_str(part),
+            }
+        } else {
+            result.push_str(part);
+        }
+        is_identifier = !is_identifier;
+    }
+    result
+}
+
+/// list segment start times in SegmentTimeline
+fn segment_timeline(body: &str) -> Option<Vec<u64>> {
+    let (_, timeline) = xml_element(body, "SegmentTimeline")?;
+    let mut times = Vec::new();
+    let mut time = 0;
+    for s in xml_tags(timeline, "S") {
+        if let Some(t) = xml_attr(s, "t").and_then(|t| t.parse().ok()) {
+            time = t;
+        }
+        let d: u64 = xml_attr(s, "d").and_then(|d| d.parse().ok()).unwrap_or(0);
+        let r: i64 = xml_attr(s, "r").and_then(|r| r.parse().ok()).unwrap_or(0);
+        // negative repeat count (open-ended) is treated as single segment
+        for _ in 0..=r.max(0) {
+            times.push(time);
+            time += d;
+        }
+    }
+    Some(times)
+}
+
+///
+/// parse DASH MPD, select first Representation with "av01" codecs
+///
+pub fn parse_mpd(xml: &str, base: &Path) -> io::Result<SegmentList> {
+    let representations: Vec<&str> = xml_tags(xml, "Representation").collect();
+    let rep_tag = representations
+        .iter()
+        .find(|tag| xml_attr(tag, "codecs").is_some_and(|codecs| codecs.starts_with("av01")))
+        .or_else(|| representations.first())
+        .copied()
+        .unwrap_or("");
+    let rep_id = xml_attr(rep_tag, "id").unwrap_or("");
+    let bandwidth = xml_attr(rep_tag, "bandwidth").unwrap_or("");
+
+    // segment information in Representation, or inherited from AdaptationSet/Period
+    let rep_pos = xml.find(rep_tag).unwrap_or(xml.len());
+    let rep_body = xml_element(&xml[rep_pos..], "Representation").map_or("", |(_, body)| body);
+    let scope = |name| {
+        xml_element(rep_body, name).or_else(|| {
+            let head = &xml[..rep_pos];
+            let pos = head.rfind(&format!("<{}", name))?;
+            xml_element(&xml[pos..], name)
+        })
+    };
+
+    let mut list = SegmentList {
+        codecs: xml_attr(rep_tag, "codecs").map(String::from),
+        ..Default::default()
+    };
+    if list.codecs.is_none() {
+        // codecs attribute inherited from AdaptationSet
+        let head = &xml[..rep_pos];
+        if let Some(pos) = head.rfind("<AdaptationSet") {
+            list.codecs = xml_element(&xml[pos..], "AdaptationSet")
+                .and_then(|(tag, _)| xml_attr(tag, "codecs"))
+                .map(String::from);
+        }
+    }
+    if let Some((tag, body)) = scope("SegmentTemplate") {
+        if let Some(init) = xml_attr(tag, "initialization") {
+            let uri = expand_template(init, rep_id, bandwidth, 0, 0);
+            list.init = Some(resolve_uri(base, &uri)?);
+        }
+        let media = xml_attr(tag, "media").unwrap_or("");
+        let start_number = xml_attr(tag, "startNumber")
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(1);
+        match segment_timeline(body) {
+            Some(times) => {
+                for (i, &time) in times.iter().enumerate() {
+                    let number = start_number + i as u64;
+                    let uri = expand_template(media, rep_id, bandwidth, number, time);
+                    list.segments.push(resolve_uri(base, &uri)?);
+                }
+            }
+            None => {
+                // enumerate existing segment files
+                for number in start_number..start_number + MAX_TEMPLATE_SEGMENTS {
+                    let uri = expand_template(media, rep_id, bandwidth, number, 0);
+                    let path = resolve_uri(base, &uri)?;
+                    if !path.is_file() {
+                        break;
+                    }
+                    list.segments.push(path);
+                }
+            }
+        }
+    } else if let Some((_, body)) = scope("SegmentList") {
+        if let Some((tag, _)) = xml_element(body, "Initialization") {
+            if let Some(uri) = xml_attr(tag, "sourceURL") {
+                list.init = Some(resolve_uri(base, uri)?);
+            }
+        }
+        for tag in xml_tags(body, "SegmentURL") {
+            if let Some(uri) = xml_attr(tag, "media") {
+                list.segments.push(resolve_uri(base, uri)?);
+            }
+        }
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "SegmentTemplate or SegmentList not found in MPD",
+        ));
+    }
+    Ok(list)
+}
+
+///
+/// parse HLS media playlist, master playlist follows first variant stream
+///
+pub fn parse_m3u8(text: &str, base: &Path) -> io::Result<SegmentList> {
+    let mut list = SegmentList::default();
+    let mut variant = None;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(attrs) = line.strip_prefix("#EXT-X-MAP:") {
+            if let Some(uri) = xml_attr(&format!(" {}", attrs.replace(',', " ")), "URI") {
+                list.init = Some(resolve_uri(base, uri)?);
+            }
+        } else if line.starts_with("#EXT-X-STREAM-INF") {
+            // AV1 entry in CODECS="..." attribute (comma separated list)
+            let codecs = line
+                .split("CODECS=\"")
+                .nth(1)
+                .and_then(|rest| rest.split('"').next())
+                .and_then(|codecs| codecs.split(',').find(|c| c.trim().starts_with("av01")))
+                .map(|c| c.trim().to_string());
+            variant = Some(codecs);
+        } else if !line.starts_with('#') {
+            let path = resolve_uri(base, line)?;
+            if let Some(codecs) = variant {
+                let mut list = open_playlist(&path)?;
+                list.codecs = list.codecs.or(codecs);
+                return Ok(list);
+            }
+            list.segments.push(path);
+        }
+    }
+    Ok(list)
+}
+
+/// check playlist file extension (.mpd or .m3u8)
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("mpd") || ext.eq_ignore_ascii_case("m3u8"))
+}
+
+///
+/// read DASH MPD or HLS playlist file
+///
+pub fn open_playlist(path: &Path) -> io::Result<SegmentList> {
+    let text = fs::read_to_string(path)?;
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let is_mpd = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("mpd"));
+    if is_mpd {
+        parse_mpd(&text, base)
+    } else {
+        parse_m3u8(&text, base)
+    }
+}
@@ -0,0 +1,410 @@
+//
+// RTP Payload Format for AV1
+// https://aomediacodec.github.io/av1-rtp-spec/
+//
+use crate::obu;
+use crate::stream;
+use byteorder::{BigEndian, ByteOrder};
+use std::collections::VecDeque;
+use std::io;
+
+pub const RTPDUMP_SIGNATURE: [u8; 4] = *b"#!rt"; // "#!rtpplay1.0 address/port"
+const RTPDUMP_HEADER_SIZE: usize = 16; // RD_hdr_t
+const RTP_VERSION: u8 = 2;
+const RTP_HEADER_SIZE: usize = 12;
+pub const RTP_CLOCK_RATE: u64 = 90_000; // timestamp clock rate for AV1 [Hz]
+
+///
+/// RTP fixed header
+///
+#[derive(Debug)]
+pub struct RtpHeader {
+    pub marker: bool,         // M
+    pub payload_type: u8,     // PT
+    pub sequence_number: u16, // sequence number
+    pub timestamp: u32,       // timestamp [90kHz]
+    pub ssrc: u32,            // SSRC
+}
+
+///
+/// AV1 aggregation header
+///
+#[derive(Debug)]
+pub struct AggregationHeader {
+    pub z: bool, // first OBU element is continuation of previous packet
+    pub y: bool, // last OBU element continues in next packet
+    pub w: u8,   // number of OBU elements (0=all elements have length field)
+    pub n: bool, // first packet of coded video sequence
+}
+
+///
+/// parse RTP packet, return (RtpHeader, payload)
+///
+pub fn parse_rtp_packet(packet: &[u8]) -> io::Result<(RtpHeader, &[u8])> {
+    if packet.len() < RTP_HEADER_SIZE || packet[0] >> 6 != RTP_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid RTP header",
+        ));
+    }
+    let padding = packet[0] & 0x20 != 0;
+    let extension = packet[0] & 0x10 != 0;
+    let csrc_count = (packet[0] & 0x0f) as usize;
+    let hdr = RtpHeader {
+        marker: packet[1] & 0x80 != 0,
+        payload_type: packet[1] & 0x7f,
+        sequence_number: BigEndian::read_u16(&packet[2..]),
+        timestamp: BigEndian::read_u32(&packet[4..]),
+        ssrc: BigEndian::read_u32(&packet[8..]),
+    };
+    let mut start = RTP_HEADER_SIZE + 4 * csrc_count;
+    if extension && start + 4 <= packet.len() {
+        let length = BigEndian::read_u16(&packet[start + 2..]) as usize;
+        start += 4 + 4 * length;
+    }
+    let mut end = packet.len();
+    if padding && end > 0 {
+        end = end.saturating_sub(packet[end - 1] as usize);
+    }
+    if end < start {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid RTP packet length",
+        ));
+    }
+    Ok((hdr, &packet[start..end]))
+}
+
+///
+/// parse aggregation header
+///
+pub fn parse_aggregation_header(b: u8) -> AggregationHeader {
+    AggregationHeader {
+        z: b & 0x80 != 0,
+        y: b & 0x40 != 0,
+        w: (b >> 4) & 0b11,
+        n: b & 0x08 != 0,
+    }
+}
+
+///
+/// AV1 RTP depacketizer
+///
+#[derive(Debug, Default)]
+pub struct Depacketizer {
+    fragment: Vec<u8>, // fragmented OBU
+    discard: bool,     // discard fragment after packet loss
+    unit: Vec<u8>,     // OBUs in current temporal unit
+    timestamp: Option<u32>,
+    last_seq: Option<u16>,
+    lost_packets: u64,
+    units: VecDeque<stream::TemporalUnit>,
+}
+
+impl Depacketizer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// get number of lost packets detected by sequence number
+    pub fn get_lost_packets(&self) -> u64 {
+        self.lost_packets
+    }
+
+    /// push RTP packet, temporal unit completes with marker bit or timestamp change
+    pub fn push_packet(&mut self, packet: &[u8]) -> io::Result<()> {
+        let (hdr, payload) = parse_rtp_packet(packet)?;
+        if let Some(last_seq) = self.last_seq {
+            let gap = hdr.sequence_number.wrapping_sub(last_seq).wrapping_sub(1);
+            if gap != 0 {
+                self.lost_packets += gap as u64;
+                self.discard = true;
+            }
+        }
+        self.last_seq = Some(hdr.sequence_number);
+        if self.timestamp.is_some() && self.timestamp != Some(hdr.timestamp) {
+            self.flush();
+        }
+        self.timestamp = Some(hdr.timestamp);
+        self.push_payload(payload)?;
+        if hdr.marker {
+            self.flush();
+        }
+        Ok(())
+    }
+
+    /// push RTP payload, reassemble OBU elements into current temporal unit
+    pub fn push_payload(&mut self, payload: &[u8]) -> io::Result<()> {
+        if payload.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Missing aggregation header",
+            ));
+        }
+        let agg = parse_aggregation_header(payload[0]);
+        let mut buf = &payload[1..];
+        let mut idx = 0;
+        while !buf.is_empty() {
+            idx += 1;
+            let element = if agg.w == 0 || idx < agg.w {
+                // OBU element with length field
+                let (_, size) = obu::leb128(&mut buf)?;
+                if buf.len() < size as usize {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "OBU element exceeds RTP payload",
+                    ));
+                }
+                let (head, tail) = buf.split_at(size as usize);
+                buf = tail;
+                head
+            } else {
+                // last OBU element
+                let element = buf;
+                buf = &[];
+                element
+            };
+            if idx == 1 && agg.z {
+                // continuation of fragmented OBU
+                if self.fragment.is_empty() {
+                    self.discard = true;
+                }
+            } else {
+                // unterminated fragment is lost
+                self.fragment.clear();
+                self.discard = false;
+            }
+            self.fragment.extend_from_slice(element);
+            if !(buf.is_empty() && agg.y) {
+                let data = std::mem::take(&mut self.fragment);
+                if !self.discard {
+                    self.append_obu(&data)?;
+                }
+                self.discard = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// append OBU into current temporal unit in low overhead bitstream format
+    fn append_obu(&mut self, data: &[u8]) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let obu = obu::parse_obu_header(&mut &data[..], data.len() as u32)?;
+        if obu.obu_type == obu::OBU_TEMPORAL_DELIMITER || obu.obu_type == obu::OBU_TILE_LIST {
+            // TemporalDelimiter and TileList OBUs should be ignored
+            return Ok(());
+        }
+        let obu_header_len = 1 + obu.obu_extension_flag as usize;
+        let end = (obu.header_len + obu.obu_size) as usize;
+        if data.len() < end {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "OBU exceeds OBU element",
+            ));
+        }
+        self.unit.push(data[0] | 0x02); // obu_has_size_field=1
+        self.unit.extend_from_slice(&data[1..obu_header_len]);
+        obu::write_leb128(&mut self.unit, obu.obu_size)?;
+        self.unit
+            .extend_from_slice(&data[obu.header_len as usize..end]);
+        Ok(())
+    }
+
+    /// complete current temporal unit
+    pub fn flush(&mut self) {
+        if !self.unit.is_empty() {
+            let mut data = stream::TEMPORAL_DELIMITER_OBU.to_vec();
+            data.append(&mut self.unit);
+            self.units.push_back(stream::TemporalUnit {
+                pts: self.timestamp.unwrap_or(0) as u64,
+                data,
+            });
+        }
+        self.fragment.clear();
+    }
+
+    /// pop completed temporal unit
+    pub fn next_unit(&mut self) -> Option<stream::TemporalUnit> {
+        self.units.pop_front()
+    }
+}
+
+///
+/// read rtpdump file header (rtptools)
+///
+pub fn read_rtpdump_header<R: io::BufRead>(reader: &mut R) -> io::Result<String> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.as_bytes().starts_with(&RTPDUMP_SIGNATURE) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid rtpdump signature",
+        ));
+    }
+    let mut rd_hdr = [0; RTPDUMP_HEADER_SIZE];
+    reader.read_exact(&mut rd_hdr)?;
+    Ok(line.trim_end().to_string())
+}
+
+///
+/// read next RTP packet in rtpdump file, RTCP packets are skipped
+///
+pub fn read_rtpdump_packet<R: io::Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    loop {
+        // RD_packet_t
+        let mut rd_packet = [0; 8];
+        match reader.read_exact(&mut rd_packet) {
+            Ok(_) => (),
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        }
+        let length = BigEndian::read_u16(&rd_packet[0..]) as usize;
+        let plen = BigEndian::read_u16(&rd_packet[2..]) as usize;
+        if length < rd_packet.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid rtpdump packet length",
+            ));
+        }
+        let mut packet = vec![0; length - rd_packet.len()];
+        reader.read_exact(&mut packet)?;
+        if plen == 0 {
+            continue; // RTCP packet
+        }
+        packet.truncate(plen);
+        return Ok(Some(packet));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SequenceHeader OBU without obu_size field (obu_has_size_field=0)
+    const OBU_ELEMENT: [u8; 3] = [0x08, 0xaa, 0xbb];
+    // same OBU in low overhead bitstream format
+    const OBU_WITH_SIZE: [u8; 4] = [0x0a, 0x02, 0xaa, 0xbb];
+
+    fn rtp_packet(seq: u16, timestamp: u32, marker: bool, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![RTP_VERSION << 6, (marker as u8) << 7 | 96];
+        packet.extend_from_slice(&seq.to_be_bytes());
+        packet.extend_from_slice(&timestamp.to_be_bytes());
+        packet.extend_from_slice(&0x1234_5678u32.to_be_bytes());
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn expected_unit(obus: &[u8]) -> Vec<u8> {
+        [&stream::TEMPORAL_DELIMITER_OBU[..], obus].concat()
+    }
+
+    #[test]
+    fn rtp_header() {
+        let packet = rtp_packet(7, 3000, true, &[0x10, 0x01]);
+        let (hdr, payload) = parse_rtp_packet(&packet).unwrap();
+        assert!(hdr.marker);
+        assert_eq!(hdr.payload_type, 96);
+        assert_eq!(hdr.sequence_number, 7);
+        assert_eq!(hdr.timestamp, 3000);
+        assert_eq!(hdr.ssrc, 0x1234_5678);
+        assert_eq!(payload, [0x10, 0x01]);
+
+        // CSRC list, header extension and padding are skipped
+        let mut packet = rtp_packet(7, 3000, false, &[]);
+        packet[0] |= 0x20 | 0x10 | 1;
+        packet.extend_from_slice(&[0; 4]); // CSRC
+        packet.extend_from_slice(&[0xbe, 0xde, 0x00, 0x01, 0, 0, 0, 0]); // extension
+        packet.extend_from_slice(&[0x10, 0x01, 0x00, 0x02]); // payload and padding
+        let (_, payload) = parse_rtp_packet(&packet).unwrap();
+        assert_eq!(payload, [0x10, 0x01]);
+
+        let mut packet = rtp_packet(7, 3000, false, &[]);
+        packet[0] = 1 << 6;
+        assert!(parse_rtp_packet(&packet).is_err());
+        assert!(parse_rtp_packet(&packet[..RTP_HEADER_SIZE - 1]).is_err());
+    }
+
+    #[test]
+    fn depacketize_aggregated_obus() {
+        let mut depacketizer = Depacketizer::new();
+        // W=2: TemporalDelimiter OBU with length field, last OBU element without length field
+        let mut payload = vec![0x20, 0x01, 0x10];
+        payload.extend_from_slice(&OBU_ELEMENT);
+        depacketizer
+            .push_packet(&rtp_packet(1, 3000, true, &payload))
+            .unwrap();
+        let tu = depacketizer.next_unit().unwrap();
+        assert_eq!(tu.pts, 3000);
+        assert_eq!(tu.data, expected_unit(&OBU_WITH_SIZE));
+        assert!(depacketizer.next_unit().is_none());
+
+        // W=0: all OBU elements have length field, timestamp change completes temporal unit
+        let mut payload = vec![0x00, OBU_ELEMENT.len() as u8];
+        payload.extend_from_slice(&OBU_ELEMENT);
+        depacketizer
+            .push_packet(&rtp_packet(2, 6000, false, &payload))
+            .unwrap();
+        assert!(depacketizer.next_unit().is_none());
+        depacketizer
+            .push_packet(&rtp_packet(3, 9000, false, &payload))
+            .unwrap();
+        let tu = depacketizer.next_unit().unwrap();
+        assert_eq!(tu.pts, 6000);
+        assert_eq!(tu.data, expected_unit(&OBU_WITH_SIZE));
+        assert_eq!(depacketizer.get_lost_packets(), 0);
+    }
+
+    #[test]
+    fn depacketize_fragmented_obu() {
+        let mut depacketizer = Depacketizer::new();
+        // Y=1, W=1: first fragment
+        let first = [&[0x50][..], &OBU_ELEMENT[..2]].concat();
+        // Z=1, W=1: last fragment
+        let last = [&[0x90][..], &OBU_ELEMENT[2..]].concat();
+        depacketizer
+            .push_packet(&rtp_packet(10, 3000, false, &first))
+            .unwrap();
+        depacketizer
+            .push_packet(&rtp_packet(11, 3000, true, &last))
+            .unwrap();
+        let tu = depacketizer.next_unit().unwrap();
+        assert_eq!(tu.data, expected_unit(&OBU_WITH_SIZE));
+
+        // packet loss in the middle of fragmented OBU discards the OBU
+        depacketizer
+            .push_packet(&rtp_packet(12, 6000, false, &first))
+            .unwrap();
+        depacketizer
+            .push_packet(&rtp_packet(14, 6000, true, &last))
+            .unwrap();
+        assert_eq!(depacketizer.get_lost_packets(), 1);
+        assert!(depacketizer.next_unit().is_none());
+
+        // OBU element exceeds payload
+        assert!(depacketizer.push_payload(&[0x00, 0x05, 0x08]).is_err());
+        assert!(depacketizer.push_payload(&[]).is_err());
+    }
+
+    #[test]
+    fn rtpdump_file() {
+        let packet = rtp_packet(1, 3000, true, &[0x10, 0x01]);
+        let mut data = b"#!rtpplay1.0 127.0.0.1/5004\n".to_vec();
+        data.extend_from_slice(&[0; RTPDUMP_HEADER_SIZE]);
+        // RTCP packet (plen=0)
+        data.extend_from_slice(&[0x00, 0x0c, 0x00, 0x00, 0, 0, 0, 0, 0x80, 0xc8, 0x00, 0x00]);
+        let length = (8 + packet.len()) as u16;
+        data.extend_from_slice(&length.to_be_bytes());
+        data.extend_from_slice(&(packet.len() as u16).to_be_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&packet);
+
+        let mut reader = &data[..];
+        let line = read_rtpdump_header(&mut reader).unwrap();
+        assert_eq!(line, "#!rtpplay1.0 127.0.0.1/5004");
+        assert_eq!(read_rtpdump_packet(&mut reader).unwrap(), Some(packet));
+        assert_eq!(read_rtpdump_packet(&mut reader).unwrap(), None);
+        assert!(read_rtpdump_header(&mut &b"#!foo"[..]).is_err());
+    }
+}
@@ -0,0 +1,517 @@
+//
+// AV1 temporal unit stream
+//
+use crate::ivf;
+use crate::obu;
+use crate::ts;
+use crate::FileFormat;
+use std::cmp;
+use std::io;
+
+/// TemporalDelimiter OBU (obu_has_size_field=1, obu_size=0)
+pub const TEMPORAL_DELIMITER_OBU: [u8; 2] = [obu::OBU_TEMPORAL_DELIMITER << 3 | 0x02, 0x00];
+
+///
+/// Temporal unit
+///
+#[derive(Debug)]
+pub struct TemporalUnit {
+    pub pts: u64,      // presentation timestamp [timebase]
+    pub data: Vec<u8>, // OBUs in low overhead bitstream format
+}
+
+/// split leading leb128() sized bytes from buffer
+fn split_sized<'a>(buf: &mut &'a [u8]) -> io::Result<&'a [u8]> {
+    let (_, size) = obu::leb128(buf)?;
+    if buf.len() < size as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "size exceeds enclosing unit",
+        ));
+    }
+    let (head, tail) = buf.split_at(size as usize);
+    *buf = tail;
+    Ok(head)
+}
+
+/// append OBU with (Section 5) or without (Annex B) obu_size field
+fn write_obu(
+    buf: &mut Vec<u8>,
+    obu: &obu::Obu,
+    bytes: &[u8],
+    has_size_field: bool,
+) -> io::Result<()> {
+    let obu_header_len = 1 + obu.obu_extension_flag as usize;
+    let payload = &bytes[obu.header_len as usize..(obu.header_len + obu.obu_size) as usize];
+    let mut obu_header = bytes[..obu_header_len].to_vec();
+    if has_size_field {
+        obu_header[0] |= 0x02; // obu_has_size_field=1
+        buf.extend_from_slice(&obu_header);
+        obu::write_leb128(buf, obu.obu_size)?;
+    } else {
+        obu_header[0] &= !0x02; // obu_has_size_field=0
+        obu::write_leb128(buf, (obu_header_len + payload.len()) as u32)?; // obu_length
+        buf.extend_from_slice(&obu_header);
+    }
+    buf.extend_from_slice(payload);
+    Ok(())
+}
+
+///
+/// convert temporal unit in low overhead bitstream format into Annex B temporal_unit()
+///
+pub fn to_annexb_unit(mut data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut frame_units: Vec<Vec<u8>> = vec![Vec::new()];
+    let mut has_frame_header = false;
+    while !data.is_empty() {
+        let sz = data.len() as u32;
+        let (obu, bytes) = read_obu(&mut data, sz)?;
+        if obu.obu_type == obu::OBU_FRAME_HEADER || obu.obu_type == obu::OBU_FRAME {
+            // frame header starts new frame_unit()
+            if has_frame_header {
+                frame_units.push(Vec::new());
+            }
+            has_frame_header = true;
+        }
+        write_obu(frame_units.last_mut().unwrap(), &obu, &bytes, false)?;
+    }
+    let mut tu = Vec::new();
+    for fu in frame_units.iter().filter(|fu| !fu.is_empty()) {
+        obu::write_leb128(&mut tu, fu.len() as u32)?; // frame_unit_size
+        tu.extend_from_slice(fu);
+    }
+    let mut unit = Vec::with_capacity(tu.len() + 4);
+    obu::write_leb128(&mut unit, tu.len() as u32)?; // temporal_unit_size
+    unit.extend_from_slice(&tu);
+    Ok(unit)
+}
+
+///
+/// normalize temporal unit into clean low overhead bitstream format,
+/// return (temporal unit, number of dropped OBUs)
+///
+/// Output starts with single TemporalDelimiter OBU and all OBUs have obu_size field.
+/// Padding OBUs, redundant TemporalDelimiter OBUs and trailing zero bytes are dropped.
+///
+pub fn normalize_unit(mut data: &[u8]) -> io::Result<(Vec<u8>, u32)> {
+    let mut unit = TEMPORAL_DELIMITER_OBU.to_vec();
+    let mut ndropped = 0;
+    let mut has_td = false;
+    while !data.is_empty() {
+        if data.iter().all(|&b| b == 0) {
+            // trailing zero bytes
+            break;
+        }
+        let sz = data.len() as u32;
+        let (obu, bytes) = read_obu(&mut data, sz)?;
+        match obu.obu_type {
+            obu::OBU_TEMPORAL_DELIMITER => {
+                // first TemporalDelimiter OBU is replaced, not dropped
+                ndropped += has_td as u32;
+                has_td = true;
+            }
+            obu::OBU_PADDING => ndropped += 1,
+            _ => write_obu(&mut unit, &obu, &bytes, true)?,
+        }
+    }
+    Ok((unit, ndropped))
+}
+
+///
+/// get sz of next open_bitstream_unit(sz) in raw bitstream,
+/// OBU without obu_size field extends to end of stream
+///
+pub fn raw_obu_sz<R: io::Read + io::Seek>(reader: &mut R) -> io::Result<u32> {
+    let start = reader.stream_position()?;
+    let end = reader.seek(io::SeekFrom::End(0))?;
+    reader.seek(io::SeekFrom::Start(start))?;
+    Ok(cmp::min(end.saturating_sub(start), u32::MAX as u64) as u32)
+}
+
+///
+/// AV1 stream found by scanner
+///
+#[derive(Debug)]
+pub struct ScanMatch {
+    pub offset: usize,           // offset of first TemporalDelimiter OBU
+    pub length: usize,           // length of consecutive OBUs
+    pub temporal_units: usize,   // number of temporal units
+    pub sh: obu::SequenceHeader, // first sequence header
+}
+
+/// read OBU with obu_size field and valid obu_type in buffer
+fn read_sized_obu(buf: &[u8]) -> Option<(obu::Obu, Vec<u8>)> {
+    let (obu, bytes) = read_obu(&mut &buf[..], buf.len() as u32).ok()?;
+    let valid_type = matches!(
+        obu.obu_type,
+        obu::OBU_SEQUENCE_HEADER..=obu::OBU_TILE_LIST | obu::OBU_PADDING
+    );
+    // obu_reserved_1bit must be 0
+    if obu.obu_has_size_field && valid_type && bytes[0] & 1 == 0 {
+        Some((obu, bytes))
+    } else {
+        None
+    }
+}
+
+/// probe consecutive OBUs starting with TemporalDelimiter and SequenceHeader OBUs
+fn probe_obu_run(buf: &[u8], start: usize) -> Option<ScanMatch> {
+    let mut pos = start + TEMPORAL_DELIMITER_OBU.len();
+    let (sh_obu, sh_bytes) = read_sized_obu(&buf[pos..])?;
+    if sh_obu.obu_type != obu::OBU_SEQUENCE_HEADER || sh_obu.obu_size == 0 {
+        return None;
+    }
+    pos += sh_bytes.len();
+    // sequence header should be followed by frame (header) OBU
+    let (obu, _) = read_sized_obu(&buf[pos..])?;
+    if obu.obu_type != obu::OBU_FRAME && obu.obu_type != obu::OBU_FRAME_HEADER {
+        return None;
+    }
+    let payload = &sh_bytes[sh_obu.header_len as usize..];
+    if payload[0] >> 5 > 2 {
+        return None; // seq_profile
+    }
+    let sh = obu::parse_sequence_header(&mut &payload[..])?;
+
+    let mut temporal_units = 1;
+    while let Some((obu, bytes)) = read_sized_obu(&buf[pos..]) {
+        if obu.obu_type == obu::OBU_TEMPORAL_DELIMITER {
+            if obu.obu_size != 0 {
+                break;
+            }
+            temporal_units += 1;
+        }
+        pos += bytes.len();
+    }
+    Some(ScanMatch {
+        offset: start,
+        length: pos - start,
+        temporal_units,
+        sh,
+    })
+}
+
+///
+/// scan buffer for AV1 streams in low overhead bitstream format,
+/// each stream starts with TemporalDelimiter OBU followed by SequenceHeader OBU
+///
+pub fn scan_obu_streams(buf: &[u8]) -> Vec<ScanMatch> {
+    let mut matches = Vec::new();
+    let mut pos = 0;
+    while pos + TEMPORAL_DELIMITER_OBU.len() < buf.len() {
+        if buf[pos..].starts_with(&TEMPORAL_DELIMITER_OBU) {
+            if let Some(m) = probe_obu_run(buf, pos) {
+                pos += m.length;
+                matches.push(m);
+                continue;
+            }
+        }
+        pos += 1;
+    }
+    matches
+}
+
+/// record all bytes read from inner reader
+struct TeeReader<'a, R: 'a> {
+    inner: &'a mut R,
+    buf: Vec<u8>,
+}
+
+impl<'a, R: io::Read> io::Read for TeeReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.buf.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+///
+/// read whole OBU, return (Obu, OBU bytes including obu_header)
+///
+pub fn read_obu<R: io::Read>(reader: &mut R, sz: u32) -> io::Result<(obu::Obu, Vec<u8>)> {
+    let mut tee = TeeReader {
+        inner: reader,
+        buf: Vec::new(),
+    };
+    let obu = obu::parse_obu_header(&mut tee, sz)?;
+    let mut data = tee.buf;
+    let header_len = data.len();
+    data.resize(header_len + obu.obu_size as usize, 0);
+    tee.inner.read_exact(&mut data[header_len..])?;
+    Ok((obu, data))
+}
+
+///
+/// read whole OBU in raw bitstream, OBU without obu_size field extends to end of stream
+///
+fn read_raw_obu<R: io::Read>(reader: &mut R) -> io::Result<(obu::Obu, Vec<u8>)> {
+    let mut tee = TeeReader {
+        inner: reader,
+        buf: Vec::new(),
+    };
+    let mut obu = obu::parse_obu_header(&mut tee, u32::MAX)?;
+    let mut data = tee.buf;
+    let header_len = data.len();
+    if obu.obu_has_size_field {
+        data.resize(header_len + obu.obu_size as usize, 0);
+        tee.inner.read_exact(&mut data[header_len..])?;
+    } else {
+        tee.inner.read_to_end(&mut data)?;
+        obu.obu_size = (data.len() - header_len) as u32;
+    }
+    Ok((obu, data))
+}
+
+///
+/// find first OBU of obu_type in temporal unit, return (Obu, OBU bytes including obu_header)
+///
+pub fn find_obu(mut data: &[u8], obu_type: u8) -> io::Result<Option<(obu::Obu, Vec<u8>)>> {
+    while !data.is_empty() {
+        let sz = data.len() as u32;
+        let (obu, bytes) = read_obu(&mut data, sz)?;
+        if obu.obu_type == obu_type {
+            return Ok(Some((obu, bytes)));
+        }
+    }
+    Ok(None)
+}
+
+///
+/// Temporal unit reader for IVF, low overhead bitstream format, Annex B and MPEG-2 TS
+///
+pub struct TemporalUnitReader<R> {
+    reader: R,
+    format: FileFormat,
+    timebase: (u32, u32), // (numerator, denominator) [sec]
+    header: Option<ivf::IvfHeader>,
+    next_obu: Option<Vec<u8>>, // first OBU of next temporal unit
+    demuxer: Option<ts::TsDemuxer>,
+    count: u64,
+}
+
+impl<R: io::Read> TemporalUnitReader<R> {
+    /// create reader for IVF or low overhead bitstream format
+    pub fn new(mut reader: R, format: FileFormat) -> io::Result<Self> {
+        let (timebase, header) = match format {
+            FileFormat::IVF => {
+                let mut ivf_header = [0; ivf::IVF_HEADER_SIZE];
+                reader.read_exact(&mut ivf_header)?;
+                let hdr = ivf::parse_ivf_header(&ivf_header)
+                    .map_err(|msg| io::Error::new(io::ErrorKind::InvalidData, msg))?;
+                // skip extra header bytes
+                let extra = (hdr.header_len as usize - ivf::IVF_HEADER_SIZE) as u64;
+                io::copy(&mut io::Read::take(&mut reader, extra), &mut io::sink())?;
+                ((hdr.timescale_den, hdr.timescale_num), Some(hdr))
+            }
+            FileFormat::Bitstream | FileFormat::AnnexB => ((1, 30), None), // assume 30fps
+            FileFormat::MPEGTS => ((1, 90000), None),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "unsupported format for temporal unit reader",
+                ))
+            }
+        };
+        Ok(TemporalUnitReader {
+            reader,
+            format,
+            timebase,
+            header,
+            next_obu: None,
+            demuxer: if format == FileFormat::MPEGTS {
+                Some(ts::TsDemuxer::new())
+            } else {
+                None
+            },
+            count: 0,
+        })
+    }
+
+    /// get timebase (numerator, denominator) in seconds
+    pub fn timebase(&self) -> (u32, u32) {
+        self.timebase
+    }
+
+    /// get IVF file header
+    pub fn ivf_header(&self) -> Option<&ivf::IvfHeader> {
+        self.header.as_ref()
+    }
+
+    /// read next temporal unit
+    pub fn next_unit(&mut self) -> io::Result<Option<TemporalUnit>> {
+        match self.format {
+            FileFormat::IVF => self.next_ivf_unit(),
+            FileFormat::AnnexB => self.next_annexb_unit(),
+            FileFormat::MPEGTS => self.next_ts_unit(),
+            _ => self.next_obu_unit(),
+        }
+    }
+
+    fn next_ivf_unit(&mut self) -> io::Result<Option<TemporalUnit>> {
+        let frame = match ivf::parse_ivf_frame(&mut self.reader) {
+            Ok(frame) => frame,
+            Err(_) => return Ok(None), // end of stream
+        };
+        let mut data = vec![0; frame.size as usize];
+        self.reader.read_exact(&mut data)?;
+        self.count += 1;
+        Ok(Some(TemporalUnit {
+            pts: frame.pts,
+            data,
+        }))
+    }
+
+    fn next_annexb_unit(&mut self) -> io::Result<Option<TemporalUnit>> {
+        let temporal_unit_size = match obu::leb128(&mut self.reader) {
+            Ok((_, size)) => size,
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let mut tu = vec![0; temporal_unit_size as usize];
+        self.reader.read_exact(&mut tu)?;
+        // convert OBUs in frame_unit()s into low overhead bitstream format
+        let mut data = Vec::with_capacity(tu.len());
+        let mut tu = &tu[..];
+        while !tu.is_empty() {
+            let mut fu = split_sized(&mut tu)?;
+            while !fu.is_empty() {
+                let bytes = split_sized(&mut fu)?;
+                let obu = obu::parse_obu_header(&mut &bytes[..], bytes.len() as u32)?;
+                write_obu(&mut data, &obu, bytes, true)?;
+            }
+        }
+        let pts = self.count;
+        self.count += 1;
+        Ok(Some(TemporalUnit { pts, data }))
+    }
+
+    fn next_ts_unit(&mut self) -> io::Result<Option<TemporalUnit>> {
+        let demuxer = self.demuxer.as_mut().unwrap();
+        let pes = match demuxer.next_pes(&mut self.reader)? {
+            Some(pes) => pes,
+            None => return Ok(None),
+        };
+        // PES packet without PTS inherits frame count
+        let pts = pes.pts.unwrap_or(self.count);
+        self.count += 1;
+        Ok(Some(TemporalUnit {
+            pts,
+            data: ts::av1_access_unit(&pes.data),
+        }))
+    }
+
+    fn next_obu_unit(&mut self) -> io::Result<Option<TemporalUnit>> {
+        let mut data = self.next_obu.take().unwrap_or_default();
+        loop {
+            let (obu, bytes) = match read_raw_obu(&mut self.reader) {
+                Ok(result) => result,
+                Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            };
+            if obu.obu_type == obu::OBU_TEMPORAL_DELIMITER && !data.is_empty() {
+                // start of next temporal unit
+                self.next_obu = Some(bytes);
+                break;
+            }
+            data.extend_from_slice(&bytes);
+        }
+        if data.is_empty() {
+            return Ok(None);
+        }
+        let pts = self.count;
+        self.count += 1;
+        Ok(Some(TemporalUnit { pts, data }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// junk, TemporalDelimiter, SequenceHeader with decoder model and initial display delay, Frame OBU
+    fn decoder_model_blob() -> Vec<u8> {
+        let mut blob = vec![0xde, 0xad, 0xbe, 0xef];
+        blob.extend_from_slice(&TEMPORAL_DELIMITER_OBU);
+        // timing_info_present_flag=1, decoder_model_info_present_flag=1,
+        // initial_display_delay_present_flag=1, operating point #0 has both parameters
+        let sh = [
+            0x04, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x79, 0x78, 0x00, 0x00, 0x00, 0x09,
+            0xcf, 0x00, 0x00, 0x21, 0x03, 0xe8, 0x07, 0xd0, 0x65, 0xdc, 0xfc, 0xfc, 0x01, 0x80,
+            0x08,
+        ];
+        blob.extend_from_slice(&[obu::OBU_SEQUENCE_HEADER << 3 | 0x02, sh.len() as u8]);
+        blob.extend_from_slice(&sh);
+        blob.extend_from_slice(&[obu::OBU_FRAME << 3 | 0x02, 0x01, 0x10]);
+        blob
+    }
+
+    #[test]
+    fn scan_decoder_model() {
+        let blob = decoder_model_blob();
+        let matches = scan_obu_streams(&blob);
+        assert_eq!(matches.len(), 1);
+        let m = &matches[0];
+        assert_eq!(
+            (m.offset, m.length, m.temporal_units),
+            (4, blob.len() - 4, 1)
+        );
+        assert!(m.sh.decoder_model_info_present_flag);
+        assert_eq!(m.sh.decoder_model_info.buffer_delay_length, 16);
+        let op = &m.sh.op[0];
+        assert_eq!(op.seq_level_idx, 8);
+        assert!(op.decoder_model_present_for_this_op);
+        assert_eq!(op.operating_parameters_info.decoder_buffer_delay, 1000);
+        assert_eq!(op.operating_parameters_info.encoder_buffer_delay, 2000);
+        assert_eq!(op.initial_display_delay, 10);
+        assert_eq!((m.sh.max_frame_width, m.sh.max_frame_height), (64, 64));
+    }
+
+    #[test]
+    fn scan_corrupted_blob() {
+        // scanner never panics on damaged streams
+        let blob = decoder_model_blob();
+        for pos in 0..blob.len() * 8 {
+            let mut data = blob.clone();
+            data[pos / 8] ^= 0x80 >> (pos % 8);
+            scan_obu_streams(&data);
+            scan_obu_streams(&data[..pos / 8]);
+        }
+    }
+
+    /// TemporalDelimiter, SequenceHeader, FrameHeader and Frame OBU in low overhead bitstream format
+    const SECTION5_UNIT: [u8; 12] = [
+        0x12, 0x00, 0x0a, 0x02, 0xaa, 0xbb, 0x1a, 0x01, 0x10, 0x32, 0x01, 0x20,
+    ];
+    /// same temporal unit in Annex B, frame header starts new frame_unit()
+    const ANNEXB_UNIT: [u8; 15] = [
+        0x0e, 0x09, 0x01, 0x10, 0x03, 0x08, 0xaa, 0xbb, 0x02, 0x18, 0x10, 0x03, 0x02, 0x30, 0x20,
+    ];
+
+    #[test]
+    fn annexb_unit() {
+        assert_eq!(to_annexb_unit(&SECTION5_UNIT).unwrap(), ANNEXB_UNIT);
+    }
+
+    #[test]
+    fn annexb_reader() {
+        let stream = [&ANNEXB_UNIT[..], &ANNEXB_UNIT[..]].concat();
+        let mut reader = TemporalUnitReader::new(&stream[..], FileFormat::AnnexB).unwrap();
+        for pts in 0..2 {
+            let tu = reader.next_unit().unwrap().unwrap();
+            assert_eq!(tu.pts, pts);
+            assert_eq!(tu.data, SECTION5_UNIT);
+        }
+        assert!(reader.next_unit().unwrap().is_none());
+
+        // frame_unit_size exceeds temporal_unit_size
+        let mut broken = ANNEXB_UNIT;
+        broken[1] = 0x0e;
+        let mut reader = TemporalUnitReader::new(&broken[..], FileFormat::AnnexB).unwrap();
+        let err = reader.next_unit().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // truncated temporal_unit()
+        let truncated = &ANNEXB_UNIT[..ANNEXB_UNIT.len() - 1];
+        let mut reader = TemporalUnitReader::new(truncated, FileFormat::AnnexB).unwrap();
+        assert!(reader.next_unit().is_err());
+    }
+}
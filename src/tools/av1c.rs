@@ -0,0 +1,95 @@
+//
+// av1C (WebCodecs decoder configuration) export for info --av1c
+//
+use av1parser::*;
+use std::fs;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+
+/// find first Sequence Header OBU in configOBUs, MP4 samples, WebM blocks or temporal units
+fn find_sequence_header_obu(fname: &str) -> io::Result<Option<Vec<u8>>> {
+    let find = |data: &[u8]| -> io::Result<Option<Vec<u8>>> {
+        Ok(stream::find_obu(data, obu::OBU_SEQUENCE_HEADER)?.map(|(_, bytes)| bytes))
+    };
+    let f = fs::OpenOptions::new().read(true).open(fname)?;
+    let mut reader = io::BufReader::new(f);
+    match probe_fileformat_with_hint(&mut reader, fname)? {
+        FileFormat::MP4 => {
+            let mp4 = mp4::open_mp4file(&mut reader)?;
+            if let Some(bytes) = mp4
+                .get_av1config()
+                .map_or(Ok(None), |(_, av1cc)| find(av1cc.config_obus()))?
+            {
+                return Ok(Some(bytes));
+            }
+            for sample in mp4.get_samples() {
+                reader.seek(SeekFrom::Start(sample.pos))?;
+                let mut data = vec![0; sample.size as usize];
+                reader.read_exact(&mut data)?;
+                if let Some(bytes) = find(&data)? {
+                    return Ok(Some(bytes));
+                }
+            }
+        }
+        FileFormat::WebM => {
+            let mut webm = mkv::open_mkvfile(&mut reader)?;
+            let track_num = match webm.find_tracks(mkv::CODEC_V_AV1).first() {
+                Some(&track_num) => track_num,
+                None => return Ok(None),
+            };
+            let track = webm.get_tracks().iter().find(|t| t.track_num == track_num);
+            // CodecPrivate is AV1CodecConfigurationRecord
+            if let Some(config_obus) = track.and_then(|t| t.codec_private.get(4..)) {
+                if let Some(bytes) = find(config_obus)? {
+                    return Ok(Some(bytes));
+                }
+            }
+            while let Some(block) = webm.next_block(&mut reader)? {
+                let mut data = vec![0; block.size as usize];
+                reader.read_exact(&mut data)?;
+                if block.track_num != track_num {
+                    continue;
+                }
+                if let Some(bytes) = find(&data)? {
+                    return Ok(Some(bytes));
+                }
+            }
+        }
+        FileFormat::AVIF => {
+            let (_, image) = mp4::read_avif(&mut reader)?;
+            if let Some(av1cc) = image.get_av1config() {
+                return find(av1cc.config_obus());
+            }
+        }
+        fmt => {
+            let mut tu_reader = stream::TemporalUnitReader::new(reader, fmt)?;
+            while let Some(tu) = tu_reader.next_unit()? {
+                if let Some(bytes) = find(&tu.data)? {
+                    return Ok(Some(bytes));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// write AV1CodecConfigurationRecord (WebCodecs VideoDecoderConfig.description) generated from Sequence Header
+pub fn write_decoder_config(fname: &str, output: &str) -> io::Result<()> {
+    let sh_obu = match find_sequence_header_obu(fname)? {
+        Some(sh_obu) => sh_obu,
+        None => {
+            logln!("{}: Sequence Header OBU not found", fname);
+            return Ok(());
+        }
+    };
+    let av1cc = mp4::AV1CodecConfigurationBox::from_sequence_header_obu(&sh_obu)?;
+    let description = av1cc.to_bytes();
+    fs::write(output, &description)?;
+    logln!(
+        "{}: write av1C ({} bytes) into {}",
+        fname,
+        description.len(),
+        output
+    );
+    Ok(())
+}
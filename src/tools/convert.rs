@@ -0,0 +1,57 @@
+//
+// bitstream format conversion for convert subcommand
+//
+use crate::tools::OutputFile;
+use av1parser::*;
+use std::fs;
+use std::io;
+use std::io::Write;
+
+/// convert bitstream between low overhead bitstream format and Annex B
+pub fn convert_bitstream(
+    input: &str,
+    output: &str,
+    annexb: bool,
+    normalize: bool,
+) -> io::Result<()> {
+    let f = fs::OpenOptions::new().read(true).open(input)?;
+    let mut reader = io::BufReader::new(f);
+    let fmt = probe_fileformat_with_hint(&mut reader, input)?;
+    let mut tu_reader = stream::TemporalUnitReader::new(reader, fmt)?;
+
+    let mut writer = OutputFile::create(output)?;
+    let (mut nunit, mut ndropped) = (0, 0);
+    while let Some(mut tu) = tu_reader.next_unit()? {
+        if normalize {
+            let (data, n) = stream::normalize_unit(&tu.data)?;
+            tu.data = data;
+            ndropped += n;
+        }
+        if annexb {
+            writer.write_all(&stream::to_annexb_unit(&tu.data)?)?;
+        } else {
+            writer.write_all(&tu.data)?;
+        }
+        nunit += 1;
+    }
+    writer.flush()?;
+    logln!(
+        "{}: convert {} temporal units into {} ({})",
+        input,
+        nunit,
+        output,
+        if annexb {
+            "Annex B"
+        } else {
+            "low overhead bitstream format"
+        }
+    );
+    if normalize {
+        logln!(
+            "{}: normalize temporal units ({} OBUs dropped)",
+            input,
+            ndropped
+        );
+    }
+    Ok(())
+}
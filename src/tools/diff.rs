@@ -0,0 +1,260 @@
+//
+// comparison of two streams for diff subcommand
+//
+use crate::{app_config, process_file, AppConfig, Command, FrameRecord, QUIET};
+use av1parser::*;
+use serde::Serialize;
+use std::cmp;
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::Ordering;
+
+///
+/// frame collected for diff subcommand
+///
+pub struct DiffFrame {
+    pub record: FrameRecord,
+    pub header: serde_json::Value, // serialized FrameHeader
+}
+
+///
+/// compare serialized values of same type field by field,
+/// push differing fields as (field path, value in a, value in b)
+///
+fn value_diff(
+    path: &str,
+    a: &serde_json::Value,
+    b: &serde_json::Value,
+    diffs: &mut Vec<(String, String, String)>,
+) {
+    use serde_json::Value;
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (name, value_a) in a.iter() {
+                let field = if path.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}.{}", path, name)
+                };
+                value_diff(&field, value_a, &b[name], diffs);
+            }
+        }
+        // different number of elements are reported as whole array
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (value_a, value_b)) in a.iter().zip(b.iter()).enumerate() {
+                value_diff(&format!("{}[{}]", path, i), value_a, value_b, diffs);
+            }
+        }
+        _ if a != b => diffs.push((path.to_string(), a.to_string(), b.to_string())),
+        _ => {}
+    }
+}
+
+/// serialize parsed header for value_diff()
+pub fn header_value<T: Serialize>(header: &T) -> serde_json::Value {
+    serde_json::to_value(header).expect("serialize header")
+}
+
+// timestamp difference [usec] regarded as same, WebM default precision is 1msec
+const PTS_TOLERANCE: i64 = 1000;
+
+/// pair frames of two streams in decoding order, or by presentation timestamp
+fn align_frames(
+    a: &[DiffFrame],
+    b: &[DiffFrame],
+    by_pts: bool,
+) -> Vec<(Option<usize>, Option<usize>)> {
+    if !by_pts {
+        return (0..cmp::max(a.len(), b.len()))
+            .map(|i| {
+                (
+                    if i < a.len() { Some(i) } else { None },
+                    if i < b.len() { Some(i) } else { None },
+                )
+            })
+            .collect();
+    }
+    // earliest unpaired frame with nearly same pts (frames in temporal unit share pts)
+    let mut by_msec: HashMap<i64, Vec<usize>> = HashMap::new();
+    for (j, frame) in b.iter().enumerate() {
+        let pts = frame.record.pts.unwrap_or_default();
+        by_msec.entry(pts.div_euclid(1000)).or_default().push(j);
+    }
+    let mut paired = vec![false; b.len()];
+    let mut pairs = Vec::new();
+    for (i, frame) in a.iter().enumerate() {
+        let pts = frame.record.pts.unwrap_or_default();
+        let found = (-1..=1)
+            .filter_map(|d| by_msec.get(&(pts.div_euclid(1000) + d)))
+            .flatten()
+            .copied()
+            .filter(|&j| {
+                !paired[j] && (b[j].record.pts.unwrap_or_default() - pts).abs() < PTS_TOLERANCE
+            })
+            .min();
+        if let Some(j) = found {
+            paired[j] = true;
+        }
+        pairs.push((Some(i), found));
+    }
+    pairs.extend(
+        (0..b.len())
+            .filter(|&j| !paired[j])
+            .map(|j| (None, Some(j))),
+    );
+    pairs
+}
+
+/// format timestamp [usec] in milliseconds
+fn format_pts(pts: Option<i64>) -> String {
+    pts.map_or("-".to_string(), |pts| {
+        format!("{:.3}ms", pts as f64 / 1000.0)
+    })
+}
+
+/// parse input for diff subcommand, return (first sequence header, frames)
+fn collect_frames(
+    fname: &str,
+    config: &AppConfig,
+) -> io::Result<(Option<obu::SequenceHeader>, Vec<DiffFrame>)> {
+    QUIET.store(true, Ordering::Relaxed);
+    let result = process_file(fname, config);
+    QUIET.store(config.quiet, Ordering::Relaxed);
+    result?;
+    let mut frames = config.frames.take();
+    // timestamp in container timebase to microseconds
+    if let Some((num, den)) = config.timebase.get() {
+        for frame in frames.iter_mut() {
+            frame.record.pts = frame
+                .record
+                .pts
+                .map(|pts| (pts as i128 * num as i128 * 1_000_000 / den as i128) as i64);
+        }
+    }
+    let sh = config.stats.borrow().sequence_header.clone();
+    Ok((sh, frames))
+}
+
+///
+/// compare sequence headers and frames of two streams, return number of differences
+///
+pub fn diff_streams(
+    fname_a: &str,
+    fname_b: &str,
+    by_pts: bool,
+    matches: &clap::ArgMatches,
+) -> io::Result<u64> {
+    let config_a = app_config(matches, Command::Diff);
+    let config_b = app_config(matches, Command::Diff);
+    let (sh_a, frames_a) = collect_frames(fname_a, &config_a)?;
+    let (sh_b, frames_b) = collect_frames(fname_b, &config_b)?;
+    outln!("--- {} ({} frames)", fname_a, frames_a.len());
+    outln!("+++ {} ({} frames)", fname_b, frames_b.len());
+
+    let mut differences = 0;
+    let diffs = match (sh_a, sh_b) {
+        (Some(sh_a), Some(sh_b)) => {
+            let mut diffs = Vec::new();
+            value_diff("", &header_value(&sh_a), &header_value(&sh_b), &mut diffs);
+            diffs
+        }
+        (None, None) => Vec::new(),
+        (sh_a, sh_b) => vec![(
+            "present".to_string(),
+            sh_a.is_some().to_string(),
+            sh_b.is_some().to_string(),
+        )],
+    };
+    if !diffs.is_empty() {
+        outln!("SequenceHeader");
+        for (field, a, b) in diffs.iter() {
+            outln!("  {}: {} -> {}", field, a, b);
+        }
+        differences += 1;
+    }
+    let has_pts = |frames: &[DiffFrame]| frames.iter().all(|frame| frame.record.pts.is_some());
+    let by_pts = by_pts && has_pts(&frames_a) && has_pts(&frames_b);
+    if !by_pts && matches.value_of("align") == Some("pts") {
+        outln!("no timestamp in input, frames are aligned by decoding order");
+    }
+    let (mut compared, mut differ, mut only_a, mut only_b) = (0, 0, 0, 0);
+    for (i, j) in align_frames(&frames_a, &frames_b, by_pts) {
+        let (a, b) = match (i, j) {
+            (Some(i), Some(j)) => (&frames_a[i], &frames_b[j]),
+            (Some(i), None) => {
+                outln!(
+                    "frame a#{} pts={}: only in {}",
+                    i,
+                    format_pts(frames_a[i].record.pts),
+                    fname_a
+                );
+                only_a += 1;
+                continue;
+            }
+            (None, Some(j)) => {
+                outln!(
+                    "frame b#{} pts={}: only in {}",
+                    j,
+                    format_pts(frames_b[j].record.pts),
+                    fname_b
+                );
+                only_b += 1;
+                continue;
+            }
+            (None, None) => continue,
+        };
+        compared += 1;
+        let (ra, rb) = (&a.record, &b.record);
+        let mut diffs = Vec::new();
+        if ra.size_bytes != rb.size_bytes {
+            diffs.push((
+                "size_bytes".to_string(),
+                ra.size_bytes.to_string(),
+                rb.size_bytes.to_string(),
+            ));
+        }
+        if ra.refs != rb.refs {
+            diffs.push((
+                "refs".to_string(),
+                format!("{:?}", ra.refs),
+                format!("{:?}", rb.refs),
+            ));
+        }
+        if (ra.temporal_id, ra.spatial_id) != (rb.temporal_id, rb.spatial_id) {
+            diffs.push((
+                "temporal_id/spatial_id".to_string(),
+                format!("{}/{}", ra.temporal_id, ra.spatial_id),
+                format!("{}/{}", rb.temporal_id, rb.spatial_id),
+            ));
+        }
+        if let (false, Some(pts_a), Some(pts_b)) = (by_pts, ra.pts, rb.pts) {
+            if (pts_a - pts_b).abs() >= PTS_TOLERANCE {
+                diffs.push(("pts".to_string(), format_pts(ra.pts), format_pts(rb.pts)));
+            }
+        }
+        value_diff("", &a.header, &b.header, &mut diffs);
+        if !diffs.is_empty() {
+            outln!(
+                "frame a#{} b#{} {} pts={}",
+                i.unwrap(),
+                j.unwrap(),
+                ra.frame_type,
+                format_pts(ra.pts)
+            );
+            for (field, a, b) in diffs.iter() {
+                outln!("  {}: {} -> {}", field, a, b);
+            }
+            differ += 1;
+        }
+    }
+    outln!(
+        "{} frames compared, {} differ, {} only in {}, {} only in {}",
+        compared,
+        differ,
+        only_a,
+        fname_a,
+        only_b,
+        fname_b
+    );
+    Ok(differences + differ + only_a + only_b)
+}
@@ -0,0 +1,122 @@
+//
+// aomenc film grain table export for stats --film-grain-table
+//
+use crate::FrameStats;
+use av1parser::*;
+use std::fs;
+use std::io;
+use std::io::Write;
+
+/// write film grain params of shown frames in libaom film grain table format (aomenc --film-grain-table)
+pub fn write_film_grain_table(fname: &str, output: &str, stats: &FrameStats) -> io::Result<()> {
+    const TICKS_PER_SEC: f64 = 10_000_000.0; // timestamp unit of film grain table
+    const DEFAULT_FPS: f64 = 30.0; // frame rate for streams without timestamp
+    if stats.film_grain.is_empty() {
+        logln!("{}: film grain params not found", fname);
+        return Ok(());
+    }
+    let start_times: Vec<i64> = stats
+        .film_grain
+        .iter()
+        .enumerate()
+        .map(|(idx, &(time, _))| {
+            let sec = time.unwrap_or(idx as f64 / DEFAULT_FPS);
+            (sec * TICKS_PER_SEC).round() as i64
+        })
+        .collect();
+    let mut f = io::BufWriter::new(fs::File::create(output)?);
+    writeln!(f, "filmgrn1")?;
+    for (idx, (_, fg)) in stats.film_grain.iter().enumerate() {
+        let start = start_times[idx];
+        // last entry lasts as long as previous frame
+        let end = match start_times.get(idx + 1) {
+            Some(&next) => next,
+            None if idx > 0 => start + (start - start_times[idx - 1]),
+            None => start + (TICKS_PER_SEC / DEFAULT_FPS) as i64,
+        };
+        write_film_grain_entry(&mut f, start, end, fg)?;
+    }
+    f.flush()?;
+    logln!(
+        "{}: write film grain table ({} entries) into {}",
+        fname,
+        stats.film_grain.len(),
+        output
+    );
+    Ok(())
+}
+
+/// write one film grain table entry, applied params are always written in full
+fn write_film_grain_entry<W: io::Write>(
+    f: &mut W,
+    start: i64,
+    end: i64,
+    fg: &obu::FilmGrainParams,
+) -> io::Result<()> {
+    fn values<T: Into<i32> + Copy>(values: &[T], n: usize, offset: i32) -> String {
+        let values: Vec<String> = (0..n)
+            .map(|i| format!(" {}", values.get(i).map_or(0, |&v| v.into() - offset)))
+            .collect();
+        values.concat()
+    }
+    fn points(value: &[u8], scaling: &[u8]) -> String {
+        let pairs: Vec<String> = value
+            .iter()
+            .zip(scaling)
+            .map(|(value, scaling)| format!(" {} {}", value, scaling))
+            .collect();
+        format!("{}{}", value.len(), pairs.concat())
+    }
+    writeln!(
+        f,
+        "E {} {} {} {} {}",
+        start, end, fg.apply_grain as u8, fg.grain_seed, fg.apply_grain as u8
+    )?;
+    if !fg.apply_grain {
+        return Ok(());
+    }
+    writeln!(
+        f,
+        "\tp {} {} {} {} {} {} {} {} {} {} {} {}",
+        fg.ar_coeff_lag,
+        fg.ar_coeff_shift_minus_6 + 6,
+        fg.grain_scale_shift,
+        fg.grain_scaling_minus_8 + 8,
+        fg.chroma_scaling_from_luma as u8,
+        fg.overlap_flag as u8,
+        fg.cb_mult,
+        fg.cb_luma_mult,
+        fg.cb_offset,
+        fg.cr_mult,
+        fg.cr_luma_mult,
+        fg.cr_offset
+    )?;
+    writeln!(f, "\tsY {}", points(&fg.point_y_value, &fg.point_y_scaling))?;
+    writeln!(
+        f,
+        "\tsCb {}",
+        points(&fg.point_cb_value, &fg.point_cb_scaling)
+    )?;
+    writeln!(
+        f,
+        "\tsCr {}",
+        points(&fg.point_cr_value, &fg.point_cr_scaling)
+    )?;
+    let num_pos_luma = 2 * fg.ar_coeff_lag as usize * (fg.ar_coeff_lag as usize + 1);
+    writeln!(
+        f,
+        "\tcY{}",
+        values(&fg.ar_coeffs_y_plus_128, num_pos_luma, 128)
+    )?;
+    writeln!(
+        f,
+        "\tcCb{}",
+        values(&fg.ar_coeffs_cb_plus_128, num_pos_luma + 1, 128)
+    )?;
+    writeln!(
+        f,
+        "\tcCr{}",
+        values(&fg.ar_coeffs_cr_plus_128, num_pos_luma + 1, 128)
+    )?;
+    Ok(())
+}
@@ -0,0 +1,273 @@
+//
+// HDR10+ (SMPTE ST 2094-40) metadata in JSON layout of hdr10plus_tool
+// https://github.com/quietvoid/hdr10plus_tool
+//
+use av1parser::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+///
+/// HDR10+ metadata in JSON layout of hdr10plus_tool
+///
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Hdr10PlusJson {
+    #[serde(rename = "JSONInfo")]
+    json_info: Hdr10PlusJsonInfo,
+    scene_info: Vec<Hdr10PlusScene>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scene_info_summary: Option<Hdr10PlusSceneSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_info: Option<Hdr10PlusToolInfo>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Hdr10PlusJsonInfo {
+    #[serde(rename = "HDR10plusProfile")]
+    hdr10plus_profile: String, // "A" without, "B" with tone mapping (Bezier curve)
+    version: String,
+}
+
+/// per-frame metadata, values are same as ST 2094-40 syntax elements
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Hdr10PlusScene {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bezier_curve_data: Option<Hdr10PlusBezierCurve>,
+    luminance_parameters: Hdr10PlusLuminance,
+    number_of_windows: u8,
+    targeted_system_display_maximum_luminance: u32,
+    scene_frame_index: u32,
+    scene_id: u32,
+    sequence_frame_index: u32,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+struct Hdr10PlusBezierCurve {
+    anchors: Vec<u16>,
+    knee_point_x: u16,
+    knee_point_y: u16,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+struct Hdr10PlusLuminance {
+    #[serde(rename = "AverageRGB")]
+    average_rgb: u32,
+    luminance_distributions: Hdr10PlusDistributions,
+    max_scl: Vec<u32>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+struct Hdr10PlusDistributions {
+    distribution_index: Vec<u8>,   // percentage
+    distribution_values: Vec<u32>, // percentile
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Hdr10PlusSceneSummary {
+    scene_first_frame_index: Vec<u32>,
+    scene_frame_numbers: Vec<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Hdr10PlusToolInfo {
+    tool: String,
+    version: String,
+}
+
+impl Hdr10PlusScene {
+    /// first processing window of ST 2094-40 metadata, scene is assigned later
+    fn new(meta: &obu::St2094_40Metadata, frame: u32) -> Hdr10PlusScene {
+        let win = &meta.windows[0];
+        Hdr10PlusScene {
+            bezier_curve_data: win.knee_point.map(|(x, y)| Hdr10PlusBezierCurve {
+                anchors: win.bezier_curve_anchors.clone(),
+                knee_point_x: x,
+                knee_point_y: y,
+            }),
+            luminance_parameters: Hdr10PlusLuminance {
+                average_rgb: win.average_maxrgb,
+                luminance_distributions: Hdr10PlusDistributions {
+                    distribution_index: win.distribution_maxrgb.iter().map(|d| d.0).collect(),
+                    distribution_values: win.distribution_maxrgb.iter().map(|d| d.1).collect(),
+                },
+                max_scl: win.maxscl.to_vec(),
+            },
+            number_of_windows: meta.num_windows,
+            targeted_system_display_maximum_luminance: meta
+                .targeted_system_display_maximum_luminance,
+            scene_frame_index: 0,
+            scene_id: 0,
+            sequence_frame_index: frame,
+        }
+    }
+
+    /// check metadata values are same, regardless of frame and scene index
+    fn same_parameters(&self, other: &Hdr10PlusScene) -> bool {
+        self.bezier_curve_data == other.bezier_curve_data
+            && self.luminance_parameters == other.luminance_parameters
+            && self.number_of_windows == other.number_of_windows
+            && self.targeted_system_display_maximum_luminance
+                == other.targeted_system_display_maximum_luminance
+    }
+
+    ///
+    /// ST 2094-40 metadata of single processing window,
+    /// JSON has no parameters of other windows then NumberOfWindows is not kept
+    ///
+    fn to_metadata(&self) -> io::Result<obu::St2094_40Metadata> {
+        let lum = &self.luminance_parameters;
+        let dist = &lum.luminance_distributions;
+        if lum.max_scl.len() != 3 || dist.distribution_index.len() != dist.distribution_values.len()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "invalid LuminanceParameters at SequenceFrameIndex={}",
+                    self.sequence_frame_index
+                ),
+            ));
+        }
+        let mut win = obu::St2094_40Window {
+            average_maxrgb: lum.average_rgb,
+            distribution_maxrgb: dist
+                .distribution_index
+                .iter()
+                .cloned()
+                .zip(dist.distribution_values.iter().cloned())
+                .collect(),
+            ..Default::default()
+        };
+        win.maxscl.copy_from_slice(&lum.max_scl);
+        if let Some(ref bezier) = self.bezier_curve_data {
+            win.knee_point = Some((bezier.knee_point_x, bezier.knee_point_y));
+            win.bezier_curve_anchors = bezier.anchors.clone();
+        }
+        Ok(obu::St2094_40Metadata {
+            application_version: 1,
+            num_windows: 1,
+            targeted_system_display_maximum_luminance: self
+                .targeted_system_display_maximum_luminance,
+            windows: vec![win],
+            ..Default::default()
+        })
+    }
+}
+
+/// dump HDR10+ metadata of each temporal unit (presentation order) as hdr10plus_tool JSON
+pub fn extract_hdr10plus(input: &str, output: &str) -> io::Result<()> {
+    let f = fs::OpenOptions::new().read(true).open(input)?;
+    let mut reader = io::BufReader::new(f);
+    let fmt = probe_fileformat_with_hint(&mut reader, input)?;
+    let mut tu_reader = stream::TemporalUnitReader::new(reader, fmt)?;
+
+    let mut scenes: Vec<Hdr10PlusScene> = Vec::new();
+    let mut summary = Hdr10PlusSceneSummary {
+        scene_first_frame_index: Vec::new(),
+        scene_frame_numbers: Vec::new(),
+    };
+    let mut nunit = 0;
+    while let Some(tu) = tu_reader.next_unit()? {
+        // temporal unit contains one shown frame
+        let frame = nunit;
+        nunit += 1;
+        let mut cursor = &tu.data[..];
+        let mut meta = None;
+        while !cursor.is_empty() && meta.is_none() {
+            let sz = cursor.len() as u32;
+            let (obu, bytes) = stream::read_obu(&mut cursor, sz)?;
+            if obu.obu_type != obu::OBU_METADATA {
+                continue;
+            }
+            if let Ok(obu::MetadataObu::ItutT35(t35)) =
+                obu::parse_metadata_obu(&mut &bytes[obu.header_len as usize..])
+            {
+                meta = t35.parse_st2094_40();
+            }
+        }
+        let mut scene = match meta {
+            Some(ref meta) => Hdr10PlusScene::new(meta, frame),
+            None => continue,
+        };
+        // new scene starts when metadata values change
+        match scenes.last() {
+            Some(prev) if prev.same_parameters(&scene) => {
+                scene.scene_id = prev.scene_id;
+                scene.scene_frame_index = prev.scene_frame_index + 1;
+                *summary.scene_frame_numbers.last_mut().unwrap() += 1;
+            }
+            _ => {
+                scene.scene_id = summary.scene_first_frame_index.len() as u32;
+                summary.scene_first_frame_index.push(frame);
+                summary.scene_frame_numbers.push(1);
+            }
+        }
+        scenes.push(scene);
+    }
+
+    let profile = if scenes.iter().any(|s| s.bezier_curve_data.is_some()) {
+        "B"
+    } else {
+        "A"
+    };
+    let nframe = scenes.len();
+    let nscene = summary.scene_first_frame_index.len();
+    let json = Hdr10PlusJson {
+        json_info: Hdr10PlusJsonInfo {
+            hdr10plus_profile: profile.to_string(),
+            version: "1.0".to_string(),
+        },
+        scene_info: scenes,
+        scene_info_summary: Some(summary),
+        tool_info: Some(Hdr10PlusToolInfo {
+            tool: crate_name!().to_string(),
+            version: crate_version!().to_string(),
+        }),
+    };
+    let json = serde_json::to_string_pretty(&json)?;
+    if output == "-" {
+        println!("{}", json);
+        return Ok(());
+    }
+    fs::write(output, json + "\n")?;
+    logln!(
+        "{}: extract HDR10+ metadata of {} frames ({} scenes) from {} temporal units into {}",
+        input,
+        nframe,
+        nscene,
+        nunit,
+        output
+    );
+    Ok(())
+}
+
+/// read HDR10+ JSON file and make ITU-T T.35 metadata OBUs keyed by SequenceFrameIndex
+pub fn read_hdr10plus_json(path: &str) -> io::Result<BTreeMap<u32, Vec<u8>>> {
+    let f = io::BufReader::new(fs::File::open(path)?);
+    let json: Hdr10PlusJson = serde_json::from_reader(f)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, e)))?;
+    let mut obus = BTreeMap::new();
+    for scene in json.scene_info.iter() {
+        let t35 = scene.to_metadata()?.to_itut_t35()?;
+        let payload = obu::write_metadata_obu(&obu::MetadataObu::ItutT35(t35))?;
+        let mut data = Vec::new();
+        obu::Obu::new(obu::OBU_METADATA, 0).write(&mut data, &payload)?;
+        if obus.insert(scene.sequence_frame_index, data).is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{}: duplicate SequenceFrameIndex={}",
+                    path, scene.sequence_frame_index
+                ),
+            ));
+        }
+    }
+    Ok(obus)
+}
@@ -0,0 +1,78 @@
+//
+// level measurement and minimum level suggestion
+//
+use crate::{stream_duration, AppConfig, FrameStats};
+use av1parser::*;
+use std::cmp;
+
+/// update picture size and tile configuration constrained by level
+pub fn measure_level(level: &mut av1::LevelMeasure, fh: &obu::FrameHeader) {
+    let (width, height) = (fh.frame_size.upscaled_width, fh.frame_size.frame_height);
+    let tile_info = &fh.tile_info;
+    level.max_width = cmp::max(level.max_width, width);
+    level.max_height = cmp::max(level.max_height, height);
+    level.max_pic_size = cmp::max(level.max_pic_size, width as u64 * height as u64);
+    level.max_tiles = cmp::max(
+        level.max_tiles,
+        tile_info.tile_cols as u32 * tile_info.tile_rows as u32,
+    );
+    level.max_tile_cols = cmp::max(level.max_tile_cols, tile_info.tile_cols as u32);
+}
+
+/// fill average rates constrained by level from stream duration
+pub fn measure_level_rates(stats: &mut FrameStats, config: &AppConfig) {
+    let duration = match stream_duration(stats, config) {
+        Some(duration) if duration > 0.0 => duration,
+        _ => return,
+    };
+    let frames: u64 = stats.frame_count.iter().sum();
+    let bytes: u64 = stats.obu_bytes.iter().sum();
+    let pic_size = stats.level.max_pic_size as f64;
+    stats.level.display_rate = Some(pic_size * stats.shown_frames as f64 / duration);
+    stats.level.decode_rate = Some(pic_size * frames as f64 / duration);
+    stats.level.header_rate = Some(stats.frame_headers as f64 / duration);
+    stats.level.bitrate = Some(bytes as f64 * 8.0 / duration);
+}
+
+/// print minimum level (and tier) which stream conforms to, compared with signaled one
+pub fn print_level_suggestion(stats: &FrameStats) {
+    let sh = match stats.sequence_header {
+        Some(ref sh) if stats.level.max_pic_size > 0 => sh,
+        _ => return,
+    };
+    let level_tier = |seq_level_idx: u8, seq_tier: u8| {
+        format!(
+            "{} {} tier",
+            av1::stringify::level(seq_level_idx),
+            if seq_tier == 0 { "Main" } else { "High" }
+        )
+    };
+    let (seq_level_idx, seq_tier) = (sh.op[0].seq_level_idx, sh.op[0].seq_tier);
+    let signaled = level_tier(seq_level_idx, seq_tier);
+    let minimum = av1::minimum_level(&stats.level, sh.seq_profile);
+    let verdict = match (av1::level_limits(seq_level_idx), minimum) {
+        (Some(limits), _) => {
+            let exceeded = limits.exceeded(&stats.level, sh.seq_profile, seq_tier);
+            if !exceeded.is_empty() {
+                format!(" under-declared (exceeds {})", exceeded.join(", "))
+            } else if minimum != Some((seq_level_idx, seq_tier)) {
+                " over-declared".to_string()
+            } else {
+                String::new()
+            }
+        }
+        _ => String::new(),
+    };
+    let rates = if stats.level.bitrate.is_none() {
+        " (frame rate unknown, picture size and tiles only)"
+    } else {
+        ""
+    };
+    logln!(
+        "  level: signaled={} minimum={}{}{}",
+        signaled,
+        minimum.map_or("none".to_string(), |(idx, tier)| level_tier(idx, tier)),
+        verdict,
+        rates
+    );
+}
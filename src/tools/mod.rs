@@ -0,0 +1,66 @@
+//
+// subcommand implementations other than stream analysis
+//
+pub mod av1c;
+pub mod convert;
+pub mod diff;
+pub mod grain;
+pub mod hdr10plus;
+pub mod level;
+pub mod mux;
+pub mod repair;
+pub mod rewrite;
+pub mod scan;
+pub mod split;
+pub mod tracks;
+
+use std::fs;
+use std::io;
+use std::sync::atomic::Ordering;
+
+/// output file of subcommand, "-" for stdout
+pub enum OutputFile {
+    File(io::BufWriter<fs::File>),
+    Stdout(io::Stdout),
+}
+
+impl OutputFile {
+    /// create output file, text log is redirected into stderr while stdout carries output stream
+    pub fn create(path: &str) -> io::Result<Self> {
+        if path == "-" {
+            crate::TEXT_LOG_STDERR.store(true, Ordering::Relaxed);
+            return Ok(OutputFile::Stdout(io::stdout()));
+        }
+        Ok(OutputFile::File(io::BufWriter::new(fs::File::create(
+            path,
+        )?)))
+    }
+}
+
+impl io::Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputFile::File(f) => f.write(buf),
+            OutputFile::Stdout(out) => out.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputFile::File(f) => f.flush(),
+            OutputFile::Stdout(out) => out.flush(),
+        }
+    }
+}
+
+impl io::Seek for OutputFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        match self {
+            OutputFile::File(f) => f.seek(pos),
+            OutputFile::Stdout(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "stdout is not seekable",
+            )),
+        }
+    }
+}
@@ -0,0 +1,494 @@
+//
+// container conversion for mux, extract, concat and avif subcommands
+//
+use crate::open_mp4_input;
+use crate::tools::OutputFile;
+use av1parser::*;
+use std::cmp;
+use std::fs;
+use std::io;
+use std::io::SeekFrom;
+
+/// container writer for mux subcommand
+enum MuxWriter<W> {
+    Mp4(mp4::Mp4Writer<W>),
+    WebM(mkv::MkvWriter<W>),
+}
+
+/// mux IVF or low overhead bitstream format into MP4/WebM file
+pub fn mux_file(input: &str, output: &str) -> io::Result<()> {
+    let f = fs::OpenOptions::new().read(true).open(input)?;
+    let mut reader = io::BufReader::new(f);
+    let fmt = probe_fileformat_with_hint(&mut reader, input)?;
+    let mut tu_reader = stream::TemporalUnitReader::new(reader, fmt)?;
+    let (tb_num, tb_den) = tu_reader.timebase();
+
+    let f = io::BufWriter::new(fs::File::create(output)?);
+    let mut writer = if output.ends_with(".webm") || output.ends_with(".mkv") {
+        MuxWriter::WebM(mkv::MkvWriter::new(f)?)
+    } else {
+        MuxWriter::Mp4(mp4::Mp4Writer::new(f, tb_den)?)
+    };
+    let mut seq = av1::Sequence::new();
+    let mut sh_obu = None; // first SequenceHeader OBU for configOBUs
+    let mut num_samples = 0;
+
+    while let Some(tu) = tu_reader.next_unit()? {
+        let mut sample = Vec::with_capacity(tu.data.len());
+        let mut is_sync = false;
+        let mut cursor = io::Cursor::new(&tu.data[..]);
+        while (cursor.position() as usize) < tu.data.len() {
+            let remain = (tu.data.len() - cursor.position() as usize) as u32;
+            let (obu, bytes) = stream::read_obu(&mut cursor, remain)?;
+            let mut payload = &bytes[bytes.len() - obu.obu_size as usize..];
+            match obu.obu_type {
+                // TemporalDelimiter OBUs are not stored in MP4 samples/WebM Blocks
+                obu::OBU_TEMPORAL_DELIMITER => continue,
+                obu::OBU_SEQUENCE_HEADER => {
+                    if let Some(sh) = obu::parse_sequence_header(&mut payload) {
+                        seq.sh = Some(sh);
+                        if sh_obu.is_none() {
+                            sh_obu = Some(bytes.clone());
+                        }
+                    }
+                }
+                obu::OBU_FRAME_HEADER | obu::OBU_FRAME => {
+                    let fh = match seq.sh {
+                        Some(ref sh) => obu::parse_frame_header(&mut payload, sh, &mut seq.rfman),
+                        None => None,
+                    };
+                    if let Some(fh) = fh {
+                        if fh.frame_type == obu::KEY_FRAME && fh.show_frame {
+                            is_sync = true;
+                        }
+                        seq.decode_frame_wrapup(&fh);
+                    }
+                }
+                _ => {}
+            }
+            sample.extend_from_slice(&bytes);
+        }
+        match writer {
+            MuxWriter::Mp4(ref mut writer) => {
+                writer.write_sample(&sample, tu.pts * tb_num as u64, is_sync)?
+            }
+            MuxWriter::WebM(ref mut writer) => {
+                let timestamp_ns = tu.pts * tb_num as u64 * 1_000_000_000 / tb_den as u64;
+                writer.write_block(&sample, timestamp_ns, is_sync)?
+            }
+        }
+        num_samples += 1;
+    }
+
+    let sh = match seq.sh {
+        Some(ref sh) => sh,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "no sequence header in input stream",
+            ))
+        }
+    };
+    let av1cc = mp4::AV1CodecConfigurationBox::from_sequence_header(sh, sh_obu.as_ref().unwrap());
+    match writer {
+        MuxWriter::Mp4(writer) => {
+            writer.finish(
+                sh.max_frame_width as u16,
+                sh.max_frame_height as u16,
+                &av1cc,
+            )?;
+            logln!(
+                "{}: mux {} samples into {} (timescale={})",
+                input,
+                num_samples,
+                output,
+                tb_den
+            );
+        }
+        MuxWriter::WebM(writer) => {
+            writer.finish(
+                sh.max_frame_width as u64,
+                sh.max_frame_height as u64,
+                &av1cc.to_bytes(),
+            )?;
+            logln!("{}: mux {} blocks into {}", input, num_samples, output);
+        }
+    }
+    Ok(())
+}
+
+///
+/// Temporal unit writer for extract subcommand (raw OBU stream or IVF)
+///
+pub struct ExtractWriter<W> {
+    writer: W,
+    ivf: bool,
+    nframe: u32,
+}
+
+impl<W: io::Write + io::Seek> ExtractWriter<W> {
+    /// create writer, write IVF file header with timebase (numerator, denominator)
+    pub fn new(
+        mut writer: W,
+        ivf: bool,
+        width: u16,
+        height: u16,
+        timebase: (u32, u32),
+    ) -> io::Result<Self> {
+        if ivf {
+            let hdr = ivf::IvfHeader {
+                codec: *b"AV01",
+                width,
+                height,
+                timescale_num: timebase.1,
+                timescale_den: timebase.0,
+                length: 0, // patched at finish()
+                header_len: ivf::IVF_HEADER_SIZE as u16,
+            };
+            ivf::write_ivf_header(&mut writer, &hdr)?;
+        }
+        Ok(ExtractWriter {
+            writer,
+            ivf,
+            nframe: 0,
+        })
+    }
+
+    /// write temporal unit, prepend TemporalDelimiter OBU (and configOBUs) if needed
+    pub fn write_unit(
+        &mut self,
+        data: &[u8],
+        config_obus: Option<&[u8]>,
+        pts: u64,
+    ) -> io::Result<()> {
+        // temporal unit shall start with TemporalDelimiter OBU
+        let obu = obu::parse_obu_header(&mut &data[..], data.len() as u32)?;
+        let td_len = if obu.obu_type == obu::OBU_TEMPORAL_DELIMITER {
+            (obu.header_len + obu.obu_size) as usize
+        } else {
+            0
+        };
+        let mut tu = Vec::with_capacity(data.len() + 2);
+        if td_len > 0 {
+            tu.extend_from_slice(&data[..td_len]);
+        } else {
+            tu.extend_from_slice(&stream::TEMPORAL_DELIMITER_OBU);
+        }
+        if let Some(config_obus) = config_obus {
+            tu.extend_from_slice(config_obus);
+        }
+        tu.extend_from_slice(&data[td_len..]);
+        self.write_raw_unit(&tu, pts)
+    }
+
+    /// write temporal unit as is
+    pub fn write_raw_unit(&mut self, tu: &[u8], pts: u64) -> io::Result<()> {
+        if self.ivf {
+            let frame = ivf::IvfFrame {
+                size: tu.len() as u32,
+                pts,
+            };
+            ivf::write_ivf_frame(&mut self.writer, &frame)?;
+        }
+        self.writer.write_all(tu)?;
+        self.nframe += 1;
+        Ok(())
+    }
+
+    /// patch number of frames in IVF file header
+    pub fn finish(mut self) -> io::Result<u32> {
+        if self.ivf {
+            self.writer.seek(SeekFrom::Start(24))?;
+            self.writer.write_all(&self.nframe.to_le_bytes())?;
+        }
+        self.writer.flush()?;
+        Ok(self.nframe)
+    }
+}
+
+/// extract AV1 samples in MP4/WebM file into low overhead bitstream format or IVF
+pub fn extract_obu(
+    input: &str,
+    output: &str,
+    with_config_obus: bool,
+    init: Option<&str>,
+    options: &mp4::Mp4Options,
+) -> io::Result<()> {
+    let f = fs::OpenOptions::new().read(true).open(input)?;
+    let mut reader = io::BufReader::new(f);
+    let format = probe_fileformat_with_hint(&mut reader, input)?;
+    let ivf = output.ends_with(".ivf");
+    let f = OutputFile::create(output)?;
+    let nframe = match format {
+        FileFormat::MP4 => extract_mp4(reader, f, ivf, with_config_obus, init, options)?,
+        FileFormat::WebM => extract_webm(reader, f, ivf, with_config_obus, options)?,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "extract supports MP4/WebM input only",
+            ))
+        }
+    };
+    logln!("{}: extract {} samples into {}", input, nframe, output);
+    Ok(())
+}
+
+/// extract 'av01' track in MP4 file
+fn extract_mp4<R: io::Read + io::Seek, W: io::Write + io::Seek>(
+    mut reader: R,
+    writer: W,
+    ivf: bool,
+    with_config_obus: bool,
+    init: Option<&str>,
+    options: &mp4::Mp4Options,
+) -> io::Result<u32> {
+    let mp4 = open_mp4_input(&mut reader, init, options)?;
+    let av1cc = match mp4.get_av1config() {
+        Some((_, av1cc)) => av1cc,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "av01 track not found",
+            ))
+        }
+    };
+    let (width, height) = match mp4.get_trackheader() {
+        Some(tkhd) => ((tkhd.width >> 16) as u16, (tkhd.height >> 16) as u16),
+        None => (0, 0),
+    };
+    let timebase = (1, mp4.get_timescale());
+    let mut writer = ExtractWriter::new(writer, ivf, width, height, timebase)?;
+    for (index, sample) in mp4.get_samples().iter().enumerate() {
+        reader.seek(SeekFrom::Start(sample.pos))?;
+        let mut data = vec![0; sample.size as usize];
+        reader.read_exact(&mut data)?;
+        let config_obus = if index == 0 && with_config_obus {
+            Some(av1cc.config_obus())
+        } else {
+            None
+        };
+        writer.write_unit(&data, config_obus, cmp::max(sample.pts, 0) as u64)?;
+    }
+    writer.finish()
+}
+
+/// extract "V_AV1" track in WebM file, pts is Block timecode in TimecodeScale
+fn extract_webm<R: io::Read + io::Seek, W: io::Write + io::Seek>(
+    mut reader: R,
+    writer: W,
+    ivf: bool,
+    with_config_obus: bool,
+    options: &mp4::Mp4Options,
+) -> io::Result<u32> {
+    let mut webm = mkv::open_mkvfile(&mut reader)?;
+    let av1_tracks = webm.find_tracks(mkv::CODEC_V_AV1);
+    let track = webm.get_tracks().iter().find(|t| match options.track_id {
+        Some(id) => t.track_num == id as u64,
+        None => av1_tracks.first() == Some(&t.track_num),
+    });
+    let (track_num, width, height, codec_private) = match track {
+        Some(t) if av1_tracks.contains(&t.track_num) => {
+            let (width, height) = t
+                .setting
+                .as_ref()
+                .map_or((0, 0), |v| (v.pixel_width as u16, v.pixel_height as u16));
+            (t.track_num, width, height, t.codec_private.clone())
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "V_AV1 track not found",
+            ))
+        }
+    };
+    // TimecodeScale [nsec] to timebase
+    let scale = webm.get_timecode_scale();
+    let gcd = (1..=cmp::min(scale, 1_000_000_000))
+        .rev()
+        .find(|d| scale % d == 0 && 1_000_000_000 % d == 0)
+        .unwrap_or(1);
+    let timebase = ((scale / gcd) as u32, (1_000_000_000 / gcd) as u32);
+    let mut writer = ExtractWriter::new(writer, ivf, width, height, timebase)?;
+    // CodecPrivate is AV1CodecConfigurationRecord
+    let config_obus = if codec_private.len() > 4 {
+        &codec_private[4..]
+    } else {
+        &[]
+    };
+    while let Some(block) = webm.next_block(&mut reader)? {
+        if block.track_num != track_num {
+            continue;
+        }
+        let mut data = vec![0; block.size as usize];
+        reader.read_exact(&mut data)?;
+        let config_obus = if writer.nframe == 0 && with_config_obus {
+            Some(config_obus)
+        } else {
+            None
+        };
+        writer.write_unit(&data, config_obus, cmp::max(block.timecode, 0) as u64)?;
+    }
+    writer.finish()
+}
+
+/// rescale timestamp from one timebase (numerator, denominator) into another
+fn rescale_pts(pts: u64, from: (u32, u32), to: (u32, u32)) -> u64 {
+    (pts as u128 * from.0 as u128 * to.1 as u128 / (from.1 as u128 * to.0 as u128)) as u64
+}
+
+/// concatenate IVF or low overhead bitstream format files into single IVF/raw OBU stream
+pub fn concat_files(inputs: &[&str], output: &str) -> io::Result<()> {
+    let ivf = output.ends_with(".ivf");
+    let mut writer = None;
+    let mut timebase = (1, 30);
+    let mut last_sh: Option<(obu::Obu, Vec<u8>)> = None;
+    let mut next_pts = 0;
+
+    for (i, input) in inputs.iter().enumerate() {
+        let f = fs::OpenOptions::new().read(true).open(input)?;
+        let mut reader = io::BufReader::new(f);
+        let fmt = probe_fileformat_with_hint(&mut reader, input)?;
+        let mut tu_reader = stream::TemporalUnitReader::new(reader, fmt)?;
+        let tb = tu_reader.timebase();
+        if i == 0 {
+            timebase = tb;
+        }
+
+        let (mut first_pts, mut last_pts) = (None, next_pts);
+        let mut interval = cmp::max(rescale_pts(1, tb, timebase), 1);
+        let mut nunit = 0;
+        while let Some(tu) = tu_reader.next_unit()? {
+            let sh = stream::find_obu(&tu.data, obu::OBU_SEQUENCE_HEADER)?;
+            let mut config_obus = None;
+            if nunit == 0 {
+                match (&sh, &last_sh) {
+                    (Some((_, sh)), Some((_, prev))) if sh != prev => {
+                        logln!("{}: sequence header differs from previous input", input)
+                    }
+                    (None, Some((_, prev))) => {
+                        logln!("{}: insert sequence header from previous input", input);
+                        config_obus = Some(prev.clone());
+                    }
+                    (None, None) => {
+                        logln!("{}: no sequence header in first temporal unit", input)
+                    }
+                    _ => {}
+                }
+            }
+            if sh.is_some() {
+                last_sh = sh;
+            }
+            if writer.is_none() {
+                let (width, height) = match last_sh {
+                    Some((ref obu, ref bytes)) => {
+                        match obu::parse_sequence_header(&mut &bytes[obu.header_len as usize..]) {
+                            Some(sh) => (sh.max_frame_width as u16, sh.max_frame_height as u16),
+                            None => (0, 0),
+                        }
+                    }
+                    None => (0, 0),
+                };
+                let f = io::BufWriter::new(fs::File::create(output)?);
+                writer = Some(ExtractWriter::new(f, ivf, width, height, timebase)?);
+            }
+
+            // renumber timestamps continuously from previous input
+            let base = *first_pts.get_or_insert(tu.pts);
+            let pts = next_pts + rescale_pts(tu.pts.saturating_sub(base), tb, timebase);
+            if nunit > 0 && pts > last_pts {
+                interval = pts - last_pts;
+            }
+            last_pts = pts;
+            let w = writer.as_mut().unwrap();
+            w.write_unit(&tu.data, config_obus.as_ref().map(|obus| &obus[..]), pts)?;
+            nunit += 1;
+        }
+        if nunit > 0 {
+            next_pts = last_pts + interval;
+        }
+        logln!("{}: {} temporal units", input, nunit);
+    }
+    let nunit = match writer {
+        Some(w) => w.finish()?,
+        None => 0,
+    };
+    logln!("concat {} temporal units into {}", nunit, output);
+    Ok(())
+}
+
+/// convert still picture (or first key frame) into AVIF file
+pub fn convert_avif(input: &str, output: &str) -> io::Result<()> {
+    let f = fs::OpenOptions::new().read(true).open(input)?;
+    let mut reader = io::BufReader::new(f);
+    let fmt = probe_fileformat_with_hint(&mut reader, input)?;
+    let mut tu_reader = stream::TemporalUnitReader::new(reader, fmt)?;
+
+    let mut seq = av1::Sequence::new();
+    let mut sh_obu = None;
+    let mut item = None;
+    while let Some(tu) = tu_reader.next_unit()? {
+        if item.is_some() {
+            let still_picture = seq.sh.as_ref().is_some_and(|sh| sh.still_picture);
+            if !still_picture {
+                logln!("{}: not still picture, use first key frame", input);
+            }
+            break;
+        }
+        let mut data = &tu.data[..];
+        let mut obus = Vec::new();
+        let mut keyframe = None;
+        while !data.is_empty() {
+            let sz = data.len() as u32;
+            let (obu, bytes) = stream::read_obu(&mut data, sz)?;
+            let mut payload = &bytes[obu.header_len as usize..];
+            match obu.obu_type {
+                // TemporalDelimiter OBUs are not stored in AV1 Image Item Data
+                obu::OBU_TEMPORAL_DELIMITER => continue,
+                obu::OBU_SEQUENCE_HEADER => {
+                    seq.sh = obu::parse_sequence_header(&mut payload);
+                    sh_obu = Some(bytes.clone());
+                }
+                obu::OBU_FRAME_HEADER | obu::OBU_FRAME => {
+                    let fh = match seq.sh {
+                        Some(ref sh) => obu::parse_frame_header(&mut payload, sh, &mut seq.rfman),
+                        None => None,
+                    };
+                    if let Some(fh) = fh {
+                        if fh.frame_type == obu::KEY_FRAME && fh.show_frame {
+                            keyframe =
+                                Some((fh.frame_size.upscaled_width, fh.frame_size.frame_height));
+                        }
+                        seq.decode_frame_wrapup(&fh);
+                    }
+                }
+                _ => {}
+            }
+            obus.extend_from_slice(&bytes);
+        }
+        if let Some(size) = keyframe {
+            item = Some((size, obus));
+        }
+    }
+
+    let (((width, height), data), sh, sh_obu) = match (item, seq.sh, sh_obu) {
+        (Some(item), Some(sh), Some(sh_obu)) => (item, sh, sh_obu),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "no sequence header or key frame in input stream",
+            ))
+        }
+    };
+    let av1cc = mp4::AV1CodecConfigurationBox::from_sequence_header(&sh, &sh_obu);
+    let f = io::BufWriter::new(fs::File::create(output)?);
+    mp4::write_avif(f, width, height, &av1cc, &data)?;
+    logln!(
+        "{}: convert {}x{} image into {} ({} bytes)",
+        input,
+        width,
+        height,
+        output,
+        data.len()
+    );
+    Ok(())
+}
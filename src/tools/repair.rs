@@ -0,0 +1,63 @@
+//
+// IVF repair for repair subcommand
+//
+use av1parser::*;
+use std::fs;
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// rescan IVF frames, fix header length and (optionally) renumber pts monotonically
+pub fn repair_ivf(input: &str, output: &str, renumber_pts: bool) -> io::Result<()> {
+    let f = fs::OpenOptions::new().read(true).open(input)?;
+    let mut reader = io::BufReader::new(f);
+    let mut ivf_header = [0; ivf::IVF_HEADER_SIZE];
+    reader.read_exact(&mut ivf_header)?;
+    let mut hdr = ivf::parse_ivf_header(&ivf_header)
+        .map_err(|msg| io::Error::new(io::ErrorKind::InvalidData, msg))?;
+    reader.seek(SeekFrom::Start(hdr.header_len as u64))?;
+    let length = hdr.length;
+    hdr.header_len = ivf::IVF_HEADER_SIZE as u16;
+
+    let mut writer = io::BufWriter::new(fs::File::create(output)?);
+    ivf::write_ivf_header(&mut writer, &hdr)?;
+    let (mut nframe, mut nrenumber) = (0, 0);
+    let mut prev_pts: Option<u64> = None;
+    let (mut pts_offset, mut pts_delta) = (0, 1);
+    while let Ok(mut frame) = ivf::parse_ivf_frame(&mut reader) {
+        let mut data = vec![0; frame.size as usize];
+        if reader.read_exact(&mut data).is_err() {
+            logln!("{}: drop truncated frame (pts={})", input, frame.pts);
+            break;
+        }
+        frame.pts += pts_offset;
+        if let Some(prev) = prev_pts {
+            if renumber_pts && frame.pts <= prev {
+                // continue from previous pts with last frame interval
+                pts_offset += prev + pts_delta - frame.pts;
+                frame.pts = prev + pts_delta;
+                nrenumber += 1;
+            }
+            if prev < frame.pts {
+                pts_delta = frame.pts - prev;
+            }
+        }
+        prev_pts = Some(frame.pts);
+        ivf::write_ivf_frame(&mut writer, &frame)?;
+        writer.write_all(&data)?;
+        nframe += 1;
+    }
+    // rewrite length field
+    writer.seek(SeekFrom::Start(24))?;
+    writer.write_all(&(nframe as u32).to_le_bytes())?;
+    writer.flush()?;
+    logln!(
+        "{}: repair {} frames into {} (length {}->{}, {} pts renumbered)",
+        input,
+        nframe,
+        output,
+        length,
+        nframe,
+        nrenumber
+    );
+    Ok(())
+}
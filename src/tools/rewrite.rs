@@ -0,0 +1,576 @@
+//
+// temporal unit rewriters for metastrip, metainject and grainstrip subcommands
+//
+use crate::is_random_access_point;
+use crate::tools::mux::ExtractWriter;
+use av1parser::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::mem;
+
+///
+/// rewrite each temporal unit of IVF, raw OBU stream, Annex B or MPEG-2 TS
+/// into IVF file (with .ivf) or raw OBU stream, MP4/WebM input is rejected
+///
+/// edit returns new temporal unit data which is written as is, IVF frame sizes are recomputed.
+///
+fn rewrite_stream<F>(input: &str, output: &str, mut edit: F) -> io::Result<u32>
+where
+    F: FnMut(&stream::TemporalUnit) -> io::Result<Vec<u8>>,
+{
+    let f = fs::OpenOptions::new().read(true).open(input)?;
+    let mut reader = io::BufReader::new(f);
+    let fmt = probe_fileformat_with_hint(&mut reader, input)?;
+    if !matches!(
+        fmt,
+        FileFormat::IVF | FileFormat::Bitstream | FileFormat::AnnexB | FileFormat::MPEGTS
+    ) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "{}: {:?} input is not supported, convert into IVF with extract subcommand",
+                input, fmt
+            ),
+        ));
+    }
+    let mut tu_reader = stream::TemporalUnitReader::new(reader, fmt)?;
+    let timebase = tu_reader.timebase();
+    let (width, height) = match tu_reader.ivf_header() {
+        Some(hdr) => (hdr.width, hdr.height),
+        None => (0, 0),
+    };
+
+    let ivf = output.ends_with(".ivf");
+    let mut writer = None;
+    while let Some(tu) = tu_reader.next_unit()? {
+        let data = edit(&tu)?;
+        if writer.is_none() {
+            // IVF frame size from first sequence header if input is not IVF
+            let (width, height) = match stream::find_obu(&data, obu::OBU_SEQUENCE_HEADER)? {
+                Some((ref obu, ref bytes)) if width == 0 => {
+                    match obu::parse_sequence_header(&mut &bytes[obu.header_len as usize..]) {
+                        Some(sh) => (sh.max_frame_width as u16, sh.max_frame_height as u16),
+                        None => (0, 0),
+                    }
+                }
+                _ => (width, height),
+            };
+            let f = io::BufWriter::new(fs::File::create(output)?);
+            writer = Some(ExtractWriter::new(f, ivf, width, height, timebase)?);
+        }
+        // temporal unit is copied byte-for-byte except OBUs rewritten by edit
+        writer.as_mut().unwrap().write_raw_unit(&data, tu.pts)?;
+    }
+    match writer {
+        Some(w) => w.finish(),
+        None => Ok(0),
+    }
+}
+
+/// selection of metadata OBUs for strip subcommand
+pub struct MetadataFilter {
+    pub metadata_types: Vec<u32>, // metadata_type
+    pub t35_providers: Vec<u16>,  // terminal provider code of ITU-T T.35 metadata
+    pub hdr10plus: bool,          // ST 2094-40 metadata
+}
+
+impl MetadataFilter {
+    /// check no metadata OBUs are selected
+    fn is_empty(&self) -> bool {
+        self.metadata_types.is_empty() && self.t35_providers.is_empty() && !self.hdr10plus
+    }
+
+    /// check metadata_obu() payload is selected
+    fn matches(&self, payload: &[u8]) -> bool {
+        let (_, metadata_type) = match obu::leb128(&mut &payload[..]) {
+            Ok(v) => v,
+            Err(_) => return false,
+        };
+        if self.metadata_types.contains(&metadata_type) {
+            return true;
+        }
+        if metadata_type != obu::METADATA_TYPE_ITUT_T35 {
+            return false;
+        }
+        match obu::parse_metadata_obu(&mut &payload[..]) {
+            Ok(obu::MetadataObu::ItutT35(t35)) => {
+                (self.hdr10plus && t35.parse_st2094_40().is_some())
+                    || t35
+                        .terminal_provider_code()
+                        .is_some_and(|code| self.t35_providers.contains(&code))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// remove selected metadata OBUs from stream
+pub fn strip_metadata(input: &str, output: &str, filter: &MetadataFilter) -> io::Result<()> {
+    if filter.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no metadata OBUs selected, specify --metadata-type, --t35-provider or --hdr10plus",
+        ));
+    }
+    let mut nstrip = 0;
+    let nunit = rewrite_stream(input, output, |tu| {
+        let mut data = Vec::with_capacity(tu.data.len());
+        let mut cursor = &tu.data[..];
+        while !cursor.is_empty() {
+            let sz = cursor.len() as u32;
+            let (obu, bytes) = stream::read_obu(&mut cursor, sz)?;
+            if obu.obu_type == obu::OBU_METADATA
+                && filter.matches(&bytes[obu.header_len as usize..])
+            {
+                nstrip += 1;
+                continue;
+            }
+            data.extend_from_slice(&bytes);
+        }
+        Ok(data)
+    })?;
+    logln!(
+        "{}: strip {} metadata OBUs from {} temporal units into {}",
+        input,
+        nstrip,
+        nunit,
+        output
+    );
+    Ok(())
+}
+
+/// HDR static metadata for metainject subcommand (layout of JSON file)
+#[derive(Default, Deserialize)]
+pub struct HdrStaticMetadata {
+    pub content_light: Option<ContentLight>,
+    pub mastering_display: Option<MasteringDisplay>,
+}
+
+/// content light level in cd/m2
+#[derive(Deserialize)]
+pub struct ContentLight {
+    max_cll: u16,
+    max_fall: u16,
+}
+
+/// mastering display in CIE 1931 xy coordinates and cd/m2
+#[derive(Default, Deserialize)]
+pub struct MasteringDisplay {
+    red: (f64, f64),
+    green: (f64, f64),
+    blue: (f64, f64),
+    white_point: (f64, f64),
+    max_luminance: f64,
+    min_luminance: f64,
+}
+
+impl ContentLight {
+    /// parse "MaxCLL,MaxFALL" (e.g. "1000,400")
+    pub fn parse(s: &str) -> io::Result<ContentLight> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid content light level: {}", s),
+            )
+        };
+        let mut values = s.split(',').map(|v| v.trim().parse::<u16>());
+        match (values.next(), values.next(), values.next()) {
+            (Some(Ok(max_cll)), Some(Ok(max_fall)), None) => Ok(ContentLight { max_cll, max_fall }),
+            _ => Err(invalid()),
+        }
+    }
+
+    fn to_metadata(&self) -> obu::HdrCllMetadata {
+        obu::HdrCllMetadata {
+            max_cll: self.max_cll,
+            max_fall: self.max_fall,
+        }
+    }
+}
+
+impl MasteringDisplay {
+    /// parse "G(x,y)B(x,y)R(x,y)WP(x,y)L(max,min)" as SVT-AV1 --mastering-display
+    pub fn parse(s: &str) -> io::Result<MasteringDisplay> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid mastering display: {}", s),
+            )
+        };
+        let mut md = MasteringDisplay::default();
+        let mut present = 0;
+        for item in s.split(')').map(str::trim).filter(|item| !item.is_empty()) {
+            let (name, values) = item.split_once('(').ok_or_else(invalid)?;
+            let (a, b) = values.split_once(',').ok_or_else(invalid)?;
+            let a = a.trim().parse::<f64>().map_err(|_| invalid())?;
+            let b = b.trim().parse::<f64>().map_err(|_| invalid())?;
+            let (field, bit) = match name.trim() {
+                "R" => (&mut md.red, 1),
+                "G" => (&mut md.green, 2),
+                "B" => (&mut md.blue, 4),
+                "WP" => (&mut md.white_point, 8),
+                "L" => {
+                    md.max_luminance = a;
+                    md.min_luminance = b;
+                    present |= 16;
+                    continue;
+                }
+                _ => return Err(invalid()),
+            };
+            *field = (a, b);
+            present |= bit;
+        }
+        if present != 31 {
+            return Err(invalid());
+        }
+        Ok(md)
+    }
+
+    /// convert into fixed-point values of metadata_hdr_mdcv()
+    fn to_metadata(&self) -> io::Result<obu::HdrMdcvMetadata> {
+        // round value in fixed-point with frac_bits, check range of f(nbits)
+        let fixed = |name: &str, value: f64, frac_bits: i32, nbits: i32| {
+            let v = (value * 2f64.powi(frac_bits)).round();
+            if !(0.0..2f64.powi(nbits)).contains(&v) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("mastering display {} out of range: {}", name, value),
+                ));
+            }
+            Ok(v as u32)
+        };
+        // chromaticity coordinates are 0.16 fixed-point
+        let xy = |name: &str, (x, y): (f64, f64)| -> io::Result<(u16, u16)> {
+            Ok((
+                fixed(name, x, 16, 16)? as u16,
+                fixed(name, y, 16, 16)? as u16,
+            ))
+        };
+        let mut mdcv = obu::HdrMdcvMetadata::default();
+        let primaries = [
+            ("red", self.red),
+            ("green", self.green),
+            ("blue", self.blue),
+        ];
+        for (i, &(name, primary)) in primaries.iter().enumerate() {
+            let (x, y) = xy(name, primary)?;
+            mdcv.primary_chromaticity_x[i] = x;
+            mdcv.primary_chromaticity_y[i] = y;
+        }
+        let (x, y) = xy("white point", self.white_point)?;
+        mdcv.white_point_chromaticity_x = x;
+        mdcv.white_point_chromaticity_y = y;
+        // luminance_max is 24.8, luminance_min is 18.14 fixed-point
+        mdcv.luminance_max = fixed("max luminance", self.max_luminance, 8, 32)?;
+        mdcv.luminance_min = fixed("min luminance", self.min_luminance, 14, 32)?;
+        Ok(mdcv)
+    }
+}
+
+///
+/// insert HDR CLL/MDCV metadata OBUs into key frame temporal units and HDR10+ metadata OBU
+/// into temporal unit of its SequenceFrameIndex (presentation order), replacing existing ones
+///
+pub fn inject_hdr_metadata(
+    input: &str,
+    output: &str,
+    hdr: &HdrStaticMetadata,
+    hdr10plus: &BTreeMap<u32, Vec<u8>>,
+) -> io::Result<()> {
+    let mut metadata = Vec::new();
+    if let Some(ref cll) = hdr.content_light {
+        metadata.push(obu::MetadataObu::HdrCll(cll.to_metadata()));
+    }
+    if let Some(ref md) = hdr.mastering_display {
+        metadata.push(obu::MetadataObu::HdrMdcv(md.to_metadata()?));
+    }
+    if metadata.is_empty() && hdr10plus.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no content light level, mastering display or HDR10+ metadata to inject",
+        ));
+    }
+    let mut obus = Vec::new();
+    let mut filter = MetadataFilter {
+        metadata_types: Vec::new(),
+        t35_providers: Vec::new(),
+        hdr10plus: !hdr10plus.is_empty(),
+    };
+    for meta in &metadata {
+        let payload = obu::write_metadata_obu(meta)?;
+        obu::Obu::new(obu::OBU_METADATA, 0).write(&mut obus, &payload)?;
+        filter.metadata_types.push(match *meta {
+            obu::MetadataObu::HdrCll(_) => obu::METADATA_TYPE_HDR_CLL,
+            _ => obu::METADATA_TYPE_HDR_MDCV,
+        });
+    }
+
+    let mut seq = av1::Sequence::new();
+    let mut nkey = 0;
+    let mut nhdr10plus = 0;
+    let mut frame = 0; // SequenceFrameIndex
+    let nunit = rewrite_stream(input, output, |tu| {
+        let key_frame = is_random_access_point(&tu.data, &mut seq) && !obus.is_empty();
+        // temporal unit contains one shown frame
+        let dynamic = hdr10plus.get(&frame);
+        frame += 1;
+        let mut pending = key_frame || dynamic.is_some();
+        let mut data = Vec::with_capacity(tu.data.len() + obus.len());
+        let mut cursor = &tu.data[..];
+        while !cursor.is_empty() {
+            let sz = cursor.len() as u32;
+            let (obu, bytes) = stream::read_obu(&mut cursor, sz)?;
+            if obu.obu_type == obu::OBU_METADATA
+                && filter.matches(&bytes[obu.header_len as usize..])
+            {
+                continue;
+            }
+            // metadata OBUs precede first frame in temporal unit
+            if pending
+                && matches!(
+                    obu.obu_type,
+                    obu::OBU_FRAME_HEADER | obu::OBU_FRAME | obu::OBU_TILE_GROUP
+                )
+            {
+                if key_frame {
+                    data.extend_from_slice(&obus);
+                    nkey += 1;
+                }
+                if let Some(dynamic) = dynamic {
+                    data.extend_from_slice(dynamic);
+                    nhdr10plus += 1;
+                }
+                pending = false;
+            }
+            data.extend_from_slice(&bytes);
+        }
+        Ok(data)
+    })?;
+    if !metadata.is_empty() {
+        logln!(
+            "{}: inject {} metadata OBUs into {} of {} temporal units into {}",
+            input,
+            metadata.len(),
+            nkey,
+            nunit,
+            output
+        );
+    }
+    if !hdr10plus.is_empty() {
+        logln!(
+            "{}: inject HDR10+ metadata of {} frames (JSON has {} frames) into {} temporal units into {}",
+            input,
+            nhdr10plus,
+            hdr10plus.len(),
+            nunit,
+            output
+        );
+    }
+    Ok(())
+}
+
+///
+/// rewrite frame headers without film grain (apply_grain=0), and clear film_grain_params_present
+/// in sequence headers if clear_sequence_header, tile data is copied untouched
+///
+pub fn remove_film_grain(input: &str, output: &str, clear_sequence_header: bool) -> io::Result<()> {
+    let invalid = |msg: &str, nunit: u32| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} in temporal unit #{}", msg, nunit),
+        )
+    };
+    let mut seq = av1::Sequence::new();
+    let mut out_sh = None; // sequence header for rewritten frame headers
+    let mut frame_header = None; // rewritten frame_header_obu() for redundant copies
+    let (mut nframe, mut ngrain, mut nunit) = (0, 0, 0);
+    rewrite_stream(input, output, |tu| {
+        nunit += 1;
+        let mut data = Vec::with_capacity(tu.data.len());
+        let mut cursor = &tu.data[..];
+        while !cursor.is_empty() {
+            let sz = cursor.len() as u32;
+            let (mut obu, bytes) = stream::read_obu(&mut cursor, sz)?;
+            let payload = &bytes[obu.header_len as usize..];
+            let rewritten = match obu.obu_type {
+                obu::OBU_SEQUENCE_HEADER => {
+                    let sh = obu::parse_sequence_header(&mut &payload[..])
+                        .ok_or_else(|| invalid("invalid sequence header", nunit))?;
+                    let mut new_sh = sh.clone();
+                    new_sh.film_grain_params_present &= !clear_sequence_header;
+                    let header = obu::write_sequence_header(&new_sh)?;
+                    seq.sh = Some(sh);
+                    out_sh = Some(new_sh);
+                    Some(header)
+                }
+                obu::OBU_FRAME_HEADER | obu::OBU_FRAME => {
+                    let (sh, out_sh) = match (&seq.sh, &out_sh) {
+                        (Some(sh), Some(out_sh)) => (sh.clone(), out_sh),
+                        _ => return Err(invalid("frame header without sequence header", nunit)),
+                    };
+                    let mut fh = obu::parse_frame_header(&mut &payload[..], &sh, &mut seq.rfman)
+                        .ok_or_else(|| invalid("invalid frame header", nunit))?;
+                    if fh.show_existing_frame {
+                        // film grain params are loaded from reference frame, no rewrite
+                        frame_header = None;
+                        seq.decode_frame_wrapup(&fh);
+                        data.extend_from_slice(&bytes);
+                        continue;
+                    }
+                    nframe += 1;
+                    let fgp = mem::take(&mut fh.film_grain_params);
+                    ngrain += fgp.apply_grain as u32;
+                    let header = obu::write_frame_header(&fh, out_sh, &seq.rfman)?;
+                    let rewritten = if obu.obu_type == obu::OBU_FRAME {
+                        let tile_group = &payload[fh.header_bits.div_ceil(8) as usize..];
+                        obu::write_frame(&fh, out_sh, &seq.rfman, tile_group)?
+                    } else {
+                        header.clone()
+                    };
+                    frame_header = Some(header);
+                    // reference frames keep original film grain params for subsequent frames
+                    fh.film_grain_params = fgp;
+                    seq.decode_frame_wrapup(&fh);
+                    Some(rewritten)
+                }
+                obu::OBU_REDUNDANT_FRAME_HEADER => frame_header.clone(),
+                _ => None,
+            };
+            // keep original bytes (e.g. non-minimal obu_size) when payload is unchanged
+            match rewritten {
+                Some(ref rewritten) if rewritten[..] != payload[..] => {
+                    obu.write(&mut data, rewritten)?
+                }
+                _ => data.extend_from_slice(&bytes),
+            }
+        }
+        Ok(data)
+    })?;
+    logln!(
+        "{}: remove film grain from {} of {} frames in {} temporal units into {}",
+        input,
+        ngrain,
+        nframe,
+        nunit,
+        output
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path;
+    use std::process;
+
+    /// parse frame headers (except show_existing_frame) in raw OBU stream
+    fn parse_frames(mut data: &[u8]) -> Vec<obu::FrameHeader> {
+        let mut seq = av1::Sequence::new();
+        let mut frames = Vec::new();
+        while !data.is_empty() {
+            let sz = data.len() as u32;
+            let (obu, bytes) = stream::read_obu(&mut data, sz).unwrap();
+            let payload = &bytes[obu.header_len as usize..];
+            match obu.obu_type {
+                obu::OBU_SEQUENCE_HEADER => seq.sh = obu::parse_sequence_header(&mut &payload[..]),
+                obu::OBU_FRAME_HEADER | obu::OBU_FRAME => {
+                    let sh = seq.sh.as_ref().unwrap();
+                    let fh =
+                        obu::parse_frame_header(&mut &payload[..], sh, &mut seq.rfman).unwrap();
+                    seq.decode_frame_wrapup(&fh);
+                    if !fh.show_existing_frame {
+                        frames.push(fh);
+                    }
+                }
+                _ => {}
+            }
+        }
+        frames
+    }
+
+    /// rewrite raw OBU stream with film grain params in every shown or showable frame
+    fn add_film_grain(mut data: &[u8]) -> Vec<u8> {
+        let mut seq = av1::Sequence::new();
+        let mut out_sh = None;
+        let mut out = Vec::new();
+        while !data.is_empty() {
+            let sz = data.len() as u32;
+            let (mut obu, bytes) = stream::read_obu(&mut data, sz).unwrap();
+            let payload = &bytes[obu.header_len as usize..];
+            let payload = match obu.obu_type {
+                obu::OBU_SEQUENCE_HEADER => {
+                    let sh = obu::parse_sequence_header(&mut &payload[..]).unwrap();
+                    let mut new_sh = sh.clone();
+                    new_sh.film_grain_params_present = true;
+                    let header = obu::write_sequence_header(&new_sh).unwrap();
+                    seq.sh = Some(sh);
+                    out_sh = Some(new_sh);
+                    header
+                }
+                obu::OBU_FRAME_HEADER | obu::OBU_FRAME => {
+                    let (sh, out_sh) = (seq.sh.as_ref().unwrap(), out_sh.as_ref().unwrap());
+                    let mut fh =
+                        obu::parse_frame_header(&mut &payload[..], sh, &mut seq.rfman).unwrap();
+                    if fh.show_existing_frame {
+                        seq.decode_frame_wrapup(&fh);
+                        out.extend_from_slice(&bytes);
+                        continue;
+                    }
+                    let tile_group = &payload[fh.header_bits.div_ceil(8) as usize..];
+                    if fh.show_frame || fh.showable_frame {
+                        fh.film_grain_params = obu::FilmGrainParams {
+                            apply_grain: true,
+                            grain_seed: 1234,
+                            update_grain: true,
+                            ..Default::default()
+                        };
+                    }
+                    let rewritten = if obu.obu_type == obu::OBU_FRAME {
+                        obu::write_frame(&fh, out_sh, &seq.rfman, tile_group).unwrap()
+                    } else {
+                        obu::write_frame_header(&fh, out_sh, &seq.rfman).unwrap()
+                    };
+                    seq.decode_frame_wrapup(&fh);
+                    rewritten
+                }
+                _ => payload.to_vec(),
+            };
+            obu.write(&mut out, &payload).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn remove_film_grain_roundtrip() {
+        let dir = path::Path::new(env!("CARGO_MANIFEST_DIR")).join("streams");
+        let original = fs::read(dir.join("parkjoy.obu")).unwrap();
+        let grain = add_film_grain(&original);
+        let applied = parse_frames(&grain)
+            .iter()
+            .filter(|fh| fh.film_grain_params.apply_grain)
+            .count();
+        assert!(applied > 0);
+
+        let tmp = std::env::temp_dir();
+        let input = tmp.join(format!("av1parser-grain-{}.obu", process::id()));
+        let output = tmp.join(format!("av1parser-nograin-{}.obu", process::id()));
+        fs::write(&input, &grain).unwrap();
+        let (input, output) = (input.to_str().unwrap(), output.to_str().unwrap());
+        let mut results = Vec::new();
+        for &clear_sequence_header in &[false, true] {
+            remove_film_grain(input, output, clear_sequence_header).unwrap();
+            results.push(fs::read(output).unwrap());
+        }
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+
+        for data in results.iter() {
+            let frames = parse_frames(data);
+            assert_eq!(frames.len(), parse_frames(&original).len());
+            assert!(frames.iter().all(|fh| !fh.film_grain_params.apply_grain));
+        }
+        // tile data and other OBUs are copied untouched
+        assert_eq!(results[1], original);
+    }
+}
@@ -0,0 +1,31 @@
+//
+// AV1 stream scanner for scan subcommand
+//
+use av1parser::*;
+use std::fs;
+use std::io;
+
+/// scan file for embedded AV1 streams, optionally write them into separate files
+pub fn scan_file(input: &str, output: Option<&str>) -> io::Result<()> {
+    let buf = fs::read(input)?;
+    let matches = stream::scan_obu_streams(&buf);
+    outln!("{}: {} AV1 streams found", input, matches.len());
+    for (i, m) in matches.iter().enumerate() {
+        outln!(
+            "  #{} offset={} length={} temporal_units={} profile={} size={}x{}",
+            i,
+            m.offset,
+            m.length,
+            m.temporal_units,
+            m.sh.seq_profile,
+            m.sh.max_frame_width,
+            m.sh.max_frame_height
+        );
+        if let Some(prefix) = output {
+            let fname = format!("{}.{}.obu", prefix, i);
+            fs::write(&fname, &buf[m.offset..m.offset + m.length])?;
+            outln!("  write #{} into {}", i, fname);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,154 @@
+//
+// temporal unit splitting for split and extractframe subcommands
+//
+use crate::tools::mux::ExtractWriter;
+use av1parser::*;
+use std::fs;
+use std::io;
+
+/// write each temporal unit (or each OBU) into separate numbered file
+pub fn split_file(input: &str, prefix: &str, per_obu: bool) -> io::Result<()> {
+    let f = fs::OpenOptions::new().read(true).open(input)?;
+    let mut reader = io::BufReader::new(f);
+    let fmt = probe_fileformat_with_hint(&mut reader, input)?;
+    let mut tu_reader = stream::TemporalUnitReader::new(reader, fmt)?;
+
+    let (mut nunit, mut nfile) = (0, 0);
+    while let Some(tu) = tu_reader.next_unit()? {
+        if per_obu {
+            let mut data = &tu.data[..];
+            let mut nobu = 0;
+            while !data.is_empty() {
+                let sz = data.len() as u32;
+                let (_, bytes) = stream::read_obu(&mut data, sz)?;
+                fs::write(format!("{}.{:04}.{:02}.obu", prefix, nunit, nobu), &bytes)?;
+                nobu += 1;
+            }
+            nfile += nobu;
+        } else {
+            fs::write(format!("{}.{:04}.obu", prefix, nunit), &tu.data)?;
+            nfile += 1;
+        }
+        nunit += 1;
+    }
+    logln!(
+        "{}: split {} temporal units into {} files ({}.*.obu)",
+        input,
+        nunit,
+        nfile,
+        prefix
+    );
+    Ok(())
+}
+
+/// coded frame and its reference frames for extractframe subcommand
+struct CodedFrame {
+    decode_order: i64,
+    refs: Vec<i64>,
+    data: Vec<u8>, // frame header and tile group OBUs
+}
+
+/// extract frame with its reference closure into standalone IVF/raw OBU stream
+pub fn extract_frame(input: &str, target: i64, output: &str) -> io::Result<()> {
+    let f = fs::OpenOptions::new().read(true).open(input)?;
+    let mut reader = io::BufReader::new(f);
+    let fmt = probe_fileformat_with_hint(&mut reader, input)?;
+    let mut tu_reader = stream::TemporalUnitReader::new(reader, fmt)?;
+
+    // collect coded frames in decoding order until target frame
+    let mut seq = av1::Sequence::new();
+    let mut sh_obu = None;
+    let mut frames: Vec<CodedFrame> = Vec::new();
+    let mut in_frame = false;
+    'read: while let Some(tu) = tu_reader.next_unit()? {
+        let mut data = &tu.data[..];
+        while !data.is_empty() {
+            let sz = data.len() as u32;
+            let (obu, bytes) = stream::read_obu(&mut data, sz)?;
+            match obu.obu_type {
+                obu::OBU_SEQUENCE_HEADER => {
+                    let payload = &bytes[obu.header_len as usize..];
+                    seq.sh = obu::parse_sequence_header(&mut &payload[..]);
+                    if sh_obu.is_none() {
+                        sh_obu = Some(bytes);
+                    }
+                }
+                obu::OBU_FRAME_HEADER | obu::OBU_FRAME => {
+                    in_frame = false;
+                    if frames.iter().any(|frame| frame.decode_order == target) {
+                        break 'read;
+                    }
+                    let sh = match seq.sh {
+                        Some(ref sh) => sh,
+                        None => continue,
+                    };
+                    let payload = &bytes[obu.header_len as usize..];
+                    let fh = match obu::parse_frame_header(&mut &payload[..], sh, &mut seq.rfman) {
+                        Some(fh) => fh,
+                        None => continue,
+                    };
+                    if !fh.show_existing_frame {
+                        frames.push(CodedFrame {
+                            decode_order: seq.rfman.decode_order,
+                            refs: seq.rfman.reference_frames(&fh),
+                            data: bytes,
+                        });
+                        in_frame = obu.obu_type == obu::OBU_FRAME_HEADER;
+                    }
+                    seq.decode_frame_wrapup(&fh);
+                }
+                obu::OBU_TILE_GROUP if in_frame => {
+                    frames.last_mut().unwrap().data.extend_from_slice(&bytes);
+                }
+                _ => {}
+            }
+        }
+    }
+    if !frames.iter().any(|frame| frame.decode_order == target) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("frame #{} not found", target),
+        ));
+    }
+
+    // reference closure of target frame
+    let mut needed = vec![target];
+    let mut i = 0;
+    while i < needed.len() {
+        if let Some(frame) = frames.iter().find(|frame| frame.decode_order == needed[i]) {
+            for r in &frame.refs {
+                if !needed.contains(r) {
+                    needed.push(*r);
+                }
+            }
+        }
+        i += 1;
+    }
+    needed.sort();
+
+    let (width, height) = match seq.sh {
+        Some(ref sh) => (sh.max_frame_width as u16, sh.max_frame_height as u16),
+        None => (0, 0),
+    };
+    let f = io::BufWriter::new(fs::File::create(output)?);
+    let mut writer = ExtractWriter::new(f, output.ends_with(".ivf"), width, height, (1, 30))?;
+    for (pts, frame) in frames
+        .iter()
+        .filter(|frame| needed.contains(&frame.decode_order))
+        .enumerate()
+    {
+        let config_obus = if pts == 0 { sh_obu.as_ref() } else { None };
+        writer.write_unit(&frame.data, config_obus.map(|obus| &obus[..]), pts as u64)?;
+    }
+    writer.finish()?;
+    let list: Vec<String> = needed.iter().map(|n| format!("#{}", n)).collect();
+    logln!(
+        "{}: extract frame #{} with {} frames ({}) into {}",
+        input,
+        target,
+        needed.len(),
+        list.join(","),
+        output
+    );
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//
+// container structure listing for tracks and mkvdump subcommands
+//
+use av1parser::*;
+use std::fs;
+use std::io;
+
+/// list all tracks in MP4/WebM file
+pub fn list_tracks(fname: &str) -> io::Result<()> {
+    let f = fs::OpenOptions::new().read(true).open(fname)?;
+    let mut reader = io::BufReader::new(f);
+    let format = probe_fileformat_with_hint(&mut reader, fname)?;
+    match format {
+        FileFormat::MP4 => list_mp4_tracks(reader, fname),
+        FileFormat::WebM => list_webm_tracks(reader, fname),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "tracks supports MP4/WebM input only",
+        )),
+    }
+}
+
+/// list all tracks in MP4 file
+fn list_mp4_tracks<R: io::Read + io::Seek>(mut reader: R, fname: &str) -> io::Result<()> {
+    let mp4 = mp4::open_mp4file(&mut reader)?;
+    outln!("{}: {} tracks", fname, mp4.get_tracks().len());
+    for track in mp4.get_tracks() {
+        let duration = if track.timescale > 0 {
+            track.duration as f64 / track.timescale as f64
+        } else {
+            0.0
+        };
+        outln!(
+            "  track#{} handler={} codec={} size={}x{} duration={:.3}s samples={}",
+            track.track_id,
+            track.handler,
+            track.codec,
+            track.width,
+            track.height,
+            duration,
+            track.sample_count
+        );
+    }
+    Ok(())
+}
+
+/// list all tracks in WebM file
+fn list_webm_tracks<R: io::Read + io::Seek>(mut reader: R, fname: &str) -> io::Result<()> {
+    let webm = mkv::open_mkvfile(&mut reader)?;
+    outln!("{}: {} tracks", fname, webm.get_tracks().len());
+    for track in webm.get_tracks() {
+        let track_type = match track.track_type {
+            1 => "video".to_string(),
+            2 => "audio".to_string(),
+            17 => "subtitle".to_string(),
+            n => n.to_string(),
+        };
+        let size = match track.setting {
+            Some(ref video) => format!("{}x{}", video.pixel_width, video.pixel_height),
+            None => "0x0".to_string(),
+        };
+        outln!(
+            "  track#{} type={} codec={} size={} language={} default={} enabled={}{}",
+            track.track_num,
+            track_type,
+            track.codec_id,
+            size,
+            track.language,
+            track.flag_default as u8,
+            track.flag_enabled as u8,
+            match track.name {
+                Some(ref name) => format!(" name=\"{}\"", name),
+                None => String::new(),
+            }
+        );
+    }
+    Ok(())
+}
+
+/// dump EBML element tree in Matroska/WebM file
+pub fn dump_mkv_elements(fname: &str) -> io::Result<()> {
+    let f = fs::OpenOptions::new().read(true).open(fname)?;
+    let mut reader = io::BufReader::new(f);
+    if probe_fileformat_with_hint(&mut reader, fname)? != FileFormat::WebM {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "mkvdump supports Matroska/WebM input only",
+        ));
+    }
+    outln!("{}: Matroska/WebM element tree", fname);
+    mkv::walk_elements(&mut reader, &mut |elem| {
+        let size = match elem.size {
+            Some(size) => size.to_string(),
+            None => "unknown".to_string(),
+        };
+        outln!(
+            "{:indent$}{} [0x{:X}] offset={} size={}",
+            "",
+            elem.name(),
+            elem.id,
+            elem.offset,
+            size,
+            indent = 2 * elem.level + 2
+        );
+    })
+}
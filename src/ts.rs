@@ -0,0 +1,448 @@
+//
+// MPEG-2 Transport Stream (ISO/IEC 13818-1)
+// https://aomediacodec.github.io/av1-mpeg2-ts/
+//
+use std::io;
+
+pub const TS_PACKET_SIZE: usize = 188;
+pub const TS_SYNC_BYTE: u8 = 0x47;
+pub const PTS_CLOCK: u64 = 90_000; // PTS/DTS clock frequency [Hz]
+
+const PID_PAT: u16 = 0x0000; // Program Association Table
+const TABLE_ID_PAT: u8 = 0x00;
+const TABLE_ID_PMT: u8 = 0x02;
+
+// stream_type
+const STREAM_TYPE_PRIVATE_PES: u8 = 0x06; // PES packets containing private data
+
+// descriptor_tag
+const DESCRIPTOR_REGISTRATION: u8 = 0x05; // registration_descriptor
+const DESCRIPTOR_AV1_VIDEO: u8 = 0x80; // AV1_video_descriptor
+
+const FORMAT_IDENTIFIER_AV01: [u8; 4] = *b"AV01";
+
+///
+/// TS packet header
+///
+#[derive(Debug)]
+struct TsPacketHeader {
+    pid: u16,                           // PID
+    payload_unit_start_indicator: bool, // payload_unit_start_indicator
+    continuity_counter: u8,             // continuity_counter
+}
+
+///
+/// elementary stream in PMT
+///
+#[derive(Debug)]
+pub struct EsInfo {
+    pub stream_type: u8,
+    pub elementary_pid: u16,
+    pub is_av1: bool, // registration_descriptor("AV01") or AV1_video_descriptor
+}
+
+///
+/// PES packet
+///
+#[derive(Debug)]
+pub struct PesPacket {
+    pub stream_id: u8,
+    pub pts: Option<u64>, // [90kHz]
+    pub dts: Option<u64>, // [90kHz]
+    pub data: Vec<u8>,    // PES_packet_data_byte
+}
+
+///
+/// MPEG-2 TS demuxer for AV1 stream
+///
+#[derive(Debug, Default)]
+pub struct TsDemuxer {
+    pmt_pids: Vec<u16>,
+    streams: Vec<EsInfo>,
+    av1_pid: Option<u16>,
+    pes_buf: Vec<u8>,
+    last_cc: Option<u8>,
+    cc_errors: u64,
+}
+
+/// decode 33bit timestamp in PES header
+fn read_timestamp(b: &[u8]) -> u64 {
+    ((b[0] as u64 >> 1) & 0x07) << 30
+        | (b[1] as u64) << 22
+        | (b[2] as u64 >> 1) << 15
+        | (b[3] as u64) << 7
+        | (b[4] as u64 >> 1)
+}
+
+/// parse PES packet header
+fn parse_pes_packet(data: &[u8]) -> io::Result<PesPacket> {
+    if data.len() < 9 || data[0..3] != [0x00, 0x00, 0x01] {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid PES packet_start_code_prefix",
+        ));
+    }
+    let stream_id = data[3];
+    let pts_dts_flags = data[7] >> 6;
+    let header_end = 9 + data[8] as usize;
+    let timestamps_len = match pts_dts_flags {
+        0b10 => 5,  // PTS
+        0b11 => 10, // PTS and DTS
+        _ => 0,
+    };
+    if data.len() < header_end || header_end < 9 + timestamps_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid PES_header_data_length",
+        ));
+    }
+    let pts = if pts_dts_flags & 0b10 != 0 {
+        Some(read_timestamp(&data[9..14]))
+    } else {
+        None
+    };
+    let dts = if pts_dts_flags == 0b11 {
+        Some(read_timestamp(&data[14..19]))
+    } else {
+        pts
+    };
+    Ok(PesPacket {
+        stream_id,
+        pts,
+        dts,
+        data: data[header_end..].to_vec(),
+    })
+}
+
+///
+/// convert AV1 access unit into low overhead bitstream format,
+/// remove start code (0x000001) and emulation prevention bytes (0x000003) if exists
+///
+pub fn av1_access_unit(data: &[u8]) -> Vec<u8> {
+    if !data.starts_with(&[0x00, 0x00, 0x01]) {
+        return data.to_vec();
+    }
+    let mut buf = Vec::with_capacity(data.len());
+    let mut zeros = 0;
+    for &b in &data[3..] {
+        if zeros >= 2 && b == 0x03 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if b == 0x00 { zeros + 1 } else { 0 };
+        buf.push(b);
+    }
+    buf
+}
+
+impl TsDemuxer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// get elementary streams in PMT
+    pub fn get_streams(&self) -> &Vec<EsInfo> {
+        &self.streams
+    }
+
+    /// get PID of AV1 stream
+    pub fn get_av1_pid(&self) -> Option<u16> {
+        self.av1_pid
+    }
+
+    /// get number of continuity_counter discontinuities in AV1 stream
+    pub fn get_cc_errors(&self) -> u64 {
+        self.cc_errors
+    }
+
+    /// read next PES packet of AV1 stream
+    pub fn next_pes<R: io::Read>(&mut self, mut reader: R) -> io::Result<Option<PesPacket>> {
+        let mut packet = [0; TS_PACKET_SIZE];
+        loop {
+            match reader.read_exact(&mut packet) {
+                Ok(_) => (),
+                Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    // flush last PES packet
+                    if self.pes_buf.is_empty() {
+                        return Ok(None);
+                    }
+                    let data = std::mem::take(&mut self.pes_buf);
+                    return parse_pes_packet(&data).map(Some);
+                }
+                Err(err) => return Err(err),
+            }
+            let (hdr, payload) = Self::parse_packet(&packet)?;
+            let payload = match payload {
+                Some(payload) => payload,
+                None => continue,
+            };
+            if hdr.pid == PID_PAT && hdr.payload_unit_start_indicator {
+                self.parse_pat(payload);
+            } else if self.pmt_pids.contains(&hdr.pid) && hdr.payload_unit_start_indicator {
+                self.parse_pmt(payload);
+            } else if Some(hdr.pid) == self.av1_pid {
+                if let Some(last_cc) = self.last_cc {
+                    if hdr.continuity_counter != (last_cc + 1) & 0x0f {
+                        self.cc_errors += 1;
+                    }
+                }
+                self.last_cc = Some(hdr.continuity_counter);
+                if hdr.payload_unit_start_indicator && !self.pes_buf.is_empty() {
+                    let data = std::mem::replace(&mut self.pes_buf, payload.to_vec());
+                    return parse_pes_packet(&data).map(Some);
+                }
+                if hdr.payload_unit_start_indicator || !self.pes_buf.is_empty() {
+                    self.pes_buf.extend_from_slice(payload);
+                }
+            }
+        }
+    }
+
+    /// parse TS packet header, return (header, payload)
+    fn parse_packet(packet: &[u8]) -> io::Result<(TsPacketHeader, Option<&[u8]>)> {
+        if packet[0] != TS_SYNC_BYTE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid TS sync_byte",
+            ));
+        }
+        let hdr = TsPacketHeader {
+            pid: ((packet[1] as u16 & 0x1f) << 8) | packet[2] as u16,
+            payload_unit_start_indicator: packet[1] & 0x40 != 0,
+            continuity_counter: packet[3] & 0x0f,
+        };
+        let adaptation_field_control = (packet[3] >> 4) & 0b11;
+        let mut pos = 4;
+        if adaptation_field_control & 0b10 != 0 {
+            pos += 1 + packet[4] as usize; // adaptation_field_length
+        }
+        let payload = if adaptation_field_control & 0b01 != 0 && pos < TS_PACKET_SIZE {
+            Some(&packet[pos..])
+        } else {
+            None
+        };
+        Ok((hdr, payload))
+    }
+
+    /// get PSI section after pointer_field, return (table_id, section body without CRC_32)
+    fn psi_section(payload: &[u8]) -> Option<(u8, &[u8])> {
+        let pos = 1 + *payload.first()? as usize; // pointer_field
+        let section = payload.get(pos..)?;
+        let table_id = *section.first()?;
+        let section_length = ((*section.get(1)? as usize & 0x0f) << 8) | *section.get(2)? as usize;
+        let body = section.get(8..(3 + section_length).checked_sub(4)?)?;
+        Some((table_id, body))
+    }
+
+    /// parse program_association_section()
+    fn parse_pat(&mut self, payload: &[u8]) {
+        if let Some((TABLE_ID_PAT, body)) = Self::psi_section(payload) {
+            for entry in body.chunks(4).filter(|entry| entry.len() == 4) {
+                let program_number = ((entry[0] as u16) << 8) | entry[1] as u16;
+                let pid = ((entry[2] as u16 & 0x1f) << 8) | entry[3] as u16;
+                if program_number != 0 && !self.pmt_pids.contains(&pid) {
+                    self.pmt_pids.push(pid);
+                }
+            }
+        }
+    }
+
+    /// parse TS_program_map_section()
+    fn parse_pmt(&mut self, payload: &[u8]) {
+        let body = match Self::psi_section(payload) {
+            Some((TABLE_ID_PMT, body)) if body.len() >= 4 => body,
+            _ => return,
+        };
+        let program_info_length = ((body[2] as usize & 0x0f) << 8) | body[3] as usize;
+        let mut pos = 4 + program_info_length;
+        while pos + 5 <= body.len() {
+            let stream_type = body[pos];
+            let elementary_pid = ((body[pos + 1] as u16 & 0x1f) << 8) | body[pos + 2] as u16;
+            let es_info_length = ((body[pos + 3] as usize & 0x0f) << 8) | body[pos + 4] as usize;
+            let descriptors = body.get(pos + 5..pos + 5 + es_info_length).unwrap_or(&[]);
+            let is_av1 =
+                stream_type == STREAM_TYPE_PRIVATE_PES && Self::has_av1_descriptor(descriptors);
+            if !self
+                .streams
+                .iter()
+                .any(|es| es.elementary_pid == elementary_pid)
+            {
+                self.streams.push(EsInfo {
+                    stream_type,
+                    elementary_pid,
+                    is_av1,
+                });
+            }
+            if is_av1 && self.av1_pid.is_none() {
+                self.av1_pid = Some(elementary_pid);
+            }
+            pos += 5 + es_info_length;
+        }
+    }
+
+    /// check registration_descriptor("AV01") or AV1_video_descriptor
+    fn has_av1_descriptor(mut descriptors: &[u8]) -> bool {
+        while descriptors.len() >= 2 {
+            let descriptor_tag = descriptors[0];
+            let descriptor_length = descriptors[1] as usize;
+            let body = descriptors.get(2..2 + descriptor_length).unwrap_or(&[]);
+            match descriptor_tag {
+                DESCRIPTOR_REGISTRATION if body.starts_with(&FORMAT_IDENTIFIER_AV01) => {
+                    return true
+                }
+                DESCRIPTOR_AV1_VIDEO => return true,
+                _ => {}
+            }
+            descriptors = descriptors.get(2 + descriptor_length..).unwrap_or(&[]);
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PID_PMT: u16 = 0x0100;
+    const PID_AV1: u16 = 0x0101;
+    const PID_AUDIO: u16 = 0x0102;
+
+    /// TS packet with payload, stuffed by adaptation field
+    fn ts_packet(pid: u16, pusi: bool, cc: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![
+            TS_SYNC_BYTE,
+            (pusi as u8) << 6 | (pid >> 8) as u8,
+            pid as u8,
+            0x10 | cc,
+        ];
+        let stuffing = TS_PACKET_SIZE - 4 - payload.len();
+        if stuffing > 0 {
+            packet[3] |= 0x20; // adaptation_field_control=0b11
+            packet.push(stuffing as u8 - 1); // adaptation_field_length
+            if stuffing > 1 {
+                packet.push(0x00);
+                packet.resize(4 + stuffing, 0xff);
+            }
+        }
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    /// PSI section with pointer_field and dummy CRC_32
+    fn psi_section(table_id: u8, body: &[u8]) -> Vec<u8> {
+        let section_length = 5 + body.len() + 4;
+        let mut payload = vec![0x00, table_id, 0xb0, section_length as u8];
+        payload.extend_from_slice(&[0x00, 0x01, 0xc1, 0x00, 0x00]);
+        payload.extend_from_slice(body);
+        payload.extend_from_slice(&[0; 4]);
+        payload
+    }
+
+    fn encode_timestamp(prefix: u8, ts: u64) -> [u8; 5] {
+        [
+            prefix << 4 | ((ts >> 29) & 0x0e) as u8 | 1,
+            (ts >> 22) as u8,
+            ((ts >> 14) & 0xfe) as u8 | 1,
+            (ts >> 7) as u8,
+            ((ts << 1) & 0xfe) as u8 | 1,
+        ]
+    }
+
+    fn pes_packet(pts: u64, dts: Option<u64>, data: &[u8]) -> Vec<u8> {
+        let mut pes = vec![0x00, 0x00, 0x01, 0xbd, 0x00, 0x00, 0x80];
+        match dts {
+            Some(dts) => {
+                pes.extend_from_slice(&[0xc0, 10]);
+                pes.extend_from_slice(&encode_timestamp(0b0011, pts));
+                pes.extend_from_slice(&encode_timestamp(0b0001, dts));
+            }
+            None => {
+                pes.extend_from_slice(&[0x80, 5]);
+                pes.extend_from_slice(&encode_timestamp(0b0010, pts));
+            }
+        }
+        pes.extend_from_slice(data);
+        pes
+    }
+
+    fn program_tables() -> Vec<u8> {
+        let pat = psi_section(TABLE_ID_PAT, &[0x00, 0x01, 0xe1, 0x00]);
+        let pmt = psi_section(
+            TABLE_ID_PMT,
+            &[
+                0xe1, 0x01, 0xf0, 0x00, // PCR_PID, program_info_length
+                0x06, 0xe1, 0x01, 0xf0, 0x06, // AV1 stream
+                0x05, 0x04, b'A', b'V', b'0', b'1', // registration_descriptor
+                0x0f, 0xe1, 0x02, 0xf0, 0x00, // AAC stream
+            ],
+        );
+        [
+            ts_packet(PID_PAT, true, 0, &pat),
+            ts_packet(PID_PMT, true, 0, &pmt),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn demux_pes_packets() {
+        let data: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        let pes1 = pes_packet(3000, None, &data);
+        let pes2 = pes_packet(9000, Some(6000), &[0x12, 0x00]);
+        let mut ts = program_tables();
+        ts.extend(ts_packet(PID_AV1, true, 0, &pes1[..184]));
+        ts.extend(ts_packet(PID_AUDIO, true, 0, &[0; 8]));
+        ts.extend(ts_packet(PID_AV1, false, 1, &pes1[184..]));
+        ts.extend(ts_packet(PID_AV1, true, 2, &pes2));
+
+        let mut demuxer = TsDemuxer::new();
+        let mut reader = &ts[..];
+        let pes = demuxer.next_pes(&mut reader).unwrap().unwrap();
+        assert_eq!(demuxer.get_av1_pid(), Some(PID_AV1));
+        let streams = demuxer.get_streams();
+        assert_eq!(streams.len(), 2);
+        assert!(streams[0].is_av1 && !streams[1].is_av1);
+        assert_eq!(streams[1].stream_type, 0x0f);
+        assert_eq!(pes.stream_id, 0xbd);
+        assert_eq!((pes.pts, pes.dts), (Some(3000), Some(3000)));
+        assert_eq!(pes.data, data);
+        // last PES packet is flushed at end of stream
+        let pes = demuxer.next_pes(&mut reader).unwrap().unwrap();
+        assert_eq!((pes.pts, pes.dts), (Some(9000), Some(6000)));
+        assert_eq!(pes.data, [0x12, 0x00]);
+        assert!(demuxer.next_pes(&mut reader).unwrap().is_none());
+        assert_eq!(demuxer.get_cc_errors(), 0);
+    }
+
+    #[test]
+    fn demux_continuity_error() {
+        let pes = pes_packet(3000, None, &[0x12, 0x00]);
+        let mut ts = program_tables();
+        ts.extend(ts_packet(PID_AV1, true, 0, &pes));
+        ts.extend(ts_packet(PID_AV1, true, 2, &pes));
+        let mut demuxer = TsDemuxer::new();
+        let mut reader = &ts[..];
+        while demuxer.next_pes(&mut reader).unwrap().is_some() {}
+        assert_eq!(demuxer.get_cc_errors(), 1);
+
+        let mut ts = program_tables();
+        ts[TS_PACKET_SIZE] = 0x00;
+        let err = TsDemuxer::new().next_pes(&ts[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn pes_header() {
+        assert!(parse_pes_packet(&[0x00, 0x00, 0x02, 0xbd, 0, 0, 0x80, 0x00, 0x00]).is_err());
+        // PES_header_data_length is shorter than PTS
+        assert!(parse_pes_packet(&[0x00, 0x00, 0x01, 0xbd, 0, 0, 0x80, 0x80, 0x00]).is_err());
+        let pes = parse_pes_packet(&pes_packet(0x1_2345_6789, None, &[])).unwrap();
+        assert_eq!(pes.pts, Some(0x1_2345_6789));
+    }
+
+    #[test]
+    fn access_unit_start_code() {
+        let au = [0x00, 0x00, 0x01, 0x12, 0x00, 0x00, 0x00, 0x03, 0x01];
+        assert_eq!(av1_access_unit(&au), [0x12, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(av1_access_unit(&[0x12, 0x00]), [0x12, 0x00]);
+    }
+}